@@ -58,6 +58,7 @@ pub enum PoolUpdate {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolUpdateMessage {
+    pub chain_id: u64,
     pub pool_id: PoolIdentifier,
     pub protocol: Protocol,
     pub update_type: UpdateType,
@@ -73,6 +74,7 @@ pub struct PoolUpdateMessage {
 pub enum ControlMessage {
     UpdateWhitelist,
     BeginBlock {
+        chain_id: u64,
         block_number: u64,
         block_timestamp: u64,
         is_revert: bool,
@@ -97,6 +99,7 @@ fn main() {
     let sqrt_price = U256::from(1382840672037684546977487336313952u128);
 
     let v3_swap_msg = PoolUpdateMessage {
+        chain_id: 1,
         pool_id: PoolIdentifier::Address(pool_addr),
         protocol: Protocol::V3,
         update_type: UpdateType::Swap,
@@ -277,6 +280,7 @@ fn main() {
     let amount1 = I256::try_from(1512537406709823118i128).unwrap();
 
     let v2_swap_msg = PoolUpdateMessage {
+        chain_id: 1,
         pool_id: PoolIdentifier::Address(v2_pool),
         protocol: Protocol::V2,
         update_type: UpdateType::Swap,
@@ -0,0 +1,182 @@
+// Runtime Admin Interface (NATS request/reply)
+//
+// Lets operators patch the whitelist and inspect tracker state without going
+// through dynamicWhitelist. Requests arrive on `exex.admin.{chain}` as JSON
+// with an `op` field; every request gets exactly one JSON reply.
+//
+//   {"op":"stats"}
+//   {"op":"list_pools"}
+//   {"op":"add",    "chain":"ethereum", "pools":[<WhitelistPool>, ...]}
+//   {"op":"remove", "chain":"ethereum", "pool_addresses":["0x..", ...]}
+//   {"op":"blacklist" | "unblacklist", "chain":"ethereum", "pool_addresses":[..]}
+//
+// `add` / `remove` payloads use the exact canonical `.add` / `.remove` wire
+// shapes, so they are parsed by the same code path as the live subscription
+// and land in `PoolTracker::queue_update` — i.e. they are applied at the next
+// block boundary like any other whitelist change.
+
+use crate::nats_client::WhitelistNatsClient;
+use crate::pool_tracker::{PoolTracker, PoolTrackerStats, WhitelistUpdate};
+use crate::types::{PoolIdentifier, Protocol};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+/// Admin request subject for a chain.
+pub fn admin_subject(chain: &str) -> String {
+    crate::subjects::admin(chain)
+}
+
+/// A parsed admin request.
+#[derive(Debug)]
+pub enum AdminCommand {
+    /// Whitelist change, queued on the tracker like a canonical delta.
+    Update(WhitelistUpdate),
+    /// List every tracked pool.
+    ListPools,
+    /// Per-protocol tracker counts.
+    Stats,
+}
+
+/// Only the `op` discriminator; the rest of the payload is handed to the
+/// canonical parsers untouched.
+#[derive(Debug, Deserialize)]
+struct AdminEnvelope {
+    op: String,
+}
+
+/// Parse an admin request payload into a command.
+pub fn parse_admin_request(payload: &[u8]) -> Result<AdminCommand> {
+    let envelope: AdminEnvelope = serde_json::from_slice(payload)?;
+    match envelope.op.as_str() {
+        "stats" => Ok(AdminCommand::Stats),
+        "list_pools" => Ok(AdminCommand::ListPools),
+        // Live `.full` replacement is deliberately not exposed: a hand-written
+        // snapshot would silently drop every pool it forgot to list.
+        "add" | "remove" | "blacklist" | "unblacklist" => {
+            WhitelistNatsClient::canonical_update(&envelope.op, payload)?
+                .map(AdminCommand::Update)
+                .ok_or_else(|| eyre::eyre!("unsupported admin op {}", envelope.op))
+        }
+        other => Err(eyre::eyre!("unknown admin op {}", other)),
+    }
+}
+
+/// One tracked pool in a `list_pools` reply.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminPoolEntry {
+    pub pool: String,
+    pub protocol: Protocol,
+}
+
+/// JSON reply for every admin request.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdminResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of pools in an accepted add/remove request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<PoolTrackerStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pools: Option<Vec<AdminPoolEntry>>,
+}
+
+impl AdminResponse {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn queued(count: usize) -> Self {
+        Self {
+            ok: true,
+            queued: Some(count),
+            ..Default::default()
+        }
+    }
+
+    pub fn stats(tracker: &PoolTracker) -> Self {
+        Self {
+            ok: true,
+            stats: Some(tracker.stats()),
+            ..Default::default()
+        }
+    }
+
+    pub fn list_pools(tracker: &PoolTracker) -> Self {
+        let mut pools: Vec<AdminPoolEntry> = tracker
+            .pools()
+            .map(|meta| AdminPoolEntry {
+                pool: format_pool_identifier(&meta.pool_id),
+                protocol: meta.protocol,
+            })
+            .collect();
+        pools.sort_by(|a, b| a.pool.cmp(&b.pool));
+        Self {
+            ok: true,
+            pools: Some(pools),
+            ..Default::default()
+        }
+    }
+}
+
+/// Number of pools carried by a whitelist update.
+pub fn update_len(update: &WhitelistUpdate) -> usize {
+    match update {
+        WhitelistUpdate::Add(pools) | WhitelistUpdate::Replace(pools) => pools.len(),
+        WhitelistUpdate::Remove(ids)
+        | WhitelistUpdate::Blacklist(ids)
+        | WhitelistUpdate::Unblacklist(ids) => ids.len(),
+    }
+}
+
+fn format_pool_identifier(id: &PoolIdentifier) -> String {
+    match id {
+        PoolIdentifier::Address(addr) => format!("{:#x}", addr),
+        PoolIdentifier::PoolId(bytes) => format!("0x{}", hex::encode(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADD_V2: &[u8] = br#"{"op":"add","chain":"ethereum","pools":[{"address":"0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc","protocol":"v2","token0":{"address":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","symbol":"USDC","decimals":6},"token1":{"address":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","symbol":"WETH","decimals":18}}]}"#;
+
+    #[test]
+    fn admin_add_then_list_and_remove() {
+        let mut tracker = PoolTracker::new();
+
+        let AdminCommand::Update(update) = parse_admin_request(ADD_V2).unwrap() else {
+            panic!("expected Update");
+        };
+        assert_eq!(update_len(&update), 1);
+        tracker.queue_update(update);
+
+        let listed = AdminResponse::list_pools(&tracker).pools.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].pool, "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc");
+        assert_eq!(listed[0].protocol, Protocol::UniswapV2);
+
+        let remove = br#"{"op":"remove","chain":"ethereum","pool_addresses":["0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"]}"#;
+        let AdminCommand::Update(update) = parse_admin_request(remove).unwrap() else {
+            panic!("expected Update");
+        };
+        tracker.queue_update(update);
+        assert_eq!(AdminResponse::stats(&tracker).stats.unwrap().total_pools, 0);
+    }
+
+    #[test]
+    fn admin_rejects_unknown_and_full_ops() {
+        assert!(parse_admin_request(br#"{"op":"stats"}"#).is_ok());
+        assert!(parse_admin_request(br#"{"op":"list_pools"}"#).is_ok());
+        assert!(parse_admin_request(br#"{"op":"full","chain":"ethereum","pools":[]}"#).is_err());
+        assert!(parse_admin_request(br#"{"op":"drop_all"}"#).is_err());
+        assert!(parse_admin_request(b"not json").is_err());
+    }
+}
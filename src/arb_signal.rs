@@ -0,0 +1,311 @@
+// Cross-Pool Arbitrage Signals
+//
+// Optional (`ARB_SIGNAL_THRESHOLD_BPS`, 0 disables; needs the pool state
+// engine): after each committed block, every pair of tokens with a pool the
+// block updated is compared across all tracked pools of that pair that have a
+// known base in the state engine. When the cheapest and the richest price of
+// token0 (in token1) are more than the threshold apart, an `ArbSignal` naming
+// both pools, their prices, fee tiers and the spread is published as JSON to
+// `arb.signals.<chain>`, so the strategy does not have to recompute every pair
+// downstream to find it.
+//
+// Prices are mid prices from the engine (V2 reserves, V3/V4 sqrt price) in
+// whole-token units; pools without known decimals, empty reserves or zero
+// active liquidity are skipped. The spread is gross of fees and gas: the fee
+// tiers are included for the consumer to net out.
+
+use crate::pool_state::PoolState;
+use crate::price_quote::{normalized_price, reserves_price};
+use crate::types::{PoolIdentifier, PoolMetadata};
+use crate::{exex_metrics, subjects};
+use alloy_primitives::Address;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const QUEUE_CAPACITY: usize = 1_000;
+
+/// Mid price of token1 per token0 in whole-token units from engine state.
+pub fn pool_price(pool: &PoolMetadata, state: &PoolState) -> Option<f64> {
+    let decimals0 = pool.token0_decimals?;
+    let decimals1 = pool.token1_decimals?;
+    match state {
+        PoolState::V2 { reserve0, reserve1 } => {
+            reserves_price(*reserve0, *reserve1, decimals0, decimals1)
+        }
+        PoolState::Concentrated(state) => {
+            if state.liquidity == 0 {
+                return None;
+            }
+            Some(normalized_price(state.sqrt_price_x96, decimals0, decimals1))
+        }
+    }
+}
+
+/// Two pools of one pair priced more than the threshold apart.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArbSignal {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Pair in address order; prices are token1 per token0.
+    pub token0: Address,
+    pub token1: Address,
+    /// Cheapest pool: buy token0 here.
+    pub buy_pool: String,
+    pub buy_price: f64,
+    pub buy_fee: Option<u32>,
+    /// Richest pool: sell token0 here.
+    pub sell_pool: String,
+    pub sell_price: f64,
+    pub sell_fee: Option<u32>,
+    /// `(sell_price / buy_price - 1)` in basis points, before fees.
+    pub spread_bps: f64,
+}
+
+/// Pools updated this block and the spread threshold.
+#[derive(Debug)]
+pub struct ArbDetector {
+    threshold_bps: f64,
+    touched: HashSet<PoolIdentifier>,
+}
+
+impl ArbDetector {
+    pub fn new(threshold_bps: u32) -> Self {
+        Self {
+            threshold_bps: f64::from(threshold_bps),
+            touched: HashSet::new(),
+        }
+    }
+
+    /// A pool's state changed this block.
+    pub fn touch(&mut self, pool_id: &PoolIdentifier) {
+        self.touched.insert(pool_id.clone());
+    }
+
+    /// Compare every pair with a touched pool across `pools`, priced by
+    /// `price`, and clear the touched set.
+    pub fn detect<'a>(
+        &mut self,
+        chain_id: u64,
+        block_number: u64,
+        pools: impl Iterator<Item = &'a PoolMetadata>,
+        price: impl Fn(&PoolMetadata) -> Option<f64>,
+    ) -> Vec<ArbSignal> {
+        if self.touched.is_empty() {
+            return Vec::new();
+        }
+        let touched = std::mem::take(&mut self.touched);
+
+        let mut pairs: BTreeMap<(Address, Address), Vec<&PoolMetadata>> = BTreeMap::new();
+        for pool in pools {
+            pairs
+                .entry(ordered_pair(pool.token0, pool.token1))
+                .or_default()
+                .push(pool);
+        }
+        pairs.retain(|_, pools| {
+            pools.len() > 1 && pools.iter().any(|pool| touched.contains(&pool.pool_id))
+        });
+
+        let mut signals = Vec::new();
+        for ((token0, token1), pools) in pairs {
+            let priced = pools.into_iter().filter_map(|pool| {
+                let price = price(pool)?;
+                // Quote every pool as token1 per token0 of the ordered pair.
+                let price = if pool.token0 == token0 {
+                    price
+                } else {
+                    1.0 / price
+                };
+                (price.is_finite() && price > 0.0).then_some((pool, price))
+            });
+            let mut buy: Option<(&PoolMetadata, f64)> = None;
+            let mut sell: Option<(&PoolMetadata, f64)> = None;
+            for (pool, price) in priced {
+                if buy.is_none_or(|(_, best)| price < best) {
+                    buy = Some((pool, price));
+                }
+                if sell.is_none_or(|(_, best)| price > best) {
+                    sell = Some((pool, price));
+                }
+            }
+            let (Some((buy_pool, buy_price)), Some((sell_pool, sell_price))) = (buy, sell) else {
+                continue;
+            };
+            let spread_bps = (sell_price / buy_price - 1.0) * 10_000.0;
+            if buy_pool.pool_id == sell_pool.pool_id || spread_bps <= self.threshold_bps {
+                continue;
+            }
+            signals.push(ArbSignal {
+                chain_id,
+                block_number,
+                token0,
+                token1,
+                buy_pool: buy_pool.pool_id.to_hex(),
+                buy_price,
+                buy_fee: buy_pool.fee,
+                sell_pool: sell_pool.pool_id.to_hex(),
+                sell_price,
+                sell_fee: sell_pool.fee,
+                spread_bps,
+            });
+        }
+        signals
+    }
+}
+
+fn ordered_pair(a: Address, b: Address) -> (Address, Address) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Detection plus the queue to its publisher task.
+#[derive(Debug)]
+pub struct ArbSignals {
+    detector: ArbDetector,
+    tx: mpsc::Sender<ArbSignal>,
+}
+
+impl ArbSignals {
+    /// Start the publisher unless `ARB_SIGNAL_THRESHOLD_BPS` is unset or 0.
+    pub fn spawn_from_env() -> Option<Self> {
+        let threshold_bps = std::env::var("ARB_SIGNAL_THRESHOLD_BPS")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .filter(|&bps| bps > 0)?;
+        let nats_url =
+            std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(publish(rx, nats_url, threshold_bps));
+        Some(Self {
+            detector: ArbDetector::new(threshold_bps),
+            tx,
+        })
+    }
+
+    pub fn touch(&mut self, pool_id: &PoolIdentifier) {
+        self.detector.touch(pool_id);
+    }
+
+    /// Detect and queue this block's signals.
+    pub fn end_block<'a>(
+        &mut self,
+        block_number: u64,
+        pools: impl Iterator<Item = &'a PoolMetadata>,
+        price: impl Fn(&PoolMetadata) -> Option<f64>,
+    ) {
+        let chain_id = crate::chain::active().chain_id;
+        for signal in self.detector.detect(chain_id, block_number, pools, price) {
+            exex_metrics::record_liquidity_arb_signal();
+            if self.tx.try_send(signal).is_err() {
+                warn!("Arb signal queue full, dropping a signal");
+                exex_metrics::record_sink_error("liquidity");
+            }
+        }
+    }
+}
+
+async fn publish(mut rx: mpsc::Receiver<ArbSignal>, nats_url: String, threshold_bps: u32) {
+    let client = match async_nats::connect(&nats_url).await {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(
+                "Arb signal publisher could not connect to NATS at {}: {}",
+                nats_url, e
+            );
+            return;
+        }
+    };
+    let subject = subjects::arb_signals(&crate::chain::chain_name());
+    info!(subject = %subject, threshold_bps, "🎯 Publishing cross-pool arb signals");
+
+    while let Some(signal) = rx.recv().await {
+        let payload = serde_json::to_vec(&signal).expect("ArbSignal serializes");
+        if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+            warn!("Failed to publish arb signal: {}", e);
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Protocol;
+    use std::collections::HashMap;
+
+    fn pool(byte: u8, token0: Address, token1: Address, fee: u32) -> PoolMetadata {
+        PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([byte; 20])),
+            token0,
+            token1,
+            protocol: Protocol::UniswapV3,
+            factory: Address::ZERO,
+            tick_spacing: Some(10),
+            fee: Some(fee),
+            token0_decimals: Some(18),
+            token1_decimals: Some(6),
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }
+    }
+
+    #[test]
+    fn touched_pairs_beyond_the_threshold_signal() {
+        let weth = Address::from([1u8; 20]);
+        let usdc = Address::from([2u8; 20]);
+        let dai = Address::from([3u8; 20]);
+        let pools = [
+            pool(10, weth, usdc, 500),
+            pool(11, weth, usdc, 3000),
+            pool(12, weth, usdc, 100),
+            pool(20, weth, dai, 500),
+            pool(21, weth, dai, 3000),
+        ];
+        let prices: HashMap<PoolIdentifier, f64> = [
+            (10, 2000.0),
+            (11, 2010.0),
+            (12, 2004.0),
+            (20, 2000.0),
+            (21, 2100.0),
+        ]
+        .into_iter()
+        .map(|(byte, price)| (PoolIdentifier::Address(Address::from([byte; 20])), price))
+        .collect();
+        let price = |pool: &PoolMetadata| prices.get(&pool.pool_id).copied();
+        let mut detector = ArbDetector::new(30);
+
+        // Only the WETH/USDC pair was touched; the WETH/DAI spread is stale.
+        detector.touch(&pools[2].pool_id);
+        let signals = detector.detect(1, 100, pools.iter(), price);
+        assert_eq!(signals.len(), 1);
+        let signal = &signals[0];
+        assert_eq!((signal.token0, signal.token1), (weth, usdc));
+        assert_eq!(signal.buy_pool, pools[0].pool_id.to_hex());
+        assert_eq!(signal.sell_pool, pools[1].pool_id.to_hex());
+        assert_eq!((signal.buy_fee, signal.sell_fee), (Some(500), Some(3000)));
+        assert!((signal.spread_bps - 50.0).abs() < 1e-9);
+
+        // The touched set is cleared; a spread under the threshold is quiet.
+        assert!(detector.detect(1, 101, pools.iter(), price).is_empty());
+        let mut detector = ArbDetector::new(60);
+        detector.touch(&pools[0].pool_id);
+        assert!(detector.detect(1, 102, pools.iter(), price).is_empty());
+
+        let v2 = PoolState::V2 {
+            reserve0: 10u128.pow(18),
+            reserve1: 2_000_000_000,
+        };
+        assert_eq!(pool_price(&pools[0], &v2), Some(2000.0));
+    }
+}
@@ -0,0 +1,184 @@
+//! Historical backfill for live-added pools.
+//!
+//! When the whitelist adds a pool mid-stream, the consumer gets its current
+//! absolute state from the live-add seed (see `state_seed`) but no history. With
+//! `BACKFILL_BLOCKS` set, the ExEx also replays the pool's events over the last
+//! N blocks — read from the node's receipts and decoded exactly like the live
+//! path — as a tagged `BackfillStart` / `BackfillUpdate` / `BackfillComplete`
+//! stream, so the consumer can rebuild recent history without an indexer.
+//!
+//! The replay runs on a blocking task so the block loop is never stalled, and
+//! writes to the socket server's own backfill channel: it waits on that channel
+//! alone, never takes capacity from the live stream, and its frames reach
+//! clients only between block envelopes. A block whose receipts or state the
+//! node no longer has (pruned) is skipped; the pools it leaves a gap in are
+//! listed in `BackfillComplete::incomplete` and the rest of the replay goes on.
+//! Fluid pools are not replayed: their updates are storage-derived, not decoded.
+
+use crate::events::decode_log;
+use crate::pool_tracker::{PoolTracker, WhitelistUpdate};
+use crate::types::{ControlMessage, PoolIdentifier, PoolMetadata};
+use crate::{event_pool_metadata, state_at_block, LiquidityExEx};
+use alloy_consensus::{BlockHeader, TxReceipt};
+use alloy_primitives::Log;
+use reth::providers::StateProviderFactory;
+use reth_provider::{BlockReader, StateProviderBox};
+use tokio::sync::mpsc::Sender;
+use tracing::{info, warn};
+
+/// Backfill window from `BACKFILL_BLOCKS`; 0 (default) disables backfill.
+pub fn backfill_blocks_from_env() -> u64 {
+    std::env::var("BACKFILL_BLOCKS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+/// Replay `pools`' events over the `blocks` blocks ending at `to_block`
+/// (inclusive) on a blocking task, into the socket server's backfill channel.
+pub fn spawn_backfill<P>(
+    provider: P,
+    backfill_tx: Sender<ControlMessage>,
+    pools: Vec<PoolMetadata>,
+    to_block: u64,
+    blocks: u64,
+) where
+    P: StateProviderFactory + BlockReader + Send + Sync + 'static,
+    P::Receipt: TxReceipt<Log = Log>,
+{
+    let from_block = to_block.saturating_sub(blocks.saturating_sub(1));
+    tokio::task::spawn_blocking(move || {
+        let pool_count = pools.len();
+        match run_backfill(&provider, &backfill_tx, pools, from_block, to_block) {
+            Ok(num_updates) => info!(
+                pools = pool_count,
+                from_block, to_block, num_updates, "⏪ Backfill complete"
+            ),
+            Err(e) => warn!(error = %e, from_block, to_block, "Backfill aborted"),
+        }
+    });
+}
+
+fn run_backfill<P>(
+    provider: &P,
+    backfill_tx: &Sender<ControlMessage>,
+    pools: Vec<PoolMetadata>,
+    from_block: u64,
+    to_block: u64,
+) -> eyre::Result<u64>
+where
+    P: StateProviderFactory + BlockReader,
+    P::Receipt: TxReceipt<Log = Log>,
+{
+    // A private tracker holding only the backfilled pools gives the replay the
+    // live path's address / pool-id / event-mask filtering for free.
+    let mut tracker = PoolTracker::new();
+    tracker.queue_update(WhitelistUpdate::Add(pools));
+    let pool_ids: Vec<PoolIdentifier> = tracker.pools().map(|p| p.pool_id.clone()).collect();
+
+    send(
+        backfill_tx,
+        ControlMessage::BackfillStart {
+            pool_ids: pool_ids.clone(),
+            from_block,
+            to_block,
+        },
+    )?;
+
+    let mut num_updates = 0u64;
+    let mut incomplete: Vec<PoolIdentifier> = Vec::new();
+    for block_number in from_block..=to_block {
+        let (receipts, header) = match (
+            provider.receipts_by_block(block_number.into()),
+            provider.header_by_number(block_number),
+        ) {
+            (Ok(Some(receipts)), Ok(Some(header))) => (receipts, header),
+            (receipts, header) => {
+                // Which pools the block touched is unknowable without its
+                // receipts, so every pool's history has the gap.
+                warn!(
+                    block_number,
+                    receipts = ?receipts.map(|r| r.is_some()),
+                    header = ?header.map(|h| h.is_some()),
+                    "Backfill skipping unreadable block"
+                );
+                incomplete.clone_from(&pool_ids);
+                continue;
+            }
+        };
+        let block_timestamp = header.timestamp();
+        // Opened lazily: most blocks carry no event for the backfilled pools.
+        let mut state: Option<StateProviderBox> = None;
+        let mut state_missing = false;
+
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            for (log_index, log) in receipt.logs().iter().enumerate() {
+                if !tracker.is_tracked_address(&log.address) {
+                    continue;
+                }
+                let Some(event) = decode_log(log) else {
+                    continue;
+                };
+                if !LiquidityExEx::should_process_event(&event, &tracker) {
+                    continue;
+                }
+                if state.is_none() && !state_missing {
+                    match state_at_block(provider, block_number, "backfill") {
+                        Ok(opened) => state = Some(opened),
+                        Err(e) => {
+                            warn!(error = %e, "Backfill skipping block without state");
+                            state_missing = true;
+                        }
+                    }
+                }
+                let Some(state) = state.as_ref() else {
+                    if let Some(pool) = event_pool_metadata(&event, &tracker)
+                        .or_else(|| tracker.pool_metadata(&log.address))
+                    {
+                        if !incomplete.contains(&pool.pool_id) {
+                            incomplete.push(pool.pool_id.clone());
+                        }
+                    }
+                    continue;
+                };
+                if let Some(event) = LiquidityExEx::create_pool_update(
+                    event,
+                    block_number,
+                    block_timestamp,
+                    tx_index as u64,
+                    log_index as u64,
+                    false,
+                    state.as_ref(),
+                    &tracker,
+                ) {
+                    send(backfill_tx, ControlMessage::BackfillUpdate { event })?;
+                    num_updates += 1;
+                }
+            }
+        }
+    }
+
+    if !incomplete.is_empty() {
+        warn!(
+            pools = incomplete.len(),
+            from_block, to_block, "Backfill left gaps in some pools' history"
+        );
+    }
+    send(
+        backfill_tx,
+        ControlMessage::BackfillComplete {
+            pool_ids,
+            to_block,
+            num_updates,
+            incomplete,
+        },
+    )?;
+    Ok(num_updates)
+}
+
+/// Enqueue a backfill frame, waiting while the backfill channel is full.
+fn send(backfill_tx: &Sender<ControlMessage>, msg: ControlMessage) -> eyre::Result<()> {
+    backfill_tx
+        .blocking_send(msg)
+        .map_err(|_| eyre::eyre!("socket backfill channel closed"))
+}
@@ -0,0 +1,177 @@
+//! Low-balance threshold alerts.
+//!
+//! `BALANCE_MONITOR_THRESHOLDS` lists per-token minimums in raw units as
+//! `token:warning[:critical]`, comma-separated (the zero address is native
+//! ETH). When a balance falls below a threshold an alert is published on
+//! `alerts.balances.<id>` with its severity, so ops is paged before the
+//! executor runs dry mid-strategy. Alerts are edge-triggered: one message per
+//! severity change, and a `resolved` message once the balance is back above
+//! the warning level.
+
+use alloy_primitives::{Address, U256};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Critical,
+    Resolved,
+}
+
+/// Minimum balances for one token. `critical` is expected below `warning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Threshold {
+    pub warning: U256,
+    pub critical: Option<U256>,
+}
+
+/// NATS alert message.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BalanceAlert {
+    pub chain: String,
+    pub block_number: u64,
+    pub token: String,
+    pub severity: Severity,
+    pub raw_balance: String,
+    /// Threshold crossed (the warning level for `resolved`).
+    pub raw_threshold: String,
+    pub decimals: u8,
+    pub ts: u64,
+}
+
+/// Parse `BALANCE_MONITOR_THRESHOLDS`. Invalid entries are returned
+/// separately so the caller can log them.
+pub fn parse_thresholds(raw: &str) -> (HashMap<Address, Threshold>, Vec<String>) {
+    let mut thresholds = HashMap::new();
+    let mut invalid = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let mut parts = entry.split(':');
+        let token = parts.next().and_then(|s| s.parse::<Address>().ok());
+        let warning = parts.next().and_then(|s| s.parse::<U256>().ok());
+        let critical = parts.next().map(|s| s.parse::<U256>().ok());
+        match (token, warning, critical, parts.next()) {
+            (Some(token), Some(warning), None, None) => {
+                thresholds.insert(
+                    token,
+                    Threshold {
+                        warning,
+                        critical: None,
+                    },
+                );
+            }
+            (Some(token), Some(warning), Some(Some(critical)), None) => {
+                thresholds.insert(
+                    token,
+                    Threshold {
+                        warning,
+                        critical: Some(critical),
+                    },
+                );
+            }
+            _ => invalid.push(entry.to_string()),
+        }
+    }
+    (thresholds, invalid)
+}
+
+/// Per-token thresholds and the severity last alerted for each.
+#[derive(Debug, Default)]
+pub struct ThresholdAlerts {
+    thresholds: HashMap<Address, Threshold>,
+    /// Tokens currently below a threshold, with their severity.
+    active: HashMap<Address, Severity>,
+}
+
+impl ThresholdAlerts {
+    pub fn new(thresholds: HashMap<Address, Threshold>) -> Self {
+        Self {
+            thresholds,
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.thresholds.is_empty()
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Address> {
+        self.thresholds.keys()
+    }
+
+    /// Evaluate `token` at `balance`. Returns the new severity and the
+    /// threshold it refers to when the severity changed since the last call.
+    pub fn check(&mut self, token: Address, balance: U256) -> Option<(Severity, U256)> {
+        let threshold = self.thresholds.get(&token)?;
+        let level = match threshold.critical {
+            Some(critical) if balance < critical => Some((Severity::Critical, critical)),
+            _ if balance < threshold.warning => Some((Severity::Warning, threshold.warning)),
+            _ => None,
+        };
+        match level {
+            Some((severity, crossed)) => {
+                if self.active.insert(token, severity) == Some(severity) {
+                    return None;
+                }
+                Some((severity, crossed))
+            }
+            None => self
+                .active
+                .remove(&token)
+                .map(|_| (Severity::Resolved, threshold.warning)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+    #[test]
+    fn parses_thresholds_and_reports_invalid() {
+        let (thresholds, invalid) =
+            parse_thresholds(&format!("{USDC:#x}:5000:1000, bad:1, {USDC:#x}:x"));
+        assert_eq!(
+            thresholds[&USDC],
+            Threshold {
+                warning: U256::from(5_000u64),
+                critical: Some(U256::from(1_000u64)),
+            }
+        );
+        assert_eq!(invalid.len(), 2);
+    }
+
+    #[test]
+    fn alerts_are_edge_triggered() {
+        let mut alerts = ThresholdAlerts::new(HashMap::from([(
+            USDC,
+            Threshold {
+                warning: U256::from(5_000u64),
+                critical: Some(U256::from(1_000u64)),
+            },
+        )]));
+
+        assert_eq!(alerts.check(USDC, U256::from(9_000u64)), None);
+        assert_eq!(
+            alerts.check(USDC, U256::from(4_000u64)),
+            Some((Severity::Warning, U256::from(5_000u64)))
+        );
+        assert_eq!(
+            alerts.check(USDC, U256::from(3_000u64)),
+            None,
+            "still warning"
+        );
+        assert_eq!(
+            alerts.check(USDC, U256::from(500u64)),
+            Some((Severity::Critical, U256::from(1_000u64)))
+        );
+        assert_eq!(
+            alerts.check(USDC, U256::from(6_000u64)),
+            Some((Severity::Resolved, U256::from(5_000u64)))
+        );
+        assert_eq!(alerts.check(USDC, U256::from(7_000u64)), None);
+    }
+}
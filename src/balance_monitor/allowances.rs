@@ -0,0 +1,197 @@
+//! ERC20 allowance tracking for the executor.
+//!
+//! Decodes `Approval(owner, spender, value)` events where the executor is the
+//! owner or the spender and keeps the latest allowance per (token, owner,
+//! spender), so the hedger sees approvals running low without polling RPC.
+//!
+//! `Approval` carries the absolute allowance, so no seeding is needed: a pair
+//! appears once its first approval is observed. Reverts restore the previous
+//! observed value from a short per-pair history. Tokens that spend allowance in
+//! `transferFrom` without emitting `Approval` (OpenZeppelin v5, FiatToken) are
+//! only refreshed on the next explicit approval. Approvals are decoded on the
+//! dispatch walk (see `scan`).
+
+use alloy_primitives::{Address, Log, U256};
+use alloy_sol_types::{sol, SolEvent};
+use std::collections::HashMap;
+
+sol! {
+    #[derive(Debug)]
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+}
+
+/// Observed values kept per pair for reverts.
+const HISTORY_LEN: usize = 16;
+
+/// (token, owner, spender)
+pub type AllowanceKey = (Address, Address, Address);
+
+/// NATS message with the current allowance of each changed pair.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChainAllowanceSnapshot {
+    pub chain: String,
+    pub block_number: u64,
+    pub allowances: Vec<ChainTokenAllowance>,
+    pub ts: u64,
+}
+
+/// One (token, owner, spender) allowance. `raw_allowance` is `None` when a
+/// revert removed every observed approval for the pair (allowance unknown).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChainTokenAllowance {
+    pub token: String,
+    pub owner: String,
+    pub spender: String,
+    pub raw_allowance: Option<String>,
+}
+
+/// Latest observed allowance per pair, with a short history for reverts.
+#[derive(Debug, Default)]
+pub struct AllowanceTracker {
+    /// Oldest first: (block_number, allowance).
+    history: HashMap<AllowanceKey, Vec<(u64, U256)>>,
+}
+
+impl AllowanceTracker {
+    pub fn current(&self, key: &AllowanceKey) -> Option<U256> {
+        self.history.get(key)?.last().map(|(_, value)| *value)
+    }
+
+    /// Fold a notification: revert the blocks from `reverted_from` on, then
+    /// apply the committed blocks' approvals, oldest first. Returns the pairs
+    /// whose allowance changed, sorted.
+    pub fn process<'a>(
+        &mut self,
+        reverted_from: Option<u64>,
+        committed: impl IntoIterator<Item = (u64, &'a [(AllowanceKey, U256)])>,
+    ) -> Vec<AllowanceKey> {
+        let mut changed = Vec::new();
+        if let Some(block_number) = reverted_from {
+            changed.extend(self.revert_block(block_number));
+        }
+        for (block_number, approvals) in committed {
+            changed.extend(self.apply(block_number, approvals));
+        }
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+
+    /// Record a block's executor approvals. Returns the pairs touched.
+    pub fn apply(
+        &mut self,
+        block_number: u64,
+        approvals: &[(AllowanceKey, U256)],
+    ) -> Vec<AllowanceKey> {
+        let mut changed = Vec::new();
+        for &(key, value) in approvals {
+            let history = self.history.entry(key).or_default();
+            history.push((block_number, value));
+            if history.len() > HISTORY_LEN {
+                history.remove(0);
+            }
+            changed.push(key);
+        }
+        changed
+    }
+
+    /// Drop approvals observed at `block_number` or later. Returns the pairs
+    /// touched.
+    pub fn revert_block(&mut self, block_number: u64) -> Vec<AllowanceKey> {
+        let mut changed = Vec::new();
+        self.history.retain(|key, history| {
+            let before = history.len();
+            history.retain(|(block, _)| *block < block_number);
+            if history.len() != before {
+                changed.push(*key);
+            }
+            !history.is_empty()
+        });
+        changed
+    }
+
+    pub fn snapshot(
+        &self,
+        chain_id: &str,
+        block_number: u64,
+        keys: &[AllowanceKey],
+        ts: u64,
+    ) -> ChainAllowanceSnapshot {
+        ChainAllowanceSnapshot {
+            chain: chain_id.to_string(),
+            block_number,
+            allowances: keys
+                .iter()
+                .map(|key @ (token, owner, spender)| ChainTokenAllowance {
+                    token: format!("{token:#x}"),
+                    owner: format!("{owner:#x}"),
+                    spender: format!("{spender:#x}"),
+                    raw_allowance: self.current(key).map(|v| v.to_string()),
+                })
+                .collect(),
+            ts,
+        }
+    }
+}
+
+/// Decode an `Approval` the executor is the owner or the spender of.
+pub fn decode_approval(log: &Log, executor: Address) -> Option<(AllowanceKey, U256)> {
+    if log.topics().first() != Some(&Approval::SIGNATURE_HASH) {
+        return None;
+    }
+    let approval = Approval::decode_log(log).ok()?;
+    if approval.owner != executor && approval.spender != executor {
+        return None;
+    }
+    Some((
+        (log.address, approval.owner, approval.spender),
+        approval.value,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    const EXECUTOR: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const ROUTER: Address = address!("BEEF000000000000000000000000000000000000");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+    fn approval_log(owner: Address, spender: Address, value: u64) -> Log {
+        let event = Approval {
+            owner,
+            spender,
+            value: U256::from(value),
+        };
+        let data = event.encode_log_data();
+        Log::new(USDC, data.topics().to_vec(), data.data.clone()).unwrap()
+    }
+
+    fn approvals(logs: &[Log]) -> Vec<(AllowanceKey, U256)> {
+        logs.iter()
+            .filter_map(|log| decode_approval(log, EXECUTOR))
+            .collect()
+    }
+
+    #[test]
+    fn tracks_executor_approvals_and_reverts() {
+        let mut tracker = AllowanceTracker::default();
+        let key = (USDC, EXECUTOR, ROUTER);
+
+        let changed = tracker.apply(10, &approvals(&[approval_log(EXECUTOR, ROUTER, 1_000)]));
+        assert_eq!(changed, vec![key]);
+        tracker.apply(11, &approvals(&[approval_log(EXECUTOR, ROUTER, 400)]));
+        assert_eq!(tracker.current(&key), Some(U256::from(400u64)));
+
+        // Approvals between other parties are ignored.
+        assert!(tracker
+            .apply(11, &approvals(&[approval_log(ROUTER, USDC, 5)]))
+            .is_empty());
+
+        assert_eq!(tracker.revert_block(11), vec![key]);
+        assert_eq!(tracker.current(&key), Some(U256::from(1_000u64)));
+        tracker.revert_block(10);
+        assert_eq!(tracker.current(&key), None, "no observed approval left");
+    }
+}
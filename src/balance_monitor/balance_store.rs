@@ -0,0 +1,114 @@
+//! Balance map persistence across restarts.
+//!
+//! After every notification the in-memory balance map is written, with the
+//! block it is valid at, to `balance_monitor_balances.json` next to the token
+//! file. On restart the map is loaded and that block is handed to reth as the
+//! ExEx head, so only the blocks missed while down are replayed (as ordinary
+//! notifications) instead of reseeding from a latest state that races with
+//! in-flight notifications.
+
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Balance file name, placed next to the persisted token file.
+const BALANCES_FILE_NAME: &str = "balance_monitor_balances.json";
+
+/// Path of the balance file for a token file at `tokens_path`.
+pub fn balances_path(tokens_path: &Path) -> PathBuf {
+    tokens_path.with_file_name(BALANCES_FILE_NAME)
+}
+
+/// JSON format: `{ "block_number", "block_hash", "balances": { "0xaddr": "raw" } }`
+#[derive(Serialize, Deserialize)]
+struct PersistedBalances {
+    block_number: u64,
+    block_hash: B256,
+    balances: HashMap<String, String>,
+}
+
+/// Load the persisted map and the block it is valid at. `None` if missing or
+/// unreadable — the caller reseeds.
+pub fn load(path: &Path) -> Option<(BlockNumHash, HashMap<Address, U256>)> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let persisted: PersistedBalances = match serde_json::from_str(&content) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(error = %e, path = %path.display(), "ignoring unreadable persisted balances");
+            return None;
+        }
+    };
+    let mut balances = HashMap::new();
+    for (addr_str, raw) in persisted.balances {
+        match (addr_str.parse::<Address>(), raw.parse::<U256>()) {
+            (Ok(addr), Ok(value)) => {
+                balances.insert(addr, value);
+            }
+            _ => warn!(address = %addr_str, "skipping invalid persisted balance"),
+        }
+    }
+    Some((
+        BlockNumHash::new(persisted.block_number, persisted.block_hash),
+        balances,
+    ))
+}
+
+/// Atomic write: serialize → write to `.tmp` → rename over target.
+pub fn save(
+    path: &Path,
+    block: BlockNumHash,
+    balances: &HashMap<Address, U256>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("create dir: {e}"))?;
+    }
+    let persisted = PersistedBalances {
+        block_number: block.number,
+        block_hash: block.hash,
+        balances: balances
+            .iter()
+            .map(|(addr, value)| (format!("{addr:#x}"), value.to_string()))
+            .collect(),
+    };
+    let json = serde_json::to_string(&persisted).map_err(|e| format!("serialize: {e}"))?;
+
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, &json).map_err(|e| format!("write tmp: {e}"))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("rename: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn balances_roundtrip_with_block() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("balance_store_test_{}.json", std::process::id()));
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let block = BlockNumHash::new(21_000_000, B256::repeat_byte(0xAB));
+        let balances = HashMap::from([(usdc, U256::from(1_000_000u64))]);
+
+        assert!(load(&path).is_none());
+        save(&path, block, &balances).unwrap();
+        let (loaded_block, loaded) = load(&path).unwrap();
+        assert_eq!(loaded_block, block);
+        assert_eq!(loaded, balances);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn balances_file_sits_next_to_token_file() {
+        let tokens = PathBuf::from("/data/exex/balance_monitor_tokens.json");
+        assert_eq!(
+            balances_path(&tokens),
+            PathBuf::from("/data/exex/balance_monitor_balances.json")
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! `balanceOf` via an EVM call, for tokens whose storage slot is not the
+//! balance.
+//!
+//! Rebasing tokens (stETH, aTokens) derive the balance from shares and an index,
+//! and fee-on-transfer tokens credit less than the `Transfer` value, so neither
+//! slot reads nor event deltas are right for them. Tokens listed in
+//! `BALANCE_MONITOR_CALL_MODE_TOKENS` are marked call-mode in the
+//! `TokenTracker`; their balances skip the slot / delta paths and are re-read
+//! with a `balanceOf` call against latest state after every notification.
+
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::{sol, SolCall};
+use reth::providers::{BlockNumReader, HeaderProvider, StateProviderFactory};
+use reth_evm::{ConfigureEvm, Evm};
+use reth_node_api::FullNodeComponents;
+use reth_revm::database::StateProviderDatabase;
+
+sol! {
+    function balanceOf(address account) external view returns (uint256);
+}
+
+/// Parse a comma-separated token list (`BALANCE_MONITOR_CALL_MODE_TOKENS`).
+/// Invalid entries are returned separately so the caller can log them.
+pub fn parse_call_mode_tokens(raw: &str) -> (Vec<Address>, Vec<String>) {
+    let mut tokens = Vec::new();
+    let mut invalid = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match entry.parse::<Address>() {
+            Ok(token) => tokens.push(token),
+            Err(_) => invalid.push(entry.to_string()),
+        }
+    }
+    (tokens, invalid)
+}
+
+/// `holder`'s balance of each token, read with `balanceOf` calls executed
+/// against the latest block's state. One EVM instance serves all calls.
+pub fn call_balances<Node: FullNodeComponents>(
+    provider: &Node::Provider,
+    evm_config: &Node::Evm,
+    holder: Address,
+    tokens: &[Address],
+) -> eyre::Result<Vec<(Address, U256)>> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    let number = provider.best_block_number()?;
+    let header = provider
+        .header_by_number(number)?
+        .ok_or_else(|| eyre::eyre!("missing header for block {number}"))?;
+    let db = StateProviderDatabase::new(provider.latest()?);
+    let mut evm = evm_config.evm_with_env(db, evm_config.evm_env(&header)?);
+
+    let data = balanceOfCall { account: holder }.abi_encode();
+    let mut balances = Vec::with_capacity(tokens.len());
+    for &token in tokens {
+        let result = evm
+            .transact_system_call(holder, token, data.clone().into())
+            .map_err(|e| eyre::eyre!("balanceOf call on {token} failed: {e}"))?
+            .result;
+        if !result.is_success() {
+            eyre::bail!("balanceOf call on {token} reverted");
+        }
+        let output = result.output().cloned().unwrap_or_default();
+        balances.push((token, balanceOfCall::abi_decode_returns(&output)?));
+    }
+    Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn parses_call_mode_tokens_and_reports_invalid() {
+        let steth = address!("ae7ab96520DE3A18E5e111B5EaAb095312D7fE84");
+        let (tokens, invalid) = parse_call_mode_tokens(&format!(" {steth:#x}, nope ,,"));
+        assert_eq!(tokens, vec![steth]);
+        assert_eq!(invalid, vec!["nope".to_string()]);
+    }
+}
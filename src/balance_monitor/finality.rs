@@ -0,0 +1,72 @@
+//! Finalized balance view.
+//!
+//! The regular snapshots are the head view: fresh, but a reorg can rewrite
+//! them. This buffer keeps the full balance map as of each recent notification
+//! tip, dropping reverted ones, so the map as of the finalized block can be
+//! published separately on `balances.chain.<id>.finalized` for risk systems
+//! that need a reorg-safe number.
+
+use alloy_primitives::{Address, U256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Views kept at most; comfortably above the usual finalization lag.
+const MAX_VIEWS: usize = 128;
+
+/// Balance maps keyed by the block they are valid at.
+#[derive(Debug, Default)]
+pub struct FinalityBuffer {
+    views: BTreeMap<u64, HashMap<Address, U256>>,
+    /// Finalized block of the last published view.
+    last_published: Option<u64>,
+}
+
+impl FinalityBuffer {
+    /// Record the map as of `block_number`.
+    pub fn record(&mut self, block_number: u64, balances: &HashMap<Address, U256>) {
+        self.views.insert(block_number, balances.clone());
+        while self.views.len() > MAX_VIEWS {
+            self.views.pop_first();
+        }
+    }
+
+    /// Drop views at or above `block_number` (reorg handling).
+    pub fn revert_from(&mut self, block_number: u64) {
+        self.views.split_off(&block_number);
+    }
+
+    /// The map as of `finalized` — the latest view at or below it — if it
+    /// was not already returned for this finalized block. Views older than it
+    /// are pruned.
+    pub fn finalized_view(&mut self, finalized: u64) -> Option<&HashMap<Address, U256>> {
+        if self.last_published.is_some_and(|b| b >= finalized) {
+            return None;
+        }
+        let (&view_block, _) = self.views.range(..=finalized).next_back()?;
+        self.views = self.views.split_off(&view_block);
+        self.last_published = Some(finalized);
+        self.views.get(&view_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finalized_view_uses_latest_view_at_or_below_and_skips_reverted() {
+        let token = Address::repeat_byte(1);
+        let map = |v: u64| HashMap::from([(token, U256::from(v))]);
+        let mut buffer = FinalityBuffer::default();
+        buffer.record(10, &map(100));
+        buffer.record(12, &map(120));
+        buffer.record(13, &map(130));
+        buffer.revert_from(13);
+        buffer.record(13, &map(131));
+
+        assert_eq!(buffer.finalized_view(9), None, "nothing at or below");
+        assert_eq!(buffer.finalized_view(11), Some(&map(100)));
+        assert_eq!(buffer.finalized_view(11), None, "already published");
+        assert_eq!(buffer.finalized_view(13), Some(&map(131)));
+        assert_eq!(buffer.views.len(), 1, "older views pruned");
+    }
+}
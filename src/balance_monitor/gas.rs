@@ -0,0 +1,123 @@
+//! Gas spent by the executor's own transactions.
+//!
+//! For every committed block, each transaction sent by the executor costs
+//! `effective_gas_price × gas_used`, with gas used taken from the difference of
+//! consecutive cumulative receipt gas. The per-block sums are taken on the
+//! dispatch walk (see `scan`). Per-block spend and a running total are
+//! published next to the ETH balance so strategy PnL can net out execution
+//! costs. Blob fees are not included.
+//!
+//! The total is process-lifetime (it restarts at zero), and reverted blocks
+//! subtract what they added while they are still in the short per-block
+//! history.
+
+use alloy_primitives::U256;
+use std::collections::BTreeMap;
+
+/// Blocks of per-block spend kept for reverts.
+const HISTORY_BLOCKS: usize = 256;
+
+/// Executor gas spend in one block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockGas {
+    pub tx_count: u64,
+    pub gas_used: u64,
+    pub fee_wei: U256,
+}
+
+impl BlockGas {
+    /// Add one executor transaction.
+    pub fn add_tx(&mut self, gas_used: u64, effective_gas_price: u128) {
+        self.tx_count += 1;
+        self.gas_used += gas_used;
+        self.fee_wei = self
+            .fee_wei
+            .saturating_add(U256::from(effective_gas_price) * U256::from(gas_used));
+    }
+}
+
+/// NATS message with per-block and cumulative executor gas spend.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChainGasSpend {
+    pub chain: String,
+    pub block_number: u64,
+    pub tx_count: u64,
+    pub gas_used: u64,
+    pub raw_fee_wei: String,
+    pub raw_cumulative_fee_wei: String,
+    /// Native ETH balance after the block, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_eth_balance: Option<String>,
+    pub ts: u64,
+}
+
+/// Running executor gas spend with a short per-block history for reverts.
+#[derive(Debug, Default)]
+pub struct GasTracker {
+    per_block: BTreeMap<u64, BlockGas>,
+    cumulative_fee_wei: U256,
+}
+
+impl GasTracker {
+    pub fn cumulative_fee_wei(&self) -> U256 {
+        self.cumulative_fee_wei
+    }
+
+    /// Fold a notification: subtract the blocks from `reverted_from` on, then
+    /// add the committed blocks' spend, oldest first. Returns the committed
+    /// blocks in which the executor spent gas.
+    pub fn process(
+        &mut self,
+        reverted_from: Option<u64>,
+        committed: impl IntoIterator<Item = (u64, BlockGas)>,
+    ) -> Vec<(u64, BlockGas)> {
+        if let Some(block_number) = reverted_from {
+            self.revert_from(block_number);
+        }
+        let mut spent = Vec::new();
+        for (block_number, gas) in committed {
+            if gas.tx_count > 0 {
+                self.record(block_number, gas);
+                spent.push((block_number, gas));
+            }
+        }
+        spent
+    }
+
+    fn record(&mut self, block_number: u64, gas: BlockGas) {
+        self.cumulative_fee_wei = self.cumulative_fee_wei.saturating_add(gas.fee_wei);
+        self.per_block.insert(block_number, gas);
+        while self.per_block.len() > HISTORY_BLOCKS {
+            self.per_block.pop_first();
+        }
+    }
+
+    /// Subtract the spend of every recorded block at or above `block_number`.
+    fn revert_from(&mut self, block_number: u64) {
+        for (_, gas) in self.per_block.split_off(&block_number) {
+            self.cumulative_fee_wei = self.cumulative_fee_wei.saturating_sub(gas.fee_wei);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_executor_gas_and_reverts() {
+        let mut gas = BlockGas::default();
+        gas.add_tx(150_000, 10);
+        gas.add_tx(50_000, 20);
+        assert_eq!(gas.tx_count, 2);
+        assert_eq!(gas.gas_used, 200_000);
+        assert_eq!(gas.fee_wei, U256::from(150_000u64 * 10 + 50_000 * 20));
+
+        let mut tracker = GasTracker::default();
+        tracker.record(10, gas);
+        tracker.record(11, gas);
+        assert_eq!(tracker.cumulative_fee_wei(), gas.fee_wei * U256::from(2u64));
+        tracker.revert_from(11);
+        assert_eq!(tracker.cumulative_fee_wei(), gas.fee_wei);
+    }
+}
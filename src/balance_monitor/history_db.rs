@@ -0,0 +1,143 @@
+//! Postgres history of executor balances.
+//!
+//! Optional (`BALANCE_MONITOR_DATABASE_URL`): every notification that changes
+//! a tracked balance writes one row per changed token at the notification tip,
+//! so PnL can be charted and balance drift audited after the fact. Reverted
+//! blocks are deleted before the replacement chain is written, so the table
+//! only ever holds the canonical history.
+
+use alloy_primitives::{Address, U256};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub struct BalanceHistoryRow {
+    pub block_number: u64,
+    pub token_address: String,
+    pub raw_balance: String, // U256.to_string() decimal representation
+}
+
+impl BalanceHistoryRow {
+    /// Rows for `tokens` at `block_number` from the in-memory balance map.
+    /// Tokens without a known balance are skipped.
+    pub fn from_balances(
+        block_number: u64,
+        tokens: &[Address],
+        balances: &HashMap<Address, U256>,
+    ) -> Vec<Self> {
+        tokens
+            .iter()
+            .filter_map(|token| {
+                Some(Self {
+                    block_number,
+                    token_address: format!("{token:#x}"),
+                    raw_balance: balances.get(token)?.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct BalanceHistoryDb {
+    pool: PgPool,
+    chain_id: String,
+    executor: String,
+}
+
+impl BalanceHistoryDb {
+    pub async fn new(database_url: &str, chain_id: &str, executor: Address) -> eyre::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .min_connections(1)
+            .acquire_timeout(Duration::from_secs(60))
+            .idle_timeout(Duration::from_secs(300))
+            .max_lifetime(Duration::from_secs(1800))
+            .connect(database_url)
+            .await?;
+
+        let db = Self {
+            pool,
+            chain_id: chain_id.to_string(),
+            executor: format!("{executor:#x}"),
+        };
+        db.init_schema().await?;
+        Ok(db)
+    }
+
+    /// Connection pool, for the readiness probe.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    async fn init_schema(&self) -> eyre::Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS executor_balance_history (
+                chain_id        TEXT NOT NULL,
+                executor        TEXT NOT NULL,
+                block_number    BIGINT NOT NULL,
+                token_address   TEXT NOT NULL,
+                raw_balance     NUMERIC NOT NULL,
+                recorded_at_ms  BIGINT NOT NULL,
+                CONSTRAINT executor_balance_history_pkey
+                    PRIMARY KEY (chain_id, executor, token_address, block_number)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_balance_history_block_number ON executor_balance_history (chain_id, executor, block_number)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Upsert balance rows. Idempotent: a replayed block overwrites its rows.
+    pub async fn insert_balances(
+        &self,
+        rows: &[BalanceHistoryRow],
+        recorded_at_ms: u64,
+    ) -> eyre::Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut qb = sqlx::QueryBuilder::new(
+            "INSERT INTO executor_balance_history (chain_id, executor, block_number, token_address, raw_balance, recorded_at_ms) ",
+        );
+        qb.push_values(rows, |mut b, r| {
+            b.push_bind(&self.chain_id)
+                .push_bind(&self.executor)
+                .push_bind(r.block_number as i64)
+                .push_bind(&r.token_address)
+                .push_bind(&r.raw_balance)
+                .push_unseparated("::NUMERIC")
+                .push_bind(recorded_at_ms as i64);
+        });
+        qb.push(
+            " ON CONFLICT (chain_id, executor, token_address, block_number) \
+             DO UPDATE SET raw_balance = EXCLUDED.raw_balance, recorded_at_ms = EXCLUDED.recorded_at_ms",
+        );
+        qb.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Delete every row at or above `block_number` (reorg handling).
+    pub async fn delete_from_block(&self, block_number: u64) -> eyre::Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM executor_balance_history WHERE chain_id = $1 AND executor = $2 AND block_number >= $3",
+        )
+        .bind(&self.chain_id)
+        .bind(&self.executor)
+        .bind(block_number as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
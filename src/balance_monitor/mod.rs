@@ -1,29 +1,84 @@
 //! Balance Monitor ExEx.
 //!
 //! Monitors ERC20 Transfer events to/from a configured executor address,
-//! maintains running token balances, and publishes updates to NATS.
+//! maintains running token balances, and publishes updates to NATS. The native
+//! ETH balance, which moves without logs (internal calls, selfdestructs), is
+//! read from each notification's execution outcome instead. WETH9 wraps and
+//! unwraps, which emit `Deposit`/`Withdrawal` instead of `Transfer`, are
+//! applied to the WETH balance (see `weth`). Executor ERC20
+//! allowances are tracked from `Approval` events (see `allowances`). Balance
+//! changes can optionally be written to Postgres (see `history_db`). Tokens
+//! whose balance is not a plain storage slot can be read with `balanceOf`
+//! calls instead (see `call_balance`).
+//!
+//! Balances falling below configured minimums raise alerts on
+//! `alerts.balances.<id>` (see `alerts`). EIP-1967 implementation changes of
+//! tracked tokens raise alerts on `alerts.upgrades.<id>` (see `upgrades`).
+//!
+//! Gas paid by the executor's own transactions is published per block on
+//! `gas.chain.<id>` (see `gas`).
+//!
+//! Swaps the executor took part in are published as `SwapConfirmation`s on
+//! `swap.confirmed.<id>`, one message per swap log of a committed block, with
+//! the hash of the transaction it came from (see `swap_monitor`). Executor
+//! transactions that reverted or traded nothing go out as `SwapFailed` on
+//! `swap.failed.<id>`.
+//!
+//! A finalized view — balances as of the finalized block — is published on
+//! `balances.chain.<id>.finalized` as finalization advances (see `finality`).
+//!
+//! Consumers can request a full snapshot at any time on
+//! `balances.chain.<id>.request` (NATS request/reply).
+//!
+//! Transfers, approvals, gas and swaps are decoded on the Dispatch ExEx's
+//! walk and taken from there per block (see `scan`); this ExEx itself only
+//! reads state at each tip.
 //!
 //! Token tracking set is append-only (persisted to JSON) and populated from
-//! whitelist NATS subscription. Initial balances are seeded from Reth DB.
-
+//! the whitelist NATS subscription and explicit token lists on
+//! `tokens.chain.<id>`. The balance map is persisted with the block it
+//! is valid at and resumed from on restart (see `balance_store`); without one,
+//! initial balances are seeded from Reth DB at the best block.
+
+pub mod alerts;
+pub mod allowances;
+pub mod balance_store;
+pub mod call_balance;
+pub mod finality;
+pub mod gas;
+pub mod history_db;
+pub mod scan;
 pub mod slots;
 pub mod token_tracker;
+pub mod upgrades;
+pub mod weth;
 
-use alloy_consensus::{transaction::TxHashRef, BlockHeader, TxReceipt};
-use alloy_primitives::{Address, Log, U256};
+use alloy_consensus::BlockHeader;
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{Address, B256, U256};
 use futures::{StreamExt, TryStreamExt};
-use reth::providers::StateProviderFactory;
-use reth_exex::{ExExContext, ExExEvent, ExExNotification};
-use reth_node_api::{BlockBody, FullNodeComponents, NodePrimitives};
+use reth::providers::{BlockHashReader, BlockIdReader, BlockNumReader, StateProviderFactory};
+use reth_exex::{ExExContext, ExExEvent, ExExHead, ExExNotification, ExExNotificationsStream};
+use reth_node_api::{FullNodeComponents, NodePrimitives};
 #[cfg(test)]
 use rust_decimal::Decimal;
+use scan::{NotificationScans, ScanStore};
+use slots::BalanceSlot;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
 use token_tracker::TokenTracker;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
 
-use crate::swap_monitor::{self, SwapConfirmation};
-use crate::transfers::events::decode_transfer;
+use crate::exex_metrics;
+use crate::finished_height::FinishedHeightBatcher;
+use crate::health;
+use crate::pool_metadata_db::PoolMetadataDb;
+use crate::shutdown::{self, ShutdownSignal};
+use crate::subjects;
+use crate::swap_monitor::PoolTokens;
+use crate::transfers::events::DecodedTransfer;
 
 /// NATS message matching `ChainBalanceSnapshot` schema in `foundation_messaging`.
 ///
@@ -76,6 +131,19 @@ pub fn u256_to_decimal(raw: U256, decimals: u8) -> Decimal {
 /// individual publishes are lost.
 const DEFAULT_FULL_SNAPSHOT_INTERVAL_BLOCKS: u64 = 5;
 
+/// Key for the executor's native ETH balance in the balance map and in
+/// snapshots (`token` = zero address, 18 decimals).
+pub const NATIVE_ETH: Address = Address::ZERO;
+
+/// Default on-chain reconciliation interval in blocks. Delta accumulation
+/// drifts on any missed event (rebasing / non-standard tokens), so the map is
+/// periodically re-read from storage and corrected.
+const DEFAULT_RECONCILE_INTERVAL_BLOCKS: u64 = 100;
+
+/// Holders probed per token before balance slot discovery gives up and the
+/// token stays on the default slot.
+const MAX_SLOT_DISCOVERY_ATTEMPTS: u32 = 3;
+
 /// Startup whitelist wait before seeding persisted balances anyway.
 const DEFAULT_STARTUP_WHITELIST_TIMEOUT_MS: u64 = 2_000;
 
@@ -91,6 +159,9 @@ const WHITELIST_RESUB_MAX_RETRIES: u32 = 5;
 /// Backoff base for whitelist resubscribe retries (doubles each attempt).
 const WHITELIST_RESUB_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
 
+/// `FinishedHeight` sink of the persisted balance map.
+const BALANCES_SINK: &str = "balances";
+
 /// Build a full snapshot of all tracked token balances.
 fn build_full_snapshot(
     chain_id: &str,
@@ -109,6 +180,42 @@ fn build_full_snapshot(
                 raw_total: None,
             }
         })
+        .chain(balances.get(&NATIVE_ETH).map(|raw| ChainTokenBalance {
+            token: format!("{NATIVE_ETH:#x}"),
+            raw_available: raw.to_string(),
+            decimals: 18,
+            raw_total: None,
+        }))
+        .collect();
+
+    ChainBalanceSnapshot {
+        chain: chain_id.to_string(),
+        block_number,
+        balances: entries,
+        ts: now_ms(),
+    }
+}
+
+/// Build a snapshot of only the given tokens' balances.
+fn build_partial_snapshot(
+    chain_id: &str,
+    block_number: u64,
+    tokens: &[Address],
+    tracker: &TokenTracker,
+    balances: &HashMap<Address, U256>,
+) -> ChainBalanceSnapshot {
+    let entries: Vec<ChainTokenBalance> = tokens
+        .iter()
+        .map(|token| {
+            let raw = balances.get(token).copied().unwrap_or(U256::ZERO);
+            let decimals = tracker.decimals(token).unwrap_or(18);
+            ChainTokenBalance {
+                token: format!("{token:#x}"),
+                raw_available: raw.to_string(),
+                decimals,
+                raw_total: None,
+            }
+        })
         .collect();
 
     ChainBalanceSnapshot {
@@ -133,6 +240,7 @@ async fn publish_with_retry(client: &async_nats::Client, subject: &str, payload:
                     tokio::time::sleep(PUBLISH_RETRY_DELAY).await;
                 } else {
                     warn!(error = %e, attempts = PUBLISH_MAX_RETRIES + 1, "NATS publish failed after all retries");
+                    exex_metrics::record_sink_error("balance_monitor");
                 }
             }
         }
@@ -140,8 +248,104 @@ async fn publish_with_retry(client: &async_nats::Client, subject: &str, payload:
     false
 }
 
-/// Run the balance monitor ExEx.
-pub async fn balance_monitor_exex<Node>(mut ctx: ExExContext<Node>) -> eyre::Result<()>
+/// Check `tokens` against their thresholds and publish an alert for every
+/// severity change.
+#[allow(clippy::too_many_arguments)]
+async fn publish_threshold_alerts(
+    client: &async_nats::Client,
+    subject: &str,
+    chain_id: &str,
+    block_number: u64,
+    tokens: &[Address],
+    tracker: &TokenTracker,
+    balances: &HashMap<Address, U256>,
+    threshold_alerts: &mut alerts::ThresholdAlerts,
+) {
+    if threshold_alerts.is_empty() {
+        return;
+    }
+    for &token in tokens {
+        let balance = balances.get(&token).copied().unwrap_or(U256::ZERO);
+        let Some((severity, threshold)) = threshold_alerts.check(token, balance) else {
+            continue;
+        };
+        let alert = alerts::BalanceAlert {
+            chain: chain_id.to_string(),
+            block_number,
+            token: format!("{token:#x}"),
+            severity,
+            raw_balance: balance.to_string(),
+            raw_threshold: threshold.to_string(),
+            decimals: tracker.decimals(&token).unwrap_or(18),
+            ts: now_ms(),
+        };
+        warn!(
+            token = %token,
+            severity = ?severity,
+            balance = %balance,
+            threshold = %threshold,
+            block = block_number,
+            "balance threshold alert"
+        );
+        let payload = serde_json::to_vec(&alert).expect("BalanceAlert serializes");
+        publish_with_retry(client, subject, payload).await;
+    }
+}
+
+/// Publish an alert for every implementation change of a tracked token in
+/// the committed chain.
+async fn publish_proxy_upgrades<N: NodePrimitives>(
+    client: &async_nats::Client,
+    subject: &str,
+    chain_id: &str,
+    provider: &impl StateProviderFactory,
+    chain: &reth_provider::Chain<N>,
+    tracker: &TokenTracker,
+) {
+    let found = match upgrades::proxy_upgrades(provider, chain, tracker) {
+        Ok(found) => found,
+        Err(e) => {
+            warn!(error = %e, "proxy upgrade check failed");
+            return;
+        }
+    };
+    for upgrade in found {
+        let alert = upgrades::ProxyUpgradeAlert {
+            chain: chain_id.to_string(),
+            block_number: upgrade.block_number,
+            token: format!("{:#x}", upgrade.token),
+            previous_implementation: upgrade.previous_implementation.map(|i| format!("{i:#x}")),
+            implementation: upgrade.implementation.map(|i| format!("{i:#x}")),
+            balance_slot: tracker.balance_slot(&upgrade.token),
+            decimals: tracker.decimals(&upgrade.token).unwrap_or(18),
+            ts: now_ms(),
+        };
+        warn!(
+            token = %upgrade.token,
+            previous = ?upgrade.previous_implementation,
+            implementation = ?upgrade.implementation,
+            block = upgrade.block_number,
+            "tracked token proxy upgraded"
+        );
+        let payload = serde_json::to_vec(&alert).expect("ProxyUpgradeAlert serializes");
+        publish_with_retry(client, subject, payload).await;
+    }
+}
+
+/// The executor whose balances are monitored, from `BALANCE_MONITOR_ADDRESS`.
+pub fn executor_address() -> eyre::Result<Address> {
+    std::env::var("BALANCE_MONITOR_ADDRESS")
+        .map_err(|_| eyre::eyre!("BALANCE_MONITOR_ADDRESS env var required"))?
+        .parse()
+        .map_err(|e| eyre::eyre!("invalid BALANCE_MONITOR_ADDRESS: {e}"))
+}
+
+/// Run the balance monitor ExEx, on the per-block results the Dispatch ExEx's
+/// handlers put in `scans`.
+pub async fn balance_monitor_exex<Node>(
+    mut ctx: ExExContext<Node>,
+    scans: Arc<ScanStore>,
+) -> eyre::Result<()>
 where
     Node: FullNodeComponents,
     Node::Provider: StateProviderFactory,
@@ -150,17 +354,15 @@ where
 
     // ── Config ──────────────────────────────────────────────────────────
 
-    let executor_address: Address = std::env::var("BALANCE_MONITOR_ADDRESS")
-        .map_err(|_| eyre::eyre!("BALANCE_MONITOR_ADDRESS env var required"))?
-        .parse()
-        .map_err(|e| eyre::eyre!("invalid BALANCE_MONITOR_ADDRESS: {e}"))?;
+    let executor_address = executor_address()?;
 
-    let chain_id = std::env::var("BALANCE_MONITOR_CHAIN_ID").unwrap_or_else(|_| "1".to_string());
+    let chain_id = std::env::var("BALANCE_MONITOR_CHAIN_ID")
+        .unwrap_or_else(|_| crate::chain::active().chain_id.to_string());
 
     let nats_url =
         std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
 
-    let chain = std::env::var("CHAIN").unwrap_or_else(|_| "ethereum".to_string());
+    let chain = crate::chain::chain_name();
 
     let full_snapshot_interval_blocks =
         std::env::var("BALANCE_MONITOR_FULL_SNAPSHOT_INTERVAL_BLOCKS")
@@ -169,6 +371,11 @@ where
             .filter(|v| *v > 0)
             .unwrap_or(DEFAULT_FULL_SNAPSHOT_INTERVAL_BLOCKS);
 
+    let reconcile_interval_blocks = std::env::var("BALANCE_MONITOR_RECONCILE_INTERVAL_BLOCKS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_RECONCILE_INTERVAL_BLOCKS);
+
     let startup_whitelist_timeout_ms =
         std::env::var("BALANCE_MONITOR_STARTUP_WHITELIST_TIMEOUT_MS")
             .ok()
@@ -185,17 +392,54 @@ where
             p
         });
 
-    let nats_subject = format!("balances.chain.{chain_id}");
-    let swap_subject = format!("swap.confirmed.{chain_id}");
+    let balances_path = balance_store::balances_path(&persist_path);
+
+    let history_database_url = std::env::var("BALANCE_MONITOR_DATABASE_URL").ok();
+
+    let (call_mode_tokens, invalid_call_mode_tokens) = call_balance::parse_call_mode_tokens(
+        &std::env::var("BALANCE_MONITOR_CALL_MODE_TOKENS").unwrap_or_default(),
+    );
+    for entry in &invalid_call_mode_tokens {
+        warn!(entry = %entry, "ignoring invalid BALANCE_MONITOR_CALL_MODE_TOKENS entry");
+    }
+
+    let (thresholds, invalid_thresholds) =
+        alerts::parse_thresholds(&std::env::var("BALANCE_MONITOR_THRESHOLDS").unwrap_or_default());
+    for entry in &invalid_thresholds {
+        warn!(entry = %entry, "ignoring invalid BALANCE_MONITOR_THRESHOLDS entry");
+    }
+    let mut threshold_alerts = alerts::ThresholdAlerts::new(thresholds);
+
+    let nats_subject = subjects::balances(&chain_id, None);
+    let finalized_subject = subjects::balances(&chain_id, Some("finalized"));
+    let swap_subject = subjects::swap("confirmed", &chain_id);
+    let swap_failed_subject = subjects::swap("failed", &chain_id);
+    let allowance_subject = subjects::allowances(&chain_id);
+    let gas_subject = subjects::gas(&chain_id);
+    let alert_subject = subjects::balance_alerts(&chain_id);
+    let upgrade_subject = subjects::upgrade_alerts(&chain_id);
+    let balance_request_subject = subjects::balances(&chain_id, Some("request"));
 
     info!(
         executor = %executor_address,
         chain_id = %chain_id,
         persist_path = %persist_path.display(),
+        balances_path = %balances_path.display(),
         nats_subject = %nats_subject,
+        finalized_subject = %finalized_subject,
         swap_subject = %swap_subject,
+        swap_failed_subject = %swap_failed_subject,
+        allowance_subject = %allowance_subject,
+        gas_subject = %gas_subject,
+        alert_subject = %alert_subject,
+        upgrade_subject = %upgrade_subject,
+        balance_request_subject = %balance_request_subject,
         full_snapshot_interval_blocks,
+        reconcile_interval_blocks,
         startup_whitelist_timeout_ms,
+        balance_history = history_database_url.is_some(),
+        call_mode_tokens = call_mode_tokens.len(),
+        thresholds = threshold_alerts.tokens().count(),
         "balance monitor + swap monitor config"
     );
 
@@ -203,14 +447,57 @@ where
 
     let nats_client = async_nats::connect(&nats_url).await?;
     info!("NATS connected for balance monitor");
+    health::watch_nats("balance_monitor.nats", nats_client.clone());
+
+    // ── Balance history (optional) ──────────────────────────────────────
+
+    let history_db = match &history_database_url {
+        Some(url) => {
+            let db = history_db::BalanceHistoryDb::new(url, &chain_id, executor_address).await?;
+            info!("Connected to PostgreSQL for balance history");
+            health::watch_postgres("balance_monitor.db", db.pool().clone());
+            Some(db)
+        }
+        None => None,
+    };
 
     // ── Token tracker ───────────────────────────────────────────────────
 
     let mut tracker = TokenTracker::new(persist_path);
+    tracker.set_call_mode(call_mode_tokens);
+    let mut allowances = allowances::AllowanceTracker::default();
+    let mut gas_tracker = gas::GasTracker::default();
+    // Swap confirmation token pairs: whitelist first, pool-creations DB for
+    // the pools it does not carry.
+    let mut pool_tokens = PoolTokens::default();
+    let pool_metadata_db = match PoolMetadataDb::from_env().await {
+        Ok(db) => db,
+        Err(e) => {
+            warn!(error = %e, "pool metadata DB unavailable; swap confirmations use whitelist tokens only");
+            None
+        }
+    };
+    let mut finality = finality::FinalityBuffer::default();
+    let mut slot_discovery_attempts: HashMap<Address, u32> = HashMap::new();
+
+    // ── Snapshot requests (consumer resync) ─────────────────────────────
+
+    let mut snapshot_request_sub = Some(
+        nats_client
+            .subscribe(balance_request_subject.clone())
+            .await?,
+    );
+    info!(subject = %balance_request_subject, "subscribed to balance snapshot requests");
+
+    // ── Token list subscription (explicit token discovery) ──────────────
+
+    let token_list_subject = subjects::token_list(&chain_id);
+    let mut token_list_sub = Some(nats_client.subscribe(token_list_subject.clone()).await?);
+    info!(subject = %token_list_subject, "subscribed to token list for token discovery");
 
     // ── Whitelist subscription (for token discovery) ────────────────────
 
-    let whitelist_subject = format!("whitelist.pools.{chain}.full");
+    let whitelist_subject = subjects::whitelist_pools(&chain, "full");
     let mut whitelist_sub = Some(nats_client.subscribe(whitelist_subject.clone()).await?);
     info!(subject = %whitelist_subject, "subscribed to whitelist for token discovery");
 
@@ -218,15 +505,12 @@ where
     // seeding balances. Persisted tokens still provide a safe startup fallback.
     let reseed_payload = br#"{"source":"balance_monitor"}"#.to_vec();
     if let Err(e) = nats_client
-        .publish(
-            "whitelist.reseed".to_string(),
-            reseed_payload.clone().into(),
-        )
+        .publish(subjects::whitelist_reseed(), reseed_payload.clone().into())
         .await
     {
         warn!(error = %e, "failed to request whitelist reseed");
     }
-    let snapshot_request_subject = format!("whitelist.snapshot.request.{chain}");
+    let snapshot_request_subject = subjects::whitelist_snapshot_request(&chain);
     if let Err(e) = nats_client
         .publish(snapshot_request_subject.clone(), reseed_payload.into())
         .await
@@ -242,7 +526,8 @@ where
         .await
         {
             Ok(Some(msg)) => {
-                let new_tokens = process_whitelist_message(&msg.payload, &mut tracker);
+                let new_tokens =
+                    process_whitelist_message(&msg.payload, &mut tracker, &mut pool_tokens);
                 info!(
                     new_tokens = new_tokens.len(),
                     total = tracker.len(),
@@ -260,17 +545,53 @@ where
 
     // ── In-memory balance map ───────────────────────────────────────────
 
-    let mut balances: HashMap<Address, U256> = HashMap::new();
+    // Resume from the persisted map when there is one; reth then replays the
+    // blocks after it. Otherwise seed at the best block and start there, so
+    // the seed and the first notification agree on the starting state.
+    let (head, mut balances) = match balance_store::load(&balances_path) {
+        Some((head, balances)) => {
+            info!(
+                block = head.number,
+                tokens = balances.len(),
+                "resuming from persisted balances"
+            );
+            (head, balances)
+        }
+        None => {
+            let number = ctx.provider().best_block_number()?;
+            let hash = ctx
+                .provider()
+                .block_hash(number)?
+                .ok_or_else(|| eyre::eyre!("missing hash for best block {number}"))?;
+            (BlockNumHash::new(number, hash), HashMap::new())
+        }
+    };
 
-    // Seed tracked tokens from Reth DB after the startup whitelist barrier.
-    seed_balances_from_db(ctx.provider(), executor_address, &tracker, &mut balances)?;
+    // Seed tracked tokens missing from the map (all of them on a fresh start,
+    // tokens discovered since the last run otherwise) at the head block.
+    seed_balances_from_db(
+        ctx.provider(),
+        head.number,
+        executor_address,
+        &tracker,
+        &mut balances,
+    )?;
+    refresh_call_balances::<Node>(
+        ctx.provider(),
+        ctx.evm_config(),
+        executor_address,
+        &tracker,
+        &mut balances,
+    )?;
     info!(
         tokens = tracker.len(),
+        block = head.number,
         "seeded initial balances from Reth DB"
     );
+    ctx.notifications.set_with_head(ExExHead::new(head));
 
     if tracker.len() > 0 {
-        let snapshot = build_full_snapshot(&chain_id, 0, &tracker, &balances);
+        let snapshot = build_full_snapshot(&chain_id, head.number, &tracker, &balances);
         let payload = serde_json::to_vec(&snapshot).expect("ChainBalanceSnapshot serializes");
         if publish_with_retry(&nats_client, &nats_subject, payload).await {
             info!(
@@ -280,15 +601,39 @@ where
         }
     }
 
+    // Alert immediately if the executor starts out below a threshold.
+    let threshold_tokens: Vec<Address> = threshold_alerts.tokens().copied().collect();
+    publish_threshold_alerts(
+        &nats_client,
+        &alert_subject,
+        &chain_id,
+        head.number,
+        &threshold_tokens,
+        &tracker,
+        &balances,
+        &mut threshold_alerts,
+    )
+    .await;
+
     // ── Stats ───────────────────────────────────────────────────────────
 
     let mut blocks_processed: u64 = 0;
+    // Tip of the last processed notification; the block full snapshots are
+    // valid at.
+    let mut last_block_number: u64 = head.number;
+    // Block the persisted map is valid at.
+    let mut last_head = head;
     let mut updates_published: u64 = 0;
 
     // ── Main loop ───────────────────────────────────────────────────────
 
+    let mut finished = FinishedHeightBatcher::from_env([BALANCES_SINK]);
+    let mut shutdown = ShutdownSignal::new("balance_monitor", ctx.task_executor());
     loop {
         tokio::select! {
+            // Node shutdown: stop between notifications, then flush below.
+            () = shutdown.wait() => break,
+
             // ExEx block notifications
             notification = ctx.notifications.try_next() => {
                 let notification = match notification? {
@@ -296,54 +641,285 @@ where
                     None => break, // stream ended
                 };
 
-                let changed = process_notification(
-                    &notification,
+                // Spans are parented explicitly: the loop body awaits, so
+                // `block_span` is never entered, but it still closes with
+                // the iteration and so times the whole notification.
+                let block_span = info_span!(
+                    "balance_monitor.block",
+                    block = notification_tip_block(&notification)
+                );
+                let scanned = scans
+                    .notification(&notification)
+                    .instrument(info_span!(parent: &block_span, "balance_monitor.scans"))
+                    .await;
+                let mut changed = apply_scans(&scanned, executor_address, &tracker, &mut balances);
+
+                // Blocks the dispatch walk did not scan moved balances we
+                // never saw; re-read them all at the new head.
+                if !scanned.missing.is_empty() {
+                    let head = notification_head(&notification).number;
+                    warn!(
+                        blocks = scanned.missing.len(),
+                        head,
+                        "blocks without a dispatch scan, reconciling balances from state"
+                    );
+                    match reconcile_balances(
+                        ctx.provider(),
+                        head,
+                        executor_address,
+                        &tracker,
+                        &mut balances,
+                    ) {
+                        Ok(corrected) => changed.extend(corrected),
+                        Err(e) => warn!(error = %e, block = head, "balance reconciliation failed"),
+                    }
+                    changed.sort_unstable();
+                    changed.dedup();
+                }
+
+                // Native ETH has no logs (internal calls, selfdestructs,
+                // coinbase payments); take it from the execution outcome.
+                if let Some(native) = native_balance_after(&notification, executor_address) {
+                    if balances.insert(NATIVE_ETH, native) != Some(native) {
+                        changed.push(NATIVE_ETH);
+                    }
+                }
+
+                // Call-mode tokens move without matching events (rebases,
+                // transfer fees); re-read them at latest after every block.
+                match refresh_call_balances::<Node>(
+                    ctx.provider(),
+                    ctx.evm_config(),
                     executor_address,
                     &tracker,
                     &mut balances,
-                );
+                ) {
+                    Ok(refreshed) if !refreshed.is_empty() => {
+                        changed.extend(refreshed);
+                        changed.sort_unstable();
+                        changed.dedup();
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(error = %e, "call-mode balance refresh failed"),
+                }
 
-                // Publish snapshot for changed tokens.
-                if !changed.is_empty() {
-                    let block_number = notification_tip_block(&notification);
-                    let entries: Vec<ChainTokenBalance> = changed
+                // ── Balance slot discovery ───────────────────────────────
+                // Tokens without a known slot are probed against a recipient
+                // of one of their transfers; a newly found slot reseeds the
+                // balance, since it was read from the default slot so far.
+                if !scanned.committed.is_empty() {
+                    let holders = scanned
+                        .committed
                         .iter()
-                        .map(|token| {
-                            let raw = balances.get(token).copied().unwrap_or(U256::ZERO);
-                            let decimals = tracker.decimals(token).unwrap_or(18);
-                            ChainTokenBalance {
-                                token: format!("{token:#x}"),
-                                raw_available: raw.to_string(),
-                                decimals,
-                                raw_total: None,
-                            }
+                        .flat_map(|(block_number, scan, _)| {
+                            slot_discovery_holders(
+                                *block_number,
+                                &scan.holders,
+                                &tracker,
+                                &slot_discovery_attempts,
+                            )
                         })
-                        .collect();
+                        .collect::<HashMap<_, _>>();
+                    for (token, (block_number, holder, min_balance)) in holders {
+                        *slot_discovery_attempts.entry(token).or_default() += 1;
+                        match discover_token_slot(
+                            ctx.provider(),
+                            block_number,
+                            token,
+                            holder,
+                            min_balance,
+                            &mut tracker,
+                        ) {
+                            Ok(Some(slot)) => {
+                                info!(token = %token, slot = ?slot, "discovered balance slot");
+                                let before = balances.get(&token).copied();
+                                match seed_token_balance(
+                                    ctx.provider(),
+                                    executor_address,
+                                    token,
+                                    &tracker,
+                                    &mut balances,
+                                ) {
+                                    Ok(()) if balances.get(&token).copied() != before => {
+                                        changed.push(token);
+                                    }
+                                    Ok(()) => {}
+                                    Err(e) => warn!(error = %e, token = %token, "failed to reseed balance after slot discovery"),
+                                }
+                            }
+                            Ok(None) if slot_discovery_attempts[&token] >= MAX_SLOT_DISCOVERY_ATTEMPTS => {
+                                warn!(token = %token, "balance slot discovery gave up, keeping default slot");
+                            }
+                            Ok(None) => debug!(token = %token, holder = %holder, "balance slot not found for holder"),
+                            Err(e) => warn!(error = %e, token = %token, "balance slot discovery failed"),
+                        }
+                    }
+                    changed.sort_unstable();
+                    changed.dedup();
+                }
 
-                    let snapshot = ChainBalanceSnapshot {
-                        chain: chain_id.clone(),
+                // ── Proxy upgrades ───────────────────────────────────────
+                if let Some(committed_chain) = notification.committed_chain() {
+                    publish_proxy_upgrades(
+                        &nats_client,
+                        &upgrade_subject,
+                        &chain_id,
+                        ctx.provider(),
+                        &committed_chain,
+                        &tracker,
+                    )
+                    .await;
+                }
+
+                // Publish snapshot for changed tokens.
+                if !changed.is_empty() {
+                    let block_number = notification_tip_block(&notification);
+                    let snapshot = build_partial_snapshot(
+                        &chain_id,
                         block_number,
-                        balances: entries,
-                        ts: now_ms(),
-                    };
+                        &changed,
+                        &tracker,
+                        &balances,
+                    );
 
                     let payload = serde_json::to_vec(&snapshot)
                         .expect("ChainBalanceSnapshot serializes");
-                    if publish_with_retry(&nats_client, &nats_subject, payload).await {
+                    if publish_with_retry(&nats_client, &nats_subject, payload)
+                        .instrument(info_span!(parent: &block_span, "balance_monitor.publish"))
+                        .await
+                    {
                         updates_published += changed.len() as u64;
+                        exex_metrics::record_balance_updates_published(changed.len() as u64);
                         debug!(
                             changed = changed.len(),
                             block = notification_tip_block(&notification),
                             "published balance snapshot"
                         );
                     }
+                    publish_threshold_alerts(
+                        &nats_client,
+                        &alert_subject,
+                        &chain_id,
+                        block_number,
+                        &changed,
+                        &tracker,
+                        &balances,
+                        &mut threshold_alerts,
+                    )
+                    .await;
                 }
 
-                // ── Swap confirmation scanning ───────────────────────────
-                let swap_confirmations = scan_swaps_in_notification(
-                    &notification,
-                    executor_address,
+                // ── Balance history ──────────────────────────────────────
+                // Drop reverted blocks first, then record the new tip.
+                if let Some(db) = &history_db {
+                    if let Some(old) = notification.reverted_chain() {
+                        if let Err(e) = db.delete_from_block(old.first().number()).await {
+                            warn!(error = %e, block = old.first().number(), "failed to delete reverted balance history");
+                        }
+                    }
+                    if notification.committed_chain().is_some() && !changed.is_empty() {
+                        let rows = history_db::BalanceHistoryRow::from_balances(
+                            notification_tip_block(&notification),
+                            &changed,
+                            &balances,
+                        );
+                        if let Err(e) = db
+                            .insert_balances(&rows, now_ms())
+                            .instrument(info_span!(parent: &block_span, "balance_monitor.history"))
+                            .await
+                        {
+                            warn!(error = %e, rows = rows.len(), "failed to write balance history");
+                        }
+                    }
+                }
+
+                // ── Gas spend ────────────────────────────────────────────
+                let reverted_from = notification.reverted_chain().map(|old| old.first().number());
+                let committed_gas = scanned
+                    .committed
+                    .iter()
+                    .map(|(block_number, scan, _)| (*block_number, scan.gas));
+                for (block_number, spent) in gas_tracker.process(reverted_from, committed_gas) {
+                    let message = gas::ChainGasSpend {
+                        chain: chain_id.clone(),
+                        block_number,
+                        tx_count: spent.tx_count,
+                        gas_used: spent.gas_used,
+                        raw_fee_wei: spent.fee_wei.to_string(),
+                        raw_cumulative_fee_wei: gas_tracker.cumulative_fee_wei().to_string(),
+                        raw_eth_balance: balances.get(&NATIVE_ETH).map(|b| b.to_string()),
+                        ts: now_ms(),
+                    };
+                    let payload =
+                        serde_json::to_vec(&message).expect("ChainGasSpend serializes");
+                    if publish_with_retry(&nats_client, &gas_subject, payload).await {
+                        debug!(
+                            block = block_number,
+                            txs = spent.tx_count,
+                            fee_wei = %spent.fee_wei,
+                            "published executor gas spend"
+                        );
+                    }
+                }
+
+                // ── Allowances ───────────────────────────────────────────
+                let allowance_changes = allowances.process(
+                    reverted_from,
+                    scanned
+                        .committed
+                        .iter()
+                        .map(|(block_number, scan, _)| (*block_number, &scan.approvals[..])),
                 );
+                if !allowance_changes.is_empty() {
+                    let snapshot = allowances.snapshot(
+                        &chain_id,
+                        notification_tip_block(&notification),
+                        &allowance_changes,
+                        now_ms(),
+                    );
+                    let payload = serde_json::to_vec(&snapshot)
+                        .expect("ChainAllowanceSnapshot serializes");
+                    if publish_with_retry(&nats_client, &allowance_subject, payload).await {
+                        debug!(
+                            changed = allowance_changes.len(),
+                            block = notification_tip_block(&notification),
+                            "published allowance snapshot"
+                        );
+                    }
+                }
+
+                // ── Swap confirmation scanning ───────────────────────────
+                let mut swap_confirmations = Vec::new();
+                let mut swap_failures = Vec::new();
+                for (_, _, swaps) in scanned.committed {
+                    swap_confirmations.extend(swaps.confirmations);
+                    swap_failures.extend(swaps.failures);
+                }
+                let missing = pool_tokens.fill(&mut swap_confirmations);
+                if let (false, Some(db)) = (missing.is_empty(), &pool_metadata_db) {
+                    match db.lookup(&missing).await {
+                        Ok(rows) => {
+                            for (pool, row) in rows {
+                                pool_tokens.insert(&format!("{pool:#x}"), row.token0, row.token1);
+                            }
+                            pool_tokens.fill(&mut swap_confirmations);
+                        }
+                        Err(e) => warn!(error = %e, pools = missing.len(), "swap pool token lookup failed"),
+                    }
+                }
+                for confirmation in &mut swap_confirmations {
+                    let decimals = |token: &str| {
+                        token
+                            .parse::<Address>()
+                            .ok()
+                            .and_then(|token| tracker.decimals(&token))
+                    };
+                    if let (Some(decimals0), Some(decimals1)) =
+                        (decimals(&confirmation.token0), decimals(&confirmation.token1))
+                    {
+                        confirmation.price_with_decimals(decimals0, decimals1);
+                    }
+                }
                 for confirmation in &swap_confirmations {
                     let payload = serde_json::to_vec(confirmation)
                         .expect("SwapConfirmation serializes");
@@ -364,14 +940,107 @@ where
                         "swap confirmations published"
                     );
                 }
+                for failure in &swap_failures {
+                    let payload = serde_json::to_vec(failure).expect("SwapFailed serializes");
+                    if publish_with_retry(&nats_client, &swap_failed_subject, payload).await {
+                        info!(
+                            tx_hash = %failure.tx_hash,
+                            reason = ?failure.reason,
+                            block = failure.block_number,
+                            "published swap failure"
+                        );
+                    }
+                }
 
-                // Acknowledge processed height.
+                // ── Periodic on-chain reconciliation ─────────────────────
+                // Re-read every tracked balance slot at the committed tip and
+                // correct drift the delta path missed.
                 if let Some(committed_chain) = notification.committed_chain() {
-                    ctx.events
-                        .send(ExExEvent::FinishedHeight(committed_chain.tip().num_hash()))?;
+                    let tip = committed_chain.tip().number();
+                    if reconcile_interval_blocks > 0 && tip % reconcile_interval_blocks == 0 {
+                        match reconcile_balances(
+                            ctx.provider(),
+                            tip,
+                            executor_address,
+                            &tracker,
+                            &mut balances,
+                        ) {
+                            Ok(corrected) if !corrected.is_empty() => {
+                                let snapshot = build_partial_snapshot(
+                                    &chain_id,
+                                    tip,
+                                    &corrected,
+                                    &tracker,
+                                    &balances,
+                                );
+                                let payload = serde_json::to_vec(&snapshot)
+                                    .expect("ChainBalanceSnapshot serializes");
+                                publish_with_retry(&nats_client, &nats_subject, payload).await;
+                                publish_threshold_alerts(
+                                    &nats_client,
+                                    &alert_subject,
+                                    &chain_id,
+                                    tip,
+                                    &corrected,
+                                    &tracker,
+                                    &balances,
+                                    &mut threshold_alerts,
+                                )
+                                .await;
+                            }
+                            Ok(_) => debug!(block = tip, "balance reconciliation: no drift"),
+                            Err(e) => warn!(error = %e, block = tip, "balance reconciliation failed"),
+                        }
+                    }
+                }
+
+                // Persist the map with the block it is now valid at.
+                last_head = notification_head(&notification);
+                if let Some(old) = notification.reverted_chain() {
+                    finished.revert_from(old.first().number());
+                }
+                if let Some(new) = notification.committed_chain() {
+                    finished.commit([new.tip().num_hash()]);
+                }
+                let saved = info_span!(parent: &block_span, "balance_monitor.persist")
+                    .in_scope(|| balance_store::save(&balances_path, last_head, &balances));
+                match saved {
+                    // Balances resume from the persisted map: only a
+                    // persisted block is finished.
+                    Ok(()) => finished.set_durable(BALANCES_SINK, last_head.number),
+                    Err(e) => warn!(error = %e, "failed to persist balances"),
+                }
+
+                // ── Finalized view ───────────────────────────────────────
+                if let Some(old) = notification.reverted_chain() {
+                    finality.revert_from(old.first().number());
+                }
+                finality.record(notification_head(&notification).number, &balances);
+                match ctx.provider().finalized_block_number() {
+                    Ok(Some(finalized)) => {
+                        if let Some(view) = finality.finalized_view(finalized) {
+                            let snapshot =
+                                build_full_snapshot(&chain_id, finalized, &tracker, view);
+                            let payload = serde_json::to_vec(&snapshot)
+                                .expect("ChainBalanceSnapshot serializes");
+                            if publish_with_retry(&nats_client, &finalized_subject, payload).await {
+                                debug!(block = finalized, "published finalized balance snapshot");
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => debug!(error = %e, "failed to read finalized block number"),
+                }
+
+                // Acknowledge the persisted height, batched under load.
+                if let Some(height) = finished.poll(Instant::now()) {
+                    ctx.events.send(ExExEvent::FinishedHeight(height))?;
                 }
 
                 blocks_processed += 1;
+                exex_metrics::record_balance_monitor_block(tracker.len());
+                last_block_number = notification_tip_block(&notification);
+                health::record_block("balance_monitor", last_block_number);
 
                 // Periodic full snapshot as heartbeat — ensures hedger has
                 // current balances even if individual per-block publishes were lost.
@@ -403,6 +1072,71 @@ where
                 }
             }
 
+            // Snapshot requests: answer with the current full snapshot so a
+            // restarted consumer resyncs without waiting for the heartbeat.
+            msg = async { snapshot_request_sub.as_mut().unwrap().next().await }, if snapshot_request_sub.is_some() => {
+                match msg {
+                    Some(msg) => {
+                        let snapshot =
+                            build_full_snapshot(&chain_id, last_block_number, &tracker, &balances);
+                        let payload = serde_json::to_vec(&snapshot)
+                            .expect("ChainBalanceSnapshot serializes");
+                        // Requests without a reply inbox get the snapshot on
+                        // the regular balance subject.
+                        let subject = msg
+                            .reply
+                            .as_ref()
+                            .map_or(nats_subject.clone(), |reply| reply.to_string());
+                        if publish_with_retry(&nats_client, &subject, payload).await {
+                            info!(
+                                tokens = tracker.len(),
+                                block = last_block_number,
+                                subject = %subject,
+                                "answered balance snapshot request"
+                            );
+                        }
+                    }
+                    None => {
+                        warn!("balance snapshot request subscription closed, requests disabled");
+                        snapshot_request_sub = None;
+                    }
+                }
+            }
+
+            // Explicit token lists (non-pool tokens: rewards, gas tokens).
+            msg = async { token_list_sub.as_mut().unwrap().next().await }, if token_list_sub.is_some() => {
+                match msg {
+                    Some(msg) => {
+                        let new_tokens = process_token_list_message(&msg.payload, &mut tracker);
+                        if !new_tokens.is_empty() {
+                            seed_new_tokens::<Node>(
+                                ctx.provider(),
+                                ctx.evm_config(),
+                                executor_address,
+                                &new_tokens,
+                                &tracker,
+                                &mut balances,
+                            );
+                            info!(
+                                new_tokens = new_tokens.len(),
+                                total = tracker.len(),
+                                "discovered tokens from token list"
+                            );
+
+                            let snapshot =
+                                build_full_snapshot(&chain_id, last_block_number, &tracker, &balances);
+                            let payload = serde_json::to_vec(&snapshot)
+                                .expect("ChainBalanceSnapshot serializes");
+                            publish_with_retry(&nats_client, &nats_subject, payload).await;
+                        }
+                    }
+                    None => {
+                        warn!("token list subscription closed, token list discovery disabled");
+                        token_list_sub = None;
+                    }
+                }
+            }
+
             // Whitelist updates (token discovery).
             // Guard: only poll if we have an active subscription.
             msg = async { whitelist_sub.as_mut().unwrap().next().await }, if whitelist_sub.is_some() => {
@@ -411,20 +1145,19 @@ where
                         let new_tokens = process_whitelist_message(
                             &msg.payload,
                             &mut tracker,
+                            &mut pool_tokens,
                         );
 
                         // Seed balances for newly discovered tokens.
                         if !new_tokens.is_empty() {
-                            for &token in &new_tokens {
-                                if let Err(e) = seed_token_balance(
-                                    ctx.provider(),
-                                    executor_address,
-                                    token,
-                                    &mut balances,
-                                ) {
-                                    warn!(error = %e, token = %token, "failed to seed balance for new token");
-                                }
-                            }
+                            seed_new_tokens::<Node>(
+                                ctx.provider(),
+                                ctx.evm_config(),
+                                executor_address,
+                                &new_tokens,
+                                &tracker,
+                                &mut balances,
+                            );
                             info!(
                                 new_tokens = new_tokens.len(),
                                 total = tracker.len(),
@@ -478,167 +1211,167 @@ where
         }
     }
 
-    info!("Balance Monitor ExEx exiting");
-    Ok(())
-}
-
-// ─── Block processing ────────────────────────────────────────────────────────
-
-/// Scan a notification for swap events involving the executor address.
-/// Only scans ChainCommitted (not reverts — we don't confirm reversed swaps).
-fn scan_swaps_in_notification<N>(
-    notification: &ExExNotification<N>,
-    executor: Address,
-) -> Vec<SwapConfirmation>
-where
-    N: NodePrimitives<Receipt: TxReceipt<Log = Log>>,
-    N::BlockBody: BlockBody<Transaction: TxHashRef>,
-{
-    let mut confirmations = Vec::new();
-    let ts = now_ms();
-
-    // Only scan committed blocks — swaps in reverted blocks are not confirmed.
-    let chain = match notification {
-        ExExNotification::ChainCommitted { new } => new,
-        ExExNotification::ChainReorged { new, .. } => new,
-        ExExNotification::ChainReverted { .. } => return confirmations,
-    };
-
-    for (block, receipts) in chain.blocks_and_receipts() {
-        let block_number = block.number();
-        for (tx_index, receipt) in receipts.iter().enumerate() {
-            let tx_hash = block
-                .body()
-                .transactions()
-                .get(tx_index)
-                .map(|tx| format!("{:#x}", tx.tx_hash()))
-                .unwrap_or_default();
-
-            if tx_hash.is_empty() {
-                continue;
+    // Tokens seeded since the last notification are only in memory; persist
+    // them too, and push out publishes still buffered in the NATS client.
+    match balance_store::save(&balances_path, last_head, &balances) {
+        Ok(()) => {
+            if let Some(height) = finished.flush() {
+                ctx.events.send(ExExEvent::FinishedHeight(height))?;
             }
-
-            let swaps = swap_monitor::scan_receipt_for_swaps(
-                receipt,
-                executor,
-                &tx_hash,
-                block_number,
-                tx_index as u64,
-                ts,
-            );
-            confirmations.extend(swaps);
         }
+        Err(e) => warn!(error = %e, "failed to persist balances on shutdown"),
+    }
+    match tokio::time::timeout(shutdown::FLUSH_TIMEOUT, nats_client.flush()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!(error = %e, "failed to flush NATS on shutdown"),
+        Err(_) => warn!("NATS flush timed out on shutdown"),
     }
 
-    confirmations
+    info!("Balance Monitor ExEx exiting");
+    shutdown.finish().await
 }
 
-/// Process a notification and return the set of tokens whose balances changed.
-fn process_notification<N: NodePrimitives<Receipt: TxReceipt<Log = Log>>>(
-    notification: &ExExNotification<N>,
+// ─── Block processing ────────────────────────────────────────────────────────
+
+/// Apply a notification's scanned transfers: undo the reverted blocks', then
+/// apply the committed blocks'. Returns the tokens whose balances changed.
+fn apply_scans(
+    scans: &NotificationScans,
     executor: Address,
     tracker: &TokenTracker,
     balances: &mut HashMap<Address, U256>,
 ) -> Vec<Address> {
     let mut changed = Vec::new();
-
-    match notification {
-        ExExNotification::ChainCommitted { new } => {
-            for (_block, receipts) in new.blocks_and_receipts() {
-                process_receipts(receipts, executor, tracker, balances, &mut changed, false);
-            }
-        }
-        ExExNotification::ChainReorged { old, new } => {
-            // Revert old blocks.
-            for (_block, receipts) in old.blocks_and_receipts() {
-                process_receipts(receipts, executor, tracker, balances, &mut changed, true);
-            }
-            // Apply new blocks.
-            for (_block, receipts) in new.blocks_and_receipts() {
-                process_receipts(receipts, executor, tracker, balances, &mut changed, false);
-            }
-        }
-        ExExNotification::ChainReverted { old } => {
-            for (_block, receipts) in old.blocks_and_receipts() {
-                process_receipts(receipts, executor, tracker, balances, &mut changed, true);
-            }
-        }
+    for scan in &scans.reverted {
+        apply_transfers(
+            &scan.transfers,
+            executor,
+            tracker,
+            balances,
+            &mut changed,
+            true,
+        );
+    }
+    for (_, scan, _) in &scans.committed {
+        apply_transfers(
+            &scan.transfers,
+            executor,
+            tracker,
+            balances,
+            &mut changed,
+            false,
+        );
     }
-
     changed.sort_unstable();
     changed.dedup();
     changed
 }
 
-fn process_receipts<R: TxReceipt<Log = alloy_primitives::Log>>(
-    receipts: &[R],
+/// Apply executor transfers (WETH9 wraps/unwraps arrive as a mint/burn), or
+/// undo them when `is_revert`.
+fn apply_transfers(
+    transfers: &[DecodedTransfer],
     executor: Address,
     tracker: &TokenTracker,
     balances: &mut HashMap<Address, U256>,
     changed: &mut Vec<Address>,
     is_revert: bool,
 ) {
-    for receipt in receipts {
-        for log in receipt.logs() {
-            let transfer = match decode_transfer(log) {
-                Some(t) => t,
-                None => continue,
-            };
-
-            // Only care about transfers involving our executor.
-            let is_incoming = transfer.to == executor;
-            let is_outgoing = transfer.from == executor;
-            if !is_incoming && !is_outgoing {
-                continue;
-            }
+    for transfer in transfers {
+        // Only care about transfers involving our executor.
+        let is_incoming = transfer.to == executor;
+        let is_outgoing = transfer.from == executor;
+        if !is_incoming && !is_outgoing {
+            continue;
+        }
 
-            // Only care about tracked tokens.
-            if !tracker.contains(&transfer.token) {
-                continue;
-            }
+        // Only care about tracked tokens. Call-mode balances are re-read,
+        // not accumulated from events.
+        if !tracker.contains(&transfer.token) || tracker.is_call_mode(&transfer.token) {
+            continue;
+        }
 
-            // Skip zero-value transfers — no balance change, no publish needed.
-            if transfer.value == U256::ZERO {
-                continue;
-            }
+        // Skip zero-value transfers — no balance change, no publish needed.
+        if transfer.value == U256::ZERO {
+            continue;
+        }
 
-            // Self-transfer (from == to == executor): net zero, skip.
-            if is_incoming && is_outgoing {
-                continue;
-            }
+        // Self-transfer (from == to == executor): net zero, skip.
+        if is_incoming && is_outgoing {
+            continue;
+        }
 
-            let entry = balances.entry(transfer.token).or_insert(U256::ZERO);
+        let entry = balances.entry(transfer.token).or_insert(U256::ZERO);
 
-            if is_revert {
-                // Undo: incoming was an add, so subtract; outgoing was a subtract, so add.
-                if is_incoming {
-                    *entry = entry.saturating_sub(transfer.value);
-                } else {
-                    *entry = entry.saturating_add(transfer.value);
-                }
-            } else if is_incoming {
-                *entry = entry.saturating_add(transfer.value);
-            } else {
+        if is_revert {
+            // Undo: incoming was an add, so subtract; outgoing was a subtract, so add.
+            if is_incoming {
                 *entry = entry.saturating_sub(transfer.value);
+            } else {
+                *entry = entry.saturating_add(transfer.value);
             }
-
-            changed.push(transfer.token);
+        } else if is_incoming {
+            *entry = entry.saturating_add(transfer.value);
+        } else {
+            *entry = entry.saturating_sub(transfer.value);
         }
+
+        changed.push(transfer.token);
     }
 }
 
+/// The executor's native ETH balance after this notification, from the
+/// chains' execution outcomes: the post-state of the new chain, or — when only
+/// reverted blocks touched the account — its pre-state in the old chain.
+/// `None` if no block in the notification changed the account.
+fn native_balance_after<N: NodePrimitives>(
+    notification: &ExExNotification<N>,
+    executor: Address,
+) -> Option<U256> {
+    let post = notification.committed_chain().and_then(|chain| {
+        let account = chain.execution_outcome().bundle.account(&executor)?;
+        Some(
+            account
+                .info
+                .as_ref()
+                .map_or(U256::ZERO, |info| info.balance),
+        )
+    });
+    let pre = notification.reverted_chain().and_then(|chain| {
+        let account = chain.execution_outcome().bundle.account(&executor)?;
+        Some(
+            account
+                .original_info
+                .as_ref()
+                .map_or(U256::ZERO, |info| info.balance),
+        )
+    });
+    post.or(pre)
+}
+
 // ─── Balance seeding ─────────────────────────────────────────────────────────
 
+/// Seed every tracked balance not already in `balances` (and native ETH)
+/// from state at `block_number`.
 fn seed_balances_from_db<P: StateProviderFactory>(
     provider: &P,
+    block_number: u64,
     executor: Address,
     tracker: &TokenTracker,
     balances: &mut HashMap<Address, U256>,
 ) -> eyre::Result<()> {
-    let state = provider.latest()?;
+    let state = provider.history_by_block_number(block_number)?;
+    if !balances.contains_key(&NATIVE_ETH) {
+        let native = state.account_balance(&executor)?.unwrap_or(U256::ZERO);
+        balances.insert(NATIVE_ETH, native);
+        debug!(balance = %native, "seeded native ETH balance from DB");
+    }
     for (&token, _decimals) in tracker.iter() {
-        let slot = slots::balance_storage_slot(token, executor);
-        let value = state.storage(token, slot.into())?.unwrap_or(U256::ZERO);
+        if tracker.is_call_mode(&token) || balances.contains_key(&token) {
+            continue;
+        }
+        let slot = token_balance_slot(tracker, token, executor);
+        let value = state.storage(token, slot)?.unwrap_or(U256::ZERO);
         balances.insert(token, value);
         debug!(token = %token, balance = %value, "seeded balance from DB");
     }
@@ -649,19 +1382,178 @@ fn seed_token_balance<P: StateProviderFactory>(
     provider: &P,
     executor: Address,
     token: Address,
+    tracker: &TokenTracker,
     balances: &mut HashMap<Address, U256>,
 ) -> eyre::Result<()> {
+    if tracker.is_call_mode(&token) {
+        return Ok(());
+    }
     let state = provider.latest()?;
-    let slot = slots::balance_storage_slot(token, executor);
-    let value = state.storage(token, slot.into())?.unwrap_or(U256::ZERO);
+    let slot = token_balance_slot(tracker, token, executor);
+    let value = state.storage(token, slot)?.unwrap_or(U256::ZERO);
     balances.insert(token, value);
     debug!(token = %token, balance = %value, "seeded balance for new token");
     Ok(())
 }
 
+/// Seed newly tracked tokens: slot reads at latest, then one `balanceOf`
+/// refresh if any of them is call-mode. Failures are logged per token.
+fn seed_new_tokens<Node: FullNodeComponents>(
+    provider: &Node::Provider,
+    evm_config: &Node::Evm,
+    executor: Address,
+    new_tokens: &[Address],
+    tracker: &TokenTracker,
+    balances: &mut HashMap<Address, U256>,
+) where
+    Node::Provider: StateProviderFactory,
+{
+    for &token in new_tokens {
+        if let Err(e) = seed_token_balance(provider, executor, token, tracker, balances) {
+            warn!(error = %e, token = %token, "failed to seed balance for new token");
+        }
+    }
+    if new_tokens.iter().any(|t| tracker.is_call_mode(t)) {
+        if let Err(e) =
+            refresh_call_balances::<Node>(provider, evm_config, executor, tracker, balances)
+        {
+            warn!(error = %e, "failed to seed call-mode balances for new tokens");
+        }
+    }
+}
+
+/// Re-read every tracked call-mode token with `balanceOf` at latest state and
+/// overwrite the map. Returns the tokens whose balance changed, sorted.
+fn refresh_call_balances<Node: FullNodeComponents>(
+    provider: &Node::Provider,
+    evm_config: &Node::Evm,
+    executor: Address,
+    tracker: &TokenTracker,
+    balances: &mut HashMap<Address, U256>,
+) -> eyre::Result<Vec<Address>> {
+    let tokens = tracker.call_mode_tokens();
+    let mut changed = Vec::new();
+    for (token, value) in
+        call_balance::call_balances::<Node>(provider, evm_config, executor, &tokens)?
+    {
+        if balances.insert(token, value) != Some(value) {
+            debug!(token = %token, balance = %value, "refreshed call-mode balance");
+            changed.push(token);
+        }
+    }
+    changed.sort_unstable();
+    Ok(changed)
+}
+
+/// Re-read every tracked token's balance slot at `block_number` and overwrite
+/// drifted in-memory balances. Returns the corrected tokens.
+fn reconcile_balances<P: StateProviderFactory>(
+    provider: &P,
+    block_number: u64,
+    executor: Address,
+    tracker: &TokenTracker,
+    balances: &mut HashMap<Address, U256>,
+) -> eyre::Result<Vec<Address>> {
+    let state = provider.history_by_block_number(block_number)?;
+    let mut observed = Vec::with_capacity(tracker.len() + 1);
+    observed.push((
+        NATIVE_ETH,
+        state.account_balance(&executor)?.unwrap_or(U256::ZERO),
+    ));
+    for (&token, _decimals) in tracker.iter() {
+        if tracker.is_call_mode(&token) {
+            continue;
+        }
+        let slot = token_balance_slot(tracker, token, executor);
+        let value = state.storage(token, slot)?.unwrap_or(U256::ZERO);
+        observed.push((token, value));
+    }
+    Ok(apply_reconciled(block_number, observed, balances))
+}
+
+/// Overwrite balances that differ from the observed on-chain values, logging
+/// each discrepancy. Returns the corrected tokens, sorted.
+fn apply_reconciled(
+    block_number: u64,
+    observed: Vec<(Address, U256)>,
+    balances: &mut HashMap<Address, U256>,
+) -> Vec<Address> {
+    let mut corrected = Vec::new();
+    for (token, on_chain) in observed {
+        let tracked = balances.get(&token).copied().unwrap_or(U256::ZERO);
+        if tracked != on_chain {
+            warn!(
+                token = %token,
+                block = block_number,
+                tracked = %tracked,
+                on_chain = %on_chain,
+                "balance drift detected, correcting from storage"
+            );
+            balances.insert(token, on_chain);
+            corrected.push(token);
+        }
+    }
+    corrected.sort_unstable();
+    corrected
+}
+
+// ─── Balance slot discovery ──────────────────────────────────────────────────
+
+/// Storage key of `balances[holder]`: the discovered slot if any, else the
+/// hardcoded override / standard slot 0.
+fn token_balance_slot(tracker: &TokenTracker, token: Address, holder: Address) -> B256 {
+    match tracker.balance_slot(&token) {
+        Some(slot) => slot.storage_key(holder),
+        None => slots::balance_storage_slot(token, holder),
+    }
+}
+
+/// For each tracked token that still needs slot discovery, the block's first
+/// transfer recipient proving a balance (see `BalanceScan::holders`):
+/// (block, holder, min balance).
+fn slot_discovery_holders(
+    block_number: u64,
+    candidates: &HashMap<Address, (Address, U256)>,
+    tracker: &TokenTracker,
+    attempts: &HashMap<Address, u32>,
+) -> HashMap<Address, (u64, Address, U256)> {
+    candidates
+        .iter()
+        .filter(|(token, _)| {
+            tracker.contains(token)
+                && !tracker.is_call_mode(token)
+                && tracker.balance_slot(token).is_none()
+                && slots::override_slot(**token).is_none()
+                && attempts.get(*token).copied().unwrap_or(0) < MAX_SLOT_DISCOVERY_ATTEMPTS
+        })
+        .map(|(&token, &(holder, value))| (token, (block_number, holder, value)))
+        .collect()
+}
+
+/// Probe `token`'s balance mapping at `block_number` using `holder`, who
+/// received `min_balance` in that block. EIP-1967 proxies try the slot already
+/// found for their implementation first. Caches a hit in the tracker.
+fn discover_token_slot<P: StateProviderFactory>(
+    provider: &P,
+    block_number: u64,
+    token: Address,
+    holder: Address,
+    min_balance: U256,
+    tracker: &mut TokenTracker,
+) -> eyre::Result<Option<BalanceSlot>> {
+    let state = provider.history_by_block_number(block_number)?;
+    let implementation = slots::eip1967_implementation(state.as_ref(), token)?;
+    let hint = implementation.and_then(|i| tracker.slot_for_implementation(i));
+    let slot = slots::discover_balance_slot(state.as_ref(), token, holder, min_balance, hint)?;
+    if let Some(slot) = slot {
+        tracker.set_balance_slot(token, slot, implementation);
+    }
+    Ok(slot)
+}
+
 // ─── Whitelist processing ────────────────────────────────────────────────────
 
-/// Minimal whitelist pool entry — only need token addresses and decimals.
+/// Minimal whitelist pool entry — pool key, token addresses and decimals.
 #[derive(Debug, serde::Deserialize)]
 struct WhitelistFullMessage {
     #[serde(default)]
@@ -670,6 +1562,11 @@ struct WhitelistFullMessage {
 
 #[derive(Debug, serde::Deserialize)]
 struct WhitelistPoolEntry {
+    #[serde(default)]
+    address: String,
+    /// 32-byte id of `pool_id`-keyed (V4) pools.
+    #[serde(default)]
+    pool_id: Option<String>,
     #[serde(default)]
     token0: Option<TokenEntry>,
     #[serde(default)]
@@ -689,8 +1586,45 @@ fn default_decimals() -> u8 {
     18
 }
 
+/// Explicit token list on `tokens.chain.<id>`.
+#[derive(Debug, serde::Deserialize)]
+struct TokenListMessage {
+    #[serde(default)]
+    tokens: Vec<TokenEntry>,
+}
+
+/// Extract new tokens from a token list message. Returns addresses of newly
+/// added tokens.
+fn process_token_list_message(payload: &[u8], tracker: &mut TokenTracker) -> Vec<Address> {
+    let msg: TokenListMessage = match serde_json::from_slice(payload) {
+        Ok(m) => m,
+        Err(e) => {
+            warn!(error = %e, "failed to parse token list message");
+            return Vec::new();
+        }
+    };
+
+    let mut new_tokens = Vec::new();
+    for token in &msg.tokens {
+        match token.address.parse::<Address>() {
+            Ok(addr) => {
+                if tracker.add(addr, token.decimals) {
+                    new_tokens.push(addr);
+                }
+            }
+            Err(_) => warn!(address = %token.address, "skipping invalid token list address"),
+        }
+    }
+    new_tokens
+}
+
 /// Extract new tokens from a whitelist message. Returns addresses of newly added tokens.
-fn process_whitelist_message(payload: &[u8], tracker: &mut TokenTracker) -> Vec<Address> {
+/// Pool token pairs are recorded in `pool_tokens` for swap confirmations.
+fn process_whitelist_message(
+    payload: &[u8],
+    tracker: &mut TokenTracker,
+    pool_tokens: &mut PoolTokens,
+) -> Vec<Address> {
     let msg: WhitelistFullMessage = match serde_json::from_slice(payload) {
         Ok(m) => m,
         Err(e) => {
@@ -702,6 +1636,15 @@ fn process_whitelist_message(payload: &[u8], tracker: &mut TokenTracker) -> Vec<
     let mut new_tokens = Vec::new();
 
     for pool in &msg.pools {
+        let key = pool.pool_id.as_deref().unwrap_or(&pool.address);
+        if let (false, Some(token0), Some(token1)) = (key.is_empty(), &pool.token0, &pool.token1) {
+            if let (Ok(token0), Ok(token1)) = (
+                token0.address.parse::<Address>(),
+                token1.address.parse::<Address>(),
+            ) {
+                pool_tokens.insert(key, token0, token1);
+            }
+        }
         for token in pool
             .token0
             .iter()
@@ -729,7 +1672,21 @@ fn notification_tip_block<N: NodePrimitives>(notification: &ExExNotification<N>)
     }
 }
 
-fn now_ms() -> u64 {
+/// The block the balance map is valid at after `notification`: the new tip,
+/// or for a pure revert the parent of the first reverted block.
+fn notification_head<N: NodePrimitives>(notification: &ExExNotification<N>) -> BlockNumHash {
+    match notification {
+        ExExNotification::ChainCommitted { new } | ExExNotification::ChainReorged { new, .. } => {
+            new.tip().num_hash()
+        }
+        ExExNotification::ChainReverted { old } => BlockNumHash::new(
+            old.first().number().saturating_sub(1),
+            old.first().parent_hash(),
+        ),
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map_or(0, |d| d.as_millis() as u64)
@@ -738,7 +1695,7 @@ fn now_ms() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_primitives::address;
+    use alloy_primitives::{address, Log};
     use rust_decimal_macros::dec;
 
     // ── Helper: build a mock receipt with Transfer logs ──────────────────
@@ -768,6 +1725,35 @@ mod tests {
         }
     }
 
+    /// The balance scan of `receipts`, log by log as the dispatch walk
+    /// decodes them.
+    fn scan_receipts(receipts: &[MockReceipt], executor: Address) -> scan::BalanceScan {
+        let mut scan = scan::BalanceScan::default();
+        for log in receipts.iter().flat_map(|receipt| &receipt.logs) {
+            scan.scan_log(log, executor);
+        }
+        scan
+    }
+
+    fn process_receipts(
+        receipts: &[MockReceipt],
+        executor: Address,
+        tracker: &TokenTracker,
+        balances: &mut HashMap<Address, U256>,
+        changed: &mut Vec<Address>,
+        is_revert: bool,
+    ) {
+        let scan = scan_receipts(receipts, executor);
+        apply_transfers(
+            &scan.transfers,
+            executor,
+            tracker,
+            balances,
+            changed,
+            is_revert,
+        );
+    }
+
     fn transfer_log(token: Address, from: Address, to: Address, value: U256) -> Log {
         use alloy_sol_types::SolEvent;
         let event = crate::transfers::events::Transfer { from, to, value };
@@ -775,6 +1761,24 @@ mod tests {
         Log::new(token, log_data.topics().to_vec(), log_data.data.clone()).unwrap()
     }
 
+    fn wrap_log(token: Address, holder: Address, value: U256, deposit: bool) -> Log {
+        use alloy_sol_types::SolEvent;
+        let data = if deposit {
+            weth::Deposit {
+                dst: holder,
+                wad: value,
+            }
+            .encode_log_data()
+        } else {
+            weth::Withdrawal {
+                src: holder,
+                wad: value,
+            }
+            .encode_log_data()
+        };
+        Log::new(token, data.topics().to_vec(), data.data.clone()).unwrap()
+    }
+
     fn make_tracker(tokens: &[(Address, u8)]) -> TokenTracker {
         let path = std::path::PathBuf::from(format!(
             "/tmp/bm_test_{}.json",
@@ -916,6 +1920,69 @@ mod tests {
     const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
     const OTHER: Address = address!("dEAD000000000000000000000000000000000000");
 
+    #[test]
+    fn call_mode_transfers_do_not_move_balance() {
+        let mut tracker = make_tracker(&[(USDC, 6), (OTHER, 18)]);
+        tracker.set_call_mode([OTHER]);
+        let mut balances = HashMap::from([(OTHER, U256::from(10u64))]);
+        let mut changed = Vec::new();
+
+        let receipt = MockReceipt {
+            logs: vec![
+                transfer_log(OTHER, USDC, EXECUTOR, U256::from(5u64)),
+                transfer_log(USDC, OTHER, EXECUTOR, U256::from(7u64)),
+            ],
+        };
+        process_receipts(
+            &[receipt],
+            EXECUTOR,
+            &tracker,
+            &mut balances,
+            &mut changed,
+            false,
+        );
+
+        assert_eq!(changed, vec![USDC]);
+        assert_eq!(balances[&OTHER], U256::from(10u64));
+        assert_eq!(tracker.call_mode_tokens(), vec![OTHER]);
+    }
+
+    #[test]
+    fn weth_wraps_and_unwraps_move_balance() {
+        let tracker = make_tracker(&[(WETH, 18), (OTHER, 18)]);
+        let mut balances = HashMap::new();
+        let mut changed = Vec::new();
+
+        let receipt = MockReceipt {
+            logs: vec![
+                wrap_log(WETH, EXECUTOR, U256::from(10u64), true),
+                wrap_log(WETH, EXECUTOR, U256::from(4u64), false),
+                // Deposit events from unknown contracts are ignored.
+                wrap_log(OTHER, EXECUTOR, U256::from(99u64), true),
+            ],
+        };
+        process_receipts(
+            &[receipt.clone()],
+            EXECUTOR,
+            &tracker,
+            &mut balances,
+            &mut changed,
+            false,
+        );
+        assert_eq!(balances[&WETH], U256::from(6u64));
+        assert!(!balances.contains_key(&OTHER));
+
+        process_receipts(
+            &[receipt],
+            EXECUTOR,
+            &tracker,
+            &mut balances,
+            &mut changed,
+            true,
+        );
+        assert_eq!(balances[&WETH], U256::ZERO);
+    }
+
     #[test]
     fn incoming_transfer_adds_balance() {
         let tracker = make_tracker(&[(USDC, 6)]);
@@ -1144,6 +2211,21 @@ mod tests {
         assert_eq!(balances[&USDC], U256::ZERO);
     }
 
+    // ── apply_reconciled ─────────────────────────────────────────────────
+
+    #[test]
+    fn reconciliation_corrects_only_drifted_tokens() {
+        let mut balances = HashMap::from([(USDC, U256::from(1_000u64)), (WETH, U256::from(5u64))]);
+        let corrected = apply_reconciled(
+            100,
+            vec![(USDC, U256::from(1_000u64)), (WETH, U256::from(7u64))],
+            &mut balances,
+        );
+        assert_eq!(corrected, vec![WETH]);
+        assert_eq!(balances[&WETH], U256::from(7u64));
+        assert_eq!(balances[&USDC], U256::from(1_000u64));
+    }
+
     // ── build_full_snapshot ──────────────────────────────────────────────
 
     #[test]
@@ -1178,6 +2260,22 @@ mod tests {
         assert_eq!(weth_entry.decimals, 18);
     }
 
+    #[test]
+    fn full_snapshot_includes_native_eth_when_seeded() {
+        let tracker = make_tracker(&[(USDC, 6)]);
+        let balances = HashMap::from([(NATIVE_ETH, U256::from(3u64))]);
+
+        let snapshot = build_full_snapshot("1", 7, &tracker, &balances);
+
+        let native = snapshot
+            .balances
+            .iter()
+            .find(|e| e.token == format!("{NATIVE_ETH:#x}"))
+            .unwrap();
+        assert_eq!(native.raw_available, "3");
+        assert_eq!(native.decimals, 18);
+    }
+
     // ── process_whitelist_message ────────────────────────────────────────
 
     #[test]
@@ -1194,7 +2292,7 @@ mod tests {
         let payload = serde_json::to_vec(&json).unwrap();
 
         let mut tracker = make_tracker(&[]);
-        let new = process_whitelist_message(&payload, &mut tracker);
+        let new = process_whitelist_message(&payload, &mut tracker, &mut PoolTokens::default());
 
         assert_eq!(new.len(), 3);
         assert_eq!(tracker.len(), 3);
@@ -1204,10 +2302,29 @@ mod tests {
         assert_eq!(tracker.decimals(&OTHER), Some(8));
     }
 
+    #[test]
+    fn token_list_message_adds_non_pool_tokens() {
+        let mut tracker = make_tracker(&[(USDC, 6)]);
+        let json = serde_json::json!({
+            "tokens": [
+                { "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "decimals": 6 },
+                { "address": "0xdEAD000000000000000000000000000000000000", "decimals": 8 },
+                { "address": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2" },
+                { "address": "not-an-address" }
+            ]
+        });
+        let payload = serde_json::to_vec(&json).unwrap();
+        let new = process_token_list_message(&payload, &mut tracker);
+
+        assert_eq!(new.len(), 2);
+        assert_eq!(tracker.decimals(&OTHER), Some(8));
+        assert_eq!(tracker.decimals(&WETH), Some(18), "decimals default to 18");
+    }
+
     #[test]
     fn whitelist_message_malformed_returns_empty() {
         let mut tracker = make_tracker(&[]);
-        let new = process_whitelist_message(b"not json", &mut tracker);
+        let new = process_whitelist_message(b"not json", &mut tracker, &mut PoolTokens::default());
         assert!(new.is_empty());
         assert_eq!(tracker.len(), 0);
     }
@@ -1222,10 +2339,41 @@ mod tests {
             }]
         });
         let payload = serde_json::to_vec(&json).unwrap();
-        let new = process_whitelist_message(&payload, &mut tracker);
+        let new = process_whitelist_message(&payload, &mut tracker, &mut PoolTokens::default());
 
         // Only WETH is new
         assert_eq!(new.len(), 1);
         assert_eq!(new[0], WETH);
     }
+
+    // ── Balance slot discovery ───────────────────────────────────────────
+
+    #[test]
+    fn slot_discovery_picks_recipients_of_undiscovered_tokens() {
+        let mut tracker = make_tracker(&[(USDC, 6), (OTHER, 8)]);
+        let holder = address!("1111111111111111111111111111111111111111");
+        let receipts = vec![MockReceipt {
+            logs: vec![
+                // USDC has a hardcoded slot, never probed.
+                transfer_log(USDC, EXECUTOR, holder, U256::from(5u64)),
+                // Zero-value and burn transfers prove no balance.
+                transfer_log(OTHER, EXECUTOR, holder, U256::ZERO),
+                transfer_log(OTHER, holder, Address::ZERO, U256::from(3u64)),
+                transfer_log(OTHER, EXECUTOR, holder, U256::from(7u64)),
+                transfer_log(OTHER, EXECUTOR, EXECUTOR, U256::from(9u64)),
+            ],
+        }];
+
+        let candidates = scan_receipts(&receipts, EXECUTOR).holders;
+
+        let holders = slot_discovery_holders(10, &candidates, &tracker, &HashMap::new());
+        assert_eq!(holders.len(), 1);
+        assert_eq!(holders[&OTHER], (10, holder, U256::from(7u64)));
+
+        // Exhausted attempts and already-discovered slots are skipped.
+        let attempts = HashMap::from([(OTHER, MAX_SLOT_DISCOVERY_ATTEMPTS)]);
+        assert!(slot_discovery_holders(10, &candidates, &tracker, &attempts).is_empty());
+        tracker.set_balance_slot(OTHER, BalanceSlot::Solidity(51), None);
+        assert!(slot_discovery_holders(10, &candidates, &tracker, &HashMap::new()).is_empty());
+    }
 }
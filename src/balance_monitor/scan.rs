@@ -0,0 +1,413 @@
+//! The balance monitor's log scans, run on the dispatch walk.
+//!
+//! `BalanceScanHandler` decodes the executor's transfers and WETH wraps,
+//! slot-discovery candidates, approvals and gas, and
+//! `swap_monitor::SwapMonitorHandler` its swaps, as handlers of the Dispatch
+//! ExEx. Each hands its per-block result to the `ScanStore`, keyed by block
+//! hash. The BalanceMonitor ExEx keeps what needs state at each tip (native
+//! balance, call-mode reads, slot probes, reconciliation, proxy upgrades) and
+//! takes the rest from the store instead of walking the receipts again.
+//!
+//! Both ExExes see every live notification, but the balance monitor also
+//! replays the blocks after its persisted head, which the walk never saw.
+//! Blocks without a scan are reconciled from state at the tip instead; their
+//! swaps, approvals and gas are not published.
+
+use super::allowances::{self, AllowanceKey};
+use super::gas::BlockGas;
+use super::weth;
+use crate::dispatch::{BlockHandler, BlockInfo, TxInfo};
+use crate::swap_monitor::BlockSwaps;
+use crate::transfers::events::{decode_transfer, DecodedTransfer, Transfer};
+use alloy_consensus::BlockHeader;
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{Address, Log, B256, U256};
+use alloy_sol_types::SolEvent;
+use futures::future::BoxFuture;
+use reth_exex::ExExNotification;
+use reth_node_api::NodePrimitives;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Blocks of scans kept behind the walk, for reverts.
+const RETAINED_BLOCKS: u64 = 256;
+
+/// How long the balance monitor waits for the walk to publish a block.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the balance monitor needs from one block's transactions and logs.
+#[derive(Default)]
+pub struct BalanceScan {
+    /// Executor transfers and WETH wraps, in log order.
+    pub transfers: Vec<DecodedTransfer>,
+    /// Per token, the first transfer that proves a balance (non-zero, not a
+    /// burn, not to the sender): (holder, value). Slot-discovery candidates.
+    pub holders: HashMap<Address, (Address, U256)>,
+    /// Executor approvals, in log order.
+    pub approvals: Vec<(AllowanceKey, U256)>,
+    /// Gas paid by executor transactions.
+    pub gas: BlockGas,
+}
+
+impl BalanceScan {
+    /// Fold one log of the block in.
+    pub fn scan_log(&mut self, log: &Log, executor: Address) {
+        if let Some(transfer) = decode_transfer(log) {
+            if !transfer.value.is_zero()
+                && transfer.to != Address::ZERO
+                && transfer.from != transfer.to
+            {
+                self.holders
+                    .entry(transfer.token)
+                    .or_insert((transfer.to, transfer.value));
+            }
+            if transfer.from == executor || transfer.to == executor {
+                self.transfers.push(transfer);
+            }
+        } else if let Some(wrap) = weth::decode_wrap(log) {
+            if wrap.from == executor || wrap.to == executor {
+                self.transfers.push(wrap);
+            }
+        } else if let Some(approval) = allowances::decode_approval(log, executor) {
+            self.approvals.push(approval);
+        }
+    }
+}
+
+/// The balance monitor's log scans as a dispatch handler.
+pub struct BalanceScanHandler {
+    executor: Address,
+    scans: Arc<ScanStore>,
+    /// The block being walked.
+    current: BalanceScan,
+    /// Walked blocks waiting for the flush.
+    pending: Vec<(BlockNumHash, BalanceScan)>,
+}
+
+/// Build the balance scan handler for the balance monitor's executor.
+pub fn balance_scan_handler(scans: Arc<ScanStore>) -> eyre::Result<Box<dyn BlockHandler>> {
+    let executor = super::executor_address()?;
+    info!(executor = %executor, "Balance scan handler starting");
+    Ok(Box::new(BalanceScanHandler {
+        executor,
+        scans,
+        current: BalanceScan::default(),
+        pending: Vec::new(),
+    }))
+}
+
+impl BlockHandler for BalanceScanHandler {
+    fn name(&self) -> &'static str {
+        "balance_scan"
+    }
+
+    fn topics(&self) -> Vec<B256> {
+        vec![
+            Transfer::SIGNATURE_HASH,
+            weth::Deposit::SIGNATURE_HASH,
+            weth::Withdrawal::SIGNATURE_HASH,
+            allowances::Approval::SIGNATURE_HASH,
+        ]
+    }
+
+    fn on_tx(&mut self, block: &BlockInfo, tx: &TxInfo) {
+        if tx.from == self.executor {
+            let base_fee = u128::from(block.base_fee_per_gas.unwrap_or(0));
+            self.current
+                .gas
+                .add_tx(tx.gas_used, base_fee + tx.priority_fee_per_gas);
+        }
+    }
+
+    fn on_log(&mut self, _block: &BlockInfo, _tx: &TxInfo, _log_index: usize, log: &Log) {
+        self.current.scan_log(log, self.executor);
+    }
+
+    fn end_block(&mut self, block: &BlockInfo) {
+        self.pending.push((
+            BlockNumHash::new(block.number, block.hash),
+            std::mem::take(&mut self.current),
+        ));
+    }
+
+    fn on_revert(&mut self, blocks: &[u64]) {
+        self.pending
+            .retain(|(block, _)| !blocks.contains(&block.number));
+    }
+
+    fn flush(&mut self, _committed_tip: Option<u64>) -> BoxFuture<'_, eyre::Result<()>> {
+        for (block, scan) in std::mem::take(&mut self.pending) {
+            self.scans.publish_balance(block, scan);
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// One notification's scans, as the balance monitor applies them.
+#[derive(Default)]
+pub struct NotificationScans {
+    /// Reverted blocks, oldest first.
+    pub reverted: Vec<Arc<BalanceScan>>,
+    /// Committed blocks, oldest first: (number, scan, swaps).
+    pub committed: Vec<(u64, Arc<BalanceScan>, BlockSwaps)>,
+    /// Blocks of the notification the walk did not scan.
+    pub missing: Vec<u64>,
+}
+
+/// Per-block scans handed from the Dispatch ExEx's handlers to the
+/// BalanceMonitor ExEx.
+#[derive(Default)]
+pub struct ScanStore {
+    blocks: Mutex<ScannedBlocks>,
+    /// Woken whenever a handler publishes.
+    published: Notify,
+}
+
+#[derive(Default)]
+struct ScannedBlocks {
+    by_hash: HashMap<B256, ScannedBlock>,
+    /// Lowest block the walk published; it never scans older blocks.
+    first: Option<u64>,
+    /// Highest block the walk published.
+    latest: u64,
+}
+
+#[derive(Default)]
+struct ScannedBlock {
+    number: u64,
+    /// Kept after the block is committed, to undo its transfers on a revert.
+    balance: Option<Arc<BalanceScan>>,
+    /// Taken when the block is committed.
+    swaps: Option<BlockSwaps>,
+}
+
+impl ScannedBlocks {
+    /// Both handlers have published `block`, or the walk will never scan it.
+    fn settled(&self, block: BlockNumHash) -> bool {
+        let published = self
+            .by_hash
+            .get(&block.hash)
+            .is_some_and(|scanned| scanned.balance.is_some() && scanned.swaps.is_some());
+        published || self.unscanned(block.number)
+    }
+
+    /// The walk started after `number`, or its scan has been pruned.
+    fn unscanned(&self, number: u64) -> bool {
+        self.first.is_some_and(|first| number < first)
+            || number < self.latest.saturating_sub(RETAINED_BLOCKS)
+    }
+
+    fn take_committed(&mut self, hash: &B256) -> Option<(Arc<BalanceScan>, BlockSwaps)> {
+        let scanned = self.by_hash.get_mut(hash)?;
+        let balance = scanned.balance.clone()?;
+        let swaps = std::mem::take(scanned.swaps.as_mut()?);
+        Some((balance, swaps))
+    }
+}
+
+impl ScanStore {
+    pub fn publish_balance(&self, block: BlockNumHash, scan: BalanceScan) {
+        self.publish(block, |scanned| scanned.balance = Some(Arc::new(scan)));
+    }
+
+    pub fn publish_swaps(&self, block: BlockNumHash, swaps: BlockSwaps) {
+        self.publish(block, |scanned| scanned.swaps = Some(swaps));
+    }
+
+    fn publish(&self, block: BlockNumHash, update: impl FnOnce(&mut ScannedBlock)) {
+        {
+            let mut guard = self.lock();
+            let blocks = &mut *guard;
+            update(
+                blocks
+                    .by_hash
+                    .entry(block.hash)
+                    .or_insert_with(|| ScannedBlock {
+                        number: block.number,
+                        ..Default::default()
+                    }),
+            );
+            blocks.first = Some(blocks.first.map_or(block.number, |f| f.min(block.number)));
+            blocks.latest = blocks.latest.max(block.number);
+            let oldest = blocks.latest.saturating_sub(RETAINED_BLOCKS);
+            blocks.by_hash.retain(|_, scanned| scanned.number >= oldest);
+        }
+        self.published.notify_waiters();
+    }
+
+    /// The scans of `notification`'s blocks. Waits for the walk to publish
+    /// the committed tip; handlers publish once per notification, so the
+    /// other committed blocks are in by then.
+    pub async fn notification<N: NodePrimitives>(
+        &self,
+        notification: &ExExNotification<N>,
+    ) -> NotificationScans {
+        let mut scans = NotificationScans::default();
+        if let Some(old) = notification.reverted_chain() {
+            let blocks = self.lock();
+            for block in old.blocks_iter() {
+                match blocks
+                    .by_hash
+                    .get(&block.hash())
+                    .and_then(|scanned| scanned.balance.clone())
+                {
+                    Some(scan) => scans.reverted.push(scan),
+                    None => scans.missing.push(block.number()),
+                }
+            }
+        }
+        if let Some(new) = notification.committed_chain() {
+            self.wait_for(new.tip().num_hash()).await;
+            let mut blocks = self.lock();
+            for block in new.blocks_iter() {
+                match blocks.take_committed(&block.hash()) {
+                    Some((scan, swaps)) => scans.committed.push((block.number(), scan, swaps)),
+                    None => scans.missing.push(block.number()),
+                }
+            }
+        }
+        scans
+    }
+
+    /// Wait until `block` is settled, at most `SCAN_TIMEOUT`.
+    async fn wait_for(&self, block: BlockNumHash) {
+        let wait = async {
+            loop {
+                // Registered before the check, so a publish in between wakes it.
+                let published = self.published.notified();
+                let settled = self.lock().settled(block);
+                if settled {
+                    return;
+                }
+                published.await;
+            }
+        };
+        if tokio::time::timeout(SCAN_TIMEOUT, wait).await.is_err() {
+            warn!(
+                block = block.number,
+                "timed out waiting for the dispatch walk to scan block"
+            );
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ScannedBlocks> {
+        self.blocks.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::Dispatcher;
+    use alloy_primitives::{address, Bytes, LogData};
+
+    const EXECUTOR: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const OTHER: Address = address!("dEAD000000000000000000000000000000000000");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+    fn block(number: u64) -> BlockInfo {
+        BlockInfo {
+            number,
+            hash: B256::with_last_byte(number as u8),
+            timestamp: 0,
+            base_fee_per_gas: Some(10),
+            gas_used: 0,
+            gas_limit: 30_000_000,
+        }
+    }
+
+    fn tx(index: usize, from: Address) -> TxInfo {
+        TxInfo {
+            index,
+            hash: B256::ZERO,
+            from,
+            to: None,
+            value: U256::ZERO,
+            selector: None,
+            success: true,
+            gas_used: 100_000,
+            priority_fee_per_gas: 2,
+        }
+    }
+
+    fn transfer_log(from: Address, to: Address, value: u64) -> Log {
+        let event = Transfer {
+            from,
+            to,
+            value: U256::from(value),
+        };
+        Log {
+            address: USDC,
+            data: event.encode_log_data(),
+        }
+    }
+
+    fn handler(scans: &Arc<ScanStore>) -> Box<dyn BlockHandler> {
+        Box::new(BalanceScanHandler {
+            executor: EXECUTOR,
+            scans: scans.clone(),
+            current: BalanceScan::default(),
+            pending: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn walk_scans_executor_transfers_holders_and_gas() {
+        let scans = Arc::new(ScanStore::default());
+        let mut dispatcher = Dispatcher::default();
+        dispatcher.register(handler(&scans));
+
+        let own = [
+            transfer_log(OTHER, EXECUTOR, 5),
+            // Not a Transfer: never routed to the handler.
+            Log {
+                address: USDC,
+                data: LogData::new_unchecked(vec![B256::ZERO], Bytes::new()),
+            },
+        ];
+        let foreign = [transfer_log(OTHER, OTHER, 9), transfer_log(OTHER, USDC, 7)];
+        let block = block(10);
+        dispatcher.dispatch_block(
+            &block,
+            [(tx(0, EXECUTOR), &own[..]), (tx(1, OTHER), &foreign[..])],
+        );
+        dispatcher.flush(Some(10)).await.unwrap();
+        scans.publish_swaps(BlockNumHash::new(10, block.hash), BlockSwaps::default());
+
+        let mut blocks = scans.lock();
+        assert!(blocks.settled(BlockNumHash::new(10, block.hash)));
+        let (scan, _) = blocks.take_committed(&block.hash).unwrap();
+        assert_eq!(scan.transfers.len(), 1);
+        assert_eq!(scan.transfers[0].to, EXECUTOR);
+        // The first transfer proving a balance; not the self-transfer.
+        assert_eq!(scan.holders[&USDC], (EXECUTOR, U256::from(5u64)));
+        assert_eq!(scan.gas.tx_count, 1);
+        assert_eq!(scan.gas.fee_wei, U256::from(100_000u64 * 12));
+    }
+
+    #[test]
+    fn blocks_before_the_walk_or_past_retention_are_unscanned() {
+        let scans = ScanStore::default();
+        let hash = B256::with_last_byte(1);
+        assert!(!scans.lock().settled(BlockNumHash::new(90, hash)));
+
+        scans.publish_balance(BlockNumHash::new(100, hash), BalanceScan::default());
+        let blocks = scans.lock();
+        assert!(blocks.unscanned(99));
+        assert!(!blocks.unscanned(100));
+        // Only the balance scan is in: not settled yet.
+        assert!(!blocks.settled(BlockNumHash::new(100, hash)));
+        drop(blocks);
+
+        scans.publish_balance(
+            BlockNumHash::new(100 + RETAINED_BLOCKS + 1, B256::with_last_byte(2)),
+            BalanceScan::default(),
+        );
+        let blocks = scans.lock();
+        assert!(blocks.unscanned(100));
+        assert!(!blocks.by_hash.contains_key(&hash));
+    }
+}
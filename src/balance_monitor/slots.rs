@@ -3,10 +3,42 @@
 //! Standard Solidity `mapping(address => uint256)` at slot N stores
 //! `balances[holder]` at `keccak256(abi.encode(holder, N))`.
 //!
-//! Most ERC20s (OpenZeppelin) use slot 0. Known exceptions are hardcoded.
+//! Most ERC20s (OpenZeppelin) use slot 0. Known exceptions are hardcoded;
+//! everything else is discovered by probing candidate slots against a holder
+//! known to have a non-zero balance (see [`discover_balance_slot`]), and the
+//! result is cached in the persisted token file.
 
-use alloy_primitives::{address, keccak256, Address, B256, U256};
+use alloy_primitives::{address, b256, keccak256, Address, B256, U256};
 use alloy_sol_types::SolValue;
+use reth::providers::StateProvider;
+use serde::{Deserialize, Serialize};
+
+/// Highest mapping slot index probed during discovery.
+const MAX_PROBE_SLOT: u64 = 32;
+
+/// EIP-1967 implementation slot: `keccak256("eip1967.proxy.implementation") - 1`.
+pub const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// Layout of a token's `balances` mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceSlot {
+    /// Solidity: `keccak256(abi.encode(holder, slot))`.
+    Solidity(u64),
+    /// Vyper: `keccak256(abi.encode(slot, holder))`.
+    Vyper(u64),
+}
+
+impl BalanceSlot {
+    /// Storage key of `balances[holder]`.
+    pub fn storage_key(self, holder: Address) -> B256 {
+        match self {
+            Self::Solidity(slot) => compute_mapping_slot(holder, slot),
+            Self::Vyper(slot) => keccak256((U256::from(slot), holder).abi_encode()),
+        }
+    }
+}
 
 /// Known tokens with non-standard balance mapping slots.
 const SLOT_OVERRIDES: &[(Address, u64)] = &[
@@ -28,12 +60,59 @@ pub fn balance_storage_slot(token: Address, holder: Address) -> B256 {
 
 /// Look up the balance mapping slot for a token. Returns 0 for standard tokens.
 fn slot_for_token(token: Address) -> u64 {
-    for &(addr, slot) in SLOT_OVERRIDES {
-        if addr == token {
-            return slot;
+    override_slot(token).unwrap_or(0)
+}
+
+/// Hardcoded balance mapping slot, if the token has one.
+pub fn override_slot(token: Address) -> Option<u64> {
+    SLOT_OVERRIDES
+        .iter()
+        .find(|(addr, _)| *addr == token)
+        .map(|&(_, slot)| slot)
+}
+
+/// EIP-1967 implementation behind `token`, if it is such a proxy.
+pub fn eip1967_implementation(
+    state: &dyn StateProvider,
+    token: Address,
+) -> eyre::Result<Option<Address>> {
+    let raw = state
+        .storage(token, EIP1967_IMPLEMENTATION_SLOT)?
+        .unwrap_or(U256::ZERO);
+    if raw.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(Address::from_word(raw.into())))
+}
+
+/// Find the balance mapping of `token` by probing candidate slots for
+/// `holder`, who is known to hold at least `min_balance` (> 0). `hint` (e.g. a
+/// slot already discovered for another proxy with the same implementation) is
+/// tried first, then Solidity and Vyper layouts for slots `0..=MAX_PROBE_SLOT`.
+/// Proxies keep balances in their own storage, so probing always reads
+/// `token` itself.
+pub fn discover_balance_slot(
+    state: &dyn StateProvider,
+    token: Address,
+    holder: Address,
+    min_balance: U256,
+    hint: Option<BalanceSlot>,
+) -> eyre::Result<Option<BalanceSlot>> {
+    if min_balance.is_zero() {
+        return Ok(None);
+    }
+    let candidates = hint.into_iter().chain(
+        (0..=MAX_PROBE_SLOT).flat_map(|i| [BalanceSlot::Solidity(i), BalanceSlot::Vyper(i)]),
+    );
+    for candidate in candidates {
+        let value = state
+            .storage(token, candidate.storage_key(holder))?
+            .unwrap_or(U256::ZERO);
+        if value >= min_balance {
+            return Ok(Some(candidate));
         }
     }
-    0
+    Ok(None)
 }
 
 /// `keccak256(abi.encode(key, mapping_slot))`
@@ -83,4 +162,21 @@ mod tests {
         let expected = compute_mapping_slot(holder, 3);
         assert_eq!(slot, expected);
     }
+
+    #[test]
+    fn balance_slot_layouts_order_keys() {
+        let holder = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert_eq!(
+            BalanceSlot::Solidity(9).storage_key(holder),
+            compute_mapping_slot(holder, 9)
+        );
+        assert_eq!(
+            BalanceSlot::Vyper(3).storage_key(holder),
+            keccak256((U256::from(3u64), holder).abi_encode())
+        );
+        assert_ne!(
+            BalanceSlot::Solidity(3).storage_key(holder),
+            BalanceSlot::Vyper(3).storage_key(holder)
+        );
+    }
 }
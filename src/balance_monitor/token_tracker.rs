@@ -3,16 +3,46 @@
 //! Tokens are added when discovered via whitelist updates but never removed.
 //! This ensures we don't lose track of a token (and its balance) if it gets
 //! removed from the whitelist while the service is down.
+//!
+//! Discovered balance slots (and the EIP-1967 implementation they were found
+//! behind) are persisted alongside decimals so discovery runs once per token.
+//! Call-mode marks (see `call_balance`) come from config and are not persisted.
 
+use super::slots::BalanceSlot;
 use alloy_primitives::Address;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
+/// Per-token tracking state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedToken {
+    pub decimals: u8,
+    /// Discovered balance mapping slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_slot: Option<BalanceSlot>,
+    /// EIP-1967 implementation the slot was discovered behind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implementation: Option<Address>,
+}
+
+impl TrackedToken {
+    fn new(decimals: u8) -> Self {
+        Self {
+            decimals,
+            balance_slot: None,
+            implementation: None,
+        }
+    }
+}
+
 /// Tracks which tokens to monitor. Append-only — tokens are never removed.
 pub struct TokenTracker {
-    /// token address → decimals
-    tokens: HashMap<Address, u8>,
+    /// token address → tracking state
+    tokens: HashMap<Address, TrackedToken>,
+    /// Tokens whose balance is read with a `balanceOf` call, not a slot.
+    call_mode: HashSet<Address>,
     /// Path to JSON persistence file
     persist_path: PathBuf,
 }
@@ -26,6 +56,7 @@ impl TokenTracker {
         }
         Self {
             tokens,
+            call_mode: HashSet::new(),
             persist_path,
         }
     }
@@ -35,11 +66,63 @@ impl TokenTracker {
         if self.tokens.contains_key(&token) {
             return false;
         }
-        self.tokens.insert(token, decimals);
+        self.tokens.insert(token, TrackedToken::new(decimals));
+        self.persist();
+        true
+    }
+
+    /// Record a discovered balance slot for a tracked token and persist it.
+    pub fn set_balance_slot(
+        &mut self,
+        token: Address,
+        slot: BalanceSlot,
+        implementation: Option<Address>,
+    ) {
+        let Some(entry) = self.tokens.get_mut(&token) else {
+            return;
+        };
+        entry.balance_slot = Some(slot);
+        entry.implementation = implementation;
+        self.persist();
+    }
+
+    /// Mark tokens as call-mode. Marks apply whether or not the token is
+    /// tracked yet, so whitelist discovery picks them up later.
+    pub fn set_call_mode(&mut self, tokens: impl IntoIterator<Item = Address>) {
+        self.call_mode.extend(tokens);
+    }
+
+    /// Whether the token's balance is read with a `balanceOf` call.
+    pub fn is_call_mode(&self, token: &Address) -> bool {
+        self.call_mode.contains(token)
+    }
+
+    /// Tracked call-mode tokens.
+    pub fn call_mode_tokens(&self) -> Vec<Address> {
+        self.tokens
+            .keys()
+            .filter(|token| self.call_mode.contains(token))
+            .copied()
+            .collect()
+    }
+
+    /// Discovered balance slot for a token, if any.
+    pub fn balance_slot(&self, token: &Address) -> Option<BalanceSlot> {
+        self.tokens.get(token)?.balance_slot
+    }
+
+    /// A slot already discovered for another token behind `implementation`.
+    pub fn slot_for_implementation(&self, implementation: Address) -> Option<BalanceSlot> {
+        self.tokens
+            .values()
+            .find(|t| t.implementation == Some(implementation))
+            .and_then(|t| t.balance_slot)
+    }
+
+    fn persist(&self) {
         if let Err(e) = save_to_disk(&self.persist_path, &self.tokens) {
             warn!(error = %e, "failed to persist token set");
         }
-        true
     }
 
     /// Check if a token is being tracked.
@@ -49,12 +132,12 @@ impl TokenTracker {
 
     /// Get the decimals for a tracked token.
     pub fn decimals(&self, token: &Address) -> Option<u8> {
-        self.tokens.get(token).copied()
+        self.tokens.get(token).map(|t| t.decimals)
     }
 
     /// Iterate over all tracked tokens.
     pub fn iter(&self) -> impl Iterator<Item = (&Address, &u8)> {
-        self.tokens.iter()
+        self.tokens.iter().map(|(token, t)| (token, &t.decimals))
     }
 
     /// Number of tracked tokens.
@@ -63,14 +146,27 @@ impl TokenTracker {
     }
 }
 
-/// JSON format: `{ "0xaddr": decimals, ... }`
-fn load_from_disk(path: &Path) -> Option<HashMap<Address, u8>> {
+/// Persisted entry: bare decimals (legacy format, and tokens without a
+/// discovered slot) or the full tracking state.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PersistedToken {
+    Decimals(u8),
+    Tracked(TrackedToken),
+}
+
+/// JSON format: `{ "0xaddr": decimals | { "decimals", "balance_slot", "implementation" }, ... }`
+fn load_from_disk(path: &Path) -> Option<HashMap<Address, TrackedToken>> {
     let content = std::fs::read_to_string(path).ok()?;
-    let raw: HashMap<String, u8> = serde_json::from_str(&content).ok()?;
+    let raw: HashMap<String, PersistedToken> = serde_json::from_str(&content).ok()?;
     let mut tokens = HashMap::new();
-    for (addr_str, decimals) in raw {
+    for (addr_str, entry) in raw {
         if let Ok(addr) = addr_str.parse::<Address>() {
-            tokens.insert(addr, decimals);
+            let token = match entry {
+                PersistedToken::Decimals(decimals) => TrackedToken::new(decimals),
+                PersistedToken::Tracked(token) => token,
+            };
+            tokens.insert(addr, token);
         } else {
             warn!(address = %addr_str, "skipping invalid address in persisted token set");
         }
@@ -81,13 +177,20 @@ fn load_from_disk(path: &Path) -> Option<HashMap<Address, u8>> {
 /// Atomic write: serialize → write to `.tmp` → rename over target.
 /// `rename` is atomic on POSIX when src and dst are on the same filesystem
 /// (guaranteed here since they share the same parent directory).
-fn save_to_disk(path: &Path, tokens: &HashMap<Address, u8>) -> Result<(), String> {
+fn save_to_disk(path: &Path, tokens: &HashMap<Address, TrackedToken>) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| format!("create dir: {e}"))?;
     }
-    let raw: HashMap<String, u8> = tokens
+    let raw: HashMap<String, PersistedToken> = tokens
         .iter()
-        .map(|(addr, dec)| (format!("{addr:#x}"), *dec))
+        .map(|(addr, token)| {
+            let entry = if token.balance_slot.is_none() {
+                PersistedToken::Decimals(token.decimals)
+            } else {
+                PersistedToken::Tracked(*token)
+            };
+            (format!("{addr:#x}"), entry)
+        })
         .collect();
     let json = serde_json::to_string_pretty(&raw).map_err(|e| format!("serialize: {e}"))?;
 
@@ -130,6 +233,27 @@ mod tests {
         assert_eq!(tracker.decimals(&weth), Some(18));
     }
 
+    #[test]
+    fn discovered_slot_persists_and_legacy_entries_load() {
+        let tmp = tempfile();
+        let usdt = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+        let impl_addr = address!("43506849D7C04F9138D1A2050bbF3A0c054402dd");
+        std::fs::write(&tmp, format!(r#"{{"{usdt:#x}": 6}}"#)).unwrap();
+
+        let mut tracker = TokenTracker::new(tmp.clone());
+        assert_eq!(tracker.decimals(&usdt), Some(6));
+        assert_eq!(tracker.balance_slot(&usdt), None);
+        tracker.set_balance_slot(usdt, BalanceSlot::Solidity(2), Some(impl_addr));
+
+        let tracker = TokenTracker::new(tmp);
+        assert_eq!(tracker.decimals(&usdt), Some(6));
+        assert_eq!(tracker.balance_slot(&usdt), Some(BalanceSlot::Solidity(2)));
+        assert_eq!(
+            tracker.slot_for_implementation(impl_addr),
+            Some(BalanceSlot::Solidity(2))
+        );
+    }
+
     #[test]
     fn loads_empty_if_no_file() {
         let tracker = TokenTracker::new(PathBuf::from("/tmp/nonexistent_test_balance_tokens.json"));
@@ -0,0 +1,123 @@
+//! EIP-1967 proxy upgrade alerts for tracked tokens.
+//!
+//! Balances are read from a discovered storage slot and kept current from
+//! `Transfer` events, both of which assume the token's implementation stays
+//! put. An upgrade can move the balance mapping or change transfer semantics
+//! (fees, rebasing) without any event the monitor understands. Every committed
+//! block's state diff is checked for writes to the EIP-1967 implementation slot
+//! of a tracked token; when the implementation read before and after the block
+//! differs, an alert with both implementations and the balance slot in use is
+//! published on `alerts.upgrades.<id>`, so ops can re-verify the token (or
+//! switch it to call mode) before balances drift.
+
+use super::slots::{self, BalanceSlot, EIP1967_IMPLEMENTATION_SLOT};
+use super::token_tracker::TokenTracker;
+use alloy_primitives::{Address, U256};
+use reth::providers::StateProviderFactory;
+use reth_node_api::NodePrimitives;
+use reth_provider::Chain;
+use reth_revm::revm::database::AccountRevert;
+
+/// NATS alert message.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProxyUpgradeAlert {
+    pub chain: String,
+    pub block_number: u64,
+    pub token: String,
+    /// `None` when the token was not a proxy before the block.
+    pub previous_implementation: Option<String>,
+    /// `None` when the implementation slot was cleared.
+    pub implementation: Option<String>,
+    /// Slot balances are read from, which the upgrade may have moved.
+    pub balance_slot: Option<BalanceSlot>,
+    pub decimals: u8,
+    pub ts: u64,
+}
+
+/// A tracked token's implementation change in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyUpgrade {
+    pub block_number: u64,
+    pub token: Address,
+    pub previous_implementation: Option<Address>,
+    pub implementation: Option<Address>,
+}
+
+/// Tokens among one block's state-diff reverts whose implementation slot
+/// was written.
+pub fn implementation_writes(
+    reverts: &[(Address, AccountRevert)],
+    is_tracked: impl Fn(&Address) -> bool,
+) -> Vec<Address> {
+    let slot = U256::from_be_bytes(EIP1967_IMPLEMENTATION_SLOT.0);
+    let mut tokens: Vec<Address> = reverts
+        .iter()
+        .filter(|(token, revert)| revert.storage.contains_key(&slot) && is_tracked(token))
+        .map(|(token, _)| *token)
+        .collect();
+    tokens.sort_unstable();
+    tokens.dedup();
+    tokens
+}
+
+/// Implementation changes of tracked tokens in a committed chain, oldest
+/// first. A write that leaves the implementation unchanged is not an upgrade.
+pub fn proxy_upgrades<N: NodePrimitives>(
+    provider: &impl StateProviderFactory,
+    chain: &Chain<N>,
+    tracker: &TokenTracker,
+) -> eyre::Result<Vec<ProxyUpgrade>> {
+    let outcome = chain.execution_outcome();
+    let mut upgrades = Vec::new();
+    for (offset, reverts) in outcome.bundle.reverts.iter().enumerate() {
+        let tokens = implementation_writes(reverts, |token| tracker.contains(token));
+        if tokens.is_empty() {
+            continue;
+        }
+        let block_number = outcome.first_block() + offset as u64;
+        let parent = provider.history_by_block_number(block_number.saturating_sub(1))?;
+        let post = provider.history_by_block_number(block_number)?;
+        for token in tokens {
+            let previous_implementation = slots::eip1967_implementation(parent.as_ref(), token)?;
+            let implementation = slots::eip1967_implementation(post.as_ref(), token)?;
+            if previous_implementation != implementation {
+                upgrades.push(ProxyUpgrade {
+                    block_number,
+                    token,
+                    previous_implementation,
+                    implementation,
+                });
+            }
+        }
+    }
+    Ok(upgrades)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_revm::revm::database::states::reverts::RevertToSlot;
+
+    #[test]
+    fn implementation_slot_writes_of_tracked_tokens() {
+        let proxy = Address::from([0x11; 20]);
+        let untracked = Address::from([0x22; 20]);
+        let plain = Address::from([0x33; 20]);
+        let write = |slot: U256| AccountRevert {
+            storage: [(slot, RevertToSlot::Some(U256::ZERO))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let implementation_slot = U256::from_be_bytes(EIP1967_IMPLEMENTATION_SLOT.0);
+        let reverts = vec![
+            (proxy, write(implementation_slot)),
+            (untracked, write(implementation_slot)),
+            // A balance write is not an upgrade.
+            (plain, write(U256::from(3u64))),
+        ];
+
+        let tokens = implementation_writes(&reverts, |token| *token != untracked);
+        assert_eq!(tokens, vec![proxy]);
+    }
+}
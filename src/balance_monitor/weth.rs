@@ -0,0 +1,55 @@
+//! WETH9 wrap/unwrap decoding.
+//!
+//! WETH9 `deposit()` / `withdraw()` emit `Deposit(dst, wad)` /
+//! `Withdrawal(src, wad)` and no `Transfer`, so without these the executor's
+//! WETH balance misses every wrap and unwrap until reconciliation. They are
+//! decoded as a mint to `dst` / burn from `src` so the transfer path applies
+//! them. Only known WETH9 deployments are decoded: wrappers that also emit a
+//! mint/burn `Transfer` would otherwise be counted twice.
+
+use crate::transfers::events::DecodedTransfer;
+use alloy_primitives::{address, Address, Log};
+use alloy_sol_types::{sol, SolEvent};
+
+sol! {
+    #[derive(Debug)]
+    event Deposit(address indexed dst, uint256 wad);
+    #[derive(Debug)]
+    event Withdrawal(address indexed src, uint256 wad);
+}
+
+/// WETH9 deployments that emit `Deposit`/`Withdrawal` without `Transfer`.
+const WETH9_ADDRESSES: &[Address] = &[
+    // Ethereum mainnet WETH9
+    address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    // OP-stack predeploy (Optimism, Base)
+    address!("4200000000000000000000000000000000000006"),
+];
+
+/// Decode a WETH9 `Deposit` as a mint and a `Withdrawal` as a burn. Returns
+/// `None` for any other log.
+pub fn decode_wrap(log: &Log) -> Option<DecodedTransfer> {
+    if !WETH9_ADDRESSES.contains(&log.address) {
+        return None;
+    }
+    let topic0 = *log.topics().first()?;
+    if topic0 == Deposit::SIGNATURE_HASH {
+        let deposit = Deposit::decode_log(log).ok()?;
+        Some(DecodedTransfer {
+            token: log.address,
+            from: Address::ZERO,
+            to: deposit.dst,
+            value: deposit.wad,
+        })
+    } else if topic0 == Withdrawal::SIGNATURE_HASH {
+        let withdrawal = Withdrawal::decode_log(log).ok()?;
+        Some(DecodedTransfer {
+            token: log.address,
+            from: withdrawal.src,
+            to: Address::ZERO,
+            value: withdrawal.wad,
+        })
+    } else {
+        None
+    }
+}
@@ -0,0 +1,184 @@
+// Block Fee Market Tracking
+//
+// A `Dispatch` handler (`--exex fees`): for every committed block it records
+// the base fee, gas used against the gas limit and the priority fees of the
+// included transactions, and publishes them as JSON to `fees.blocks.<chain>`
+// after each notification. Execution-cost models then get fee data at the
+// same cadence as pool updates instead of polling `eth_feeHistory`.
+//
+// Priority-fee percentiles are weighted by gas used, as `eth_feeHistory`
+// computes its rewards: the p50 is the tip paid by the transaction covering
+// the middle unit of the block's gas. Empty blocks report zero tips.
+//
+// Blocks reverted before their flush are dropped; a replacement block is
+// published under the same number, and consumers keep the latest per block.
+
+use crate::dispatch::{BlockHandler, BlockInfo, TxInfo};
+use crate::{exex_metrics, health, subjects};
+use alloy_primitives::Log;
+use alloy_primitives::B256;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Gas-weighted tip percentiles of one block, in wei per gas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PriorityFees {
+    pub p10: u128,
+    pub p25: u128,
+    pub p50: u128,
+    pub p75: u128,
+    pub p90: u128,
+}
+
+impl PriorityFees {
+    /// Percentiles of `(priority fee per gas, gas used)` per transaction.
+    pub fn from_txs(mut txs: Vec<(u128, u64)>) -> Self {
+        txs.sort_unstable_by_key(|&(tip, _)| tip);
+        let total_gas: u64 = txs.iter().map(|&(_, gas)| gas).sum();
+        let percentile = |p: u64| -> u128 {
+            let threshold = total_gas as u128 * p as u128 / 100;
+            let mut cumulative = 0u128;
+            for &(tip, gas) in &txs {
+                cumulative += gas as u128;
+                if cumulative >= threshold {
+                    return tip;
+                }
+            }
+            txs.last().map(|&(tip, _)| tip).unwrap_or(0)
+        };
+        Self {
+            p10: percentile(10),
+            p25: percentile(25),
+            p50: percentile(50),
+            p75: percentile(75),
+            p90: percentile(90),
+        }
+    }
+}
+
+/// NATS message with one block's fee market data.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockFees {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    /// `None` before London.
+    pub base_fee_per_gas: Option<u64>,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    pub gas_used_ratio: f64,
+    pub tx_count: u64,
+    pub priority_fees: PriorityFees,
+}
+
+pub struct BlockFeesHandler {
+    chain_id: u64,
+    client: async_nats::Client,
+    subject: String,
+    /// `(priority fee per gas, gas used)` of the block being walked.
+    txs: Vec<(u128, u64)>,
+    /// Walked blocks waiting for the flush.
+    pending: Vec<BlockFees>,
+}
+
+/// Connect to NATS and build the handler.
+pub async fn block_fees_handler() -> eyre::Result<Box<dyn BlockHandler>> {
+    let nats_url =
+        std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+    let client = async_nats::connect(&nats_url).await?;
+    health::watch_nats("fees.nats", client.clone());
+    let subject = subjects::block_fees(&crate::chain::chain_name());
+    info!(subject = %subject, "⛽ Publishing per-block fee market data");
+    Ok(Box::new(BlockFeesHandler {
+        chain_id: crate::chain::active().chain_id,
+        client,
+        subject,
+        txs: Vec::new(),
+        pending: Vec::new(),
+    }))
+}
+
+impl BlockHandler for BlockFeesHandler {
+    fn name(&self) -> &'static str {
+        "fees"
+    }
+
+    fn topics(&self) -> Vec<B256> {
+        Vec::new()
+    }
+
+    fn on_tx(&mut self, _block: &BlockInfo, tx: &TxInfo) {
+        self.txs.push((tx.priority_fee_per_gas, tx.gas_used));
+    }
+
+    fn on_log(&mut self, _block: &BlockInfo, _tx: &TxInfo, _log_index: usize, _log: &Log) {}
+
+    fn end_block(&mut self, block: &BlockInfo) {
+        let txs = std::mem::take(&mut self.txs);
+        self.pending.push(BlockFees {
+            chain_id: self.chain_id,
+            block_number: block.number,
+            timestamp: block.timestamp,
+            base_fee_per_gas: block.base_fee_per_gas,
+            gas_used: block.gas_used,
+            gas_limit: block.gas_limit,
+            gas_used_ratio: if block.gas_limit == 0 {
+                0.0
+            } else {
+                block.gas_used as f64 / block.gas_limit as f64
+            },
+            tx_count: txs.len() as u64,
+            priority_fees: PriorityFees::from_txs(txs),
+        });
+    }
+
+    fn on_revert(&mut self, blocks: &[u64]) {
+        self.pending
+            .retain(|fees| !blocks.contains(&fees.block_number));
+    }
+
+    fn flush(&mut self, _committed_tip: Option<u64>) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async move {
+            for fees in std::mem::take(&mut self.pending) {
+                let payload = serde_json::to_vec(&fees).expect("BlockFees serializes");
+                if let Err(e) = self
+                    .client
+                    .publish(self.subject.clone(), payload.into())
+                    .await
+                {
+                    warn!(
+                        block = fees.block_number,
+                        "Failed to publish block fees: {}", e
+                    );
+                    exex_metrics::record_sink_error("fees");
+                }
+                exex_metrics::record_fees_block();
+                health::record_block("fees", fees.block_number);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_fee_percentiles_are_gas_weighted() {
+        // One big low-tip transaction dominates the lower percentiles.
+        let fees = PriorityFees::from_txs(vec![
+            (5_000_000_000, 21_000),
+            (1_000_000_000, 600_000),
+            (2_000_000_000, 300_000),
+            (3_000_000_000, 79_000),
+        ]);
+        assert_eq!(fees.p10, 1_000_000_000);
+        assert_eq!(fees.p50, 1_000_000_000);
+        assert_eq!(fees.p75, 2_000_000_000);
+        assert_eq!(fees.p90, 2_000_000_000);
+
+        assert_eq!(PriorityFees::from_txs(Vec::new()), PriorityFees::default());
+    }
+}
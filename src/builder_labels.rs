@@ -0,0 +1,74 @@
+// Block Builder Labels
+//
+// Every `BeginBlock` carries the block's fee recipient (the header
+// beneficiary). Under PBS that is usually the builder that won the slot, so
+// `BUILDER_LABELS` maps known fee recipients to a name
+// (`0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5=beaverbuild,...`) and the label
+// goes out as `BeginBlock.builder`, letting analytics group fill quality and
+// reorgs by builder without keeping their own address book. Unlisted
+// recipients (solo stakers, builders paying to the proposer) get `None`.
+
+use alloy_primitives::Address;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+#[derive(Debug, Default)]
+pub struct BuilderLabels {
+    labels: HashMap<Address, String>,
+}
+
+impl BuilderLabels {
+    /// Parse comma-separated `address=label` entries, skipping bad ones.
+    pub fn parse(raw: &str) -> Self {
+        let mut labels = HashMap::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(address, label)| {
+                let label = label.trim();
+                let address = address.trim().parse::<Address>().ok()?;
+                (!label.is_empty()).then(|| (address, label.to_string()))
+            });
+            match parsed {
+                Some((address, label)) => {
+                    labels.insert(address, label);
+                }
+                None => warn!("Ignoring BUILDER_LABELS entry {:?}", entry),
+            }
+        }
+        Self { labels }
+    }
+
+    pub fn from_env() -> Self {
+        let labels = std::env::var("BUILDER_LABELS")
+            .map(|raw| Self::parse(&raw))
+            .unwrap_or_default();
+        if !labels.labels.is_empty() {
+            info!(
+                builders = labels.labels.len(),
+                "🏗️ Labelling BeginBlock fee recipients"
+            );
+        }
+        labels
+    }
+
+    pub fn label(&self, fee_recipient: &Address) -> Option<String> {
+        self.labels.get(fee_recipient).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_parse_and_skip_bad_entries() {
+        let beaver = Address::from([0xbb; 20]);
+        let titan = Address::from([0x77; 20]);
+        let labels = BuilderLabels::parse(&format!(
+            "{beaver}=beaverbuild, {titan} = titan ,0x12=bad,{titan}=,nolabel"
+        ));
+        assert_eq!(labels.label(&beaver).as_deref(), Some("beaverbuild"));
+        assert_eq!(labels.label(&titan).as_deref(), Some("titan"));
+        assert_eq!(labels.label(&Address::ZERO), None);
+        assert_eq!(labels.labels.len(), 2);
+    }
+}
@@ -0,0 +1,366 @@
+// Trace-Based Swap Detection
+//
+// Some AMMs emit no swap log at all, and a pool driven through low-level calls
+// may leave nothing decodable in the receipts. For the tracked pools listed in
+// `TRACE_SWAP_POOLS` (comma-separated addresses), the liquidity ExEx
+// re-executes each committed block on its parent state with a call inspector
+// and records every token movement the calls make: ERC-20 `transfer` /
+// `transferFrom` calls and native ETH call value (as token `Address::ZERO`).
+// Movements inside reverted call frames are discarded.
+//
+// Within one transaction, a listed pool whose net flow is positive in one of
+// its tokens and negative in the other swapped: the pair becomes a
+// `DecodedEvent::TracedSwap` (input = what the pool gained, output = what it
+// paid) and joins the block's decoded events with `log_index = u64::MAX`,
+// after the transaction's logs. From there it follows the log path: emitted as
+// a `PoolUpdate::TracedSwap`, journaled, and reverted on reorgs.
+//
+// Only transactions that call a listed pool directly, or whose receipt has an
+// ERC-20 `Transfer` log to or from one, are candidates; blocks without a
+// candidate are not re-executed. Pre-block system calls (beacon root, block
+// hashes) are not replayed, which cannot move pool tokens. `exex replay`
+// does not trace.
+
+use crate::events::DecodedEvent;
+use crate::exex_metrics;
+use crate::pool_tracker::PoolTracker;
+use crate::reorg_journal::JournaledEvent;
+use alloy_consensus::{BlockHeader, Transaction, TxReceipt};
+use alloy_primitives::{Address, Log, I256, U256};
+use alloy_sol_types::{sol, SolCall, SolEvent};
+use reth::primitives::RecoveredBlock;
+use reth::providers::StateProviderFactory;
+use reth_evm::{ConfigureEvm, Evm};
+use reth_node_api::{BlockBody, BlockTy, FullNodeComponents, PrimitivesTy};
+use reth_provider::Chain;
+use reth_revm::database::StateProviderDatabase;
+use reth_revm::db::State;
+use reth_revm::revm::context_interface::ContextTr;
+use reth_revm::revm::interpreter::{CallInputs, CallOutcome, CallScheme};
+use reth_revm::revm::Inspector;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{debug, info, warn};
+
+sol! {
+    function transfer(address to, uint256 amount) external returns (bool);
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+    event Transfer(address indexed from, address indexed to, uint256 value);
+}
+
+/// One token movement made by a call. `token` is `Address::ZERO` for ETH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenTransfer {
+    pub token: Address,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+/// Records token movements, dropping those of reverted frames.
+#[derive(Debug, Default)]
+pub struct TransferInspector {
+    transfers: Vec<TokenTransfer>,
+    /// `transfers.len()` when each open frame started.
+    frames: Vec<usize>,
+}
+
+impl TransferInspector {
+    /// Movements of the last transaction.
+    pub fn take(&mut self) -> Vec<TokenTransfer> {
+        self.frames.clear();
+        std::mem::take(&mut self.transfers)
+    }
+
+    /// A `CALL` from `caller` to `target`, which is the token for transfers.
+    fn record(&mut self, caller: Address, target: Address, input: &[u8], value: U256) {
+        if !value.is_zero() {
+            self.transfers.push(TokenTransfer {
+                token: Address::ZERO,
+                from: caller,
+                to: target,
+                amount: value,
+            });
+        }
+        if let Ok(call) = transferCall::abi_decode(input) {
+            self.transfers.push(TokenTransfer {
+                token: target,
+                from: caller,
+                to: call.to,
+                amount: call.amount,
+            });
+        } else if let Ok(call) = transferFromCall::abi_decode(input) {
+            self.transfers.push(TokenTransfer {
+                token: target,
+                from: call.from,
+                to: call.to,
+                amount: call.amount,
+            });
+        }
+    }
+}
+
+impl<CTX: ContextTr> Inspector<CTX> for TransferInspector {
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.frames.push(self.transfers.len());
+        // Delegate and static calls move nothing of their own.
+        if inputs.scheme == CallScheme::Call {
+            let input = inputs.input.bytes(context);
+            let value = inputs.transfer_value().unwrap_or_default();
+            self.record(inputs.caller, inputs.target_address, &input, value);
+        }
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+        let start = self.frames.pop().unwrap_or(0);
+        if !outcome.result.is_ok() {
+            self.transfers.truncate(start);
+        }
+    }
+}
+
+/// A pool's swap within one transaction, from its net token flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetSwap {
+    pub pool: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+/// Swaps of `pools` (address → token0, token1) in one transaction's movements.
+/// A pool whose flow is not one token in and the other out did not swap.
+pub fn net_swaps(
+    transfers: &[TokenTransfer],
+    pools: &HashMap<Address, (Address, Address)>,
+) -> Vec<NetSwap> {
+    let mut flows: BTreeMap<Address, (I256, I256)> = BTreeMap::new();
+    for transfer in transfers {
+        let amount = I256::try_from(transfer.amount).unwrap_or(I256::MAX);
+        for (pool, sign) in [(transfer.to, amount), (transfer.from, -amount)] {
+            let Some(&(token0, token1)) = pools.get(&pool) else {
+                continue;
+            };
+            let flow = flows.entry(pool).or_default();
+            if transfer.token == token0 {
+                flow.0 = flow.0.saturating_add(sign);
+            } else if transfer.token == token1 {
+                flow.1 = flow.1.saturating_add(sign);
+            }
+        }
+    }
+    flows
+        .into_iter()
+        .filter_map(|(pool, (flow0, flow1))| {
+            let (token0, token1) = pools[&pool];
+            let swap = |token_in, token_out, gained: I256, paid: I256| NetSwap {
+                pool,
+                token_in,
+                token_out,
+                amount_in: gained.unsigned_abs(),
+                amount_out: paid.unsigned_abs(),
+            };
+            if flow0.is_positive() && flow1.is_negative() {
+                Some(swap(token0, token1, flow0, flow1))
+            } else if flow1.is_positive() && flow0.is_negative() {
+                Some(swap(token1, token0, flow1, flow0))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// `TRACE_SWAP_POOLS`: the pools to reconstruct swaps for.
+#[derive(Debug)]
+pub struct SwapTracer {
+    pools: HashSet<Address>,
+}
+
+impl SwapTracer {
+    pub fn from_env() -> Option<Self> {
+        let raw = std::env::var("TRACE_SWAP_POOLS").ok()?;
+        let mut pools = HashSet::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.parse::<Address>() {
+                Ok(pool) => {
+                    pools.insert(pool);
+                }
+                Err(e) => warn!("Ignoring TRACE_SWAP_POOLS entry {:?}: {}", entry, e),
+            }
+        }
+        if pools.is_empty() {
+            return None;
+        }
+        info!(pools = pools.len(), "🔬 Trace-based swap detection enabled");
+        Some(Self { pools })
+    }
+
+    /// Traced swaps of every block of a committed chain, by block number.
+    /// A block that fails to re-execute is logged and yields none.
+    pub fn trace_chain<Node: FullNodeComponents>(
+        &self,
+        provider: &Node::Provider,
+        evm_config: &Node::Evm,
+        chain: &Chain<PrimitivesTy<Node::Types>>,
+        tracker: &PoolTracker,
+    ) -> BTreeMap<u64, Vec<JournaledEvent>> {
+        // Listed pools the whitelist still tracks, with their tokens.
+        let pools: HashMap<Address, (Address, Address)> = self
+            .pools
+            .iter()
+            .filter_map(|pool| {
+                let meta = tracker.get_by_address(pool)?;
+                Some((*pool, (meta.token0, meta.token1)))
+            })
+            .collect();
+        let mut traced = BTreeMap::new();
+        if pools.is_empty() {
+            return traced;
+        }
+        for (block, receipts) in chain.blocks_and_receipts() {
+            let candidates: Vec<usize> = block
+                .body()
+                .transactions()
+                .iter()
+                .zip(receipts)
+                .enumerate()
+                .filter(|(_, (tx, receipt))| {
+                    tx.to().is_some_and(|to| pools.contains_key(&to))
+                        || receipt
+                            .logs()
+                            .iter()
+                            .any(|log| moves_pool_tokens(log, &pools))
+                })
+                .map(|(tx_index, _)| tx_index)
+                .collect();
+            let Some(&last) = candidates.last() else {
+                continue;
+            };
+            match self.trace_block::<Node>(provider, evm_config, block, &candidates, last, &pools) {
+                Ok(events) if !events.is_empty() => {
+                    traced.insert(block.number(), events);
+                }
+                Ok(_) => {}
+                Err(e) => warn!(block = block.number(), "Swap tracing failed: {}", e),
+            }
+        }
+        traced
+    }
+
+    fn trace_block<Node: FullNodeComponents>(
+        &self,
+        provider: &Node::Provider,
+        evm_config: &Node::Evm,
+        block: &RecoveredBlock<BlockTy<Node::Types>>,
+        candidates: &[usize],
+        last: usize,
+        pools: &HashMap<Address, (Address, Address)>,
+    ) -> eyre::Result<Vec<JournaledEvent>> {
+        let parent = provider.history_by_block_number(block.number().saturating_sub(1))?;
+        let mut db = State::builder()
+            .with_database(StateProviderDatabase::new(parent))
+            .build();
+        let env = evm_config.evm_env(block.header())?;
+        let mut evm =
+            evm_config.evm_with_env_and_inspector(&mut db, env, TransferInspector::default());
+
+        let mut events = Vec::new();
+        for (tx_index, tx) in block.transactions_recovered().enumerate().take(last + 1) {
+            evm.transact_commit(evm_config.tx_env(tx))
+                .map_err(|e| eyre::eyre!("tx {tx_index} failed to re-execute: {e}"))?;
+            let transfers = evm.inspector_mut().take();
+            if !candidates.contains(&tx_index) {
+                continue;
+            }
+            for swap in net_swaps(&transfers, pools) {
+                debug!(pool = %swap.pool, tx_index, "Traced swap without logs");
+                exex_metrics::record_liquidity_traced_swap();
+                events.push(JournaledEvent {
+                    tx_index: tx_index as u64,
+                    log_index: u64::MAX,
+                    event: DecodedEvent::TracedSwap {
+                        pool: swap.pool,
+                        token_in: swap.token_in,
+                        token_out: swap.token_out,
+                        amount_in: swap.amount_in,
+                        amount_out: swap.amount_out,
+                    },
+                });
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Merge a block's traced swaps into its decoded events, in execution order.
+pub fn merge_traced(events: &mut Vec<JournaledEvent>, traced: Option<Vec<JournaledEvent>>) {
+    if let Some(traced) = traced {
+        events.extend(traced);
+        events.sort_by_key(|event| (event.tx_index, event.log_index));
+    }
+}
+
+/// An ERC-20 `Transfer` log to or from a listed pool.
+fn moves_pool_tokens(log: &Log, pools: &HashMap<Address, (Address, Address)>) -> bool {
+    let topics = log.topics();
+    topics.first() == Some(&Transfer::SIGNATURE_HASH)
+        && topics[1..]
+            .iter()
+            .take(2)
+            .any(|topic| pools.contains_key(&Address::from_word(*topic)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_flows_become_swaps() {
+        let pool = Address::from([0xaa; 20]);
+        let other_pool = Address::from([0xbb; 20]);
+        let token0 = Address::from([1u8; 20]);
+        let token1 = Address::from([2u8; 20]);
+        let trader = Address::from([3u8; 20]);
+        let pools = HashMap::from([(pool, (token0, token1)), (other_pool, (token0, token1))]);
+        let transfer = |token, from, to, amount: u64| TokenTransfer {
+            token,
+            from,
+            to,
+            amount: U256::from(amount),
+        };
+
+        let swaps = net_swaps(
+            &[
+                // token1 in (in two legs), token0 out.
+                transfer(token1, trader, pool, 60),
+                transfer(token1, trader, pool, 40),
+                transfer(token0, pool, trader, 5),
+                // Both tokens into the other pool: a deposit, not a swap.
+                transfer(token0, trader, other_pool, 1),
+                transfer(token1, trader, other_pool, 1),
+            ],
+            &pools,
+        );
+        assert_eq!(
+            swaps,
+            vec![NetSwap {
+                pool,
+                token_in: token1,
+                token_out: token0,
+                amount_in: U256::from(100u64),
+                amount_out: U256::from(5u64),
+            }]
+        );
+
+        let mut inspector = TransferInspector::default();
+        let call = transferCall {
+            to: pool,
+            amount: U256::from(7u64),
+        }
+        .abi_encode();
+        inspector.record(trader, token0, &call, U256::ZERO);
+        assert_eq!(inspector.take(), vec![transfer(token0, trader, pool, 7)]);
+    }
+}
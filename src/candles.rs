@@ -0,0 +1,480 @@
+// Per-Pool OHLCV Candles
+//
+// Optional (`CANDLE_INTERVALS`, e.g. `1s,1m`): every forward V3/V4 swap of a
+// tracked pool is folded into one candle per configured interval, bucketed by
+// block timestamp. A candle carries the open/high/low/close post-swap price
+// (token1 per token0 in whole-token units, as in `SwapQuote`), both token
+// volumes, the swap count and the blocks it spans. Pools without known
+// decimals for both tokens get no candles, and intervals without swaps get
+// none either (no flat fill-forward).
+//
+// A candle closes at the first block whose timestamp is past its bucket; it is
+// then published as JSON to `candles.<chain>.<interval>` and, when
+// `CANDLES_DATABASE_URL` is set, upserted into `pool_candles`. A reorg drops
+// the open candles the reverted blocks touched (including their swaps from
+// earlier blocks), deletes stored candles that reach into the reverted range
+// and publishes the first reverted block to `candles.<chain>.revert`;
+// consumers key candles by `(pool, interval_secs, open_time)`.
+//
+// Publishing runs on its own task behind a bounded queue; a full queue drops
+// candles (counted, never blocking the block loop).
+
+use crate::price_quote::{normalize_amount, normalized_price};
+use crate::types::{PoolIdentifier, PoolMetadata, PoolUpdate};
+use crate::{exex_metrics, subjects};
+use alloy_primitives::I256;
+use serde::Serialize;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Parse one interval: seconds with an `s`, `m` or `h` unit (`1s`, `5m`).
+pub fn parse_interval(raw: &str) -> eyre::Result<u64> {
+    let raw = raw.trim();
+    let (count, scale) = if let Some(count) = raw.strip_suffix('s') {
+        (count, 1)
+    } else if let Some(count) = raw.strip_suffix('m') {
+        (count, 60)
+    } else if let Some(count) = raw.strip_suffix('h') {
+        (count, 3600)
+    } else {
+        eyre::bail!("candle interval {raw:?} needs an s, m or h unit")
+    };
+    match count.parse::<u64>() {
+        Ok(count) if count > 0 => Ok(count * scale),
+        _ => eyre::bail!("candle interval {raw:?} is not a positive duration"),
+    }
+}
+
+/// `1s`, `1m`, `4h`: the interval's subject token.
+pub fn interval_label(secs: u64) -> String {
+    if secs % 3600 == 0 {
+        format!("{}h", secs / 3600)
+    } else if secs % 60 == 0 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+/// One pool's OHLCV over `[open_time, open_time + interval_secs)`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candle {
+    pub chain_id: u64,
+    pub pool: String,
+    pub interval_secs: u64,
+    /// Bucket start, unix seconds.
+    pub open_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Absolute token0 / token1 swapped, in whole-token units.
+    pub volume0: f64,
+    pub volume1: f64,
+    pub swap_count: u64,
+    pub first_block: u64,
+    pub last_block: u64,
+}
+
+/// Open candles per pool and interval.
+#[derive(Debug, Default)]
+pub struct CandleAggregator {
+    chain_id: u64,
+    intervals: Vec<u64>,
+    open: HashMap<(PoolIdentifier, u64), Candle>,
+}
+
+impl CandleAggregator {
+    pub fn new(chain_id: u64, intervals: Vec<u64>) -> Self {
+        Self {
+            chain_id,
+            intervals,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Fold one swap into every interval's candle for the pool. The block's
+    /// `close_through` must run first, so an open candle is always the
+    /// block's bucket.
+    pub fn record(
+        &mut self,
+        pool_id: &PoolIdentifier,
+        block_number: u64,
+        block_timestamp: u64,
+        price: f64,
+        volume0: f64,
+        volume1: f64,
+    ) {
+        for &interval_secs in &self.intervals {
+            let open_time = block_timestamp - block_timestamp % interval_secs;
+            let candle = self
+                .open
+                .entry((pool_id.clone(), interval_secs))
+                .or_insert_with(|| Candle {
+                    chain_id: self.chain_id,
+                    pool: pool_id.to_hex(),
+                    interval_secs,
+                    open_time,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume0: 0.0,
+                    volume1: 0.0,
+                    swap_count: 0,
+                    first_block: block_number,
+                    last_block: block_number,
+                });
+            candle.high = candle.high.max(price);
+            candle.low = candle.low.min(price);
+            candle.close = price;
+            candle.volume0 += volume0;
+            candle.volume1 += volume1;
+            candle.swap_count += 1;
+            candle.last_block = block_number;
+        }
+    }
+
+    /// Remove and return the candles whose bucket ends at or before
+    /// `timestamp`, oldest first.
+    pub fn close_through(&mut self, timestamp: u64) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        self.open.retain(|_, candle| {
+            if candle.open_time + candle.interval_secs <= timestamp {
+                closed.push(candle.clone());
+                false
+            } else {
+                true
+            }
+        });
+        closed.sort_by(|a, b| {
+            (a.open_time, a.interval_secs, &a.pool).cmp(&(b.open_time, b.interval_secs, &b.pool))
+        });
+        closed
+    }
+
+    /// Blocks from `first_block` on were reverted: drop the open candles
+    /// they touched.
+    pub fn revert_from(&mut self, first_block: u64) {
+        self.open
+            .retain(|_, candle| candle.last_block < first_block);
+    }
+}
+
+#[derive(Debug)]
+enum CandleMessage {
+    Closed(Vec<Candle>),
+    Revert { first_block: u64 },
+}
+
+/// Candle aggregation plus the queue to its publisher task.
+#[derive(Debug)]
+pub struct CandleFeed {
+    aggregator: CandleAggregator,
+    tx: mpsc::Sender<CandleMessage>,
+}
+
+impl CandleFeed {
+    /// Start the publisher when `CANDLE_INTERVALS` lists at least one interval.
+    pub fn spawn_from_env() -> Option<Self> {
+        let raw = std::env::var("CANDLE_INTERVALS").ok()?;
+        let mut intervals = Vec::new();
+        for entry in raw.split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_interval(entry) {
+                Ok(secs) => intervals.push(secs),
+                Err(e) => warn!("Ignoring CANDLE_INTERVALS entry: {}", e),
+            }
+        }
+        intervals.sort_unstable();
+        intervals.dedup();
+        if intervals.is_empty() {
+            return None;
+        }
+        let nats_url =
+            std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+        let database_url = std::env::var("CANDLES_DATABASE_URL").ok();
+        let chain_id = crate::chain::active().chain_id;
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(publish(rx, nats_url, database_url, chain_id));
+        Some(Self {
+            aggregator: CandleAggregator::new(chain_id, intervals),
+            tx,
+        })
+    }
+
+    /// Fold a forward V3/V4 swap in. Other updates and pools without both
+    /// token decimals are ignored.
+    pub fn record_swap(
+        &mut self,
+        pool: &PoolMetadata,
+        update: &PoolUpdate,
+        amount0: I256,
+        amount1: I256,
+        block_number: u64,
+        block_timestamp: u64,
+    ) {
+        let sqrt_price_x96 = match update {
+            PoolUpdate::V3Swap { sqrt_price_x96, .. }
+            | PoolUpdate::V4Swap { sqrt_price_x96, .. } => *sqrt_price_x96,
+            _ => return,
+        };
+        let (Some(decimals0), Some(decimals1)) = (pool.token0_decimals, pool.token1_decimals)
+        else {
+            return;
+        };
+        self.aggregator.record(
+            &pool.pool_id,
+            block_number,
+            block_timestamp,
+            normalized_price(sqrt_price_x96, decimals0, decimals1),
+            normalize_amount(amount0, decimals0),
+            normalize_amount(amount1, decimals1),
+        );
+    }
+
+    /// Publish the candles a block at `block_timestamp` closes.
+    pub fn close_through(&mut self, block_timestamp: u64) {
+        let closed = self.aggregator.close_through(block_timestamp);
+        if !closed.is_empty() {
+            self.send(CandleMessage::Closed(closed));
+        }
+    }
+
+    pub fn revert_from(&mut self, first_block: u64) {
+        self.aggregator.revert_from(first_block);
+        self.send(CandleMessage::Revert { first_block });
+    }
+
+    fn send(&self, message: CandleMessage) {
+        if self.tx.try_send(message).is_err() {
+            warn!("Candle queue full, dropping candles");
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+async fn publish(
+    mut rx: mpsc::Receiver<CandleMessage>,
+    nats_url: String,
+    database_url: Option<String>,
+    chain_id: u64,
+) {
+    let client = match async_nats::connect(&nats_url).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            warn!(
+                "Candle publisher could not connect to NATS at {}: {}",
+                nats_url, e
+            );
+            None
+        }
+    };
+    let db = match database_url {
+        Some(url) => match CandleDb::new(&url, &chain_id.to_string()).await {
+            Ok(db) => Some(db),
+            Err(e) => {
+                warn!("Candle DB unavailable, candles are only published: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    let chain = crate::chain::chain_name();
+    info!(chain = %chain, postgres = db.is_some(), "🕯️ Publishing pool candles");
+
+    while let Some(message) = rx.recv().await {
+        match message {
+            CandleMessage::Closed(candles) => {
+                exex_metrics::record_liquidity_candles(candles.len() as u64);
+                if let Some(db) = &db {
+                    if let Err(e) = db.upsert_candles(&candles).await {
+                        warn!("Failed to store candles: {}", e);
+                        exex_metrics::record_sink_error("liquidity");
+                    }
+                }
+                if let Some(client) = &client {
+                    for candle in &candles {
+                        let subject =
+                            subjects::candles(&chain, &interval_label(candle.interval_secs));
+                        let payload = serde_json::to_vec(candle).expect("Candle serializes");
+                        if let Err(e) = client.publish(subject, payload.into()).await {
+                            warn!("Failed to publish candle: {}", e);
+                            exex_metrics::record_sink_error("liquidity");
+                        }
+                    }
+                }
+            }
+            CandleMessage::Revert { first_block } => {
+                if let Some(db) = &db {
+                    if let Err(e) = db.delete_from_block(first_block).await {
+                        warn!("Failed to delete reverted candles: {}", e);
+                        exex_metrics::record_sink_error("liquidity");
+                    }
+                }
+                if let Some(client) = &client {
+                    let payload = serde_json::json!({ "first_block": first_block });
+                    let subject = subjects::candles(&chain, "revert");
+                    if let Err(e) = client.publish(subject, payload.to_string().into()).await {
+                        warn!("Failed to publish candle revert: {}", e);
+                        exex_metrics::record_sink_error("liquidity");
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Postgres store of closed candles.
+struct CandleDb {
+    pool: PgPool,
+    chain_id: String,
+}
+
+impl CandleDb {
+    async fn new(database_url: &str, chain_id: &str) -> eyre::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(60))
+            .idle_timeout(Duration::from_secs(300))
+            .connect(database_url)
+            .await?;
+        let db = Self {
+            pool,
+            chain_id: chain_id.to_string(),
+        };
+        db.init_schema().await?;
+        Ok(db)
+    }
+
+    async fn init_schema(&self) -> eyre::Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pool_candles (
+                chain_id        TEXT NOT NULL,
+                pool            TEXT NOT NULL,
+                interval_secs   BIGINT NOT NULL,
+                open_time       BIGINT NOT NULL,
+                open            DOUBLE PRECISION NOT NULL,
+                high            DOUBLE PRECISION NOT NULL,
+                low             DOUBLE PRECISION NOT NULL,
+                close           DOUBLE PRECISION NOT NULL,
+                volume0         DOUBLE PRECISION NOT NULL,
+                volume1         DOUBLE PRECISION NOT NULL,
+                swap_count      BIGINT NOT NULL,
+                first_block     BIGINT NOT NULL,
+                last_block      BIGINT NOT NULL,
+                CONSTRAINT pool_candles_pkey
+                    PRIMARY KEY (chain_id, pool, interval_secs, open_time)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_pool_candles_last_block ON pool_candles (chain_id, last_block)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Upsert closed candles. A candle rebuilt after a reorg overwrites its row.
+    async fn upsert_candles(&self, candles: &[Candle]) -> eyre::Result<()> {
+        if candles.is_empty() {
+            return Ok(());
+        }
+
+        let mut qb = sqlx::QueryBuilder::new(
+            "INSERT INTO pool_candles (chain_id, pool, interval_secs, open_time, open, high, low, close, volume0, volume1, swap_count, first_block, last_block) ",
+        );
+        qb.push_values(candles, |mut b, c| {
+            b.push_bind(&self.chain_id)
+                .push_bind(&c.pool)
+                .push_bind(c.interval_secs as i64)
+                .push_bind(c.open_time as i64)
+                .push_bind(c.open)
+                .push_bind(c.high)
+                .push_bind(c.low)
+                .push_bind(c.close)
+                .push_bind(c.volume0)
+                .push_bind(c.volume1)
+                .push_bind(c.swap_count as i64)
+                .push_bind(c.first_block as i64)
+                .push_bind(c.last_block as i64);
+        });
+        qb.push(
+            " ON CONFLICT (chain_id, pool, interval_secs, open_time) DO UPDATE SET \
+             open = EXCLUDED.open, high = EXCLUDED.high, low = EXCLUDED.low, close = EXCLUDED.close, \
+             volume0 = EXCLUDED.volume0, volume1 = EXCLUDED.volume1, swap_count = EXCLUDED.swap_count, \
+             first_block = EXCLUDED.first_block, last_block = EXCLUDED.last_block",
+        );
+        qb.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Delete candles that include a block at or above `block_number`.
+    async fn delete_from_block(&self, block_number: u64) -> eyre::Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM pool_candles WHERE chain_id = $1 AND last_block >= $2")
+                .bind(&self.chain_id)
+                .bind(block_number as i64)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn swaps_fold_into_candles_that_close_past_their_bucket() {
+        let pool = PoolIdentifier::Address(Address::from([7u8; 20]));
+        let mut candles = CandleAggregator::new(1, vec![1, 60]);
+
+        candles.record(&pool, 100, 1_200_000_012, 2.0, 1.0, 2.0);
+        candles.record(&pool, 100, 1_200_000_012, 3.0, 1.0, 3.0);
+        // Block 101 closes the 1s candle of block 100; the 1m one stays open.
+        let closed = candles.close_through(1_200_000_024);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].interval_secs, 1);
+        assert_eq!(closed[0].open_time, 1_200_000_012);
+        assert_eq!((closed[0].open, closed[0].close), (2.0, 3.0));
+        candles.record(&pool, 101, 1_200_000_024, 1.5, 2.0, 3.0);
+
+        let closed = candles.close_through(1_200_000_080);
+        assert_eq!(closed.len(), 2);
+        let minute = &closed[0];
+        assert_eq!(minute.interval_secs, 60);
+        assert_eq!(minute.open_time, 1_200_000_000);
+        assert_eq!(
+            (minute.open, minute.high, minute.low, minute.close),
+            (2.0, 3.0, 1.5, 1.5)
+        );
+        assert_eq!((minute.volume0, minute.volume1), (4.0, 8.0));
+        assert_eq!(minute.swap_count, 3);
+        assert_eq!((minute.first_block, minute.last_block), (100, 101));
+
+        // A revert drops the open candles the reverted blocks touched.
+        candles.record(&pool, 102, 1_200_000_090, 1.0, 1.0, 1.0);
+        candles.revert_from(102);
+        assert!(candles.close_through(u64::MAX).is_empty());
+
+        assert_eq!(parse_interval("1m").unwrap(), 60);
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("5").is_err());
+        assert_eq!(interval_label(60), "1m");
+        assert_eq!(interval_label(1), "1s");
+    }
+}
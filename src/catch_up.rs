@@ -0,0 +1,90 @@
+// Catch-up Detection
+//
+// During initial sync, and while reth replays the blocks missed before a
+// restart, the ExEx emits blocks far behind the chain tip. Quoting off that
+// state would be quoting the past, so every `BeginBlock` carries
+// `is_historical`: the block is more than `HISTORICAL_LAG_BLOCKS` (default 5)
+// block times older than the wall clock, i.e. roughly that far behind the
+// tip. Block age is used rather than the node's own head because during
+// initial sync the node is behind as well.
+//
+// The first live block after historical ones (or the first block of the
+// stream, when it is already live) is preceded by an unsequenced `CaughtUp`
+// marker.
+
+use crate::chain;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_HISTORICAL_LAG_BLOCKS: u64 = 5;
+
+#[derive(Debug)]
+pub struct CatchUpTracker {
+    max_age: Duration,
+    /// Whether the previous block was historical; `None` before the first.
+    historical: Option<bool>,
+}
+
+impl CatchUpTracker {
+    pub fn new(lag_blocks: u64, block_time: Duration) -> Self {
+        Self {
+            max_age: block_time.saturating_mul(u32::try_from(lag_blocks).unwrap_or(u32::MAX)),
+            historical: None,
+        }
+    }
+
+    /// `HISTORICAL_LAG_BLOCKS` block times of the active chain.
+    pub fn from_env() -> Self {
+        let lag_blocks = std::env::var("HISTORICAL_LAG_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_HISTORICAL_LAG_BLOCKS);
+        Self::new(lag_blocks, chain::active().block_time)
+    }
+
+    /// Classify the next block by its timestamp. Returns `(is_historical,
+    /// caught_up)`; `caught_up` is set for the block that ends a historical
+    /// run (or starts the stream live).
+    pub fn observe(&mut self, block_timestamp: u64, now: SystemTime) -> (bool, bool) {
+        let produced = UNIX_EPOCH + Duration::from_secs(block_timestamp);
+        // A timestamp ahead of the local clock counts as fresh.
+        let age = now.duration_since(produced).unwrap_or_default();
+        let historical = age > self.max_age;
+        let caught_up = !historical && self.historical != Some(false);
+        self.historical = Some(historical);
+        (historical, caught_up)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catching_up_is_announced_once_per_historical_run() {
+        let mut tracker = CatchUpTracker::new(5, Duration::from_secs(12));
+        let now = UNIX_EPOCH + Duration::from_secs(10_000);
+
+        // Backlog: older than 60s.
+        assert_eq!(tracker.observe(9_000, now), (true, false));
+        assert_eq!(tracker.observe(9_900, now), (true, false));
+        // Within 5 block times: live, announced once.
+        assert_eq!(tracker.observe(9_950, now), (false, true));
+        assert_eq!(tracker.observe(9_962, now), (false, false));
+        // A stall puts the stream behind again; recovering re-announces.
+        assert_eq!(
+            tracker.observe(9_962, now + Duration::from_secs(120)),
+            (true, false)
+        );
+        assert_eq!(
+            tracker.observe(10_100, now + Duration::from_secs(120)),
+            (false, true)
+        );
+    }
+
+    #[test]
+    fn a_live_first_block_is_announced_and_future_timestamps_are_fresh() {
+        let mut tracker = CatchUpTracker::new(5, Duration::from_secs(12));
+        let now = UNIX_EPOCH + Duration::from_secs(10_000);
+        assert_eq!(tracker.observe(10_005, now), (false, true));
+    }
+}
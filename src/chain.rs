@@ -0,0 +1,132 @@
+// Chain Profiles
+//
+// Singleton contract addresses, the Uniswap UniversalRouter deployments
+// (for attributing executor trades routed through them), the default `CHAIN`
+// name used in NATS subjects, and the block time differ per chain. The node's
+// chain id selects a profile once at startup (`init`); every caller then
+// reads it through `active()`. Until `init` runs (unit tests, library users)
+// the Ethereum mainnet profile is active, so existing behaviour is unchanged.
+//
+// A pool whose metadata carries its singleton in `factory` (V4 PoolManager,
+// Ekubo Core) still uses that address; the profile is only the fallback.
+
+use crate::events::{BALANCER_V2_VAULT, EKUBO_CORE};
+use crate::pool_tracker::{FLUID_LIQUIDITY_LAYER, UNISWAP_V4_POOL_MANAGER};
+use alloy_primitives::{address, Address};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Per-chain constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainProfile {
+    pub chain_id: u64,
+    /// Default `CHAIN` value (NATS subject suffix).
+    pub name: &'static str,
+    pub block_time: Duration,
+    // `Address::ZERO` where the protocol is not deployed: no log is ever
+    // emitted from it, so nothing matches.
+    pub uniswap_v4_pool_manager: Address,
+    pub ekubo_core: Address,
+    pub balancer_v2_vault: Address,
+    pub fluid_liquidity_layer: Address,
+    /// UniversalRouter deployments (V3 and V4 routers).
+    pub universal_routers: &'static [Address],
+}
+
+pub const ETHEREUM: ChainProfile = ChainProfile {
+    chain_id: 1,
+    name: "ethereum",
+    block_time: Duration::from_secs(12),
+    uniswap_v4_pool_manager: UNISWAP_V4_POOL_MANAGER,
+    ekubo_core: EKUBO_CORE,
+    balancer_v2_vault: BALANCER_V2_VAULT,
+    fluid_liquidity_layer: FLUID_LIQUIDITY_LAYER,
+    universal_routers: &[
+        address!("3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"),
+        address!("66a9893cC07D91D95644AEDD05D03f95e1dBA8Af"),
+    ],
+};
+
+pub const OP_MAINNET: ChainProfile = ChainProfile {
+    chain_id: 10,
+    name: "optimism",
+    block_time: Duration::from_secs(2),
+    uniswap_v4_pool_manager: address!("9a13F98Cb987694C9F086b1F5eB990EeA8264Ec3"),
+    ekubo_core: Address::ZERO,
+    balancer_v2_vault: BALANCER_V2_VAULT,
+    fluid_liquidity_layer: Address::ZERO,
+    universal_routers: &[
+        address!("CB1355ff08Ab38bBCE60111F1bb2B784bE25D7e8"),
+        address!("851116D9223fabED8E56C0E6b8Ad0c31d98B3507"),
+    ],
+};
+
+pub const BASE: ChainProfile = ChainProfile {
+    chain_id: 8453,
+    name: "base",
+    block_time: Duration::from_secs(2),
+    uniswap_v4_pool_manager: address!("498581fF718922c3f8e6A244956aF099B2652b2b"),
+    ekubo_core: Address::ZERO,
+    balancer_v2_vault: BALANCER_V2_VAULT,
+    fluid_liquidity_layer: Address::ZERO,
+    universal_routers: &[
+        address!("3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"),
+        address!("6fF5693b99212Da76ad316178A184AB56D299b43"),
+    ],
+};
+
+const PROFILES: [ChainProfile; 3] = [ETHEREUM, OP_MAINNET, BASE];
+
+static ACTIVE: OnceLock<ChainProfile> = OnceLock::new();
+
+/// Select the profile for the node's chain. Unknown chains keep the Ethereum
+/// singletons (pools must then carry theirs in `factory`) under their own
+/// chain id. Only the first call has any effect.
+pub fn init(chain_id: u64) -> ChainProfile {
+    let profile = profile_for(chain_id).unwrap_or_else(|| {
+        warn!(
+            chain_id,
+            "No chain profile for this chain; using Ethereum singleton addresses"
+        );
+        ChainProfile {
+            chain_id,
+            ..ETHEREUM
+        }
+    });
+    let active = *ACTIVE.get_or_init(|| profile);
+    info!(
+        chain_id = active.chain_id,
+        chain = active.name,
+        "Chain profile selected"
+    );
+    active
+}
+
+pub fn profile_for(chain_id: u64) -> Option<ChainProfile> {
+    PROFILES.into_iter().find(|p| p.chain_id == chain_id)
+}
+
+/// The profile selected by `init`, or Ethereum mainnet before that.
+pub fn active() -> &'static ChainProfile {
+    ACTIVE.get().unwrap_or(&ETHEREUM)
+}
+
+/// `CHAIN`, defaulting to the active profile's name.
+pub fn chain_name() -> String {
+    std::env::var("CHAIN").unwrap_or_else(|_| active().name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_are_selected_by_chain_id() {
+        assert_eq!(profile_for(1), Some(ETHEREUM));
+        assert_eq!(profile_for(8453).map(|p| p.name), Some("base"));
+        assert_eq!(profile_for(999), None);
+        // Before `init`, callers see Ethereum mainnet.
+        assert_eq!(active().uniswap_v4_pool_manager, UNISWAP_V4_POOL_MANAGER);
+    }
+}
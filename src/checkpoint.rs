@@ -0,0 +1,147 @@
+// Emitted-Block Checkpoint
+//
+// Persists the last block whose envelope the socket writer received whole
+// (behind any confirmation buffer, and not one that lost a frame on overflow),
+// so that after a restart the ExEx resumes from it instead of the node head.
+// On startup the checkpoint is handed to reth as the ExEx head; reth then
+// replays every block between it and the node head (from its WAL / a backfill
+// job over the provider) as ordinary `ChainCommitted` notifications before
+// live ones, and reverts the checkpoint first if it was reorged out while the
+// ExEx was down.
+//
+// Reth may still re-notify blocks at or below the checkpoint (a committed
+// chain straddling it, or one it re-sends from its WAL). `ResumeDedupe` drops
+// those, so the orderbook engine never applies a block's deltas twice.
+//
+// Path from `EXEX_CHECKPOINT_PATH`, by default in the reth datadir (see
+// below). The file is rewritten atomically (temp file + fsync + rename + fsync
+// of the directory) after every notification that released a block.
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Checkpoint file of the socket sink, under `<datadir>/exex/`.
+const CHECKPOINT_FILE: &str = "liquidity_socket.checkpoint.json";
+
+/// `EXEX_CHECKPOINT_PATH`, or `<datadir>/exex/liquidity_socket.checkpoint.json`.
+pub fn checkpoint_path(data_dir: &Path) -> PathBuf {
+    std::env::var("EXEX_CHECKPOINT_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_dir.join("exex").join(CHECKPOINT_FILE))
+}
+
+/// Last block whose updates were fully emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub block_number: u64,
+    pub block_hash: B256,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint; `Ok(None)` if none has been written yet.
+    pub fn load(path: &Path) -> eyre::Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Atomically and durably replace the checkpoint file: the temp file is
+    /// synced before the rename, and the directory after it.
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        std::fs::create_dir_all(dir)?;
+        let tmp = path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec(self)?)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp, path)?;
+        std::fs::File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Drops committed blocks already emitted before a restart.
+#[derive(Debug, Default)]
+pub struct ResumeDedupe {
+    /// Highest block emitted before the restart, until the stream passes it.
+    emitted_through: Option<u64>,
+}
+
+impl ResumeDedupe {
+    pub fn new(checkpoint: Option<&Checkpoint>) -> Self {
+        Self {
+            emitted_through: checkpoint.map(|cp| cp.block_number),
+        }
+    }
+
+    /// Whether a committed block was already emitted. The first block past
+    /// the checkpoint ends deduplication.
+    pub fn is_duplicate(&mut self, block_number: u64) -> bool {
+        match self.emitted_through {
+            Some(through) if block_number <= through => true,
+            Some(_) => {
+                self.emitted_through = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Blocks from `first` on were reverted: their replacements are new.
+    pub fn revert_from(&mut self, first: u64) {
+        if let Some(through) = self.emitted_through.as_mut() {
+            *through = (*through).min(first.saturating_sub(1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_roundtrips_and_missing_is_none() {
+        let dir = std::env::temp_dir().join(format!("exex-checkpoint-{}", std::process::id()));
+        let path = dir.join("checkpoint.json");
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            block_number: 21_000_000,
+            block_hash: B256::repeat_byte(0xAB),
+        };
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resume_dedupe_drops_renotified_blocks_until_the_stream_passes_them() {
+        let checkpoint = Checkpoint {
+            block_number: 100,
+            block_hash: B256::ZERO,
+        };
+        let mut dedupe = ResumeDedupe::new(Some(&checkpoint));
+        assert!(dedupe.is_duplicate(99));
+        assert!(dedupe.is_duplicate(100));
+        assert!(!dedupe.is_duplicate(101));
+        // Past the checkpoint the stream is new, even at lower heights.
+        assert!(!dedupe.is_duplicate(100));
+
+        // A revert below the checkpoint makes the replacement blocks new.
+        let mut dedupe = ResumeDedupe::new(Some(&checkpoint));
+        dedupe.revert_from(98);
+        assert!(dedupe.is_duplicate(97));
+        assert!(!dedupe.is_duplicate(98));
+
+        assert!(!ResumeDedupe::default().is_duplicate(0));
+    }
+}
@@ -0,0 +1,680 @@
+// Startup configuration file
+//
+// Every subsystem reads its settings from env vars (NATS_URL, CHAIN,
+// DATABASE_URL, BALANCE_MONITOR_*, EXEX_SOCKET, ...). `EXEX_CONFIG` points at
+// a TOML file holding the same settings in per-ExEx sections; it is parsed and
+// validated once in `main`, before the CLI is parsed or any thread exists, and
+// each key is exported as the env var its subsystem already reads. A variable
+// that is already set in the environment wins over the file, so one-off
+// overrides don't need a file edit.
+//
+// Unknown keys, wrong types and invalid values (addresses, URLs, enums) fail
+// startup with the offending key named, instead of being silently ignored.
+
+use alloy_primitives::{Address, B256};
+use eyre::WrapErr;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Top-level layout of the config file. Every section and key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExExConfig {
+    pub general: GeneralConfig,
+    pub liquidity: LiquidityConfig,
+    pub balance_monitor: BalanceMonitorConfig,
+    pub transfers: TransfersConfig,
+}
+
+/// Settings shared by every ExEx.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneralConfig {
+    /// ExExes to install (`EXEX_ENABLED`): `liquidity`, `transfers`, `balance`,
+    /// `fees`.
+    pub exex: Option<Vec<String>>,
+    pub chain: Option<String>,
+    pub nats_url: Option<String>,
+    pub rpc_url: Option<String>,
+    pub price_feed_database_url: Option<String>,
+    pub price_feed_refresh_secs: Option<u64>,
+    /// `host:port` serving `/healthz` and `/readyz`.
+    pub health_addr: Option<String>,
+    pub health_max_block_age_secs: Option<u64>,
+    pub finished_height_batch_blocks: Option<u64>,
+    pub finished_height_max_delay_ms: Option<u64>,
+    pub nats_subject_prefix: Option<String>,
+    pub status_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LiquidityConfig {
+    pub socket_path: Option<String>,
+    pub socket_channel_capacity: Option<usize>,
+    pub socket_overflow: Option<SocketOverflowConfig>,
+    pub socket_spill_max: Option<usize>,
+    pub arena_notify_socket: Option<String>,
+    pub checkpoint_path: Option<String>,
+    pub confirmation_depth: Option<u64>,
+    pub historical_lag_blocks: Option<u64>,
+    pub reorg_journal_blocks: Option<u64>,
+    pub backfill_blocks: Option<u64>,
+    pub pool_snapshot_interval_blocks: Option<u64>,
+    pub pool_state_mode: Option<PoolStateModeConfig>,
+    pub swap_quotes: Option<bool>,
+    pub liquidity_depth_spacings: Option<u32>,
+    pub state_verify_interval_blocks: Option<u64>,
+    pub state_verify_sample: Option<u64>,
+    pub pool_metadata_database_url: Option<String>,
+    pub pool_metadata_table: Option<String>,
+    pub whitelist_jetstream_stream: Option<String>,
+    pub whitelist_jetstream_durable: Option<String>,
+    pub strict_decoding: Option<bool>,
+    pub strict_decoding_ignore_topics: Option<Vec<String>>,
+    pub dead_letter_path: Option<String>,
+    /// `1s`, `1m`, ... candle intervals.
+    pub candle_intervals: Option<Vec<String>>,
+    pub candles_database_url: Option<String>,
+    pub fee_report_interval_blocks: Option<u64>,
+    pub arb_signal_threshold_bps: Option<u32>,
+    pub depeg_pools: Option<Vec<String>>,
+    pub depeg_threshold_bps: Option<u32>,
+    pub depeg_consecutive_blocks: Option<u64>,
+    pub mempool_watch: Option<bool>,
+    /// Fee recipient address → builder name.
+    pub builder_labels: Option<BTreeMap<String, String>>,
+    pub trace_swap_pools: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SocketOverflowConfig {
+    Drop,
+    Spill,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolStateModeConfig {
+    Off,
+    Alongside,
+    Absolute,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceMonitorConfig {
+    pub address: Option<String>,
+    pub chain_id: Option<u64>,
+    pub database_url: Option<String>,
+    /// `token:warning[:critical]` entries.
+    pub thresholds: Option<Vec<String>>,
+    pub call_mode_tokens: Option<Vec<String>>,
+    pub full_snapshot_interval_blocks: Option<u64>,
+    pub reconcile_interval_blocks: Option<u64>,
+    pub persist_path: Option<String>,
+    pub startup_whitelist_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransfersConfig {
+    pub backend: Option<TransfersBackendConfig>,
+    /// Postgres URL (`DATABASE_URL`).
+    pub database_url: Option<String>,
+    pub chain_id: Option<u64>,
+    pub db_schema: Option<String>,
+    pub retention_days: Option<u64>,
+    pub api_addr: Option<String>,
+    pub wal_dir: Option<String>,
+    pub parquet_dir: Option<String>,
+    pub parquet_blocks_per_file: Option<u64>,
+    pub token_allowlist: Option<Vec<String>>,
+    pub address_allowlist: Option<Vec<String>>,
+    pub min_amount: Option<String>,
+    /// `token:raw_amount` entries.
+    pub whale_thresholds: Option<Vec<String>>,
+    pub whale_usd: Option<f64>,
+    pub backfill_from: Option<u64>,
+    pub backfill_to: Option<u64>,
+    pub clickhouse: ClickHouseConfig,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransfersBackendConfig {
+    Postgres,
+    Clickhouse,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClickHouseConfig {
+    pub url: Option<String>,
+    pub database: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+const EXEX_NAMES: [&str; 4] = ["liquidity", "transfers", "balance", "fees"];
+
+impl ExExConfig {
+    /// Read, parse and validate the file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("reading config file {}", path.display()))?;
+        Self::parse(&raw).wrap_err_with(|| format!("invalid config file {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> eyre::Result<Self> {
+        let config: Self = toml::from_str(raw)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Export every configured key as its env var, unless the variable is
+    /// already set. Must run before any other thread is spawned (tracing is
+    /// not initialized yet, so nothing is logged here).
+    pub fn apply_env(&self) {
+        for (var, value) in self.env_vars() {
+            if std::env::var_os(var).is_none() {
+                std::env::set_var(var, value);
+            }
+        }
+    }
+
+    fn validate(&self) -> eyre::Result<()> {
+        let g = &self.general;
+        for name in g.exex.iter().flatten() {
+            if !EXEX_NAMES.contains(&name.as_str()) {
+                eyre::bail!("general.exex: unknown ExEx {name:?}, expected one of {EXEX_NAMES:?}");
+            }
+        }
+        check_url(
+            "general.nats_url",
+            &g.nats_url,
+            &["nats", "tls", "ws", "wss"],
+        )?;
+        check_url("general.rpc_url", &g.rpc_url, &["http", "https"])?;
+        if let Some(addr) = &g.health_addr {
+            addr.parse::<SocketAddr>()
+                .map_err(|e| eyre::eyre!("general.health_addr: {addr:?} is not host:port: {e}"))?;
+        }
+        if g.health_max_block_age_secs == Some(0) {
+            eyre::bail!("general.health_max_block_age_secs must be at least 1");
+        }
+        if g.finished_height_batch_blocks == Some(0) {
+            eyre::bail!("general.finished_height_batch_blocks must be at least 1");
+        }
+        if let Some(prefix) = &g.nats_subject_prefix {
+            if prefix.is_empty()
+                || prefix.starts_with('.')
+                || prefix.ends_with('.')
+                || prefix.contains(|c: char| c.is_whitespace() || c == '*' || c == '>')
+            {
+                eyre::bail!(
+                    "general.nats_subject_prefix: {prefix:?} must be dot-separated tokens without wildcards"
+                );
+            }
+        }
+        check_postgres_url(
+            "general.price_feed_database_url",
+            &g.price_feed_database_url,
+        )?;
+
+        let l = &self.liquidity;
+        if l.socket_channel_capacity == Some(0) {
+            eyre::bail!("liquidity.socket_channel_capacity must be at least 1");
+        }
+        check_postgres_url(
+            "liquidity.pool_metadata_database_url",
+            &l.pool_metadata_database_url,
+        )?;
+        check_identifier("liquidity.pool_metadata_table", &l.pool_metadata_table)?;
+        check_identifier(
+            "liquidity.whitelist_jetstream_stream",
+            &l.whitelist_jetstream_stream,
+        )?;
+        check_identifier(
+            "liquidity.whitelist_jetstream_durable",
+            &l.whitelist_jetstream_durable,
+        )?;
+        for topic in l.strict_decoding_ignore_topics.iter().flatten() {
+            topic.trim().parse::<B256>().map_err(|_| {
+                eyre::eyre!(
+                    "liquidity.strict_decoding_ignore_topics: {topic:?} is not a topic hash"
+                )
+            })?;
+        }
+        for interval in l.candle_intervals.iter().flatten() {
+            crate::candles::parse_interval(interval)
+                .map_err(|e| eyre::eyre!("liquidity.candle_intervals: {e}"))?;
+        }
+        check_postgres_url("liquidity.candles_database_url", &l.candles_database_url)?;
+        for pool in l.depeg_pools.iter().flatten() {
+            check_address("liquidity.depeg_pools", pool)?;
+        }
+        if l.depeg_consecutive_blocks == Some(0) {
+            eyre::bail!("liquidity.depeg_consecutive_blocks must be at least 1");
+        }
+        for (address, label) in l.builder_labels.iter().flatten() {
+            check_address("liquidity.builder_labels", address)?;
+            if label.trim().is_empty() || label.contains([',', '=']) {
+                eyre::bail!("liquidity.builder_labels: label {label:?} must be non-empty without ',' or '='");
+            }
+        }
+        for pool in l.trace_swap_pools.iter().flatten() {
+            check_address("liquidity.trace_swap_pools", pool)?;
+        }
+
+        let b = &self.balance_monitor;
+        if let Some(address) = &b.address {
+            check_address("balance_monitor.address", address)?;
+        }
+        check_postgres_url("balance_monitor.database_url", &b.database_url)?;
+        for entry in b.thresholds.iter().flatten() {
+            let token = entry.split(':').next().unwrap_or_default();
+            check_address("balance_monitor.thresholds", token)?;
+        }
+        for token in b.call_mode_tokens.iter().flatten() {
+            check_address("balance_monitor.call_mode_tokens", token)?;
+        }
+
+        let t = &self.transfers;
+        check_postgres_url("transfers.database_url", &t.database_url)?;
+        check_identifier("transfers.db_schema", &t.db_schema)?;
+        if t.retention_days == Some(0) {
+            eyre::bail!("transfers.retention_days must be at least 1");
+        }
+        if t.parquet_blocks_per_file == Some(0) {
+            eyre::bail!("transfers.parquet_blocks_per_file must be at least 1");
+        }
+        if let Some(addr) = &t.api_addr {
+            addr.parse::<SocketAddr>()
+                .map_err(|e| eyre::eyre!("transfers.api_addr: {addr:?} is not host:port: {e}"))?;
+        }
+        for token in t.token_allowlist.iter().flatten() {
+            check_address("transfers.token_allowlist", token)?;
+        }
+        for address in t.address_allowlist.iter().flatten() {
+            check_address("transfers.address_allowlist", address)?;
+        }
+        for entry in t.whale_thresholds.iter().flatten() {
+            let token = entry
+                .split_once(':')
+                .map_or(entry.as_str(), |(token, _)| token);
+            check_address("transfers.whale_thresholds", token)?;
+        }
+        if let (Some(from), Some(to)) = (t.backfill_from, t.backfill_to) {
+            if from > to {
+                eyre::bail!("transfers.backfill_from ({from}) is after backfill_to ({to})");
+            }
+        }
+        check_url(
+            "transfers.clickhouse.url",
+            &t.clickhouse.url,
+            &["http", "https"],
+        )?;
+        Ok(())
+    }
+
+    /// The env var each configured key maps to, with its value.
+    fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        let mut push = |var: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                vars.push((var, value));
+            }
+        };
+
+        let g = &self.general;
+        push("EXEX_ENABLED", g.exex.as_ref().map(|v| v.join(",")));
+        push("CHAIN", g.chain.clone());
+        push("NATS_URL", g.nats_url.clone());
+        push("RPC_URL", g.rpc_url.clone());
+        push("PRICE_FEED_DATABASE_URL", g.price_feed_database_url.clone());
+        push(
+            "PRICE_FEED_REFRESH_SECS",
+            g.price_feed_refresh_secs.map(|v| v.to_string()),
+        );
+        push("HEALTH_ADDR", g.health_addr.clone());
+        push(
+            "HEALTH_MAX_BLOCK_AGE_SECS",
+            g.health_max_block_age_secs.map(|v| v.to_string()),
+        );
+        push(
+            "FINISHED_HEIGHT_BATCH_BLOCKS",
+            g.finished_height_batch_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "FINISHED_HEIGHT_MAX_DELAY_MS",
+            g.finished_height_max_delay_ms.map(|v| v.to_string()),
+        );
+        push("NATS_SUBJECT_PREFIX", g.nats_subject_prefix.clone());
+        push(
+            "STATUS_INTERVAL_SECS",
+            g.status_interval_secs.map(|v| v.to_string()),
+        );
+
+        let l = &self.liquidity;
+        push("EXEX_SOCKET", l.socket_path.clone());
+        push("ARENA_NOTIFY_SOCKET", l.arena_notify_socket.clone());
+        push("EXEX_CHECKPOINT_PATH", l.checkpoint_path.clone());
+        push(
+            "CONFIRMATION_DEPTH",
+            l.confirmation_depth.map(|v| v.to_string()),
+        );
+        push(
+            "HISTORICAL_LAG_BLOCKS",
+            l.historical_lag_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "REORG_JOURNAL_BLOCKS",
+            l.reorg_journal_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "SOCKET_CHANNEL_CAPACITY",
+            l.socket_channel_capacity.map(|v| v.to_string()),
+        );
+        push(
+            "SOCKET_OVERFLOW",
+            l.socket_overflow.map(|overflow| {
+                match overflow {
+                    SocketOverflowConfig::Drop => "drop",
+                    SocketOverflowConfig::Spill => "spill",
+                }
+                .to_string()
+            }),
+        );
+        push(
+            "SOCKET_SPILL_MAX",
+            l.socket_spill_max.map(|v| v.to_string()),
+        );
+        push("BACKFILL_BLOCKS", l.backfill_blocks.map(|v| v.to_string()));
+        push(
+            "POOL_SNAPSHOT_INTERVAL_BLOCKS",
+            l.pool_snapshot_interval_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "POOL_STATE_MODE",
+            l.pool_state_mode.map(|mode| {
+                match mode {
+                    PoolStateModeConfig::Off => "off",
+                    PoolStateModeConfig::Alongside => "alongside",
+                    PoolStateModeConfig::Absolute => "absolute",
+                }
+                .to_string()
+            }),
+        );
+        push("SWAP_QUOTES", l.swap_quotes.map(|v| v.to_string()));
+        push(
+            "LIQUIDITY_DEPTH_SPACINGS",
+            l.liquidity_depth_spacings.map(|v| v.to_string()),
+        );
+        push(
+            "STATE_VERIFY_INTERVAL_BLOCKS",
+            l.state_verify_interval_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "STATE_VERIFY_SAMPLE",
+            l.state_verify_sample.map(|v| v.to_string()),
+        );
+        push(
+            "POOL_METADATA_DATABASE_URL",
+            l.pool_metadata_database_url.clone(),
+        );
+        push("POOL_METADATA_TABLE", l.pool_metadata_table.clone());
+        push(
+            "WHITELIST_JETSTREAM_STREAM",
+            l.whitelist_jetstream_stream.clone(),
+        );
+        push(
+            "WHITELIST_JETSTREAM_DURABLE",
+            l.whitelist_jetstream_durable.clone(),
+        );
+        push("STRICT_DECODING", l.strict_decoding.map(|v| v.to_string()));
+        push(
+            "STRICT_DECODING_IGNORE_TOPICS",
+            l.strict_decoding_ignore_topics
+                .as_ref()
+                .map(|v| v.join(",")),
+        );
+        push("DEAD_LETTER_PATH", l.dead_letter_path.clone());
+        push(
+            "CANDLE_INTERVALS",
+            l.candle_intervals.as_ref().map(|v| v.join(",")),
+        );
+        push("CANDLES_DATABASE_URL", l.candles_database_url.clone());
+        push(
+            "FEE_REPORT_INTERVAL_BLOCKS",
+            l.fee_report_interval_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "ARB_SIGNAL_THRESHOLD_BPS",
+            l.arb_signal_threshold_bps.map(|v| v.to_string()),
+        );
+        push("DEPEG_POOLS", l.depeg_pools.as_ref().map(|v| v.join(",")));
+        push(
+            "DEPEG_THRESHOLD_BPS",
+            l.depeg_threshold_bps.map(|v| v.to_string()),
+        );
+        push(
+            "DEPEG_CONSECUTIVE_BLOCKS",
+            l.depeg_consecutive_blocks.map(|v| v.to_string()),
+        );
+        push("MEMPOOL_WATCH", l.mempool_watch.map(|v| v.to_string()));
+        push(
+            "BUILDER_LABELS",
+            l.builder_labels.as_ref().map(|labels| {
+                labels
+                    .iter()
+                    .map(|(address, label)| format!("{address}={label}"))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        push(
+            "TRACE_SWAP_POOLS",
+            l.trace_swap_pools.as_ref().map(|v| v.join(",")),
+        );
+
+        let b = &self.balance_monitor;
+        push("BALANCE_MONITOR_ADDRESS", b.address.clone());
+        push(
+            "BALANCE_MONITOR_CHAIN_ID",
+            b.chain_id.map(|v| v.to_string()),
+        );
+        push("BALANCE_MONITOR_DATABASE_URL", b.database_url.clone());
+        push(
+            "BALANCE_MONITOR_THRESHOLDS",
+            b.thresholds.as_ref().map(|v| v.join(",")),
+        );
+        push(
+            "BALANCE_MONITOR_CALL_MODE_TOKENS",
+            b.call_mode_tokens.as_ref().map(|v| v.join(",")),
+        );
+        push(
+            "BALANCE_MONITOR_FULL_SNAPSHOT_INTERVAL_BLOCKS",
+            b.full_snapshot_interval_blocks.map(|v| v.to_string()),
+        );
+        push(
+            "BALANCE_MONITOR_RECONCILE_INTERVAL_BLOCKS",
+            b.reconcile_interval_blocks.map(|v| v.to_string()),
+        );
+        push("BALANCE_MONITOR_PERSIST_PATH", b.persist_path.clone());
+        push(
+            "BALANCE_MONITOR_STARTUP_WHITELIST_TIMEOUT_MS",
+            b.startup_whitelist_timeout_ms.map(|v| v.to_string()),
+        );
+
+        let t = &self.transfers;
+        push(
+            "TRANSFERS_BACKEND",
+            t.backend.map(|backend| {
+                match backend {
+                    TransfersBackendConfig::Postgres => "postgres",
+                    TransfersBackendConfig::Clickhouse => "clickhouse",
+                }
+                .to_string()
+            }),
+        );
+        push("DATABASE_URL", t.database_url.clone());
+        push("TRANSFERS_CHAIN_ID", t.chain_id.map(|v| v.to_string()));
+        push("TRANSFERS_DB_SCHEMA", t.db_schema.clone());
+        push(
+            "TRANSFERS_RETENTION_DAYS",
+            t.retention_days.map(|v| v.to_string()),
+        );
+        push("TRANSFERS_API_ADDR", t.api_addr.clone());
+        push("TRANSFERS_WAL_DIR", t.wal_dir.clone());
+        push("TRANSFERS_PARQUET_DIR", t.parquet_dir.clone());
+        push(
+            "TRANSFERS_PARQUET_BLOCKS_PER_FILE",
+            t.parquet_blocks_per_file.map(|v| v.to_string()),
+        );
+        push(
+            "TRANSFERS_TOKEN_ALLOWLIST",
+            t.token_allowlist.as_ref().map(|v| v.join(",")),
+        );
+        push(
+            "TRANSFERS_ADDRESS_ALLOWLIST",
+            t.address_allowlist.as_ref().map(|v| v.join(",")),
+        );
+        push("TRANSFERS_MIN_AMOUNT", t.min_amount.clone());
+        push(
+            "TRANSFERS_WHALE_THRESHOLDS",
+            t.whale_thresholds.as_ref().map(|v| v.join(",")),
+        );
+        push("TRANSFERS_WHALE_USD", t.whale_usd.map(|v| v.to_string()));
+        push(
+            "TRANSFERS_BACKFILL_FROM",
+            t.backfill_from.map(|v| v.to_string()),
+        );
+        push(
+            "TRANSFERS_BACKFILL_TO",
+            t.backfill_to.map(|v| v.to_string()),
+        );
+        push("CLICKHOUSE_URL", t.clickhouse.url.clone());
+        push("CLICKHOUSE_DATABASE", t.clickhouse.database.clone());
+        push("CLICKHOUSE_USER", t.clickhouse.user.clone());
+        push("CLICKHOUSE_PASSWORD", t.clickhouse.password.clone());
+        vars
+    }
+}
+
+fn check_address(key: &str, value: &str) -> eyre::Result<()> {
+    value
+        .trim()
+        .parse::<Address>()
+        .map(|_| ())
+        .map_err(|_| eyre::eyre!("{key}: {value:?} is not an address"))
+}
+
+fn check_url(key: &str, value: &Option<String>, schemes: &[&str]) -> eyre::Result<()> {
+    let Some(url) = value else {
+        return Ok(());
+    };
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+    match scheme {
+        Some(scheme) if schemes.contains(&scheme) => Ok(()),
+        _ => eyre::bail!("{key}: {url:?} must start with one of {schemes:?} followed by ://"),
+    }
+}
+
+fn check_postgres_url(key: &str, value: &Option<String>) -> eyre::Result<()> {
+    check_url(key, value, &["postgres", "postgresql"])
+}
+
+/// Table and schema names are interpolated into SQL; only accept identifiers.
+fn check_identifier(key: &str, value: &Option<String>) -> eyre::Result<()> {
+    match value {
+        Some(name)
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+        {
+            eyre::bail!("{key}: {name:?} must be letters, digits and underscores")
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_map_to_env_vars() {
+        let config = ExExConfig::parse(
+            r#"
+            [general]
+            exex = ["liquidity", "transfers"]
+            nats_url = "nats://nats:4222"
+
+            [liquidity]
+            socket_path = "/run/exex.sock"
+            pool_state_mode = "absolute"
+
+            [transfers]
+            backend = "clickhouse"
+            token_allowlist = [
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+            ]
+
+            [transfers.clickhouse]
+            url = "http://clickhouse:8123"
+            "#,
+        )
+        .unwrap();
+        let vars = config.env_vars();
+        let get = |var: &str| {
+            vars.iter()
+                .find(|(v, _)| *v == var)
+                .map(|(_, value)| value.as_str())
+        };
+
+        assert_eq!(get("EXEX_ENABLED"), Some("liquidity,transfers"));
+        assert_eq!(get("NATS_URL"), Some("nats://nats:4222"));
+        assert_eq!(get("EXEX_SOCKET"), Some("/run/exex.sock"));
+        assert_eq!(get("POOL_STATE_MODE"), Some("absolute"));
+        assert_eq!(get("TRANSFERS_BACKEND"), Some("clickhouse"));
+        assert_eq!(
+            get("TRANSFERS_TOKEN_ALLOWLIST"),
+            Some(
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48,0xdAC17F958D2ee523a2206206994597C13D831ec7"
+            )
+        );
+        assert_eq!(get("CLICKHOUSE_URL"), Some("http://clickhouse:8123"));
+        assert_eq!(get("CHAIN"), None);
+    }
+
+    #[test]
+    fn invalid_settings_name_the_key() {
+        let err = |raw: &str| ExExConfig::parse(raw).unwrap_err().to_string();
+
+        assert!(err("[liquidity]\nsocket = \"/tmp/x\"").contains("unknown field"));
+        assert!(err("[liquidity]\npool_state_mode = \"full\"").contains("unknown variant"));
+        assert!(err("[general]\nexex = [\"swaps\"]").contains("general.exex"));
+        assert!(err("[general]\nnats_url = \"localhost:4222\"").contains("general.nats_url"));
+        assert!(err("[general]\nnats_subject_prefix = \"staging.*\"")
+            .contains("general.nats_subject_prefix"));
+        assert!(
+            err("[liquidity]\nstrict_decoding_ignore_topics = [\"0x12\"]")
+                .contains("liquidity.strict_decoding_ignore_topics")
+        );
+        assert!(
+            err("[liquidity]\ncandle_intervals = [\"1d\"]").contains("liquidity.candle_intervals")
+        );
+        assert!(err("[liquidity]\ndepeg_pools = [\"0x12\"]").contains("liquidity.depeg_pools"));
+        assert!(err("[liquidity.builder_labels]\n\"0x12\" = \"titan\"")
+            .contains("liquidity.builder_labels"));
+        assert!(err("[liquidity]\ntrace_swap_pools = [\"pool\"]")
+            .contains("liquidity.trace_swap_pools"));
+        assert!(err("[balance_monitor]\naddress = \"0x1234\"").contains("balance_monitor.address"));
+        assert!(err("[transfers]\nbackfill_from = 10\nbackfill_to = 5")
+            .contains("transfers.backfill_from"));
+    }
+}
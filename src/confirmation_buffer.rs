@@ -0,0 +1,434 @@
+// Confirmation-Depth Buffering
+//
+// Optional (`CONFIRMATION_DEPTH`): holds each complete block envelope
+// (BeginBlock .. EndBlock) until `depth` further blocks have been committed on
+// top of it, so consumers that cannot handle reverts see a reorg-free stream.
+// The default (depth 0) keeps the low-latency unbuffered stream.
+//
+// Reorgs that only touch still-buffered blocks are absorbed: the reverted
+// blocks are dropped from the buffer, the new-chain blocks are buffered like
+// any other block, and the ReorgStart / revert blocks / ReorgEpilogue /
+// ReorgComplete envelope is swallowed (the consumer never saw the old blocks,
+// so there is nothing to correct). A reorg deeper than `depth` reaches blocks
+// the consumer already has: ReorgStart and the reverts of released blocks go
+// out at once (reverts of never-released blocks are dropped), while the new
+// chain's blocks are buffered like any other block and the ReorgEpilogue /
+// ReorgComplete tail is released right behind the newest of them.
+//
+// Whitelist updates, finality and pool invalidations keep their place in the
+// stream: they ride in the block being assembled, else behind the newest
+// buffered block, so they never overtake a block produced before them.
+//
+// Released messages are re-stamped with a contiguous socket `stream_seq`,
+// since dropped blocks would otherwise leave gaps. Other unsequenced messages
+// pass straight through. Blocks still buffered at shutdown are never released;
+// `discard_unreleased` reports them.
+
+use crate::types::ControlMessage;
+use std::collections::VecDeque;
+
+/// One buffered block envelope, BeginBlock .. EndBlock inclusive.
+#[derive(Debug)]
+struct BufferedBlock {
+    block_number: u64,
+    messages: Vec<ControlMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReorgMode {
+    /// No reorg envelope open.
+    None,
+    /// Every reverted block is still buffered; swallow the envelope.
+    Absorbed,
+    /// The reorg reaches released blocks; pass its reverts through.
+    PassThrough,
+}
+
+/// Re-orders the socket stream so blocks are released `depth` blocks late.
+#[derive(Debug)]
+pub struct ConfirmationBuffer {
+    depth: u64,
+    pending: VecDeque<BufferedBlock>,
+    /// Forward block currently being assembled.
+    current: Option<BufferedBlock>,
+    /// Revert block currently being dropped (its target was never released).
+    dropping_revert: bool,
+    reorg: ReorgMode,
+    /// Highest block number released to the consumer.
+    last_released: Option<u64>,
+    next_seq: u64,
+}
+
+impl ConfirmationBuffer {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            pending: VecDeque::new(),
+            current: None,
+            dropping_revert: false,
+            reorg: ReorgMode::None,
+            last_released: None,
+            next_seq: 0,
+        }
+    }
+
+    /// Depth from `CONFIRMATION_DEPTH`; 0 (default) disables buffering.
+    pub fn depth_from_env() -> u64 {
+        std::env::var("CONFIRMATION_DEPTH")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0)
+    }
+
+    /// Feed one producer message; returns the messages to release, in order.
+    pub fn push(&mut self, msg: ControlMessage) -> Vec<ControlMessage> {
+        let mut out = Vec::new();
+        match msg {
+            ControlMessage::ReorgStart { ref old_range, .. } => {
+                let reaches_released = match (old_range.first_block, self.last_released) {
+                    (Some(first), Some(released)) => first <= released,
+                    _ => false,
+                };
+                if reaches_released {
+                    self.reorg = ReorgMode::PassThrough;
+                    self.release(msg, &mut out);
+                } else {
+                    self.reorg = ReorgMode::Absorbed;
+                }
+            }
+            ControlMessage::BeginBlock {
+                block_number,
+                is_revert: true,
+                ..
+            } => {
+                // A revert of a block the consumer never saw cancels it.
+                if let Some(pos) = self
+                    .pending
+                    .iter()
+                    .position(|b| b.block_number == block_number)
+                {
+                    let dropped = self.pending.remove(pos).expect("position checked");
+                    // Anything that rode along with the block outlives it.
+                    for msg in dropped.messages {
+                        if !is_block_frame(&msg) {
+                            self.hold(msg, &mut out);
+                        }
+                    }
+                    self.dropping_revert = true;
+                } else if self.reorg == ReorgMode::Absorbed {
+                    self.dropping_revert = true;
+                } else {
+                    self.release(msg, &mut out);
+                }
+            }
+            ControlMessage::BeginBlock { block_number, .. } => {
+                self.current = Some(BufferedBlock {
+                    block_number,
+                    messages: vec![msg],
+                });
+            }
+            ControlMessage::PoolUpdate { .. } => {
+                if let Some(current) = self.current.as_mut() {
+                    current.messages.push(msg);
+                } else if !self.dropping_revert {
+                    self.release(msg, &mut out);
+                }
+            }
+            ControlMessage::EndBlock { block_number, .. } => {
+                if self.dropping_revert {
+                    self.dropping_revert = false;
+                } else if let Some(mut current) = self.current.take() {
+                    current.messages.push(msg);
+                    self.pending.push_back(current);
+                    self.release_confirmed(block_number, &mut out);
+                } else {
+                    self.release(msg, &mut out);
+                }
+            }
+            ControlMessage::ReorgEpilogue { .. } => {
+                if self.reorg != ReorgMode::Absorbed {
+                    self.hold(msg, &mut out);
+                }
+            }
+            ControlMessage::ReorgComplete { .. } => {
+                if self.reorg != ReorgMode::Absorbed {
+                    self.hold(msg, &mut out);
+                }
+                self.reorg = ReorgMode::None;
+            }
+            ControlMessage::UpdateWhitelist(_)
+            | ControlMessage::Finalized { .. }
+            | ControlMessage::PoolInvalidated { .. } => self.hold(msg, &mut out),
+            ControlMessage::Ping
+            | ControlMessage::Pong
+            | ControlMessage::BackfillStart { .. }
+            | ControlMessage::BackfillUpdate { .. }
+            | ControlMessage::BackfillComplete { .. }
+            | ControlMessage::CaughtUp { .. }
+            | ControlMessage::PendingSwap { .. }
+            | ControlMessage::Shutdown => out.push(msg),
+        }
+        out
+    }
+
+    /// Drop every block not yet released (on shutdown or a closed channel);
+    /// returns their block numbers, oldest first.
+    pub fn discard_unreleased(&mut self) -> Vec<u64> {
+        self.dropping_revert = false;
+        self.pending
+            .drain(..)
+            .chain(self.current.take())
+            .map(|block| block.block_number)
+            .collect()
+    }
+
+    /// Keep `msg` at its place in the stream: in the block being assembled,
+    /// else behind the newest buffered block, else released now.
+    fn hold(&mut self, msg: ControlMessage, out: &mut Vec<ControlMessage>) {
+        if let Some(current) = self.current.as_mut() {
+            current.messages.push(msg);
+        } else if let Some(newest) = self.pending.back_mut() {
+            newest.messages.push(msg);
+        } else {
+            self.release(msg, out);
+        }
+    }
+
+    /// Release every buffered block with at least `depth` blocks on top of it.
+    fn release_confirmed(&mut self, tip: u64, out: &mut Vec<ControlMessage>) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|b| b.block_number.saturating_add(self.depth) <= tip)
+        {
+            let block = self.pending.pop_front().expect("front checked");
+            self.last_released = Some(block.block_number);
+            for msg in block.messages {
+                self.release(msg, out);
+            }
+        }
+    }
+
+    fn release(&mut self, mut msg: ControlMessage, out: &mut Vec<ControlMessage>) {
+        if let ControlMessage::BeginBlock {
+            block_number,
+            is_revert,
+            ..
+        } = msg
+        {
+            self.last_released = if is_revert {
+                block_number.checked_sub(1)
+            } else {
+                Some(block_number)
+            };
+        }
+        if let Some(seq) = stream_seq_mut(&mut msg) {
+            *seq = self.next_seq;
+            self.next_seq += 1;
+        }
+        out.push(msg);
+    }
+}
+
+/// A block envelope's own frames, as opposed to messages riding along with it.
+fn is_block_frame(msg: &ControlMessage) -> bool {
+    matches!(
+        msg,
+        ControlMessage::BeginBlock { .. }
+            | ControlMessage::PoolUpdate { .. }
+            | ControlMessage::EndBlock { .. }
+    )
+}
+
+fn stream_seq_mut(msg: &mut ControlMessage) -> Option<&mut u64> {
+    match msg {
+        ControlMessage::BeginBlock { stream_seq, .. }
+        | ControlMessage::PoolUpdate { stream_seq, .. }
+        | ControlMessage::EndBlock { stream_seq, .. }
+        | ControlMessage::ReorgStart { stream_seq, .. }
+        | ControlMessage::ReorgEpilogue { stream_seq, .. }
+        | ControlMessage::ReorgComplete { stream_seq, .. } => Some(stream_seq),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ReorgRange;
+    use alloy_primitives::{Address, B256};
+
+    fn block(block_number: u64, is_revert: bool) -> Vec<ControlMessage> {
+        vec![
+            ControlMessage::BeginBlock {
+                stream_seq: 0,
+                chain_id: 1,
+                block_number,
+                block_hash: B256::ZERO,
+                parent_hash: B256::ZERO,
+                block_timestamp: 0,
+                base_fee_per_gas: 0,
+                fee_recipient: Address::ZERO,
+                builder: None,
+                is_revert,
+                is_historical: false,
+            },
+            ControlMessage::EndBlock {
+                stream_seq: 0,
+                block_number,
+                num_updates: 0,
+                updates_checksum: B256::ZERO,
+            },
+        ]
+    }
+
+    fn feed(buffer: &mut ConfirmationBuffer, msgs: Vec<ControlMessage>) -> Vec<ControlMessage> {
+        msgs.into_iter().flat_map(|m| buffer.push(m)).collect()
+    }
+
+    fn begin_blocks(msgs: &[ControlMessage]) -> Vec<(u64, bool)> {
+        msgs.iter()
+            .filter_map(|m| match m {
+                ControlMessage::BeginBlock {
+                    block_number,
+                    is_revert,
+                    ..
+                } => Some((*block_number, *is_revert)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn range(first: u64, last: u64) -> ReorgRange {
+        ReorgRange {
+            first_block: Some(first),
+            last_block: Some(last),
+            block_count: last - first + 1,
+        }
+    }
+
+    #[test]
+    fn releases_blocks_after_depth_with_contiguous_seq() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        assert!(feed(&mut buffer, block(10, false)).is_empty());
+        assert!(feed(&mut buffer, block(11, false)).is_empty());
+        let out = feed(&mut buffer, block(12, false));
+        assert_eq!(begin_blocks(&out), vec![(10, false)]);
+        let seqs: Vec<_> = out.iter().filter_map(ControlMessage::stream_seq).collect();
+        assert_eq!(seqs, vec![0, 1]);
+    }
+
+    #[test]
+    fn shutdown_passes_unconfirmed_blocks() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        feed(&mut buffer, block(10, false));
+        let out = buffer.push(ControlMessage::Shutdown);
+        assert!(
+            matches!(out.as_slice(), [ControlMessage::Shutdown]),
+            "unconfirmed blocks stay buffered; Shutdown goes out alone"
+        );
+    }
+
+    #[test]
+    fn shallow_reorg_is_absorbed() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        feed(&mut buffer, block(10, false));
+        feed(&mut buffer, block(11, false));
+        let released = feed(&mut buffer, block(12, false));
+        assert_eq!(begin_blocks(&released), vec![(10, false)]);
+
+        // Replace 12 (still buffered) with 12'.
+        let mut reorg = vec![ControlMessage::ReorgStart {
+            stream_seq: 0,
+            old_range: range(12, 12),
+            new_range: range(12, 12),
+        }];
+        reorg.extend(block(12, true));
+        reorg.extend(block(12, false));
+        reorg.push(ControlMessage::ReorgComplete {
+            stream_seq: 0,
+            final_tip_block: 12,
+        });
+        let out = feed(&mut buffer, reorg);
+        assert!(
+            out.iter().all(|m| !matches!(
+                m,
+                ControlMessage::ReorgStart { .. } | ControlMessage::ReorgComplete { .. }
+            )),
+            "absorbed reorg envelope is not emitted"
+        );
+        assert!(begin_blocks(&out).is_empty());
+
+        let out = feed(&mut buffer, block(13, false));
+        assert_eq!(begin_blocks(&out), vec![(11, false)]);
+        let seqs: Vec<_> = out.iter().filter_map(ControlMessage::stream_seq).collect();
+        assert_eq!(seqs, vec![2, 3], "no gap for the dropped block");
+    }
+
+    #[test]
+    fn deep_reorg_passes_through() {
+        let mut buffer = ConfirmationBuffer::new(1);
+        feed(&mut buffer, block(10, false));
+        feed(&mut buffer, block(11, false)); // releases 10
+
+        let mut reorg = vec![ControlMessage::ReorgStart {
+            stream_seq: 0,
+            old_range: range(10, 11),
+            new_range: range(10, 10),
+        }];
+        reorg.extend(block(11, true)); // never released: dropped
+        reorg.extend(block(10, true)); // released: passed through
+        reorg.extend(block(10, false));
+        reorg.push(ControlMessage::ReorgComplete {
+            stream_seq: 0,
+            final_tip_block: 10,
+        });
+        let out = feed(&mut buffer, reorg);
+        assert!(matches!(out[0], ControlMessage::ReorgStart { .. }));
+        assert_eq!(
+            begin_blocks(&out),
+            vec![(10, true)],
+            "the replacement block waits out the depth like any other"
+        );
+        assert!(!out
+            .iter()
+            .any(|m| matches!(m, ControlMessage::ReorgComplete { .. })));
+
+        let out = feed(&mut buffer, block(11, false));
+        assert_eq!(begin_blocks(&out), vec![(10, false)]);
+        assert!(matches!(
+            out.last(),
+            Some(ControlMessage::ReorgComplete { .. })
+        ));
+        let seqs: Vec<_> = out.iter().filter_map(ControlMessage::stream_seq).collect();
+        assert_eq!(seqs, vec![5, 6, 7], "contiguous after the released revert");
+    }
+
+    #[test]
+    fn side_messages_keep_their_place_behind_buffered_blocks() {
+        let mut buffer = ConfirmationBuffer::new(1);
+        feed(&mut buffer, block(10, false));
+        let finalized = ControlMessage::Finalized { block_number: 9 };
+        assert!(
+            buffer.push(finalized).is_empty(),
+            "held behind block 10, which is still buffered"
+        );
+
+        let out = feed(&mut buffer, block(11, false));
+        assert_eq!(begin_blocks(&out), vec![(10, false)]);
+        assert!(matches!(
+            out.last(),
+            Some(ControlMessage::Finalized { block_number: 9 })
+        ));
+    }
+
+    #[test]
+    fn discard_unreleased_reports_buffered_blocks() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        feed(&mut buffer, block(10, false));
+        feed(&mut buffer, block(11, false));
+        buffer.push(block(12, false).remove(0));
+        assert_eq!(buffer.discard_unreleased(), vec![10, 11, 12]);
+        assert!(buffer.discard_unreleased().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// Strict Decoding Dead Letters
+//
+// By default a log from a tracked address that no decoder accepts is skipped
+// silently, so an event whose ABI drifted (like the V4 `Swap` fee field) just
+// stops producing updates. With `STRICT_DECODING=true` such logs are captured
+// instead (address, topics, data, block, tx hash and position) and written as
+// JSON to the dead-letter NATS subject `deadletter.liquidity.<chain>` and, when
+// `DEAD_LETTER_PATH` is set, appended to that file as JSON lines.
+//
+// Tracked addresses also emit logs the ExEx has no use for: ERC-20 `Transfer`
+// and `Approval` (pool LP tokens) are never captured, and further topic0s can
+// be listed in `STRICT_DECODING_IGNORE_TOPICS` (comma-separated hex).
+//
+// Publishing runs on its own task behind a bounded queue; a full queue drops
+// letters (counted, never blocking the block loop).
+
+use crate::{exex_metrics, subjects};
+use alloy_primitives::{b256, Address, Bytes, Log, B256};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// `Transfer(address,address,uint256)`
+const ERC20_TRANSFER: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+/// `Approval(address,address,uint256)`
+const ERC20_APPROVAL: B256 =
+    b256!("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+
+const QUEUE_CAPACITY: usize = 10_000;
+
+static STRICT: OnceLock<Option<StrictDecoding>> = OnceLock::new();
+
+/// Which undecodable logs strict decoding captures.
+#[derive(Debug, Clone)]
+pub struct StrictDecoding {
+    ignore: HashSet<B256>,
+}
+
+impl StrictDecoding {
+    pub fn new(extra_ignore: impl IntoIterator<Item = B256>) -> Self {
+        let mut ignore: HashSet<B256> = extra_ignore.into_iter().collect();
+        ignore.extend([ERC20_TRANSFER, ERC20_APPROVAL]);
+        Self { ignore }
+    }
+
+    /// Whether an undecodable log from a tracked address is a dead letter.
+    pub fn captures(&self, log: &Log) -> bool {
+        log.topics()
+            .first()
+            .is_none_or(|topic0| !self.ignore.contains(topic0))
+    }
+}
+
+/// Strict decoding, if `STRICT_DECODING` enables it (read once).
+pub fn strict() -> Option<&'static StrictDecoding> {
+    STRICT
+        .get_or_init(|| {
+            let enabled = std::env::var("STRICT_DECODING")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+            if !enabled {
+                return None;
+            }
+            let ignore = std::env::var("STRICT_DECODING_IGNORE_TOPICS").unwrap_or_default();
+            let ignore = ignore.split(',').map(str::trim).filter(|t| !t.is_empty());
+            let ignore = ignore.filter_map(|topic| match topic.parse::<B256>() {
+                Ok(topic) => Some(topic),
+                Err(e) => {
+                    warn!(
+                        "Ignoring STRICT_DECODING_IGNORE_TOPICS entry {:?}: {}",
+                        topic, e
+                    );
+                    None
+                }
+            });
+            Some(StrictDecoding::new(ignore))
+        })
+        .as_ref()
+}
+
+/// A tracked-address log that no decoder accepted.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub tx_index: u64,
+    pub log_index: u64,
+    /// `None` if the block's transactions could not be read.
+    pub tx_hash: Option<B256>,
+    pub address: Address,
+    pub topic0: Option<B256>,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+/// Queue to the dead-letter publisher task.
+#[derive(Debug, Clone)]
+pub struct DeadLetterSink {
+    tx: mpsc::Sender<DeadLetter>,
+}
+
+impl DeadLetterSink {
+    /// Start the publisher when strict decoding is enabled.
+    pub fn spawn_from_env() -> Option<Self> {
+        strict()?;
+        let nats_url =
+            std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+        let path = std::env::var("DEAD_LETTER_PATH").ok().map(PathBuf::from);
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(publish(rx, nats_url, path));
+        Some(Self { tx })
+    }
+
+    pub fn send(&self, letter: DeadLetter) {
+        exex_metrics::record_liquidity_dead_letter();
+        if self.tx.try_send(letter).is_err() {
+            warn!("Dead-letter queue full, dropping a dead letter");
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+async fn publish(mut rx: mpsc::Receiver<DeadLetter>, nats_url: String, path: Option<PathBuf>) {
+    let client = match async_nats::connect(&nats_url).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            warn!(
+                "Dead-letter publisher could not connect to NATS at {}: {}",
+                nats_url, e
+            );
+            None
+        }
+    };
+    let subject = subjects::dead_letters(&crate::chain::chain_name());
+    info!(subject = %subject, path = ?path, "🪦 Strict decoding: publishing dead letters");
+
+    while let Some(letter) = rx.recv().await {
+        warn!(
+            block = letter.block_number,
+            tx_index = letter.tx_index,
+            log_index = letter.log_index,
+            address = %letter.address,
+            topic0 = ?letter.topic0,
+            "Undecodable log from a tracked address"
+        );
+        let payload = serde_json::to_vec(&letter).expect("DeadLetter serializes");
+        if let Some(path) = &path {
+            if let Err(e) = append_line(path, &payload) {
+                warn!("Failed to write dead letter to {}: {}", path.display(), e);
+                exex_metrics::record_sink_error("liquidity");
+            }
+        }
+        if let Some(client) = &client {
+            if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+                warn!("Failed to publish dead letter: {}", e);
+                exex_metrics::record_sink_error("liquidity");
+            }
+        }
+    }
+}
+
+fn append_line(path: &Path, payload: &[u8]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(payload)?;
+    file.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{keccak256, LogData};
+
+    #[test]
+    fn erc20_and_listed_topics_are_not_captured() {
+        let log = |topic0: B256| Log {
+            address: Address::ZERO,
+            data: LogData::new_unchecked(vec![topic0], Bytes::new()),
+        };
+        let listed = keccak256("Initialize(bytes32)");
+        let strict = StrictDecoding::new([listed]);
+
+        assert_eq!(
+            ERC20_TRANSFER,
+            keccak256("Transfer(address,address,uint256)")
+        );
+        assert_eq!(
+            ERC20_APPROVAL,
+            keccak256("Approval(address,address,uint256)")
+        );
+        assert!(!strict.captures(&log(ERC20_TRANSFER)));
+        assert!(!strict.captures(&log(ERC20_APPROVAL)));
+        assert!(!strict.captures(&log(listed)));
+        assert!(strict.captures(&log(keccak256("Swap(bytes32,int128)"))));
+        assert!(strict.captures(&Log {
+            address: Address::ZERO,
+            data: LogData::new_unchecked(vec![], Bytes::new()),
+        }));
+    }
+}
@@ -0,0 +1,230 @@
+// Stablecoin Depeg Alerts
+//
+// Optional (`DEPEG_POOLS`, comma-separated pool addresses): after each
+// committed block the liquidity ExEx reads the mid price of every listed
+// stable/stable pool (USDC/USDT, DAI/USDC, ...) from the block's post-state —
+// V2 reserves or the V3 slot0 sqrt price, in whole-token units — and measures
+// its deviation from 1:1. A pool that stays more than `DEPEG_THRESHOLD_BPS`
+// (default 50) off peg for `DEPEG_CONSECUTIVE_BLOCKS` (default 3) blocks in a
+// row raises a `depegged` alert on `alerts.depeg.<chain>`; the first block back
+// within the threshold sends `resolved`. Alerts are edge-triggered, like the
+// balance alerts.
+//
+// Listed pools must be in the whitelist (for their protocol and token
+// decimals); untracked or non-V2/V3 pools are skipped. Replacement blocks of a
+// reorg continue the streak of the blocks they replace.
+
+use crate::types::PoolMetadata;
+use crate::{exex_metrics, subjects};
+use alloy_primitives::Address;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const QUEUE_CAPACITY: usize = 256;
+const DEFAULT_THRESHOLD_BPS: u32 = 50;
+const DEFAULT_CONSECUTIVE_BLOCKS: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepegStatus {
+    Depegged,
+    Resolved,
+}
+
+/// NATS alert message.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepegAlert {
+    pub chain: String,
+    pub block_number: u64,
+    pub pool: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub status: DepegStatus,
+    /// token1 per token0.
+    pub price: f64,
+    pub deviation_bps: f64,
+    /// Consecutive off-peg blocks (0 for `resolved`).
+    pub off_peg_blocks: u64,
+    pub ts: u64,
+}
+
+/// Off-peg streak per pool and whether it has been alerted.
+#[derive(Debug)]
+pub struct DepegMonitor {
+    threshold_bps: f64,
+    consecutive_blocks: u64,
+    streaks: HashMap<Address, (u64, bool)>,
+}
+
+impl DepegMonitor {
+    pub fn new(threshold_bps: u32, consecutive_blocks: u64) -> Self {
+        Self {
+            threshold_bps: f64::from(threshold_bps),
+            consecutive_blocks: consecutive_blocks.max(1),
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// Evaluate a pool's price at one block. Returns the status and streak
+    /// length when an alert is due.
+    pub fn observe(&mut self, pool: Address, price: f64) -> Option<(DepegStatus, u64)> {
+        let (streak, alerted) = self.streaks.entry(pool).or_insert((0, false));
+        if deviation_bps(price) > self.threshold_bps {
+            *streak += 1;
+            if !*alerted && *streak >= self.consecutive_blocks {
+                *alerted = true;
+                return Some((DepegStatus::Depegged, *streak));
+            }
+            return None;
+        }
+        *streak = 0;
+        if std::mem::take(alerted) {
+            return Some((DepegStatus::Resolved, 0));
+        }
+        None
+    }
+}
+
+/// Distance of a price from 1:1, in basis points.
+pub fn deviation_bps(price: f64) -> f64 {
+    (price - 1.0).abs() * 10_000.0
+}
+
+/// The monitor, its pools and the queue to its publisher task.
+#[derive(Debug)]
+pub struct DepegWatch {
+    pools: Vec<Address>,
+    monitor: DepegMonitor,
+    tx: mpsc::Sender<DepegAlert>,
+}
+
+impl DepegWatch {
+    /// Start the publisher when `DEPEG_POOLS` lists at least one pool.
+    pub fn spawn_from_env() -> Option<Self> {
+        let raw = std::env::var("DEPEG_POOLS").ok()?;
+        let mut pools = Vec::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.parse::<Address>() {
+                Ok(pool) => pools.push(pool),
+                Err(e) => warn!("Ignoring DEPEG_POOLS entry {:?}: {}", entry, e),
+            }
+        }
+        if pools.is_empty() {
+            return None;
+        }
+        let threshold_bps = std::env::var("DEPEG_THRESHOLD_BPS")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_THRESHOLD_BPS);
+        let consecutive_blocks = std::env::var("DEPEG_CONSECUTIVE_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_CONSECUTIVE_BLOCKS);
+        let nats_url =
+            std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+        info!(
+            pools = pools.len(),
+            threshold_bps, consecutive_blocks, "🪙 Stablecoin depeg monitor enabled"
+        );
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(publish(rx, nats_url));
+        Some(Self {
+            pools,
+            monitor: DepegMonitor::new(threshold_bps, consecutive_blocks),
+            tx,
+        })
+    }
+
+    pub fn pools(&self) -> &[Address] {
+        &self.pools
+    }
+
+    /// Evaluate one pool's post-block price and queue an alert when due.
+    pub fn observe(&mut self, pool: &PoolMetadata, block_number: u64, price: f64) {
+        let Some(address) = pool.pool_id.as_address() else {
+            return;
+        };
+        let Some((status, off_peg_blocks)) = self.monitor.observe(address, price) else {
+            return;
+        };
+        let alert = DepegAlert {
+            chain: crate::chain::chain_name(),
+            block_number,
+            pool: address,
+            token0: pool.token0,
+            token1: pool.token1,
+            status,
+            price,
+            deviation_bps: deviation_bps(price),
+            off_peg_blocks,
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        warn!(
+            pool = %address,
+            block_number,
+            ?status,
+            price,
+            "Stablecoin pool peg status changed"
+        );
+        if self.tx.try_send(alert).is_err() {
+            warn!("Depeg alert queue full, dropping an alert");
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+async fn publish(mut rx: mpsc::Receiver<DepegAlert>, nats_url: String) {
+    let client = match async_nats::connect(&nats_url).await {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(
+                "Depeg alert publisher could not connect to NATS at {}: {}",
+                nats_url, e
+            );
+            return;
+        }
+    };
+    let subject = subjects::depeg_alerts(&crate::chain::chain_name());
+
+    while let Some(alert) = rx.recv().await {
+        let payload = serde_json::to_vec(&alert).expect("DepegAlert serializes");
+        if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+            warn!("Failed to publish depeg alert: {}", e);
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depeg_alerts_after_consecutive_blocks_and_resolves_once() {
+        let pool = Address::from([1u8; 20]);
+        let mut monitor = DepegMonitor::new(50, 3);
+
+        assert_eq!(monitor.observe(pool, 0.9990), None);
+        assert_eq!(monitor.observe(pool, 0.9900), None);
+        // A block back on peg restarts the streak.
+        assert_eq!(monitor.observe(pool, 1.0001), None);
+        assert_eq!(monitor.observe(pool, 0.9900), None);
+        assert_eq!(monitor.observe(pool, 0.9800), None);
+        assert_eq!(
+            monitor.observe(pool, 1.0100),
+            Some((DepegStatus::Depegged, 3))
+        );
+        assert_eq!(monitor.observe(pool, 0.9700), None);
+        assert_eq!(
+            monitor.observe(pool, 0.9998),
+            Some((DepegStatus::Resolved, 0))
+        );
+        assert_eq!(monitor.observe(pool, 0.9999), None);
+    }
+}
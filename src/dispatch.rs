@@ -0,0 +1,429 @@
+// Single-Pass Block Dispatch
+//
+// Subsystems that only need a block's transactions and logs register a
+// `BlockHandler` with one `Dispatcher` instead of running as their own ExEx
+// that walks the same receipts again. The dispatcher walks each notification
+// once: tx hashes and senders are read once, each log's topic0 is looked up
+// once, and a handler only sees the logs whose topic0 it registered. Blocks
+// arrive in chain order and logs in (tx_index, log_index) order.
+//
+// Decoding runs synchronously on the walk. Side effects (DB writes, NATS
+// publishes) happen in `flush`, which the dispatcher awaits once per
+// notification. FinishedHeight goes through a `FinishedHeightBatcher` with one
+// sink per handler, so it never covers a block past the lowest durable height
+// of any handler and sharing the walk does not weaken any handler's durability.
+//
+// On node shutdown the walk stops between notifications and every handler's
+// `shutdown` runs before reth's shutdown guard is released.
+//
+// The balance monitor's log scans and swap monitoring are handlers here too;
+// the BalanceMonitor ExEx keeps only the reads of state at each tip and takes
+// their per-block results from a shared store (see `balance_monitor::scan`).
+// The liquidity ExEx still walks on its own: it owns stream sequencing and
+// the reorg journal.
+
+use crate::finished_height::FinishedHeightBatcher;
+use crate::shutdown::{self, ShutdownSignal};
+use alloy_consensus::{transaction::TxHashRef, BlockHeader, Transaction, TxReceipt};
+use alloy_primitives::{Address, Log, B256, U256};
+use futures::future::BoxFuture;
+use reth_exex::{ExExContext, ExExEvent, ExExNotification};
+use reth_node_api::{BlockBody, FullNodeComponents, NodePrimitives};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{info, info_span, warn, Instrument};
+
+/// The block being walked.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockInfo {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+    /// `None` before London.
+    pub base_fee_per_gas: Option<u64>,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+}
+
+/// A transaction of the block being walked, read once for every handler.
+#[derive(Debug, Clone)]
+pub struct TxInfo {
+    pub index: usize,
+    pub hash: B256,
+    pub from: Address,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub value: U256,
+    /// First four calldata bytes; `None` for shorter calldata.
+    pub selector: Option<[u8; 4]>,
+    pub success: bool,
+    /// From the difference of consecutive cumulative receipt gas.
+    pub gas_used: u64,
+    /// Effective priority fee per gas paid to the block producer.
+    pub priority_fee_per_gas: u128,
+}
+
+/// A subsystem fed by the shared walk.
+pub trait BlockHandler: Send {
+    fn name(&self) -> &'static str;
+
+    /// Log topic0s routed to `on_log`.
+    fn topics(&self) -> Vec<B256>;
+
+    /// Every transaction, before its logs.
+    fn on_tx(&mut self, _block: &BlockInfo, _tx: &TxInfo) {}
+
+    /// A log whose topic0 is in `topics`. `log_index` is within the receipt.
+    fn on_log(&mut self, block: &BlockInfo, tx: &TxInfo, log_index: usize, log: &Log);
+
+    /// All transactions of `block` have been walked.
+    fn end_block(&mut self, _block: &BlockInfo) {}
+
+    /// `blocks` were reverted; called before any replacement blocks.
+    fn on_revert(&mut self, _blocks: &[u64]) {}
+
+    /// Apply the notification's side effects. `committed_tip` is the new tip,
+    /// if the notification committed blocks. An error stops the ExEx.
+    fn flush(&mut self, committed_tip: Option<u64>) -> BoxFuture<'_, eyre::Result<()>>;
+
+    /// Highest block whose side effects are durable, when that lags the last
+    /// flush (e.g. rows still buffered in memory). `None`: everything flushed
+    /// is durable.
+    fn durable_height(&self) -> Option<u64> {
+        None
+    }
+
+    /// The node is shutting down after the last flush: push out anything
+    /// still buffered.
+    fn shutdown(&mut self) -> BoxFuture<'_, eyre::Result<()>> {
+        self.flush(None)
+    }
+}
+
+/// `FinishedHeight` sink of the walk: the last flushed notification.
+const WALK_SINK: &str = "walk";
+
+/// Routes one walk over each notification to every registered handler.
+#[derive(Default)]
+pub struct Dispatcher {
+    handlers: Vec<Box<dyn BlockHandler>>,
+    /// topic0 → indices into `handlers`.
+    routes: HashMap<B256, Vec<usize>>,
+}
+
+impl Dispatcher {
+    pub fn register(&mut self, handler: Box<dyn BlockHandler>) {
+        let index = self.handlers.len();
+        for topic in handler.topics() {
+            self.routes.entry(topic).or_default().push(index);
+        }
+        info!(handler = handler.name(), "Registered block handler");
+        self.handlers.push(handler);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Walk one block: each transaction, then its routed logs.
+    pub fn dispatch_block<'a>(
+        &mut self,
+        block: &BlockInfo,
+        txs: impl IntoIterator<Item = (TxInfo, &'a [Log])>,
+    ) {
+        let _span = info_span!("dispatch.block", block = block.number).entered();
+        for (tx, logs) in txs {
+            for handler in &mut self.handlers {
+                handler.on_tx(block, &tx);
+            }
+            for (log_index, log) in logs.iter().enumerate() {
+                let Some(targets) = log.topics().first().and_then(|t| self.routes.get(t)) else {
+                    continue;
+                };
+                for &target in targets {
+                    self.handlers[target].on_log(block, &tx, log_index, log);
+                }
+            }
+        }
+        for handler in &mut self.handlers {
+            handler.end_block(block);
+        }
+    }
+
+    pub fn dispatch_revert(&mut self, blocks: &[u64]) {
+        for handler in &mut self.handlers {
+            handler.on_revert(blocks);
+        }
+    }
+
+    /// Walk a notification: reverted blocks first, then committed ones.
+    pub fn dispatch_notification<N>(&mut self, notification: &ExExNotification<N>)
+    where
+        N: NodePrimitives<Receipt: TxReceipt<Log = Log>>,
+        N::BlockBody: BlockBody<Transaction: TxHashRef + Transaction>,
+    {
+        if let Some(old) = notification.reverted_chain() {
+            let blocks: Vec<u64> = old.blocks().keys().copied().collect();
+            self.dispatch_revert(&blocks);
+        }
+        if let Some(new) = notification.committed_chain() {
+            for (block, receipts) in new.blocks_and_receipts() {
+                let info = BlockInfo {
+                    number: block.number(),
+                    hash: block.hash(),
+                    timestamp: block.timestamp(),
+                    base_fee_per_gas: block.base_fee_per_gas(),
+                    gas_used: block.gas_used(),
+                    gas_limit: block.gas_limit(),
+                };
+                let mut previous_cumulative = 0u64;
+                let txs = block
+                    .transactions_with_sender()
+                    .zip(receipts)
+                    .enumerate()
+                    .map(|(index, ((sender, tx), receipt))| {
+                        let tx_info = TxInfo {
+                            index,
+                            hash: *tx.tx_hash(),
+                            from: *sender,
+                            to: tx.to(),
+                            value: tx.value(),
+                            selector: tx
+                                .input()
+                                .get(..4)
+                                .and_then(|selector| selector.try_into().ok()),
+                            success: receipt.status(),
+                            gas_used: receipt
+                                .cumulative_gas_used()
+                                .saturating_sub(previous_cumulative),
+                            priority_fee_per_gas: tx
+                                .effective_tip_per_gas(info.base_fee_per_gas.unwrap_or(0))
+                                .unwrap_or(0),
+                        };
+                        previous_cumulative = receipt.cumulative_gas_used();
+                        (tx_info, receipt.logs())
+                    });
+                self.dispatch_block(&info, txs);
+            }
+        }
+    }
+
+    /// Flush every handler in registration order.
+    pub async fn flush(&mut self, committed_tip: Option<u64>) -> eyre::Result<()> {
+        for handler in &mut self.handlers {
+            let name = handler.name();
+            handler
+                .flush(committed_tip)
+                .instrument(info_span!("dispatch.sink", handler = name))
+                .await
+                .map_err(|e| eyre::eyre!("{name} handler failed: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// The batcher's sinks: the walk itself and every handler.
+    pub fn sinks(&self) -> Vec<&'static str> {
+        std::iter::once(WALK_SINK)
+            .chain(self.handlers.iter().map(|handler| handler.name()))
+            .collect()
+    }
+
+    /// Highest block each sink has made durable, given that all blocks up to
+    /// `flushed` have been flushed.
+    pub fn durable_heights(&self, flushed: u64) -> Vec<(&'static str, u64)> {
+        std::iter::once((WALK_SINK, flushed))
+            .chain(self.handlers.iter().map(|handler| {
+                let durable = handler
+                    .durable_height()
+                    .map_or(flushed, |height| height.min(flushed));
+                (handler.name(), durable)
+            }))
+            .collect()
+    }
+
+    /// Shut every handler down, each bounded by `FLUSH_TIMEOUT`. Failures are
+    /// logged: one handler must not keep the others from flushing.
+    pub async fn shutdown(&mut self) {
+        for handler in &mut self.handlers {
+            let name = handler.name();
+            match tokio::time::timeout(shutdown::FLUSH_TIMEOUT, handler.shutdown()).await {
+                Ok(Ok(())) => info!(handler = name, "Handler flushed"),
+                Ok(Err(e)) => warn!(handler = name, "Handler shutdown failed: {e}"),
+                Err(_) => warn!(handler = name, "Handler shutdown timed out"),
+            }
+        }
+    }
+}
+
+/// The ExEx hosting `dispatcher`'s handlers.
+pub async fn dispatch_exex<Node: FullNodeComponents>(
+    mut ctx: ExExContext<Node>,
+    mut dispatcher: Dispatcher,
+) -> eyre::Result<()> {
+    if dispatcher.is_empty() {
+        warn!("Dispatch ExEx started with no handlers");
+    }
+    info!("Dispatch ExEx starting");
+
+    let mut finished = FinishedHeightBatcher::from_env(dispatcher.sinks());
+    let mut flushed = None;
+    let mut shutdown = ShutdownSignal::new("dispatch", ctx.task_executor());
+    while let Some(notification) = shutdown.next(&mut ctx.notifications).await? {
+        info_span!("dispatch.walk").in_scope(|| dispatcher.dispatch_notification(&notification));
+        if let Some(old) = notification.reverted_chain() {
+            let first = old.first().number();
+            finished.revert_from(first);
+            flushed = flushed.map(|flushed: u64| flushed.min(first.saturating_sub(1)));
+        }
+
+        let tip = notification
+            .committed_chain()
+            .map(|chain| chain.tip().num_hash());
+        dispatcher.flush(tip.map(|tip| tip.number)).await?;
+
+        if let Some(new) = notification.committed_chain() {
+            finished.commit(new.blocks_iter().map(|block| block.num_hash()));
+            flushed = Some(new.tip().number());
+        }
+        if let Some(flushed) = flushed {
+            for (sink, durable) in dispatcher.durable_heights(flushed) {
+                finished.set_durable(sink, durable);
+            }
+        }
+        if let Some(height) = finished.poll(Instant::now()) {
+            ctx.events.send(ExExEvent::FinishedHeight(height))?;
+        }
+    }
+
+    dispatcher.shutdown().await;
+    if let Some(flushed) = flushed {
+        for (sink, durable) in dispatcher.durable_heights(flushed) {
+            finished.set_durable(sink, durable);
+        }
+    }
+    if let Some(height) = finished.flush() {
+        ctx.events.send(ExExEvent::FinishedHeight(height))?;
+    }
+    shutdown.finish().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256, Bytes, LogData};
+    use std::sync::{Arc, Mutex};
+
+    const TOPIC_A: B256 = b256!("00000000000000000000000000000000000000000000000000000000000000aa");
+    const TOPIC_B: B256 = b256!("00000000000000000000000000000000000000000000000000000000000000bb");
+
+    struct Recorder {
+        topics: Vec<B256>,
+        seen: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl BlockHandler for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        fn topics(&self) -> Vec<B256> {
+            self.topics.clone()
+        }
+
+        fn on_tx(&mut self, block: &BlockInfo, tx: &TxInfo) {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(format!("tx {}:{}", block.number, tx.index));
+        }
+
+        fn on_log(&mut self, _block: &BlockInfo, tx: &TxInfo, log_index: usize, _log: &Log) {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(format!("log {}:{}", tx.index, log_index));
+        }
+
+        fn end_block(&mut self, block: &BlockInfo) {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(format!("end {}", block.number));
+        }
+
+        fn on_revert(&mut self, blocks: &[u64]) {
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(format!("revert {blocks:?}"));
+        }
+
+        fn flush(&mut self, _committed_tip: Option<u64>) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn log(topic: B256) -> Log {
+        Log {
+            address: address!("0000000000000000000000000000000000000001"),
+            data: LogData::new_unchecked(vec![topic], Bytes::new()),
+        }
+    }
+
+    fn tx(index: usize) -> TxInfo {
+        TxInfo {
+            index,
+            hash: B256::ZERO,
+            from: Address::ZERO,
+            to: None,
+            value: U256::ZERO,
+            selector: None,
+            success: true,
+            gas_used: 21_000,
+            priority_fee_per_gas: 0,
+        }
+    }
+
+    #[test]
+    fn logs_are_routed_by_topic_in_order() {
+        let a_seen = Arc::new(Mutex::new(Vec::new()));
+        let b_seen = Arc::new(Mutex::new(Vec::new()));
+        let mut dispatcher = Dispatcher::default();
+        dispatcher.register(Box::new(Recorder {
+            topics: vec![TOPIC_A],
+            seen: a_seen.clone(),
+        }));
+        dispatcher.register(Box::new(Recorder {
+            topics: vec![TOPIC_B],
+            seen: b_seen.clone(),
+        }));
+
+        let tx0_logs = [log(TOPIC_A), log(TOPIC_B), log(TOPIC_A)];
+        let tx1_logs = [log(TOPIC_B)];
+        let block = BlockInfo {
+            number: 7,
+            hash: B256::ZERO,
+            timestamp: 0,
+            base_fee_per_gas: None,
+            gas_used: 0,
+            gas_limit: 30_000_000,
+        };
+        dispatcher.dispatch_revert(&[7]);
+        dispatcher.dispatch_block(&block, [(tx(0), &tx0_logs[..]), (tx(1), &tx1_logs[..])]);
+
+        assert_eq!(
+            *a_seen.lock().unwrap(),
+            [
+                "revert [7]",
+                "tx 7:0",
+                "log 0:0",
+                "log 0:2",
+                "tx 7:1",
+                "end 7"
+            ]
+        );
+        assert_eq!(
+            *b_seen.lock().unwrap(),
+            [
+                "revert [7]",
+                "tx 7:0",
+                "log 0:1",
+                "tx 7:1",
+                "log 1:0",
+                "end 7"
+            ]
+        );
+    }
+}
@@ -2,7 +2,9 @@
 //
 // This module defines all liquidity events and provides decoding logic
 
-use alloy_primitives::{Address, Log, U256};
+use crate::chain;
+use crate::types::EventClass;
+use alloy_primitives::{Address, Log, I256, U256};
 use alloy_sol_types::{sol, SolEvent};
 
 // ============================================================================
@@ -416,7 +418,7 @@ mod ekubo {
 
 use ekubo::PositionUpdated as EkuboPositionUpdated;
 
-/// Ekubo Core contract address on Ethereum mainnet.
+/// Ekubo Core contract address on Ethereum mainnet. Other chains: see `chain`.
 pub const EKUBO_CORE: Address = Address::new([
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x14, 0xaA, 0x86, 0xC5, 0xd3, 0xc4, 0x17, 0x65, 0xbB, 0x24,
     0xe1, 0x1b, 0xd7, 0x01,
@@ -469,7 +471,7 @@ use balancer::{
     SwapFeePercentageChanged,
 };
 
-/// Balancer V2 Vault contract address (Ethereum Mainnet).
+/// Balancer V2 Vault contract address (Ethereum Mainnet). Other chains: see `chain`.
 pub const BALANCER_V2_VAULT: Address = Address::new([
     0xBA, 0x12, 0x22, 0x22, 0x22, 0x22, 0x8d, 0x8B, 0xa4, 0x45, 0x95, 0x8a, 0x75, 0xa0, 0x70, 0x4d,
     0x56, 0x6B, 0xF2, 0xC8,
@@ -534,6 +536,9 @@ pub enum DecodedEvent {
     },
     V3Swap {
         pool: Address,
+        /// Pool-perspective token deltas (positive = paid into the pool).
+        amount0: I256,
+        amount1: I256,
         sqrt_price_x96: U256,
         liquidity: u128,
         tick: i32,
@@ -552,6 +557,9 @@ pub enum DecodedEvent {
     },
     V4Swap {
         pool_id: [u8; 32],
+        /// Swapper-perspective balance deltas (negative = paid by the swapper).
+        amount0: I256,
+        amount1: I256,
         sqrt_price_x96: U256,
         liquidity: u128,
         tick: i32,
@@ -665,6 +673,53 @@ pub enum DecodedEvent {
     BalancerFeeChange {
         pool: Address,
     },
+    /// Swap of a `TRACE_SWAP_POOLS` pool reconstructed from its net token
+    /// flow in a transaction's call trace (see `call_trace`), not from a log.
+    TracedSwap {
+        pool: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        amount_out: U256,
+    },
+}
+
+impl DecodedEvent {
+    /// Swap vs liquidity class for per-pool event masks. `None` for state-sync
+    /// and parameter events, which are never masked.
+    pub fn event_class(&self) -> Option<EventClass> {
+        match self {
+            DecodedEvent::V2Swap { .. }
+            | DecodedEvent::V3Swap { .. }
+            | DecodedEvent::V4Swap { .. }
+            | DecodedEvent::EkuboSwap { .. }
+            | DecodedEvent::CurveSwap { .. }
+            | DecodedEvent::TwoCryptoSwap { .. }
+            | DecodedEvent::BalancerSwap { .. }
+            | DecodedEvent::TracedSwap { .. } => Some(EventClass::Swap),
+
+            DecodedEvent::V2Mint { .. }
+            | DecodedEvent::V2Burn { .. }
+            | DecodedEvent::V3Mint { .. }
+            | DecodedEvent::V3Burn { .. }
+            | DecodedEvent::V4ModifyLiquidity { .. }
+            | DecodedEvent::EkuboPositionUpdated { .. }
+            | DecodedEvent::CurveLiquidityChange { .. }
+            | DecodedEvent::TwoCryptoLiquidityChange { .. }
+            | DecodedEvent::TricryptoLiquidityChange { .. }
+            | DecodedEvent::BalancerPoolBalanceChanged { .. } => Some(EventClass::Liquidity),
+
+            // V2 Sync carries absolute reserves after swaps AND mints/burns;
+            // Fluid LogOperate is a storage re-read trigger for either.
+            DecodedEvent::V2Sync { .. }
+            | DecodedEvent::FluidOperate { .. }
+            | DecodedEvent::CurveRampA { .. }
+            | DecodedEvent::CurveApplyNewFee { .. }
+            | DecodedEvent::TwoCryptoRampAgamma { .. }
+            | DecodedEvent::TwoCryptoNewParameters { .. }
+            | DecodedEvent::BalancerFeeChange { .. } => None,
+        }
+    }
 }
 
 /// Check if a log is a Fluid `LogOperate` for a specific pool address
@@ -733,6 +788,8 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
     if let Ok(event) = UniswapV3Swap::decode_log(log) {
         return Some(DecodedEvent::V3Swap {
             pool,
+            amount0: event.data.amount0,
+            amount1: event.data.amount1,
             sqrt_price_x96: U256::from(event.data.sqrtPriceX96),
             liquidity: event.data.liquidity,
             tick: event.data.tick.as_i32(),
@@ -743,6 +800,8 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
     if let Ok(event) = PancakeV3Swap::decode_log(log) {
         return Some(DecodedEvent::V3Swap {
             pool,
+            amount0: event.data.amount0,
+            amount1: event.data.amount1,
             sqrt_price_x96: U256::from(event.data.sqrtPriceX96),
             liquidity: event.data.liquidity,
             tick: event.data.tick.as_i32(),
@@ -787,6 +846,8 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
                 let pool_id: [u8; 32] = log.topics()[1].into();
                 return Some(DecodedEvent::V4Swap {
                     pool_id,
+                    amount0: I256::try_from(event.amount0).unwrap_or_default(),
+                    amount1: I256::try_from(event.amount1).unwrap_or_default(),
                     sqrt_price_x96: U256::from(event.sqrtPriceX96),
                     liquidity: event.liquidity,
                     tick: event.tick.as_i32(),
@@ -799,7 +860,7 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
                 let pool_id: [u8; 32] = log.topics()[1].into();
 
                 // Convert i256 to i128 (safe because liquidity deltas won't overflow i128)
-                let liquidity_delta = if event.liquidityDelta >= alloy_primitives::I256::ZERO {
+                let liquidity_delta = if event.liquidityDelta >= I256::ZERO {
                     let abs = event.liquidityDelta.into_raw();
                     i128::try_from(abs.saturating_to::<u128>()).unwrap_or(i128::MAX)
                 } else {
@@ -914,7 +975,7 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
     // ── Ekubo events ──────────────────────────────────────────────────────
     // Ekubo Core uses anonymous log0 for swaps and standard events for liquidity.
 
-    if log.address == EKUBO_CORE {
+    if log.address == chain::active().ekubo_core {
         // Anonymous swap log0: no topics, exactly 116 bytes data.
         // Layout: locker(20) | poolId(32) | balanceUpdate(32) | stateAfter(32)
         if log.topics().is_empty() && log.data.data.len() == 116 {
@@ -981,7 +1042,7 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
     // The Vault singleton emits Swap and PoolBalanceChanged for all Balancer pools.
     // poolId is in topics[1]; tokenIn/tokenOut are indexed for Swap.
 
-    if log.address == BALANCER_V2_VAULT {
+    if log.address == chain::active().balancer_v2_vault {
         // Vault Swap: topics = [sig, poolId, tokenIn, tokenOut], data = (amountIn, amountOut)
         if log.topics().len() >= 4 && log.topics()[0] == BalancerVaultSwap::SIGNATURE_HASH {
             if let Ok(event) = BalancerVaultSwap::decode_log_data(&log.data) {
@@ -1007,7 +1068,7 @@ pub fn decode_log(log: &Log) -> Option<DecodedEvent> {
                     .deltas
                     .iter()
                     .map(|d| {
-                        if *d >= alloy_primitives::I256::ZERO {
+                        if *d >= I256::ZERO {
                             i128::try_from(d.into_raw().saturating_to::<u128>())
                                 .unwrap_or(i128::MAX)
                         } else {
@@ -1514,6 +1575,7 @@ mod tests {
                 sqrt_price_x96,
                 liquidity,
                 tick,
+                ..
             } => {
                 assert_eq!(pool, pool_address);
                 assert!(sqrt_price_x96 > U256::ZERO);
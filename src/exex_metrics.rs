@@ -0,0 +1,199 @@
+// ExEx Metrics
+//
+// Counters and gauges for every ExEx, recorded through the `metrics` facade.
+// reth installs its Prometheus recorder when the node runs with
+// `--metrics <addr>` and serves these next to its own metrics on that HTTP
+// endpoint (`curl <addr>/metrics`); without the flag recording is a no-op.
+// All names share the `exex_` prefix and carry the ExEx in the second word.
+// The recorders also keep the in-process totals `status` publishes to NATS.
+
+use crate::status;
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+
+pub const LIQUIDITY_BLOCKS: &str = "exex_liquidity_blocks_total";
+pub const LIQUIDITY_LOGS_CHECKED: &str = "exex_liquidity_logs_checked_total";
+pub const LIQUIDITY_LOGS_MATCHED: &str = "exex_liquidity_logs_matched_total";
+pub const LIQUIDITY_EVENTS_DECODED: &str = "exex_liquidity_events_decoded_total";
+pub const LIQUIDITY_EVENTS_FILTERED: &str = "exex_liquidity_events_filtered_total";
+pub const LIQUIDITY_UPDATES_EMITTED: &str = "exex_liquidity_updates_emitted_total";
+pub const LIQUIDITY_SOCKET_SEND_FAILURES: &str = "exex_liquidity_socket_send_failures_total";
+pub const LIQUIDITY_SOCKET_QUEUE_DEPTH: &str = "exex_liquidity_socket_queue_depth";
+pub const LIQUIDITY_TRACKED_POOLS: &str = "exex_liquidity_tracked_pools";
+pub const LIQUIDITY_DEAD_LETTERS: &str = "exex_liquidity_dead_letters_total";
+pub const LIQUIDITY_CANDLES: &str = "exex_liquidity_candles_total";
+pub const LIQUIDITY_ARB_SIGNALS: &str = "exex_liquidity_arb_signals_total";
+pub const LIQUIDITY_PENDING_SWAPS: &str = "exex_liquidity_pending_swaps_total";
+pub const LIQUIDITY_TRACED_SWAPS: &str = "exex_liquidity_traced_swaps_total";
+pub const LIQUIDITY_POOLS_INVALIDATED: &str = "exex_liquidity_pools_invalidated_total";
+
+pub const BALANCE_MONITOR_BLOCKS: &str = "exex_balance_monitor_blocks_total";
+pub const BALANCE_MONITOR_UPDATES_PUBLISHED: &str = "exex_balance_monitor_updates_published_total";
+pub const BALANCE_MONITOR_TRACKED_TOKENS: &str = "exex_balance_monitor_tracked_tokens";
+
+pub const TRANSFERS_BLOCKS: &str = "exex_transfers_blocks_total";
+pub const TRANSFERS_INSERTED: &str = "exex_transfers_inserted_total";
+
+pub const FEES_BLOCKS: &str = "exex_fees_blocks_total";
+
+pub const SINK_ERRORS: &str = "exex_sink_errors_total";
+
+/// Register descriptions for every metric. Call once at startup.
+pub fn describe() {
+    describe_counter!(
+        LIQUIDITY_BLOCKS,
+        "Committed blocks processed by the liquidity ExEx"
+    );
+    describe_counter!(LIQUIDITY_LOGS_CHECKED, "Receipt logs scanned");
+    describe_counter!(LIQUIDITY_LOGS_MATCHED, "Logs emitted by a tracked address");
+    describe_counter!(
+        LIQUIDITY_EVENTS_DECODED,
+        "Logs decoded as a liquidity event"
+    );
+    describe_counter!(
+        LIQUIDITY_EVENTS_FILTERED,
+        "Decoded events dropped because their pool is not tracked"
+    );
+    describe_counter!(
+        LIQUIDITY_UPDATES_EMITTED,
+        "Pool update frames written to the socket"
+    );
+    describe_counter!(
+        LIQUIDITY_SOCKET_SEND_FAILURES,
+        "Frames dropped because the socket queue was full or closed"
+    );
+    describe_gauge!(
+        LIQUIDITY_SOCKET_QUEUE_DEPTH,
+        "Frames waiting in the socket queue"
+    );
+    describe_gauge!(LIQUIDITY_TRACKED_POOLS, "Pools in the whitelist");
+    describe_counter!(
+        LIQUIDITY_DEAD_LETTERS,
+        "Undecodable tracked-address logs captured by STRICT_DECODING"
+    );
+    describe_counter!(LIQUIDITY_CANDLES, "Closed pool candles published");
+    describe_counter!(
+        LIQUIDITY_ARB_SIGNALS,
+        "Cross-pool price divergences beyond ARB_SIGNAL_THRESHOLD_BPS"
+    );
+    describe_counter!(
+        LIQUIDITY_PENDING_SWAPS,
+        "PendingSwap frames written for mempool transactions"
+    );
+    describe_counter!(
+        LIQUIDITY_TRACED_SWAPS,
+        "Swaps of TRACE_SWAP_POOLS pools reconstructed from call traces"
+    );
+    describe_counter!(
+        LIQUIDITY_POOLS_INVALIDATED,
+        "Tracked pools blacklisted after their contract self-destructed or changed code"
+    );
+
+    describe_counter!(
+        BALANCE_MONITOR_BLOCKS,
+        "Notifications processed by the balance monitor"
+    );
+    describe_counter!(
+        BALANCE_MONITOR_UPDATES_PUBLISHED,
+        "Token balance updates published to NATS"
+    );
+    describe_gauge!(
+        BALANCE_MONITOR_TRACKED_TOKENS,
+        "Tokens tracked by the balance monitor"
+    );
+
+    describe_counter!(
+        TRANSFERS_BLOCKS,
+        "Committed blocks processed by the transfers ExEx"
+    );
+    describe_counter!(TRANSFERS_INSERTED, "ERC20 transfers inserted into Postgres");
+
+    describe_counter!(FEES_BLOCKS, "Block fee market messages published");
+
+    describe_counter!(
+        SINK_ERRORS,
+        "Failed socket sends, NATS publishes or store writes, by `exex`"
+    );
+}
+
+/// Per-block log funnel of the liquidity ExEx.
+pub fn record_liquidity_block(logs_checked: u64, logs_matched: u64, events_decoded: u64) {
+    counter!(LIQUIDITY_BLOCKS).increment(1);
+    counter!(LIQUIDITY_LOGS_CHECKED).increment(logs_checked);
+    counter!(LIQUIDITY_LOGS_MATCHED).increment(logs_matched);
+    counter!(LIQUIDITY_EVENTS_DECODED).increment(events_decoded);
+    status::record_block("liquidity", events_decoded);
+}
+
+pub fn record_liquidity_filtered(events: u64) {
+    counter!(LIQUIDITY_EVENTS_FILTERED).increment(events);
+}
+
+pub fn record_liquidity_emitted() {
+    counter!(LIQUIDITY_UPDATES_EMITTED).increment(1);
+}
+
+pub fn record_socket_send_failure() {
+    counter!(LIQUIDITY_SOCKET_SEND_FAILURES).increment(1);
+    record_sink_error("liquidity");
+}
+
+pub fn set_socket_queue_depth(depth: usize) {
+    gauge!(LIQUIDITY_SOCKET_QUEUE_DEPTH).set(depth as f64);
+}
+
+pub fn record_liquidity_dead_letter() {
+    counter!(LIQUIDITY_DEAD_LETTERS).increment(1);
+}
+
+pub fn record_liquidity_candles(candles: u64) {
+    counter!(LIQUIDITY_CANDLES).increment(candles);
+}
+
+pub fn record_liquidity_arb_signal() {
+    counter!(LIQUIDITY_ARB_SIGNALS).increment(1);
+}
+
+pub fn record_liquidity_pending_swap() {
+    counter!(LIQUIDITY_PENDING_SWAPS).increment(1);
+}
+
+pub fn record_liquidity_traced_swap() {
+    counter!(LIQUIDITY_TRACED_SWAPS).increment(1);
+}
+
+pub fn record_liquidity_pool_invalidated() {
+    counter!(LIQUIDITY_POOLS_INVALIDATED).increment(1);
+}
+
+pub fn set_tracked_pools(pools: usize) {
+    gauge!(LIQUIDITY_TRACKED_POOLS).set(pools as f64);
+    status::set_tracked("liquidity", pools);
+}
+
+pub fn record_balance_monitor_block(tracked_tokens: usize) {
+    counter!(BALANCE_MONITOR_BLOCKS).increment(1);
+    gauge!(BALANCE_MONITOR_TRACKED_TOKENS).set(tracked_tokens as f64);
+    status::record_block("balance_monitor", 0);
+    status::set_tracked("balance_monitor", tracked_tokens);
+}
+
+pub fn record_balance_updates_published(updates: u64) {
+    counter!(BALANCE_MONITOR_UPDATES_PUBLISHED).increment(updates);
+    status::record_events("balance_monitor", updates);
+}
+
+pub fn record_transfers_block(inserted: u64) {
+    counter!(TRANSFERS_BLOCKS).increment(1);
+    counter!(TRANSFERS_INSERTED).increment(inserted);
+    status::record_block("transfers", inserted);
+}
+
+pub fn record_fees_block() {
+    counter!(FEES_BLOCKS).increment(1);
+    status::record_block("fees", 0);
+}
+
+pub fn record_sink_error(exex: &'static str) {
+    counter!(SINK_ERRORS, "exex" => exex).increment(1);
+    status::record_sink_error(exex);
+}
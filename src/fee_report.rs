@@ -0,0 +1,369 @@
+// Pool Fee Revenue Reports
+//
+// Optional (`FEE_REPORT_INTERVAL_BLOCKS`, 0 disables): per tracked V3/V4 pool
+// the ExEx sums the raw volume of every forward swap and the fee it paid (the
+// input amount times the pool's fee tier). For V3 pools it also reads
+// `feeGrowthGlobal{0,1}X128` from the post-state of every block the pool
+// swapped in, so the report carries the fee growth per unit of liquidity
+// actually accrued (protocol fee and rounding included). Every block whose
+// number is a multiple of the interval closes the window and publishes a
+// `FeeReport` as JSON to `fees.pools.<chain>`, the input of the
+// pool-selection scoring in dynamicWhitelist.
+//
+// V4 pools with a dynamic fee report volume without fees: the fee they charged
+// is not in the `Swap` log. Amounts are raw token units; the report lists the
+// tokens so consumers can normalize.
+//
+// The window is kept per block, so a revert drops exactly the reverted blocks'
+// swaps. The first fee-growth read after a revert becomes the pool's new
+// baseline (the delta across the revert is not reported). Published reports
+// are final.
+
+use crate::types::{PoolIdentifier, PoolMetadata, Protocol};
+use crate::{exex_metrics, subjects};
+use alloy_primitives::{Address, I256, U256};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const QUEUE_CAPACITY: usize = 16;
+
+/// Fee-tier denominator: fees are in hundredths of a basis point.
+const FEE_DENOMINATOR: u64 = 1_000_000;
+
+/// V4 `LPFeeLibrary.DYNAMIC_FEE_FLAG`.
+const V4_DYNAMIC_FEE_FLAG: u32 = 0x80_0000;
+
+/// One pool's swap volume and fee income over a report window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolFees {
+    pub pool: String,
+    pub protocol: Protocol,
+    pub token0: Address,
+    pub token1: Address,
+    /// Fee tier in hundredths of a basis point; `None` if unknown or dynamic.
+    pub fee: Option<u32>,
+    pub swap_count: u64,
+    pub volume0: U256,
+    pub volume1: U256,
+    /// Volume × fee tier, charged on the input token.
+    pub fees0: U256,
+    pub fees1: U256,
+    /// `feeGrowthGlobal{0,1}X128` accrued (V3; wrapping like the pool).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_growth0_x128: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_growth1_x128: Option<U256>,
+}
+
+impl PoolFees {
+    fn new(pool: &PoolMetadata) -> Self {
+        let fee = pool
+            .fee
+            .filter(|fee| pool.protocol != Protocol::UniswapV4 || fee & V4_DYNAMIC_FEE_FLAG == 0);
+        Self {
+            pool: pool.pool_id.to_hex(),
+            protocol: pool.protocol,
+            token0: pool.token0,
+            token1: pool.token1,
+            fee,
+            swap_count: 0,
+            volume0: U256::ZERO,
+            volume1: U256::ZERO,
+            fees0: U256::ZERO,
+            fees1: U256::ZERO,
+            fee_growth0_x128: None,
+            fee_growth1_x128: None,
+        }
+    }
+
+    fn merge(&mut self, other: &PoolFees) {
+        self.swap_count += other.swap_count;
+        self.volume0 += other.volume0;
+        self.volume1 += other.volume1;
+        self.fees0 += other.fees0;
+        self.fees1 += other.fees1;
+        self.fee_growth0_x128 = add_growth(self.fee_growth0_x128, other.fee_growth0_x128);
+        self.fee_growth1_x128 = add_growth(self.fee_growth1_x128, other.fee_growth1_x128);
+    }
+}
+
+fn add_growth(a: Option<U256>, b: Option<U256>) -> Option<U256> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.wrapping_add(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Fee income of every pool that swapped in `[from_block, to_block]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeReport {
+    pub chain_id: u64,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub pools: Vec<PoolFees>,
+}
+
+/// Per-block fee accumulation since the last report.
+#[derive(Debug, Default)]
+pub struct FeeTracker {
+    blocks: BTreeMap<u64, HashMap<PoolIdentifier, PoolFees>>,
+    /// First block of the open window.
+    window_start: Option<u64>,
+    /// Last `feeGrowthGlobal{0,1}X128` read per V3 pool, with its block.
+    fee_growth: HashMap<PoolIdentifier, (u64, U256, U256)>,
+}
+
+impl FeeTracker {
+    /// Add a forward V3/V4 swap. `amount0`/`amount1` are the event's deltas:
+    /// pool perspective for V3 (positive = paid in), swapper perspective for
+    /// V4 (negative = paid in).
+    pub fn record_swap(
+        &mut self,
+        pool: &PoolMetadata,
+        block_number: u64,
+        amount0: I256,
+        amount1: I256,
+    ) {
+        let (in0, in1) = match pool.protocol {
+            Protocol::UniswapV3 => (amount0.is_positive(), amount1.is_positive()),
+            Protocol::UniswapV4 => (amount0.is_negative(), amount1.is_negative()),
+            _ => return,
+        };
+        self.window_start.get_or_insert(block_number);
+        let fees = self
+            .blocks
+            .entry(block_number)
+            .or_default()
+            .entry(pool.pool_id.clone())
+            .or_insert_with(|| PoolFees::new(pool));
+        let (volume0, volume1) = (amount0.unsigned_abs(), amount1.unsigned_abs());
+        fees.swap_count += 1;
+        fees.volume0 += volume0;
+        fees.volume1 += volume1;
+        if let Some(fee) = fees.fee {
+            let fee_of = |amount: U256| amount * U256::from(fee) / U256::from(FEE_DENOMINATOR);
+            if in0 {
+                fees.fees0 += fee_of(volume0);
+            }
+            if in1 {
+                fees.fees1 += fee_of(volume1);
+            }
+        }
+    }
+
+    /// V3 pools that swapped in `block_number`, for the fee-growth read.
+    pub fn v3_pools_in_block(&self, block_number: u64) -> Vec<Address> {
+        self.blocks
+            .get(&block_number)
+            .into_iter()
+            .flat_map(|pools| pools.iter())
+            .filter(|(_, fees)| fees.protocol == Protocol::UniswapV3)
+            .filter_map(|(pool_id, _)| pool_id.as_address())
+            .collect()
+    }
+
+    /// Record a V3 pool's post-block fee growth. The first read of a pool
+    /// only sets its baseline.
+    pub fn record_fee_growth(
+        &mut self,
+        pool: Address,
+        block_number: u64,
+        growth0: U256,
+        growth1: U256,
+    ) {
+        let pool_id = PoolIdentifier::Address(pool);
+        let previous = self
+            .fee_growth
+            .insert(pool_id.clone(), (block_number, growth0, growth1));
+        let Some((_, previous0, previous1)) = previous else {
+            return;
+        };
+        if let Some(fees) = self
+            .blocks
+            .get_mut(&block_number)
+            .and_then(|pools| pools.get_mut(&pool_id))
+        {
+            fees.fee_growth0_x128 = Some(growth0.wrapping_sub(previous0));
+            fees.fee_growth1_x128 = Some(growth1.wrapping_sub(previous1));
+        }
+    }
+
+    /// Blocks from `first_block` on were reverted.
+    pub fn revert_from(&mut self, first_block: u64) {
+        self.blocks.split_off(&first_block);
+        self.fee_growth
+            .retain(|_, (block, _, _)| *block < first_block);
+        if self.window_start.is_some_and(|start| start >= first_block) {
+            self.window_start = None;
+        }
+    }
+
+    /// Close the window through `to_block`. `None` if nothing swapped.
+    pub fn report(&mut self, chain_id: u64, to_block: u64) -> Option<FeeReport> {
+        let from_block = self.window_start.take()?;
+        if self.blocks.is_empty() {
+            return None;
+        }
+        let mut pools: HashMap<PoolIdentifier, PoolFees> = HashMap::new();
+        for block in std::mem::take(&mut self.blocks).into_values() {
+            for (pool_id, fees) in block {
+                match pools.get_mut(&pool_id) {
+                    Some(total) => total.merge(&fees),
+                    None => {
+                        pools.insert(pool_id, fees);
+                    }
+                }
+            }
+        }
+        let mut pools: Vec<PoolFees> = pools.into_values().collect();
+        pools.sort_by(|a, b| a.pool.cmp(&b.pool));
+        Some(FeeReport {
+            chain_id,
+            from_block,
+            to_block,
+            pools,
+        })
+    }
+}
+
+/// Fee tracking plus the queue to its publisher task.
+#[derive(Debug)]
+pub struct FeeReporter {
+    tracker: FeeTracker,
+    interval_blocks: u64,
+    tx: mpsc::Sender<FeeReport>,
+}
+
+impl FeeReporter {
+    /// Start the publisher unless `FEE_REPORT_INTERVAL_BLOCKS` is unset or 0.
+    pub fn spawn_from_env() -> Option<Self> {
+        let interval_blocks = std::env::var("FEE_REPORT_INTERVAL_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|&blocks| blocks > 0)?;
+        let nats_url =
+            std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(publish(rx, nats_url));
+        Some(Self {
+            tracker: FeeTracker::default(),
+            interval_blocks,
+            tx,
+        })
+    }
+
+    pub fn tracker(&mut self) -> &mut FeeTracker {
+        &mut self.tracker
+    }
+
+    /// Publish the report when `block_number` closes a window.
+    pub fn end_block(&mut self, block_number: u64) {
+        if block_number % self.interval_blocks != 0 {
+            return;
+        }
+        let Some(report) = self
+            .tracker
+            .report(crate::chain::active().chain_id, block_number)
+        else {
+            return;
+        };
+        if self.tx.try_send(report).is_err() {
+            warn!("Fee report queue full, dropping a report");
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+async fn publish(mut rx: mpsc::Receiver<FeeReport>, nats_url: String) {
+    let client = match async_nats::connect(&nats_url).await {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(
+                "Fee report publisher could not connect to NATS at {}: {}",
+                nats_url, e
+            );
+            return;
+        }
+    };
+    let subject = subjects::fee_reports(&crate::chain::chain_name());
+    info!(subject = %subject, "💸 Publishing pool fee reports");
+
+    while let Some(report) = rx.recv().await {
+        let payload = serde_json::to_vec(&report).expect("FeeReport serializes");
+        if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+            warn!("Failed to publish fee report: {}", e);
+            exex_metrics::record_sink_error("liquidity");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(protocol: Protocol, byte: u8, fee: u32) -> PoolMetadata {
+        PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([byte; 20])),
+            token0: Address::from([1u8; 20]),
+            token1: Address::from([2u8; 20]),
+            protocol,
+            factory: Address::ZERO,
+            tick_spacing: Some(10),
+            fee: Some(fee),
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }
+    }
+
+    fn amount(value: i64) -> I256 {
+        I256::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn fees_accrue_on_the_input_token_and_reverts_drop_blocks() {
+        let v3 = pool(Protocol::UniswapV3, 3, 3000);
+        let v4_dynamic = pool(Protocol::UniswapV4, 4, V4_DYNAMIC_FEE_FLAG);
+        let mut tracker = FeeTracker::default();
+
+        // V3: token0 paid in (positive, pool perspective).
+        tracker.record_swap(&v3, 10, amount(1_000_000), amount(-990_000));
+        tracker.record_fee_growth(Address::from([3u8; 20]), 10, U256::from(100), U256::from(7));
+        // V4: token1 paid in (negative, swapper perspective); dynamic fee.
+        tracker.record_swap(&v4_dynamic, 10, amount(500), amount(-510));
+        tracker.record_swap(&v3, 11, amount(-2_000), amount(2_000_000));
+        assert_eq!(
+            tracker.v3_pools_in_block(11),
+            vec![Address::from([3u8; 20])]
+        );
+        tracker.record_fee_growth(Address::from([3u8; 20]), 11, U256::from(160), U256::from(9));
+        tracker.record_swap(&v3, 12, amount(1_000_000), amount(-1));
+        tracker.revert_from(12);
+
+        let report = tracker.report(1, 12).unwrap();
+        assert_eq!((report.from_block, report.to_block), (10, 12));
+        let v3_fees = &report.pools[0];
+        assert_eq!(v3_fees.swap_count, 2);
+        assert_eq!(v3_fees.volume0, U256::from(1_002_000));
+        assert_eq!(v3_fees.fees0, U256::from(3_000));
+        assert_eq!(v3_fees.fees1, U256::from(6_000));
+        // Block 10's read was the baseline.
+        assert_eq!(v3_fees.fee_growth0_x128, Some(U256::from(60)));
+        assert_eq!(v3_fees.fee_growth1_x128, Some(U256::from(2)));
+        let v4_fees = &report.pools[1];
+        assert_eq!(v4_fees.fee, None);
+        assert_eq!(v4_fees.volume1, U256::from(510));
+        assert_eq!((v4_fees.fees0, v4_fees.fees1), (U256::ZERO, U256::ZERO));
+
+        assert!(tracker.report(1, 13).is_none());
+    }
+}
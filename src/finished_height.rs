@@ -0,0 +1,199 @@
+// FinishedHeight Batching
+//
+// `FinishedHeight` tells reth it may prune the ExEx WAL and blocks up to that
+// height: a block acknowledged before every sink has durably processed it is
+// lost on a crash. Each ExEx feeds its committed blocks, and each of its
+// enabled sinks (the liquidity socket writer, every dispatch handler such as
+// the transfers store, the balance map) its own durable height, into a
+// `FinishedHeightBatcher`, which only ever acknowledges a height at or below
+// the lowest of them. A sink that has not reported yet holds everything back.
+//
+// Under load the acknowledgement is batched: a height is sent once it is
+// `FINISHED_HEIGHT_BATCH_BLOCKS` (default 1, i.e. every notification) past the
+// last one sent, or `FINISHED_HEIGHT_MAX_DELAY_MS` (default 5000) after it.
+// Both are checked as notifications arrive. On shutdown the durable height is
+// acknowledged regardless of the batch.
+
+use alloy_eips::BlockNumHash;
+use alloy_primitives::B256;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BATCH_BLOCKS: u64 = 1;
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct FinishedHeightBatcher {
+    batch_blocks: u64,
+    max_delay: Duration,
+    /// Committed blocks not yet acknowledged, number → hash.
+    unacked: BTreeMap<u64, B256>,
+    /// Highest block each sink has made durable; `None` until it reports.
+    durable: BTreeMap<&'static str, Option<u64>>,
+    /// Last height sent, and when.
+    last_sent: Option<(u64, Instant)>,
+}
+
+impl FinishedHeightBatcher {
+    /// A batcher gated on `sinks`, each of which reports through
+    /// `set_durable`.
+    pub fn new(
+        batch_blocks: u64,
+        max_delay: Duration,
+        sinks: impl IntoIterator<Item = &'static str>,
+    ) -> Self {
+        Self {
+            batch_blocks: batch_blocks.max(1),
+            max_delay,
+            unacked: BTreeMap::new(),
+            durable: sinks.into_iter().map(|sink| (sink, None)).collect(),
+            last_sent: None,
+        }
+    }
+
+    /// `FINISHED_HEIGHT_BATCH_BLOCKS` / `FINISHED_HEIGHT_MAX_DELAY_MS`.
+    pub fn from_env(sinks: impl IntoIterator<Item = &'static str>) -> Self {
+        let batch_blocks = std::env::var("FINISHED_HEIGHT_BATCH_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BATCH_BLOCKS);
+        let max_delay = std::env::var("FINISHED_HEIGHT_MAX_DELAY_MS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_MAX_DELAY);
+        Self::new(batch_blocks, max_delay, sinks)
+    }
+
+    /// Blocks of a committed chain. Callers that are durable only at chain
+    /// tips may pass just the tip.
+    pub fn commit(&mut self, blocks: impl IntoIterator<Item = BlockNumHash>) {
+        for block in blocks {
+            self.unacked.insert(block.number, block.hash);
+        }
+    }
+
+    /// Blocks from `first` on were reverted: none of them may be acknowledged.
+    pub fn revert_from(&mut self, first: u64) {
+        self.unacked.split_off(&first);
+        for durable in self.durable.values_mut() {
+            if durable.is_some_and(|durable| durable >= first) {
+                *durable = first.checked_sub(1);
+            }
+        }
+    }
+
+    /// `sink` has durably processed the blocks up to `number`.
+    pub fn set_durable(&mut self, sink: &'static str, number: u64) {
+        self.durable.insert(sink, Some(number));
+    }
+
+    /// Highest block every sink has made durable.
+    fn durable(&self) -> Option<u64> {
+        self.durable.values().copied().min().flatten()
+    }
+
+    /// The height to acknowledge now, if the batch policy allows one.
+    pub fn poll(&mut self, now: Instant) -> Option<BlockNumHash> {
+        let candidate = self.candidate()?;
+        let due = match self.last_sent {
+            None => true,
+            Some((number, at)) => {
+                candidate.number.saturating_sub(number) >= self.batch_blocks
+                    || now.duration_since(at) >= self.max_delay
+            }
+        };
+        due.then(|| self.take(candidate, now))
+    }
+
+    /// The durable height, regardless of the batch policy (shutdown).
+    pub fn flush(&mut self) -> Option<BlockNumHash> {
+        let candidate = self.candidate()?;
+        Some(self.take(candidate, Instant::now()))
+    }
+
+    /// Highest committed, unacknowledged block at or below the durable height.
+    fn candidate(&self) -> Option<BlockNumHash> {
+        let durable = self.durable()?;
+        self.unacked
+            .range(..=durable)
+            .next_back()
+            .map(|(&number, &hash)| BlockNumHash::new(number, hash))
+    }
+
+    fn take(&mut self, height: BlockNumHash, now: Instant) -> BlockNumHash {
+        self.unacked = self.unacked.split_off(&(height.number + 1));
+        self.last_sent = Some((height.number, now));
+        height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64) -> BlockNumHash {
+        BlockNumHash::new(number, B256::with_last_byte(number as u8))
+    }
+
+    #[test]
+    fn acknowledges_in_batches_and_never_past_the_durable_height() {
+        let start = Instant::now();
+        let mut batcher = FinishedHeightBatcher::new(3, Duration::from_secs(60), ["sink"]);
+
+        batcher.commit([block(1), block(2)]);
+        // Nothing is durable yet; then only block 1 is.
+        assert_eq!(batcher.poll(start), None);
+        batcher.set_durable("sink", 1);
+        assert_eq!(batcher.poll(start), Some(block(1)));
+
+        batcher.commit([block(3), block(4), block(5)]);
+        batcher.set_durable("sink", 3);
+        // Two blocks past the last ack: still batching.
+        assert_eq!(batcher.poll(start), None);
+        batcher.set_durable("sink", 5);
+        assert_eq!(batcher.poll(start), Some(block(5)));
+        assert_eq!(batcher.poll(start), None);
+
+        // The delay releases a partial batch.
+        batcher.commit([block(6)]);
+        batcher.set_durable("sink", 6);
+        assert_eq!(batcher.poll(start), None);
+        assert_eq!(
+            batcher.poll(start + Duration::from_secs(60)),
+            Some(block(6))
+        );
+    }
+
+    #[test]
+    fn reverted_blocks_are_never_acknowledged() {
+        let mut batcher = FinishedHeightBatcher::new(10, Duration::from_secs(60), ["sink"]);
+        batcher.commit([block(1), block(2), block(3)]);
+        batcher.set_durable("sink", 3);
+        batcher.revert_from(2);
+        assert_eq!(batcher.flush(), Some(block(1)));
+
+        // The replacement block is acknowledged under its new hash.
+        let replacement = BlockNumHash::new(2, B256::repeat_byte(0xee));
+        batcher.commit([replacement]);
+        batcher.set_durable("sink", 2);
+        assert_eq!(batcher.flush(), Some(replacement));
+        assert_eq!(batcher.flush(), None);
+    }
+
+    #[test]
+    fn acknowledges_only_up_to_the_lowest_sink() {
+        let mut batcher =
+            FinishedHeightBatcher::new(1, Duration::from_secs(60), ["socket", "store"]);
+        batcher.commit([block(1), block(2), block(3)]);
+
+        // The store has not reported: nothing is finished.
+        batcher.set_durable("socket", 3);
+        assert_eq!(batcher.flush(), None);
+
+        batcher.set_durable("store", 2);
+        assert_eq!(batcher.flush(), Some(block(2)));
+        batcher.set_durable("store", 3);
+        assert_eq!(batcher.flush(), Some(block(3)));
+    }
+}
@@ -0,0 +1,284 @@
+// Health and Readiness Endpoint
+//
+// `HEALTH_ADDR` enables a small HTTP server for orchestration probes:
+//
+//   GET /healthz   503 once any ExEx has gone `HEALTH_MAX_BLOCK_AGE_SECS`
+//                  (default 120) without processing a block, so a wedged ExEx
+//                  is restarted instead of being noticed through stale data
+//   GET /readyz    503 while any dependency is down (NATS connection, socket
+//                  bound, Postgres reachable) or any ExEx is stale
+//
+// Both return the full JSON report. Subsystems register their dependencies
+// and record processed blocks here; nothing is probed until a request
+// arrives. An ExEx that has not processed its first block yet is not stale:
+// the node may still be syncing.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const DEFAULT_MAX_BLOCK_AGE: Duration = Duration::from_secs(120);
+/// Per-dependency bound, so one hung probe cannot hang the endpoint.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+
+/// A dependency checked on every `/readyz`.
+#[derive(Clone)]
+enum Check {
+    /// Set by its owner, e.g. the socket while it is bound.
+    Flag(bool),
+    Nats(async_nats::Client),
+    Postgres(PgPool),
+}
+
+#[derive(Default)]
+struct Registry {
+    checks: BTreeMap<String, Check>,
+    /// ExEx → (last processed block, when).
+    blocks: BTreeMap<&'static str, (u64, Instant)>,
+}
+
+/// One dependency in the report.
+#[derive(Debug, Serialize)]
+pub struct CheckStatus {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One ExEx's progress in the report.
+#[derive(Debug, Serialize)]
+pub struct BlockStatus {
+    pub exex: &'static str,
+    pub block_number: u64,
+    pub age_secs: u64,
+    pub ok: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// No ExEx is stale.
+    pub live: bool,
+    /// Live, and every dependency is up.
+    pub ready: bool,
+    pub checks: Vec<CheckStatus>,
+    pub blocks: Vec<BlockStatus>,
+}
+
+/// Record that `exex` finished processing `block_number`.
+pub fn record_block(exex: &'static str, block_number: u64) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.blocks.insert(exex, (block_number, Instant::now()));
+}
+
+/// Mark a dependency whose state its owner tracks (e.g. `socket`).
+pub fn set_up(name: &str, up: bool) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.checks.insert(name.to_string(), Check::Flag(up));
+}
+
+/// Report `client`'s connection state under `name`.
+pub fn watch_nats(name: &str, client: async_nats::Client) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry
+        .checks
+        .insert(name.to_string(), Check::Nats(client));
+}
+
+/// Probe `pool` with `SELECT 1` under `name`.
+pub fn watch_postgres(name: &str, pool: PgPool) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry
+        .checks
+        .insert(name.to_string(), Check::Postgres(pool));
+}
+
+impl Check {
+    async fn probe(self) -> Result<(), String> {
+        match self {
+            Check::Flag(true) => Ok(()),
+            Check::Flag(false) => Err("down".to_string()),
+            Check::Nats(client) => match client.connection_state() {
+                async_nats::connection::State::Connected => Ok(()),
+                state => Err(format!("NATS {state:?}")),
+            },
+            Check::Postgres(pool) => {
+                let ping = sqlx::query("SELECT 1").execute(&pool);
+                match tokio::time::timeout(PROBE_TIMEOUT, ping).await {
+                    Ok(Ok(_)) => Ok(()),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!("no reply within {PROBE_TIMEOUT:?}")),
+                }
+            }
+        }
+    }
+}
+
+impl Registry {
+    fn block_statuses(&self, now: Instant, max_block_age: Duration) -> Vec<BlockStatus> {
+        self.blocks
+            .iter()
+            .map(|(&exex, &(block_number, at))| {
+                let age = now.saturating_duration_since(at);
+                BlockStatus {
+                    exex,
+                    block_number,
+                    age_secs: age.as_secs(),
+                    ok: age <= max_block_age,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Last processed block per ExEx, without probing dependencies.
+pub fn blocks() -> Vec<BlockStatus> {
+    let registry = REGISTRY.lock().unwrap();
+    registry.block_statuses(Instant::now(), Duration::MAX)
+}
+
+/// Probe every dependency and evaluate block ages.
+pub async fn report(max_block_age: Duration) -> HealthReport {
+    // Snapshot under the lock; probes await without it.
+    let (checks, blocks) = {
+        let registry = REGISTRY.lock().unwrap();
+        let checks: Vec<(String, Check)> = registry
+            .checks
+            .iter()
+            .map(|(name, check)| (name.clone(), check.clone()))
+            .collect();
+        (
+            checks,
+            registry.block_statuses(Instant::now(), max_block_age),
+        )
+    };
+
+    let results = futures::future::join_all(checks.into_iter().map(|(name, check)| async move {
+        let result = check.probe().await;
+        CheckStatus {
+            name,
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }))
+    .await;
+
+    let live = blocks.iter().all(|b| b.ok);
+    HealthReport {
+        live,
+        ready: live && results.iter().all(|c| c.ok),
+        checks: results,
+        blocks,
+    }
+}
+
+/// Start the server when `HEALTH_ADDR` is set.
+pub fn spawn_from_env() {
+    let Ok(raw) = std::env::var("HEALTH_ADDR") else {
+        return;
+    };
+    let addr: SocketAddr = match raw.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            warn!("Invalid HEALTH_ADDR {:?}: {}", raw, e);
+            return;
+        }
+    };
+    let max_block_age = std::env::var("HEALTH_MAX_BLOCK_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_MAX_BLOCK_AGE);
+
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(max_block_age);
+
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Health endpoint failed to bind {}: {}", addr, e);
+                return;
+            }
+        };
+        info!("Health endpoint listening on {}", addr);
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!("Health endpoint stopped: {}", e);
+        }
+    });
+}
+
+async fn healthz(State(max_block_age): State<Duration>) -> (StatusCode, Json<HealthReport>) {
+    let report = report(max_block_age).await;
+    (status(report.live), Json(report))
+}
+
+async fn readyz(State(max_block_age): State<Duration>) -> (StatusCode, Json<HealthReport>) {
+    let report = report(max_block_age).await;
+    (status(report.ready), Json(report))
+}
+
+fn status(ok: bool) -> StatusCode {
+    if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_older_than_the_max_age_are_stale() {
+        let start = Instant::now();
+        let mut registry = Registry::default();
+        registry.blocks.insert("liquidity", (100, start));
+        registry
+            .blocks
+            .insert("transfers", (90, start + Duration::from_secs(100)));
+
+        let statuses =
+            registry.block_statuses(start + Duration::from_secs(150), Duration::from_secs(120));
+        let by_exex: Vec<_> = statuses
+            .iter()
+            .map(|s| (s.exex, s.age_secs, s.ok))
+            .collect();
+        assert_eq!(
+            by_exex,
+            [("liquidity", 150, false), ("transfers", 50, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_and_blocks_drive_liveness_and_readiness() {
+        set_up("test.socket", true);
+        record_block("test", 1);
+        let health = report(Duration::from_secs(60)).await;
+        assert!(health.live && health.ready);
+
+        set_up("test.socket", false);
+        let health = report(Duration::from_secs(60)).await;
+        assert!(health.live, "a down dependency does not fail liveness");
+        assert!(!health.ready);
+        let socket = health
+            .checks
+            .iter()
+            .find(|c| c.name == "test.socket")
+            .unwrap();
+        assert_eq!(socket.error.as_deref(), Some("down"));
+    }
+}
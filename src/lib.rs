@@ -2,15 +2,42 @@
 //
 // Exposes modules for reuse and testing
 
+pub mod admin;
+pub mod arb_signal;
 pub mod balance_monitor;
 pub mod balancer_storage;
+pub mod block_fees;
+pub mod builder_labels;
+pub mod call_trace;
+pub mod candles;
+pub mod catch_up;
+pub mod chain;
+pub mod checkpoint;
+pub mod confirmation_buffer;
+pub mod dead_letter;
+pub mod depeg;
+pub mod dispatch;
 pub mod events;
+pub mod exex_metrics;
+pub mod fee_report;
+pub mod finished_height;
 pub mod fluid_decoder;
+pub mod health;
+pub mod mempool;
 pub mod nats_client;
+pub mod pool_invalidation;
+pub mod pool_metadata_db;
+pub mod pool_resync;
+pub mod pool_state;
 pub mod pool_tracker;
+pub mod price_quote;
+pub mod reorg_journal;
 pub mod shadow_apply;
 pub mod shadow_arena;
+pub mod shutdown;
 pub mod socket;
+pub mod status;
+pub mod subjects;
 pub mod swap_monitor;
 pub mod transfers;
 pub mod types;
@@ -20,7 +47,7 @@ pub use events::{
     decode_log, fluid_log_operate_pool, is_fluid_log_operate_for_pool, DecodedEvent, EKUBO_CORE,
 };
 pub use pool_tracker::{
-    PoolTracker, WhitelistUpdate, FLUID_LIQUIDITY_LAYER, UNISWAP_V4_POOL_MANAGER,
+    PoolTracker, SharedPoolTracker, WhitelistUpdate, FLUID_LIQUIDITY_LAYER, UNISWAP_V4_POOL_MANAGER,
 };
 pub use types::{
     ControlMessage, PoolIdentifier, PoolMetadata, PoolUpdate, Protocol, ReorgRange, UpdateType,
@@ -14,62 +14,104 @@
 #[global_allocator]
 static ALLOC: reth_cli_util::allocator::Allocator = reth_cli_util::allocator::new_allocator();
 
+mod admin;
+mod arb_signal;
 mod arena_notifier;
+mod backfill;
 mod balance_monitor;
 mod balancer_storage;
+mod block_fees;
+mod builder_labels;
+mod call_trace;
+mod candles;
+mod catch_up;
+mod chain;
+mod checkpoint;
+mod config;
+mod confirmation_buffer;
+mod dead_letter;
+mod depeg;
+mod dispatch;
 mod events;
+mod exex_metrics;
+mod fee_report;
+mod finished_height;
 mod fluid_decoder;
+mod health;
+mod mempool;
 mod nats_client;
+mod pool_invalidation;
+mod pool_metadata_db;
+mod pool_resync;
+mod pool_state;
 mod pool_tracker;
+mod price_quote;
+mod reorg_journal;
+#[cfg(not(feature = "optimism"))]
+mod replay;
 mod shadow_apply;
 mod shadow_arena;
+mod shutdown;
 #[allow(dead_code)]
 mod socket;
+mod state_seed;
+mod state_verifier;
+mod status;
+mod subjects;
 mod swap_monitor;
 #[allow(dead_code)]
 mod transfers;
 mod types;
 
-use alloy_consensus::{BlockHeader, TxReceipt};
-use alloy_primitives::{Address, U256};
+use alloy_consensus::{transaction::TxHashRef, BlockHeader, TxReceipt};
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{Address, I256, U256};
 use arena_layout::ekubo::EkuboPoolData;
 use arena_layout::{
     AnyEkuboPool, AnyUniswapV3Pool, AnyUniswapV4Pool, CurveStablePoolData, CurveTricryptoPoolData,
     CurveTwoCryptoPoolData, PoolTier, UniswapV3PoolData, UniswapV4PoolData,
 };
+use clap::Parser;
 use events::{decode_log, fluid_log_operate_pool, DecodedEvent};
 use fluid_decoder::FluidPoolConfig;
-use futures::{StreamExt, TryStreamExt};
-use nats_client::WhitelistNatsClient;
-use pool_tracker::PoolTracker;
+use futures::StreamExt;
+use nats_client::{JetStreamWhitelist, WhitelistNatsClient};
+use pool_tracker::{PoolTracker, SharedPoolTracker};
+use rayon::prelude::*;
+use reorg_journal::{JournalBlock, JournaledEvent, JournaledUpdate, ReorgJournal};
+use reth::chainspec::EthChainSpec;
+#[cfg(not(feature = "optimism"))]
+use reth::chainspec::EthereumChainSpecParser;
 use reth::providers::StateProviderFactory;
-use reth_exex::{ExExContext, ExExEvent, ExExNotification};
-use reth_node_api::FullNodeComponents;
+use reth_exex::{ExExContext, ExExEvent, ExExHead, ExExNotification, ExExNotificationsStream};
+use reth_node_api::{FullNodeComponents, NodePrimitives};
+#[cfg(not(feature = "optimism"))]
 use reth_node_ethereum::EthereumNode;
-use reth_provider::StateProvider;
+use reth_provider::{BlockIdReader, BlockReader, StateProvider};
 use shadow_arena::{
     CurveStableHydration, CurveTricryptoHydration, CurveTwoCryptoHydration, EkuboHydration,
     FluidHydration, ShadowArena, UniswapV3Hydration, UniswapV4Hydration, V2Hydration,
 };
 use socket::PoolUpdateSocketServer;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
-use tracing::{debug, info, warn};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn, Instrument};
 use types::{
     ControlMessage, FluidState, PoolIdentifier, PoolMetadata, PoolUpdate, PoolUpdateMessage,
-    Protocol, ReorgEpilogueUpdate, ReorgRange, Slot0State, TokenMetadata, UpdateType,
+    Protocol, ReorgEpilogueUpdate, ReorgRange, Slot0State, TokenMetadata, UpdateChecksum,
+    UpdateType,
 };
 
 /// Main ExEx state
 struct LiquidityExEx {
-    /// Pool tracker (shared, can be updated from whitelist subscription)
-    pool_tracker: Arc<RwLock<PoolTracker>>,
+    /// Pool tracker (shared, can be updated from whitelist subscription).
+    /// The block loop reads a per-block lock-free snapshot.
+    pool_tracker: Arc<SharedPoolTracker>,
 
-    /// Socket sender for outgoing messages
-    socket_tx: tokio::sync::mpsc::Sender<ControlMessage>,
+    /// Socket sender for outgoing messages, applying `SOCKET_OVERFLOW`.
+    socket_tx: socket::SocketSender,
 
     /// In-process pool-arena writer. `None` unless `SHADOW_ARENA_PATH` (ITE-16
     /// diff harness) or `SHARED_ARENA_PATH` (ITE-20 production sole writer) is
@@ -81,6 +123,86 @@ struct LiquidityExEx {
     /// notification that `arena_service` previously sent `curve_service`.
     curve_notifier: Option<arena_notifier::ArenaCurveNotifier>,
 
+    /// In-ExEx pool state engine. `None` unless `POOL_STATE_MODE` enables it;
+    /// when present, outgoing updates are folded into it and absolute
+    /// post-state updates are emitted alongside or instead of deltas.
+    state_engine: Option<pool_state::PoolStateEngine>,
+
+    /// Round-robin re-seed of every tracked pool once per
+    /// `POOL_SNAPSHOT_INTERVAL_BLOCKS`, a batch per block. `None` disables it.
+    resync: Option<pool_resync::PoolResync>,
+
+    /// Replay this many blocks of history for every live-added pool
+    /// (`BACKFILL_BLOCKS`). 0 disables backfill.
+    backfill_blocks: u64,
+
+    /// The socket server's backfill channel. Backfill is off without it.
+    backfill_tx: Option<tokio::sync::mpsc::Sender<ControlMessage>>,
+
+    /// Updates emitted for recent blocks, so reorgs/reverts invert the exact
+    /// forward stream instead of re-decoding old receipts.
+    journal: ReorgJournal,
+    /// Forward updates sent so far in the current block, journaled at its end.
+    block_updates: Vec<JournaledUpdate>,
+
+    /// Periodic engine-vs-storage cross-check (`STATE_VERIFY_INTERVAL_BLOCKS`).
+    /// Only runs when the state engine is enabled.
+    verifier: Option<state_verifier::StateVerifier>,
+
+    /// Emit a `LiquidityDepth` update of ±N tick spacings for every V3/V4
+    /// pool the block touched (`LIQUIDITY_DEPTH_SPACINGS`). 0 disables it;
+    /// needs the state engine for the tick map.
+    depth_spacings: u32,
+
+    /// Swap price/USD enrichment (`SWAP_QUOTES`). When present, every forward
+    /// V3/V4 swap is followed by a `SwapQuote` update.
+    quoter: Option<price_quote::SwapQuoter>,
+
+    /// Last finalized block announced with `ControlMessage::Finalized`.
+    last_finalized: Option<u64>,
+
+    /// Tags `BeginBlock.is_historical` and emits `CaughtUp`. `None` (replay)
+    /// emits every block as live.
+    catch_up: Option<catch_up::CatchUpTracker>,
+
+    /// Drops committed blocks already emitted before a restart.
+    resume: checkpoint::ResumeDedupe,
+
+    /// Undecodable tracked-address logs (`STRICT_DECODING`). `None` when off.
+    dead_letters: Option<dead_letter::DeadLetterSink>,
+
+    /// Per-pool OHLCV candles of forward swaps (`CANDLE_INTERVALS`). `None`
+    /// when off.
+    candles: Option<candles::CandleFeed>,
+
+    /// Per-pool swap fee income, reported every `FEE_REPORT_INTERVAL_BLOCKS`.
+    /// `None` when off.
+    fee_reporter: Option<fee_report::FeeReporter>,
+
+    /// Cross-pool price divergence signals (`ARB_SIGNAL_THRESHOLD_BPS`).
+    /// Needs the state engine for pool prices.
+    arb_signals: Option<arb_signal::ArbSignals>,
+
+    /// Stable-pool peg alerts (`DEPEG_POOLS`). `None` when off.
+    depeg: Option<depeg::DepegWatch>,
+
+    /// Fee recipient → builder name for `BeginBlock.builder`
+    /// (`BUILDER_LABELS`).
+    builder_labels: builder_labels::BuilderLabels,
+
+    /// Call-trace swap reconstruction for logless pools (`TRACE_SWAP_POOLS`).
+    /// `None` when off.
+    swap_tracer: Option<call_trace::SwapTracer>,
+    /// Traced swaps of the notification being handled, by block number;
+    /// merged into each block's decoded events. Replay never fills it.
+    traced_swaps: BTreeMap<u64, Vec<JournaledEvent>>,
+    /// Tracked pools that died in the notification being handled, by block
+    /// number; announced and blacklisted at each block's end.
+    invalidated_pools: BTreeMap<u64, Vec<pool_invalidation::Invalidation>>,
+
+    /// Running `EndBlock.updates_checksum` of the open block envelope.
+    block_checksum: UpdateChecksum,
+
     /// Statistics
     events_processed: u64,
     blocks_processed: u64,
@@ -94,7 +216,7 @@ struct LiquidityExEx {
 /// skip; the shadow is a validation harness and must never crash the ExEx.
 ///
 /// Takes the `shadow` field directly (not `&mut self`) so callers can apply while
-/// holding the `pool_tracker` read guard — a disjoint field borrow.
+/// holding the per-block `pool_tracker` snapshot.
 fn apply_to_shadow(shadow: &mut Option<ShadowArena>, event: &PoolUpdateMessage) {
     let Some(shadow) = shadow.as_mut() else {
         return;
@@ -216,15 +338,97 @@ impl LiquidityExEx {
         curve_notifier: Option<arena_notifier::ArenaCurveNotifier>,
     ) -> Self {
         Self {
-            pool_tracker: Arc::new(RwLock::new(PoolTracker::new())),
-            socket_tx,
+            pool_tracker: Arc::new(SharedPoolTracker::default()),
+            socket_tx: socket::SocketSender::new(socket_tx, socket::SocketOverflow::Drop),
             shadow,
             curve_notifier,
+            state_engine: None,
+            resync: None,
+            backfill_blocks: 0,
+            backfill_tx: None,
+            journal: ReorgJournal::from_env(),
+            block_updates: Vec::new(),
+            verifier: None,
+            quoter: None,
+            depth_spacings: 0,
+            last_finalized: None,
+            catch_up: Some(catch_up::CatchUpTracker::from_env()),
+            resume: checkpoint::ResumeDedupe::default(),
+            dead_letters: None,
+            candles: None,
+            fee_reporter: None,
+            arb_signals: None,
+            depeg: None,
+            builder_labels: builder_labels::BuilderLabels::default(),
+            swap_tracer: None,
+            traced_swaps: BTreeMap::new(),
+            invalidated_pools: BTreeMap::new(),
+            block_checksum: UpdateChecksum::default(),
             events_processed: 0,
             blocks_processed: 0,
         }
     }
 
+    /// Enable the optional stages (state engine, verifier, depth, snapshots,
+    /// quotes, dead letters, candles, fee reports, arb signals, depeg
+    /// alerts, backfill) from their env vars.
+    fn configure_from_env(&mut self) {
+        let overflow = socket::SocketOverflow::from_env();
+        if let socket::SocketOverflow::Spill { max } = overflow {
+            info!(
+                max,
+                "🧺 Socket overflow frames held back instead of dropped"
+            );
+        }
+        self.socket_tx.set_overflow(overflow);
+        let state_mode = pool_state::PoolStateMode::from_env();
+        if state_mode != pool_state::PoolStateMode::Off {
+            info!(mode = ?state_mode, "🧮 Pool state engine enabled");
+            self.state_engine = Some(pool_state::PoolStateEngine::new(state_mode));
+            self.verifier = state_verifier::StateVerifier::from_env();
+            self.depth_spacings = std::env::var("LIQUIDITY_DEPTH_SPACINGS")
+                .ok()
+                .and_then(|s| s.parse::<u32>().ok())
+                .unwrap_or(0);
+            if self.depth_spacings > 0 {
+                info!(
+                    spacings = self.depth_spacings,
+                    "📊 Per-block liquidity depth snapshots enabled"
+                );
+            }
+            if self.verifier.is_some() {
+                info!("🩺 Pool state verification enabled");
+            }
+            self.arb_signals = arb_signal::ArbSignals::spawn_from_env();
+        } else if std::env::var("ARB_SIGNAL_THRESHOLD_BPS").is_ok_and(|v| v.trim() != "0") {
+            warn!("ARB_SIGNAL_THRESHOLD_BPS needs POOL_STATE_MODE; arb signals are off");
+        }
+        self.resync = pool_resync::PoolResync::from_env();
+        if let Some(resync) = &self.resync {
+            info!(
+                interval_blocks = resync.interval_blocks(),
+                "📸 Periodic pool-state snapshots enabled"
+            );
+        }
+        self.quoter = price_quote::SwapQuoter::from_env();
+        if self.quoter.is_some() {
+            info!("💲 Swap price/USD quotes enabled");
+        }
+        self.dead_letters = dead_letter::DeadLetterSink::spawn_from_env();
+        self.candles = candles::CandleFeed::spawn_from_env();
+        self.fee_reporter = fee_report::FeeReporter::spawn_from_env();
+        self.depeg = depeg::DepegWatch::spawn_from_env();
+        self.builder_labels = builder_labels::BuilderLabels::from_env();
+        self.swap_tracer = call_trace::SwapTracer::from_env();
+        self.backfill_blocks = backfill::backfill_blocks_from_env();
+        if self.backfill_blocks > 0 {
+            info!(
+                blocks = self.backfill_blocks,
+                "⏪ Historical backfill enabled for live-added pools"
+            );
+        }
+    }
+
     /// Close a block in the arena writer (if enabled) and, in production mode,
     /// emit the arena → curve notification (ITE-20).
     ///
@@ -258,7 +462,6 @@ impl LiquidityExEx {
 
     /// Convert a decoded event into a PoolUpdateMessage
     fn create_pool_update(
-        &self,
         event: DecodedEvent,
         block_number: u64,
         block_timestamp: u64,
@@ -285,9 +488,10 @@ impl LiquidityExEx {
                     return None;
                 }
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol: Protocol::UniswapV2,
-                    update_type: UpdateType::Swap,
+                    update_type: UpdateType::Sync,
                     block_number,
                     block_timestamp,
                     tx_index,
@@ -305,7 +509,9 @@ impl LiquidityExEx {
                 sqrt_price_x96,
                 liquidity,
                 tick,
+                ..
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(pool),
                 protocol: Protocol::UniswapV3,
                 update_type: UpdateType::Swap,
@@ -327,6 +533,7 @@ impl LiquidityExEx {
                 tick_upper,
                 amount,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(pool),
                 protocol: Protocol::UniswapV3,
                 update_type: UpdateType::Mint,
@@ -351,6 +558,7 @@ impl LiquidityExEx {
                 tick_upper,
                 amount,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(pool),
                 protocol: Protocol::UniswapV3,
                 update_type: UpdateType::Burn,
@@ -377,7 +585,9 @@ impl LiquidityExEx {
                 sqrt_price_x96,
                 liquidity,
                 tick,
+                ..
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::PoolId(pool_id),
                 protocol: Protocol::UniswapV4,
                 update_type: UpdateType::Swap,
@@ -406,6 +616,7 @@ impl LiquidityExEx {
                 };
 
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::PoolId(pool_id),
                     protocol: Protocol::UniswapV4,
                     update_type,
@@ -431,6 +642,7 @@ impl LiquidityExEx {
                 liquidity,
                 tick,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::PoolId(pool_id),
                 protocol: Protocol::Ekubo,
                 update_type: UpdateType::Swap,
@@ -462,6 +674,7 @@ impl LiquidityExEx {
                 };
 
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::PoolId(pool_id),
                     protocol: Protocol::Ekubo,
                     update_type,
@@ -487,6 +700,7 @@ impl LiquidityExEx {
             DecodedEvent::CurveSwap { pool } => {
                 let curve_state = read_curve_stable_liquidity_state(state, pool);
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol: Protocol::CurveStable,
                     update_type: UpdateType::Swap,
@@ -510,6 +724,7 @@ impl LiquidityExEx {
             DecodedEvent::CurveLiquidityChange { pool } => {
                 let curve_state = read_curve_stable_liquidity_state(state, pool);
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol: Protocol::CurveStable,
                     update_type: UpdateType::Mint,
@@ -537,9 +752,10 @@ impl LiquidityExEx {
                 initial_time,
                 future_time,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(pool),
                 protocol: Protocol::CurveStable,
-                update_type: UpdateType::Swap, // No specific type for param changes
+                update_type: UpdateType::ParamChange,
                 block_number,
                 block_timestamp,
                 tx_index,
@@ -558,9 +774,10 @@ impl LiquidityExEx {
                 fee,
                 offpeg_fee_multiplier,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(pool),
                 protocol: Protocol::CurveStable,
-                update_type: UpdateType::Swap, // No specific type for param changes
+                update_type: UpdateType::ParamChange,
                 block_number,
                 block_timestamp,
                 tx_index,
@@ -607,6 +824,7 @@ impl LiquidityExEx {
                     }
                 };
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol,
                     update_type: UpdateType::Swap,
@@ -646,6 +864,7 @@ impl LiquidityExEx {
                     }
                 };
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol,
                     update_type: UpdateType::Mint,
@@ -694,9 +913,10 @@ impl LiquidityExEx {
                     }
                 };
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol,
-                    update_type: UpdateType::Swap,
+                    update_type: UpdateType::ParamChange,
                     block_number,
                     block_timestamp,
                     tx_index,
@@ -733,9 +953,10 @@ impl LiquidityExEx {
                     }
                 };
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol,
-                    update_type: UpdateType::Swap,
+                    update_type: UpdateType::ParamChange,
                     block_number,
                     block_timestamp,
                     tx_index,
@@ -751,6 +972,7 @@ impl LiquidityExEx {
             DecodedEvent::TricryptoLiquidityChange { pool } => {
                 let crypto_state = read_tricrypto_full_state(state, pool);
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::Address(pool),
                     protocol: Protocol::CurveTricrypto,
                     update_type: UpdateType::Mint,
@@ -777,6 +999,7 @@ impl LiquidityExEx {
                 amount_in,
                 amount_out,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::PoolId(pool_id),
                 protocol: Protocol::BalancerV2Weighted,
                 update_type: UpdateType::Swap,
@@ -798,6 +1021,7 @@ impl LiquidityExEx {
                 tokens,
                 deltas,
             } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::PoolId(pool_id),
                 protocol: Protocol::BalancerV2Weighted,
                 update_type: UpdateType::Mint,
@@ -822,9 +1046,10 @@ impl LiquidityExEx {
                     .and_then(|meta| meta.balancer_version.as_deref());
                 let swap_fee_percentage = read_balancer_swap_fee_onchain(state, pool, version)?;
                 Some(PoolUpdateMessage {
+                    chain_id: chain::active().chain_id,
                     pool_id: PoolIdentifier::PoolId(pool_id),
                     protocol: Protocol::BalancerV2Weighted,
-                    update_type: UpdateType::Swap,
+                    update_type: UpdateType::ParamChange,
                     block_number,
                     block_timestamp,
                     tx_index,
@@ -842,37 +1067,305 @@ impl LiquidityExEx {
             // FluidOperate is handled separately — the caller collects touched
             // pools and batch-decodes reserves from storage after the log loop.
             DecodedEvent::FluidOperate { .. } => None,
+
+            // ============================================================================
+            // TRACED SWAPS (logless pools, `TRACE_SWAP_POOLS`)
+            // ============================================================================
+            DecodedEvent::TracedSwap {
+                pool,
+                token_in,
+                token_out,
+                amount_in,
+                amount_out,
+            } => Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
+                pool_id: PoolIdentifier::Address(pool),
+                protocol: pool_tracker.get_protocol(&pool)?,
+                update_type: UpdateType::Swap,
+                block_number,
+                block_timestamp,
+                tx_index,
+                log_index,
+                is_revert,
+                update: PoolUpdate::TracedSwap {
+                    token_in,
+                    token_out,
+                    amount_in,
+                    amount_out,
+                },
+            }),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn send_begin_block(
-        &self,
+        &mut self,
         stream_seq: &mut u64,
         block_number: u64,
+        block_hash: alloy_primitives::B256,
+        parent_hash: alloy_primitives::B256,
         block_timestamp: u64,
         base_fee_per_gas: u64,
+        fee_recipient: Address,
         is_revert: bool,
     ) {
+        let (is_historical, caught_up) = match self.catch_up.as_mut() {
+            Some(tracker) => tracker.observe(block_timestamp, std::time::SystemTime::now()),
+            None => (false, false),
+        };
+        if caught_up {
+            info!(block_number, "⚡ Caught up with the chain tip");
+            if let Err(e) = self
+                .socket_tx
+                .try_send(ControlMessage::CaughtUp { block_number })
+            {
+                warn!("Failed to send CaughtUp: {}", e);
+            }
+        }
+        self.block_checksum = UpdateChecksum::default();
+        self.block_updates.clear();
         let seq = next_stream_seq(stream_seq);
         if let Err(e) = self.socket_tx.try_send(ControlMessage::BeginBlock {
             stream_seq: seq,
+            chain_id: chain::active().chain_id,
             block_number,
+            block_hash,
+            parent_hash,
             block_timestamp,
             base_fee_per_gas,
+            fee_recipient,
+            builder: self.builder_labels.label(&fee_recipient),
             is_revert,
+            is_historical,
         }) {
             warn!("Failed to send BeginBlock: {}", e);
         }
     }
 
-    fn send_pool_update(&self, stream_seq: &mut u64, update_msg: PoolUpdateMessage) {
+    /// Fold a forward swap into the candles and fee report, if enabled.
+    fn record_swap(
+        &mut self,
+        (pool, amount0, amount1): (&PoolMetadata, I256, I256),
+        update_msg: &PoolUpdateMessage,
+    ) {
+        if let Some(candles) = self.candles.as_mut() {
+            candles.record_swap(
+                pool,
+                &update_msg.update,
+                amount0,
+                amount1,
+                update_msg.block_number,
+                update_msg.block_timestamp,
+            );
+        }
+        if let Some(reporter) = self.fee_reporter.as_mut() {
+            reporter
+                .tracker()
+                .record_swap(pool, update_msg.block_number, amount0, amount1);
+        }
+    }
+
+    /// Read the fee growth of the V3 pools that swapped this block from its
+    /// post-state, and publish the fee report when the block closes a window.
+    fn end_block_fee_report(&mut self, state: &dyn StateProvider, block_number: u64) {
+        let Some(reporter) = self.fee_reporter.as_mut() else {
+            return;
+        };
+        let tracker = reporter.tracker();
+        for pool in tracker.v3_pools_in_block(block_number) {
+            let (growth0, growth1) = read_v3_fee_growth(state, pool);
+            tracker.record_fee_growth(pool, block_number, growth0, growth1);
+        }
+        reporter.end_block(block_number);
+    }
+
+    /// Send one pool update, routed through the state engine when enabled.
+    /// Returns the number of `PoolUpdate` frames written (counted into the
+    /// block's `EndBlock.num_updates`).
+    fn send_pool_update(&mut self, stream_seq: &mut u64, update_msg: PoolUpdateMessage) -> u64 {
+        if let Some(arb_signals) = self.arb_signals.as_mut() {
+            arb_signals.touch(&update_msg.pool_id);
+        }
+        let Some(engine) = self.state_engine.as_mut() else {
+            self.emit_pool_update(stream_seq, update_msg);
+            return 1;
+        };
+        let mode = engine.mode();
+        let Some(update) = engine.apply(&update_msg) else {
+            self.emit_pool_update(stream_seq, update_msg);
+            return 1;
+        };
+        // Same position as the delta it folds, but absolute state.
+        let absolute_msg = PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
+            update_type: UpdateType::Sync,
+            update,
+            ..update_msg.clone()
+        };
+        if mode == pool_state::PoolStateMode::Absolute {
+            self.emit_pool_update(stream_seq, absolute_msg);
+            return 1;
+        }
+        self.emit_pool_update(stream_seq, update_msg);
+        self.emit_pool_update(stream_seq, absolute_msg);
+        2
+    }
+
+    /// [`Self::send_pool_update`] for an update whose pool may mask its event
+    /// class: a masked update still moves the state engine (so later absolute
+    /// frames include it) but writes no frame.
+    fn send_pool_update_unless_masked(
+        &mut self,
+        stream_seq: &mut u64,
+        update_msg: PoolUpdateMessage,
+        masked: bool,
+    ) -> u64 {
+        if !masked {
+            return self.send_pool_update(stream_seq, update_msg);
+        }
+        if let Some(arb_signals) = self.arb_signals.as_mut() {
+            arb_signals.touch(&update_msg.pool_id);
+        }
+        if let Some(engine) = self.state_engine.as_mut() {
+            engine.apply(&update_msg);
+        }
+        0
+    }
+
+    /// [`Self::send_pool_update_unless_masked`] for a forward update, recorded
+    /// for the block's reorg-journal entry.
+    fn send_journaled_update(
+        &mut self,
+        stream_seq: &mut u64,
+        update_msg: PoolUpdateMessage,
+        masked: bool,
+    ) -> u64 {
+        self.block_updates.push(JournaledUpdate {
+            update: update_msg.clone(),
+            masked,
+        });
+        self.send_pool_update_unless_masked(stream_seq, update_msg, masked)
+    }
+
+    /// Record the current block's forward updates in the reorg journal.
+    fn journal_block(
+        &mut self,
+        block_number: u64,
+        block_hash: alloy_primitives::B256,
+        fluid_touched: &HashSet<Address>,
+    ) {
+        self.journal.record(JournalBlock {
+            block_number,
+            block_hash,
+            updates: std::mem::take(&mut self.block_updates),
+            fluid_touched: fluid_touched.iter().copied().collect(),
+        });
+    }
+
+    fn emit_pool_update(&mut self, stream_seq: &mut u64, update_msg: PoolUpdateMessage) {
+        // Folded in even if the frame is dropped below: the consumer's
+        // checksum then disagrees and it knows the block is incomplete.
+        self.block_checksum.push(&update_msg);
         let seq = next_stream_seq(stream_seq);
         if let Err(e) = self.socket_tx.try_send(ControlMessage::PoolUpdate {
             stream_seq: seq,
             event: update_msg,
         }) {
             warn!("Failed to send PoolUpdate: {}", e);
+        } else {
+            exex_metrics::record_liquidity_emitted();
+        }
+    }
+
+    /// Check the listed stable pools' post-block prices against their peg.
+    fn check_depeg(&mut self, state: &dyn StateProvider, block_number: u64) {
+        let Some(watch) = self.depeg.as_mut() else {
+            return;
+        };
+        let pool_tracker = self.pool_tracker.snapshot();
+        for address in watch.pools().to_vec() {
+            let Some(pool) = pool_tracker.pool_metadata(&address) else {
+                debug!(pool = %address, "Depeg pool not in the whitelist, skipping");
+                continue;
+            };
+            if let Some(price) = read_pool_mid_price(state, pool) {
+                watch.observe(pool, block_number, price);
+            }
+        }
+    }
+
+    /// Compare the pairs of the pools updated this block across the engine's
+    /// prices and publish the divergent ones.
+    fn send_arb_signals(&mut self, block_number: u64) {
+        let (Some(arb_signals), Some(engine)) =
+            (self.arb_signals.as_mut(), self.state_engine.as_ref())
+        else {
+            return;
+        };
+        let pool_tracker = self.pool_tracker.snapshot();
+        arb_signals.end_block(block_number, pool_tracker.pools(), |pool| {
+            arb_signal::pool_price(pool, engine.state(&pool.pool_id)?)
+        });
+    }
+
+    /// Emit the end-of-block liquidity depth of every concentrated pool the
+    /// state engine saw change this block. Returns the frames written.
+    fn send_liquidity_depth(
+        &mut self,
+        stream_seq: &mut u64,
+        block_number: u64,
+        block_timestamp: u64,
+    ) -> u64 {
+        let touched = match self.state_engine.as_mut() {
+            Some(engine) => engine.take_touched(),
+            None => return 0,
+        };
+        let Some(engine) = self.state_engine.as_ref() else {
+            return 0;
+        };
+        if self.depth_spacings == 0 {
+            return 0;
+        }
+        let pool_tracker = self.pool_tracker.snapshot();
+        let mut depth_msgs = Vec::new();
+        for pool_id in touched {
+            let Some(pool_state::PoolState::Concentrated(state)) = engine.state(&pool_id) else {
+                continue;
+            };
+            let meta = match &pool_id {
+                PoolIdentifier::Address(addr) => pool_tracker.pool_metadata(addr),
+                PoolIdentifier::PoolId(id) => pool_tracker.pool_metadata_by_id(id),
+            };
+            let Some((protocol, tick_spacing)) =
+                meta.and_then(|m| Some((m.protocol, m.tick_spacing?)))
+            else {
+                continue;
+            };
+            let Some(ranges) = state.depth(tick_spacing, self.depth_spacings) else {
+                warn!(pool_id = ?pool_id, "Liquidity depth walk overflowed, skipping");
+                continue;
+            };
+            depth_msgs.push(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
+                pool_id,
+                protocol,
+                update_type: UpdateType::Sync,
+                block_number,
+                block_timestamp,
+                tx_index: u64::MAX,
+                log_index: u64::MAX,
+                is_revert: false,
+                update: PoolUpdate::LiquidityDepth {
+                    tick_spacing,
+                    ranges,
+                },
+            });
+        }
+        let sent = depth_msgs.len() as u64;
+        for msg in depth_msgs {
+            self.emit_pool_update(stream_seq, msg);
         }
+        sent
     }
 
     fn send_end_block(&self, stream_seq: &mut u64, block_number: u64, num_updates: u64) {
@@ -881,6 +1374,7 @@ impl LiquidityExEx {
             stream_seq: seq,
             block_number,
             num_updates,
+            updates_checksum: self.block_checksum.value(),
         }) {
             warn!("Failed to send EndBlock: {}", e);
         }
@@ -898,12 +1392,15 @@ impl LiquidityExEx {
     }
 
     fn send_reorg_epilogue(
-        &self,
+        &mut self,
         stream_seq: &mut u64,
         final_tip_block: u64,
         final_tip_timestamp: u64,
         update: ReorgEpilogueUpdate,
     ) {
+        if let Some(engine) = self.state_engine.as_mut() {
+            engine.apply_epilogue(&update);
+        }
         let seq = next_stream_seq(stream_seq);
         if let Err(e) = self.socket_tx.try_send(ControlMessage::ReorgEpilogue {
             stream_seq: seq,
@@ -915,6 +1412,22 @@ impl LiquidityExEx {
         }
     }
 
+    /// Announce a new finalized head. Capped at the emitted tip so the marker
+    /// never runs ahead of blocks the consumer has seen; repeats are suppressed.
+    fn send_finalized(&mut self, finalized: u64, emitted_tip: u64) {
+        let block_number = finalized.min(emitted_tip);
+        if self.last_finalized.is_some_and(|last| block_number <= last) {
+            return;
+        }
+        self.last_finalized = Some(block_number);
+        if let Err(e) = self
+            .socket_tx
+            .try_send(ControlMessage::Finalized { block_number })
+        {
+            warn!("Failed to send Finalized: {}", e);
+        }
+    }
+
     fn send_reorg_complete(&self, stream_seq: u64, final_tip_block: u64) {
         if let Err(e) = self.socket_tx.try_send(ControlMessage::ReorgComplete {
             stream_seq,
@@ -938,6 +1451,38 @@ impl LiquidityExEx {
         self.send_reorg_complete(seq, final_tip_block);
     }
 
+    /// Announce the pools that died in this block and blacklist them; the
+    /// blacklist lands with the block's other whitelist updates.
+    async fn invalidate_pools(&mut self, block_number: u64) {
+        let Some(dead) = self.invalidated_pools.remove(&block_number) else {
+            return;
+        };
+        for pool in &dead {
+            warn!(
+                pool = ?pool.pool_id,
+                reason = ?pool.reason,
+                block_number,
+                "☠️ Tracked pool contract died, blacklisting"
+            );
+            exex_metrics::record_liquidity_pool_invalidated();
+            if let Err(e) = self.socket_tx.try_send(ControlMessage::PoolInvalidated {
+                chain_id: chain::active().chain_id,
+                block_number,
+                pool_id: pool.pool_id.clone(),
+                protocol: pool.protocol,
+                reason: pool.reason,
+            }) {
+                warn!("Failed to send PoolInvalidated: {}", e);
+            }
+        }
+        self.pool_tracker
+            .write()
+            .await
+            .queue_update(pool_tracker::WhitelistUpdate::Blacklist(
+                dead.into_iter().map(|pool| pool.pool_id).collect(),
+            ));
+    }
+
     /// Block-boundary whitelist topology step: apply queued whitelist updates
     /// (`end_block`) and remove de-whitelisted pools' arena slots.
     ///
@@ -970,7 +1515,8 @@ impl LiquidityExEx {
     /// Check if we should process this decoded event
     /// For V2/V3: checks if pool address is tracked
     /// For V4: checks if pool_id is tracked (NOT the PoolManager address)
-    fn should_process_event(&self, event: &DecodedEvent, pool_tracker: &PoolTracker) -> bool {
+    /// The per-pool event mask is not applied here; see [`Self::event_masked`].
+    fn should_process_event(event: &DecodedEvent, pool_tracker: &PoolTracker) -> bool {
         let should_process = match event {
             // V2/V3 events: check pool address
             DecodedEvent::V2Swap { pool, .. }
@@ -1030,6 +1576,8 @@ impl LiquidityExEx {
             // Fluid LogOperate: emitted by Liquidity Layer, `pool` is the
             // DEX pool address extracted from the indexed `user` topic.
             DecodedEvent::FluidOperate { pool, .. } => pool_tracker.is_tracked_fluid_pool(pool),
+
+            DecodedEvent::TracedSwap { pool, .. } => pool_tracker.is_tracked_address(pool),
         };
 
         // Log when events are filtered out to help with debugging
@@ -1097,10 +1645,79 @@ impl LiquidityExEx {
                 DecodedEvent::FluidOperate { pool, .. } => {
                     debug!("Filtered Fluid LogOperate from untracked pool: {:?}", pool);
                 }
+                DecodedEvent::TracedSwap { pool, .. } => {
+                    debug!("Filtered traced swap from untracked pool: {:?}", pool);
+                }
             }
+            return false;
+        }
+
+        true
+    }
+
+    /// Per-pool event mask: high-churn pools can opt out of the event class
+    /// the consumer ignores. A masked event is still applied to the shadow
+    /// arena and the state engine; only its `PoolUpdate` frame is withheld.
+    /// Unmasked classes (state sync / params) always pass.
+    fn event_masked(event: &DecodedEvent, pool_tracker: &PoolTracker) -> bool {
+        let Some(class) = event.event_class() else {
+            return false;
+        };
+        let mask = event_pool_metadata(event, pool_tracker)
+            .and_then(|meta| meta.event_mask)
+            .unwrap_or_default();
+        if mask.allows(class) {
+            return false;
+        }
+        debug!(?class, ?mask, "Withheld event frame by per-pool event mask");
+        true
+    }
+}
+
+/// Pool and token deltas of a forward V3/V4 swap: the input of swap quotes,
+/// candles and fee reports.
+fn forward_swap_input<'a>(
+    event: &DecodedEvent,
+    pool_tracker: &'a PoolTracker,
+) -> Option<(&'a PoolMetadata, I256, I256)> {
+    match event {
+        DecodedEvent::V3Swap {
+            amount0, amount1, ..
         }
+        | DecodedEvent::V4Swap {
+            amount0, amount1, ..
+        } => event_pool_metadata(event, pool_tracker).map(|pool| (pool, *amount0, *amount1)),
+        _ => None,
+    }
+}
 
-        should_process
+/// Whitelist metadata for the pool a swap/liquidity event belongs to.
+fn event_pool_metadata<'a>(
+    event: &DecodedEvent,
+    pool_tracker: &'a PoolTracker,
+) -> Option<&'a PoolMetadata> {
+    match event {
+        DecodedEvent::V2Swap { pool }
+        | DecodedEvent::V2Mint { pool }
+        | DecodedEvent::V2Burn { pool }
+        | DecodedEvent::V3Swap { pool, .. }
+        | DecodedEvent::V3Mint { pool, .. }
+        | DecodedEvent::V3Burn { pool, .. }
+        | DecodedEvent::CurveSwap { pool }
+        | DecodedEvent::CurveLiquidityChange { pool }
+        | DecodedEvent::TwoCryptoSwap { pool }
+        | DecodedEvent::TwoCryptoLiquidityChange { pool }
+        | DecodedEvent::TricryptoLiquidityChange { pool }
+        | DecodedEvent::TracedSwap { pool, .. } => pool_tracker.pool_metadata(pool),
+        DecodedEvent::V4Swap { pool_id, .. }
+        | DecodedEvent::V4ModifyLiquidity { pool_id, .. }
+        | DecodedEvent::EkuboSwap { pool_id, .. }
+        | DecodedEvent::EkuboPositionUpdated { pool_id, .. }
+        | DecodedEvent::BalancerSwap { pool_id, .. }
+        | DecodedEvent::BalancerPoolBalanceChanged { pool_id, .. } => {
+            pool_tracker.pool_metadata_by_id(pool_id)
+        }
+        _ => None,
     }
 }
 
@@ -1145,6 +1762,15 @@ fn read_v2_reserves(state: &dyn StateProvider, address: Address) -> (u128, u128)
     (reserve0, reserve1)
 }
 
+/// Read a UniswapV3Pool's `feeGrowthGlobal0X128` / `feeGrowthGlobal1X128`
+/// (slots 1 and 2) from a held state snapshot.
+fn read_v3_fee_growth(state: &dyn StateProvider, address: Address) -> (U256, U256) {
+    (
+        read_storage_slot(state, address, U256::from(1u64)),
+        read_storage_slot(state, address, U256::from(2u64)),
+    )
+}
+
 fn pool_address(pool: &PoolMetadata) -> Option<Address> {
     pool.pool_id.as_address()
 }
@@ -1153,6 +1779,26 @@ fn pool_id_32(pool: &PoolMetadata) -> Option<[u8; 32]> {
     pool.pool_id.as_pool_id()
 }
 
+/// Complete the pools of an add or full replace from the pool-creations DB
+/// and reject those still missing token addresses (`pool_metadata_db::complete`).
+async fn complete_whitelist_update(
+    db: Option<&pool_metadata_db::PoolMetadataDb>,
+    update: &mut pool_tracker::WhitelistUpdate,
+) {
+    let (pool_tracker::WhitelistUpdate::Add(pools) | pool_tracker::WhitelistUpdate::Replace(pools)) =
+        update
+    else {
+        return;
+    };
+    let rejected = pool_metadata_db::complete(db, pools).await;
+    if rejected > 0 {
+        warn!(
+            rejected,
+            "Whitelist pools without token addresses not tracked"
+        );
+    }
+}
+
 fn v3_factory(pool: &PoolMetadata) -> Option<Address> {
     (pool.factory != Address::ZERO).then_some(pool.factory)
 }
@@ -1254,8 +1900,6 @@ fn v4_hydration_from_snapshot(
     state: &dyn StateProvider,
     pool: &PoolMetadata,
 ) -> Option<UniswapV4Hydration> {
-    use pool_tracker::UNISWAP_V4_POOL_MANAGER;
-
     let pool_id = pool_id_32(pool)?;
     if pool.tick_spacing.is_none()
         || pool.fee.is_none()
@@ -1265,7 +1909,7 @@ fn v4_hydration_from_snapshot(
         warn!(pool_id = ?pool_id, "Skipping V4 hydration: missing fee/tick/decimal metadata");
         return None;
     }
-    let pool_manager = singleton_contract_or(pool, UNISWAP_V4_POOL_MANAGER);
+    let pool_manager = singleton_contract_or(pool, chain::active().uniswap_v4_pool_manager);
     let snapshot = read_v4_full_state(state, pool_manager, &pool_id, pool.tick_spacing?)?;
     let arena_pool = build_v4_pool(pool_id, pool, &snapshot)?;
     Some(UniswapV4Hydration {
@@ -1278,8 +1922,6 @@ fn ekubo_hydration_from_snapshot(
     state: &dyn StateProvider,
     pool: &PoolMetadata,
 ) -> Option<EkuboHydration> {
-    use events::EKUBO_CORE;
-
     let pool_id = pool_id_32(pool)?;
     if pool.tick_spacing.is_none()
         || pool.ekubo_fee.is_none()
@@ -1290,7 +1932,7 @@ fn ekubo_hydration_from_snapshot(
         warn!(pool_id = ?pool_id, "Skipping Ekubo hydration: missing fee/type_config/tick/decimal metadata");
         return None;
     }
-    let ekubo_core = singleton_contract_or(pool, EKUBO_CORE);
+    let ekubo_core = singleton_contract_or(pool, chain::active().ekubo_core);
     let snapshot = read_ekubo_full_state(state, ekubo_core, &pool_id, pool.tick_spacing?)?;
     let arena_pool = build_ekubo_pool(pool_id, pool, &snapshot)?;
     Some(EkuboHydration {
@@ -1446,7 +2088,7 @@ fn read_balancer_v2_balances(
     expected_tokens: &[Address],
 ) -> Option<Vec<u128>> {
     use balancer_storage::PoolSpecialization;
-    let vault = events::BALANCER_V2_VAULT;
+    let vault = chain::active().balancer_v2_vault;
     let n = expected_tokens.len();
     match PoolSpecialization::from_pool_id(pool_id) {
         // Both balances packed in one sharedCash word; read the Vault's canonical
@@ -2115,6 +2757,28 @@ fn read_v3_slot0(state: &dyn StateProvider, address: Address) -> Option<(U256, i
     Some((sqrt_price_x96, tick, liquidity))
 }
 
+/// Mid price (token1 per token0, whole-token units) of a V2/V3 pool from a
+/// held state snapshot. `None` for other protocols or unknown decimals.
+fn read_pool_mid_price(state: &dyn StateProvider, pool: &PoolMetadata) -> Option<f64> {
+    let address = pool.pool_id.as_address()?;
+    let (decimals0, decimals1) = (pool.token0_decimals?, pool.token1_decimals?);
+    match pool.protocol {
+        Protocol::UniswapV2 => {
+            let (reserve0, reserve1) = read_v2_reserves(state, address);
+            price_quote::reserves_price(reserve0, reserve1, decimals0, decimals1)
+        }
+        Protocol::UniswapV3 => {
+            let (sqrt_price_x96, _, _) = read_v3_slot0(state, address)?;
+            Some(price_quote::normalized_price(
+                sqrt_price_x96,
+                decimals0,
+                decimals1,
+            ))
+        }
+        _ => None,
+    }
+}
+
 /// Read slot0 override for a V4 pool from a held state snapshot.
 fn read_v4_slot0(
     state: &dyn StateProvider,
@@ -2716,6 +3380,65 @@ fn send_v2_finals(
     }
 }
 
+/// Send final Curve full-state epilogue messages for still-tracked pools whose
+/// full-state updates were reverted, read from the final-tip snapshot with
+/// the forward path's readers.
+fn send_state_finals(
+    state: &dyn StateProvider,
+    affected_pools: &HashSet<(PoolIdentifier, Protocol)>,
+    exex: &mut LiquidityExEx,
+    stream_seq: &mut u64,
+    block_number: u64,
+    block_timestamp: u64,
+) {
+    let pool_tracker = exex.pool_tracker.snapshot();
+    let mut overrides_sent = 0u32;
+
+    for (pool_id, protocol) in affected_pools {
+        let PoolIdentifier::Address(pool) = pool_id else {
+            continue;
+        };
+        if pool_tracker.get_protocol(pool) != Some(*protocol) {
+            continue;
+        }
+        let event = match protocol {
+            Protocol::CurveStable => DecodedEvent::CurveLiquidityChange { pool: *pool },
+            Protocol::CurveTwoCrypto | Protocol::CurveTricrypto => {
+                DecodedEvent::TwoCryptoLiquidityChange { pool: *pool }
+            }
+            _ => continue,
+        };
+        let Some(final_msg) = LiquidityExEx::create_pool_update(
+            event,
+            block_number,
+            block_timestamp,
+            0,
+            0,
+            false,
+            state,
+            &pool_tracker,
+        ) else {
+            continue;
+        };
+
+        let update = ReorgEpilogueUpdate::PoolStateFinal {
+            pool_id: final_msg.pool_id,
+            protocol: final_msg.protocol,
+            update: final_msg.update,
+        };
+        apply_epilogue_to_shadow(&mut exex.shadow, &update);
+        exex.send_reorg_epilogue(stream_seq, block_number, block_timestamp, update);
+        overrides_sent += 1;
+    }
+
+    if overrides_sent > 0 {
+        info!(
+            "Sent {} pool-state final epilogue updates after reorg",
+            overrides_sent
+        );
+    }
+}
+
 /// Send final slot0 epilogue messages for all affected pools after a reorg.
 ///
 /// Reads definitive post-reorg state from one held final-tip snapshot and sends
@@ -2728,19 +3451,17 @@ fn send_slot0_finals(
     block_number: u64,
     block_timestamp: u64,
 ) {
-    use events::EKUBO_CORE;
-    use pool_tracker::UNISWAP_V4_POOL_MANAGER;
-
+    let chain = chain::active();
     let mut overrides_sent = 0u32;
 
     for (pool_id, protocol) in affected_pools {
         let slot0 = match (pool_id, protocol) {
             (PoolIdentifier::Address(addr), Protocol::UniswapV3) => read_v3_slot0(state, *addr),
             (PoolIdentifier::PoolId(id), Protocol::UniswapV4) => {
-                read_v4_slot0(state, UNISWAP_V4_POOL_MANAGER, id)
+                read_v4_slot0(state, chain.uniswap_v4_pool_manager, id)
             }
             (PoolIdentifier::PoolId(id), Protocol::Ekubo) => {
-                read_ekubo_state(state, EKUBO_CORE, id)
+                read_ekubo_state(state, chain.ekubo_core, id)
             }
             _ => continue,
         };
@@ -2796,46 +3517,1296 @@ fn record_affected_slot0_pool(
     }
 }
 
-/// Record V2 pools touched by reverted-away logs. This runs on decoded logs,
-/// not produced updates, because explicit `sync()` has no Swap/Mint/Burn delta
-/// message but still needs the final reserve epilogue after a reorg/revert.
-fn record_affected_v2_pool(event: &DecodedEvent, affected: &mut HashSet<Address>) {
-    match event {
-        DecodedEvent::V2Swap { pool, .. }
-        | DecodedEvent::V2Mint { pool, .. }
-        | DecodedEvent::V2Burn { pool, .. }
-        | DecodedEvent::V2Sync { pool, .. } => {
-            affected.insert(*pool);
+/// Whether a reverted update is absolute state with no inverse of its own.
+/// Such an update is not sent; its pool is recorded so the reorg epilogue
+/// settles it from final-tip state (V2 reserves, Fluid reserves, Curve full
+/// state). Seeds need nothing: the inverted deltas reverted after them, on
+/// top of the seeded base, restore the pool's pre-block state.
+fn settled_by_epilogue(
+    update: &PoolUpdateMessage,
+    affected_v2: &mut HashSet<Address>,
+    fluid_touched: &mut HashSet<Address>,
+    affected_state: &mut HashSet<(PoolIdentifier, Protocol)>,
+) -> bool {
+    match (&update.update, &update.pool_id) {
+        (PoolUpdate::V2Sync { .. }, PoolIdentifier::Address(pool)) => {
+            affected_v2.insert(*pool);
+        }
+        (PoolUpdate::FluidState { .. }, PoolIdentifier::Address(pool)) => {
+            fluid_touched.insert(*pool);
+        }
+        (
+            PoolUpdate::CurveLiquidity { .. }
+            | PoolUpdate::TwoCryptoState { .. }
+            | PoolUpdate::TricryptoState { .. },
+            _,
+        ) => {
+            affected_state.insert((update.pool_id.clone(), update.protocol));
         }
-        _ => {}
+        (PoolUpdate::V3Snapshot { .. } | PoolUpdate::V4Snapshot { .. }, _) => {}
+        _ => return false,
     }
+    true
 }
 
-fn state_at_block<P: StateProviderFactory>(
-    provider: &P,
-    block_number: u64,
-    context: &str,
-) -> eyre::Result<reth_provider::StateProviderBox> {
-    provider
-        .history_by_block_number(block_number)
-        .map_err(|e| eyre::eyre!("{context}: failed to open state at block {block_number}: {e}"))
+/// Old blocks in revert order: newest first.
+fn newest_first<B>(blocks: impl Iterator<Item = B>) -> Vec<B> {
+    let mut blocks: Vec<B> = blocks.collect();
+    blocks.reverse();
+    blocks
 }
 
-/// Main ExEx entry point
-async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) -> eyre::Result<()> {
-    info!("🚀 Liquidity ExEx starting");
+/// Updates that undo one old block, in revert order, each flagged
+/// `is_revert`. Taken from the reorg journal when it holds this exact block:
+/// the inverses of exactly the updates that were sent, in reverse emission
+/// order, with their original event-mask outcome. Otherwise the block's
+/// receipts are re-decoded against the current whitelist (see
+/// [`revert_events`]). Touched Fluid pools are added to `fluid_touched`
+/// either way.
+fn revert_updates<R: TxReceipt<Log = alloy_primitives::Log>>(
+    journal: &mut ReorgJournal,
+    block: BlockNumHash,
+    block_timestamp: u64,
+    receipts: &[R],
+    state: &dyn StateProvider,
+    pool_tracker: &PoolTracker,
+    fluid_touched: &mut HashSet<Address>,
+) -> Vec<JournaledUpdate> {
+    if let Some(entry) = journal.take(block.number, block.hash) {
+        return journaled_reverts(entry, fluid_touched);
+    }
+    revert_events(receipts, pool_tracker, fluid_touched)
+        .into_iter()
+        .filter(|e| LiquidityExEx::should_process_event(&e.event, pool_tracker))
+        .filter_map(|e| {
+            let masked = LiquidityExEx::event_masked(&e.event, pool_tracker);
+            // The forward update, inverted like a journaled one.
+            let update = LiquidityExEx::create_pool_update(
+                e.event,
+                block.number,
+                block_timestamp,
+                e.tx_index,
+                e.log_index,
+                false,
+                state,
+                pool_tracker,
+            )?;
+            Some(JournaledUpdate {
+                update: PoolUpdateMessage {
+                    is_revert: true,
+                    ..update
+                },
+                masked,
+            })
+        })
+        .collect()
+}
 
-    // Start Unix socket server
-    let socket_server = PoolUpdateSocketServer::new()?;
+/// The inverses of a journaled block's updates, newest first.
+fn journaled_reverts(
+    entry: JournalBlock,
+    fluid_touched: &mut HashSet<Address>,
+) -> Vec<JournaledUpdate> {
+    fluid_touched.extend(entry.fluid_touched);
+    entry
+        .updates
+        .into_iter()
+        .rev()
+        .map(|journaled| JournaledUpdate {
+            update: PoolUpdateMessage {
+                is_revert: true,
+                ..journaled.update
+            },
+            masked: journaled.masked,
+        })
+        .collect()
+}
+
+/// Tracked events of one old block re-decoded from its receipts, newest first
+/// (descending ordinal, original indexes kept). Touched tracked Fluid pools
+/// are added to `fluid_touched`.
+fn revert_events<R: TxReceipt<Log = alloy_primitives::Log>>(
+    receipts: &[R],
+    pool_tracker: &PoolTracker,
+    fluid_touched: &mut HashSet<Address>,
+) -> Vec<JournaledEvent> {
+    let mut events = Vec::new();
+    for (tx_index, receipt) in receipts.iter().enumerate().rev() {
+        for (log_index, log) in receipt.logs().iter().enumerate().rev() {
+            let log_address = log.address;
+
+            if log_address == chain::active().fluid_liquidity_layer {
+                if let Some(pool) = fluid_log_operate_pool(log) {
+                    if pool_tracker.is_tracked_fluid_pool(&pool) {
+                        fluid_touched.insert(pool);
+                    }
+                }
+                continue;
+            }
+
+            // Quick address filter (includes V2/V3 pools + PoolManager for V4)
+            if !pool_tracker.is_tracked_address(&log_address) {
+                continue;
+            }
+
+            if let Some(event) = decode_log(log) {
+                events.push(JournaledEvent {
+                    tx_index: tx_index as u64,
+                    log_index: log_index as u64,
+                    event,
+                });
+            }
+        }
+    }
+    events
+}
+
+/// Logs a block needs before decoding fans out across rayon's pool; below
+/// this the hand-off costs more than the decode.
+const PARALLEL_DECODE_MIN_LOGS: usize = 256;
+
+/// The tracked events of one block's receipts, before any state is read.
+#[derive(Default)]
+struct DecodedBlock {
+    /// In (tx_index, log_index) order.
+    events: Vec<JournaledEvent>,
+    fluid_touched: HashSet<Address>,
+    logs_checked: u64,
+    logs_matched: u64,
+    logs_decoded: u64,
+    events_filtered: u64,
+    /// Tracked-address logs no decoder accepted, as (tx_index, log_index,
+    /// log). Only collected under `STRICT_DECODING`.
+    undecoded: Vec<(u64, u64, alloy_primitives::Log)>,
+}
+
+impl DecodedBlock {
+    /// Append `later`, whose transactions all follow ours.
+    fn merge(mut self, later: DecodedBlock) -> Self {
+        self.events.extend(later.events);
+        self.fluid_touched.extend(later.fluid_touched);
+        self.logs_checked += later.logs_checked;
+        self.logs_matched += later.logs_matched;
+        self.logs_decoded += later.logs_decoded;
+        self.events_filtered += later.events_filtered;
+        self.undecoded.extend(later.undecoded);
+        self
+    }
+}
+
+/// Filter and decode one receipt's logs against the whitelist.
+fn decode_receipt_logs<R: TxReceipt<Log = alloy_primitives::Log>>(
+    tx_index: usize,
+    receipt: &R,
+    pool_tracker: &PoolTracker,
+) -> DecodedBlock {
+    let mut decoded = DecodedBlock::default();
+    // Only when the receipt carries its bloom: computing one costs more than
+    // the address filter below (reth's stored receipts carry none).
+    if receipt
+        .bloom_cheap()
+        .is_some_and(|bloom| !pool_tracker.may_contain_tracked(&bloom))
+    {
+        return decoded;
+    }
+    for (log_index, log) in receipt.logs().iter().enumerate() {
+        let log_address = log.address;
+        decoded.logs_checked += 1;
+
+        // Quick address filter (includes V2/V3 pools + PoolManager for V4 + Liquidity Layer for Fluid)
+        if !pool_tracker.is_tracked_address(&log_address) {
+            continue;
+        }
+        decoded.logs_matched += 1;
+
+        // For Fluid Liquidity Layer: pre-filter by indexed pool
+        // address in topics[1] before full ABI decode. The
+        // Liquidity Layer emits LogOperate for ALL protocols
+        // (fTokens, Vaults, etc.), not just our tracked DEX pools.
+        // Touched pools are decoded from storage after the log loop.
+        if log_address == chain::active().fluid_liquidity_layer {
+            if let Some(pool) = fluid_log_operate_pool(log) {
+                if pool_tracker.is_tracked_fluid_pool(&pool) {
+                    decoded.fluid_touched.insert(pool);
+                }
+            }
+            continue;
+        }
+
+        let Some(event) = decode_log(log) else {
+            if dead_letter::strict().is_some_and(|strict| strict.captures(log)) {
+                decoded
+                    .undecoded
+                    .push((tx_index as u64, log_index as u64, log.clone()));
+            }
+            continue;
+        };
+        decoded.logs_decoded += 1;
+
+        // For V2/V3: checks pool address
+        // For V4: checks pool_id from event data (NOT PoolManager address)
+        if !LiquidityExEx::should_process_event(&event, pool_tracker) {
+            decoded.events_filtered += 1;
+            continue;
+        }
+        decoded.events.push(JournaledEvent {
+            tx_index: tx_index as u64,
+            log_index: log_index as u64,
+            event,
+        });
+    }
+    decoded
+}
+
+/// Filter and decode a block's logs. Large blocks decode their receipts in
+/// parallel; results are merged back in (tx_index, log_index) order, so the
+/// emitted stream is identical to a sequential pass. Reading state (slot0,
+/// Fluid reserves) stays sequential in the caller. A block whose header logs
+/// bloom rules out every tracked address is skipped without scanning.
+fn decode_block_logs<R: TxReceipt<Log = alloy_primitives::Log> + Sync>(
+    receipts: &[R],
+    block_bloom: Option<&alloy_primitives::Bloom>,
+    pool_tracker: &PoolTracker,
+) -> DecodedBlock {
+    if block_bloom.is_some_and(|bloom| !pool_tracker.may_contain_tracked(bloom)) {
+        return DecodedBlock::default();
+    }
+    let total_logs: usize = receipts.iter().map(|r| r.logs().len()).sum();
+    if total_logs < PARALLEL_DECODE_MIN_LOGS {
+        return receipts.iter().enumerate().fold(
+            DecodedBlock::default(),
+            |acc, (tx_index, receipt)| {
+                acc.merge(decode_receipt_logs(tx_index, receipt, pool_tracker))
+            },
+        );
+    }
+    // Indexed collect keeps receipt order.
+    let per_receipt: Vec<DecodedBlock> = receipts
+        .par_iter()
+        .enumerate()
+        .map(|(tx_index, receipt)| decode_receipt_logs(tx_index, receipt, pool_tracker))
+        .collect();
+    per_receipt
+        .into_iter()
+        .fold(DecodedBlock::default(), DecodedBlock::merge)
+}
+
+/// Hand a block's undecodable tracked-address logs to the dead-letter sink,
+/// with transaction hashes read from the provider.
+fn send_dead_letters<P>(
+    sink: Option<&dead_letter::DeadLetterSink>,
+    provider: &P,
+    block_number: u64,
+    block_hash: alloy_primitives::B256,
+    undecoded: Vec<(u64, u64, alloy_primitives::Log)>,
+) where
+    P: BlockReader,
+    P::Transaction: TxHashRef,
+{
+    let Some(sink) = sink else {
+        return;
+    };
+    if undecoded.is_empty() {
+        return;
+    }
+    let tx_hashes: Vec<_> = match provider.transactions_by_block(block_hash.into()) {
+        Ok(Some(txs)) => txs.iter().map(|tx| *tx.tx_hash()).collect(),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            debug!(
+                block_number,
+                "Failed to read transactions for dead letters: {}", e
+            );
+            Vec::new()
+        }
+    };
+    for (tx_index, log_index, log) in undecoded {
+        sink.send(dead_letter::DeadLetter {
+            chain_id: chain::active().chain_id,
+            block_number,
+            block_hash,
+            tx_index,
+            log_index,
+            tx_hash: tx_hashes.get(tx_index as usize).copied(),
+            address: log.address,
+            topic0: log.topics().first().copied(),
+            topics: log.topics().to_vec(),
+            data: log.data.data,
+        });
+    }
+}
+
+fn state_at_block<P: StateProviderFactory>(
+    provider: &P,
+    block_number: u64,
+    context: &str,
+) -> eyre::Result<reth_provider::StateProviderBox> {
+    provider
+        .history_by_block_number(block_number)
+        .map_err(|e| eyre::eyre!("{context}: failed to open state at block {block_number}: {e}"))
+}
+
+/// Convert one decoded forward event into its pool update and send it, with
+/// its swap quote when quotes are enabled. Shared by committed blocks and a
+/// reorg's replacement blocks, so a block produces the same frames whichever
+/// notification delivers it. `shadow_apply` picks the live or reorg arena
+/// write. Returns the `PoolUpdate` frames written.
+#[allow(clippy::too_many_arguments)]
+fn emit_forward_event(
+    exex: &mut LiquidityExEx,
+    stream_seq: &mut u64,
+    journaled: &JournaledEvent,
+    block_number: u64,
+    block_timestamp: u64,
+    state: &dyn StateProvider,
+    pool_tracker: &PoolTracker,
+    shadow_apply: fn(&mut Option<ShadowArena>, &PoolUpdateMessage),
+) -> u64 {
+    let swap_quote_input = forward_swap_input(&journaled.event, pool_tracker);
+    let masked = LiquidityExEx::event_masked(&journaled.event, pool_tracker);
+    let Some(update_msg) = LiquidityExEx::create_pool_update(
+        journaled.event.clone(),
+        block_number,
+        block_timestamp,
+        journaled.tx_index,
+        journaled.log_index,
+        false,
+        state,
+        pool_tracker,
+    ) else {
+        return 0;
+    };
+    shadow_apply(&mut exex.shadow, &update_msg);
+    if let Some(swap) = swap_quote_input {
+        exex.record_swap(swap, &update_msg);
+    }
+    let quote_msg = exex
+        .quoter
+        .as_ref()
+        .zip(swap_quote_input)
+        .filter(|_| !masked)
+        .and_then(|(quoter, (pool, amount0, amount1))| {
+            let update = quoter.quote(pool, &update_msg.update, amount0, amount1)?;
+            Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
+                update,
+                ..update_msg.clone()
+            })
+        });
+    let mut sent = exex.send_journaled_update(stream_seq, update_msg, masked);
+    if let Some(quote_msg) = quote_msg {
+        sent += exex.send_pool_update(stream_seq, quote_msg);
+    }
+    exex.events_processed += 1;
+    sent
+}
+
+/// Emit one committed block's envelope: decode and send its events, seed and
+/// verify pools, hydrate live adds, then the block signal. Shared by the live
+/// loop, a reorg's replacement blocks and `replay`, so a block produces the
+/// same stream whichever way it arrives.
+///
+/// `reorg_fluid_touched` is `Some` for a reorg's replacement block: the shadow
+/// takes reorg writes, overflow promotion is left to the reorg's final-tip
+/// drain, and the Fluid pools this block decodes are taken out of the set the
+/// reorg still has to settle.
+#[tracing::instrument(name = "liquidity.block", skip_all, fields(block = header.number()))]
+async fn process_committed_block<P, H, R>(
+    exex: &mut LiquidityExEx,
+    provider: &P,
+    stream_seq: &mut u64,
+    header: &H,
+    block_hash: alloy_primitives::B256,
+    receipts: &[R],
+    reorg_fluid_touched: Option<&mut HashSet<Address>>,
+) -> eyre::Result<()>
+where
+    P: StateProviderFactory + BlockReader + Clone + Send + Sync + 'static,
+    P::Receipt: TxReceipt<Log = alloy_primitives::Log>,
+    P::Transaction: TxHashRef,
+    H: BlockHeader,
+    R: TxReceipt<Log = alloy_primitives::Log> + Sync,
+{
+    let block_number = header.number();
+    let block_timestamp = header.timestamp();
+    let base_fee_per_gas = header.base_fee_per_gas().unwrap_or(0);
+    let replaces_reorged = reorg_fluid_touched.is_some();
+    let shadow_apply: fn(&mut Option<ShadowArena>, &PoolUpdateMessage) = if replaces_reorged {
+        apply_reorg_to_shadow
+    } else {
+        apply_to_shadow
+    };
+
+    // 🔒 Begin block - lock whitelist updates until block completes
+    {
+        let mut pool_tracker = exex.pool_tracker.write().await;
+        pool_tracker.begin_block();
+    }
+
+    exex.send_begin_block(
+        stream_seq,
+        block_number,
+        block_hash,
+        header.parent_hash(),
+        block_timestamp,
+        base_fee_per_gas,
+        header.beneficiary(),
+        false,
+    );
+    if let Some(candles) = exex.candles.as_mut() {
+        candles.close_through(block_timestamp);
+    }
+
+    let pool_tracker = exex.pool_tracker.snapshot();
+    let state = state_at_block(provider, block_number, "ChainCommitted")?;
+    let mut events_in_block = 0;
+    let mut decoded = info_span!("liquidity.decode", receipts = receipts.len())
+        .in_scope(|| decode_block_logs(receipts, Some(&header.logs_bloom()), &pool_tracker));
+    call_trace::merge_traced(&mut decoded.events, exex.traced_swaps.remove(&block_number));
+    let logs_checked = decoded.logs_checked;
+    let logs_matched_address = decoded.logs_matched;
+    let logs_decoded = decoded.logs_decoded;
+    let fluid_touched = decoded.fluid_touched;
+    exex_metrics::record_liquidity_filtered(decoded.events_filtered);
+    send_dead_letters(
+        exex.dead_letters.as_ref(),
+        provider,
+        block_number,
+        block_hash,
+        decoded.undecoded,
+    );
+
+    // Event → update conversion (state reads for V3/V4 context) and the
+    // socket / shadow sends; nothing is awaited until the span is dropped.
+    let emit_span = info_span!("liquidity.emit", events = decoded.events.len()).entered();
+    for journaled in &decoded.events {
+        events_in_block += emit_forward_event(
+            exex,
+            stream_seq,
+            journaled,
+            block_number,
+            block_timestamp,
+            state.as_ref(),
+            &pool_tracker,
+            shadow_apply,
+        );
+    }
+
+    // ── Fluid batch decode ───────────────────────────────────
+    // For each Fluid pool touched in this block, read 8 storage
+    // slots from the state provider and decode reserves.
+    for pool_addr in &fluid_touched {
+        if let Some(config) = pool_tracker.fluid_config(pool_addr) {
+            match decode_fluid_pool(state.as_ref(), config, block_timestamp) {
+                Some(reserves) => {
+                    let update_msg =
+                        fluid_update_msg(*pool_addr, &reserves, block_number, block_timestamp);
+                    shadow_apply(&mut exex.shadow, &update_msg);
+                    events_in_block += exex.send_journaled_update(stream_seq, update_msg, false);
+                    exex.events_processed += 1;
+                    debug!(pool = %pool_addr, "Decoded Fluid reserves from storage");
+                }
+                None => {
+                    warn!(pool = %pool_addr, "Failed to decode Fluid reserves from storage");
+                }
+            }
+        } else {
+            debug!(pool = %pool_addr, "Fluid pool touched but no config cached — skipping");
+        }
+    }
+    if let Some(reorg_fluid_touched) = reorg_fluid_touched {
+        // Handled in the new chain: the reorg need not re-decode them.
+        for pool_addr in &fluid_touched {
+            reorg_fluid_touched.remove(pool_addr);
+        }
+    }
+
+    exex.end_block_fee_report(state.as_ref(), block_number);
+    exex.check_depeg(state.as_ref(), block_number);
+
+    // Promote any pools that overflowed their tier this block
+    // (re-scrape + in-place re-tier) while state + tracker are held. A
+    // reorg drains them once from its final tip instead: later replacement
+    // blocks' deltas would double-apply on top of a re-scrape here.
+    if !replaces_reorged {
+        promote_overflowed_pools(&mut exex.shadow, &pool_tracker, state.as_ref());
+    }
+
+    // Release state/tracker snapshot before sending EndBlock and awaiting tracker writes.
+    drop(state);
+    drop(pool_tracker);
+    drop(emit_span);
+
+    exex.invalidate_pools(block_number).await;
+
+    // 🔓 End block — apply pending whitelist updates and drop
+    // removed pools' arena slots BEFORE this block's EndBlock /
+    // arena signal, so a reader synchronized on the block signal
+    // never observes a stale active slot for a de-whitelisted
+    // pool (see `end_block_whitelist_topology`).
+    exex.end_block_whitelist_topology(block_number)
+        .instrument(info_span!("liquidity.whitelist"))
+        .await;
+
+    // Seed consumers with the post-block absolute state of pools
+    // the whitelist just added (V2 reserves, V3/V4 full tick state), inside
+    // this block's envelope and ahead of their first delta.
+    let mut to_seed = exex.pool_tracker.write().await.take_pending_seed();
+
+    // Optional history for the same pools, replayed off-loop up to
+    // this block (the seed's post-state) as a tagged backfill stream.
+    if exex.backfill_blocks > 0 && !to_seed.is_empty() {
+        if let Some(backfill_tx) = &exex.backfill_tx {
+            backfill::spawn_backfill(
+                provider.clone(),
+                backfill_tx.clone(),
+                to_seed.clone(),
+                block_number,
+                exex.backfill_blocks,
+            );
+        }
+    }
+
+    // Periodic resync: re-emit the absolute state of this block's
+    // round-robin batch of tracked pools, so every pool is re-seeded once
+    // per `POOL_SNAPSHOT_INTERVAL_BLOCKS` and a consumer that dropped
+    // messages converges without reconnecting, with no block carrying
+    // the whole whitelist.
+    let mut resync_batch = Vec::new();
+    if let Some(resync) = exex.resync.as_mut() {
+        let (batch, completes_pass) = resync.next_batch(exex.pool_tracker.snapshot().pools());
+        resync_batch = batch;
+        if completes_pass {
+            // Pools the engine still has no base for are handed out again.
+            if let Some(engine) = exex.state_engine.as_mut() {
+                engine.retry_seeds();
+            }
+            info!(
+                block_number,
+                interval_blocks = resync.interval_blocks(),
+                "📸 Completed periodic pool-state snapshot pass"
+            );
+        }
+    }
+    // The state engine needs a base for every pool it folds, not only live
+    // adds: seed tracked pools it has none for (the startup snapshot first).
+    if let Some(engine) = exex.state_engine.as_mut() {
+        let tracker = exex.pool_tracker.snapshot();
+        resync_batch.extend(engine.take_unseeded(tracker.pools()));
+    }
+    for pool in resync_batch {
+        if !to_seed.iter().any(|seed| seed.pool_id == pool.pool_id) {
+            to_seed.push(pool);
+        }
+    }
+    if !to_seed.is_empty() {
+        match state_at_block(provider, block_number, "ChainCommitted live-add seed") {
+            Ok(seed_state) => {
+                let seeds = info_span!("liquidity.seed", pools = to_seed.len()).in_scope(|| {
+                    state_seed::seed_update_messages(
+                        seed_state.as_ref(),
+                        &to_seed,
+                        block_number,
+                        block_timestamp,
+                    )
+                });
+                for update_msg in seeds {
+                    events_in_block += exex.send_journaled_update(stream_seq, update_msg, false);
+                }
+            }
+            Err(e) => {
+                warn!(
+                    error = %e,
+                    block_number,
+                    pools = to_seed.len(),
+                    "live-add seed: no state; pools start from deltas"
+                );
+            }
+        }
+    }
+
+    // Cross-check a sample of the state engine's pools against
+    // storage; diverged pools get a corrective seed in-envelope. A reorg's
+    // replacement blocks, where the engine most likely diverged, are
+    // checked whatever the interval.
+    let mut corrections = Vec::new();
+    if let (Some(verifier), Some(engine)) = (exex.verifier.as_mut(), exex.state_engine.as_ref()) {
+        if replaces_reorged || verifier.due(block_number) {
+            let sample = verifier.sample(engine, exex.pool_tracker.snapshot().pools());
+            if !sample.is_empty() {
+                match state_at_block(provider, block_number, "ChainCommitted state verify") {
+                    Ok(verify_state) => {
+                        corrections = info_span!("liquidity.verify", pools = sample.len())
+                            .in_scope(|| {
+                                verifier.verify(
+                                    engine,
+                                    verify_state.as_ref(),
+                                    &sample,
+                                    block_number,
+                                    block_timestamp,
+                                )
+                            });
+                    }
+                    Err(e) => {
+                        warn!(error = %e, block_number, "state verify skipped")
+                    }
+                }
+            }
+        }
+    }
+    for update_msg in corrections {
+        events_in_block += exex.send_journaled_update(stream_seq, update_msg, false);
+    }
+    // Every forward update of the block is out: journal them for a reorg.
+    exex.journal_block(block_number, block_hash, &fluid_touched);
+
+    // Hydrate pools added by this block's whitelist `.add` into the
+    // shadow arena from current state — also before the block
+    // signal, so the topology a reader rebuilds at this block
+    // already contains them. Pools that cannot hydrate yet (e.g.
+    // a Fluid config still resolving) are re-queued for the next
+    // committed block.
+    if exex.shadow.is_some() {
+        let added = exex.pool_tracker.write().await.take_newly_added();
+        if !added.is_empty() {
+            match state_at_block(provider, block_number, "ChainCommitted live-add hydrate") {
+                Ok(add_state) => {
+                    let (batch, unhydrated) = {
+                        let pool_tracker = exex.pool_tracker.snapshot();
+                        // Drop additions that were removed between the
+                        // drain and now (a failed add + later remove
+                        // must not hydrate a stale slot).
+                        let still_tracked: Vec<PoolMetadata> = added
+                            .into_iter()
+                            .filter(|p| pool_tracker.is_tracked(&p.pool_id))
+                            .collect();
+                        build_hydration_batch(
+                            add_state.as_ref(),
+                            &still_tracked,
+                            pool_tracker.fluid_configs_map(),
+                            block_timestamp,
+                        )
+                    };
+                    if !unhydrated.is_empty() {
+                        exex.pool_tracker
+                            .write()
+                            .await
+                            .requeue_newly_added(unhydrated);
+                    }
+                    if let Some(shadow) = exex.shadow.as_mut() {
+                        if !batch.is_empty() {
+                            let added_counts = shadow.hydrate_added(&batch);
+                            info!(
+                                ?added_counts,
+                                block_number, "shadow arena: hydrated live-added pools"
+                            );
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        block_number,
+                        "live-add shadow hydration: no state; re-queueing"
+                    );
+                    exex.pool_tracker.write().await.requeue_newly_added(added);
+                }
+            }
+        }
+    }
+
+    // Block signal LAST: the socket EndBlock and the arena block
+    // signal / arena → curve notification are emitted only after
+    // this block's whitelist topology (removals + additions) has
+    // landed, so readers synchronized on them see one coherent
+    // post-block topology.
+    events_in_block += exex.send_liquidity_depth(stream_seq, block_number, block_timestamp);
+    exex.send_arb_signals(block_number);
+    exex.send_end_block(stream_seq, block_number, events_in_block);
+    exex.shadow_end_block(block_number, base_fee_per_gas, *stream_seq)
+        .instrument(info_span!("liquidity.arena"))
+        .await;
+
+    if events_in_block > 0 {
+        info!(
+            "Block {}: processed {} liquidity events",
+            block_number, events_in_block
+        );
+    }
+
+    // Debug logging every block for now
+    if logs_checked > 0 || events_in_block > 0 {
+        info!(
+            "🔍 Block {}: checked {} logs, {} matched address, {} decoded, {} events",
+            block_number, logs_checked, logs_matched_address, logs_decoded, events_in_block
+        );
+    }
+    exex_metrics::record_liquidity_block(logs_checked, logs_matched_address, logs_decoded);
+    health::record_block("liquidity", block_number);
+    exex_metrics::set_socket_queue_depth(exex.socket_tx.depth());
+
+    exex.blocks_processed += 1;
+
+    // Log stats every 100 blocks
+    if exex.blocks_processed % 100 == 0 {
+        info!(
+            "Stats: {} blocks, {} events processed",
+            exex.blocks_processed, exex.events_processed
+        );
+
+        let pool_tracker = exex.pool_tracker.snapshot();
+        let stats = pool_tracker.stats();
+        exex_metrics::set_tracked_pools(stats.total_pools);
+        info!(
+            "Tracking: {} pools ({} V2, {} V3, {} V4)",
+            stats.total_pools, stats.v2_pools, stats.v3_pools, stats.v4_pools
+        );
+
+        if stats.total_pools == 0 {
+            warn!("⚠️  No pools in whitelist! Events will be filtered out.");
+            warn!("   Check that NATS whitelist updates are being received.");
+        }
+    }
+    Ok(())
+}
+
+/// Emit the socket stream (and arena writes) for one notification.
+#[tracing::instrument(name = "liquidity.notification", skip_all)]
+async fn handle_notification<N, P>(
+    exex: &mut LiquidityExEx,
+    provider: &P,
+    stream_seq: &mut u64,
+    notification: &ExExNotification<N>,
+) -> eyre::Result<()>
+where
+    N: NodePrimitives,
+    P: StateProviderFactory + BlockReader + Clone + Send + Sync + 'static,
+    P::Receipt: TxReceipt<Log = alloy_primitives::Log>,
+    P::Transaction: TxHashRef,
+{
+    match notification {
+        ExExNotification::ChainCommitted { new } => {
+            debug!(
+                "Processing committed chain with {} blocks",
+                new.blocks().len()
+            );
+
+            // Process each block with block boundaries.
+            for (block, receipts) in new.blocks_and_receipts() {
+                if exex.resume.is_duplicate(block.number()) {
+                    debug!(
+                        block = block.number(),
+                        "Skipping block emitted before the restart"
+                    );
+                    continue;
+                }
+                process_committed_block(
+                    exex,
+                    provider,
+                    stream_seq,
+                    block.header(),
+                    block.hash(),
+                    receipts,
+                    None,
+                )
+                .await?;
+            }
+        }
+
+        ExExNotification::ChainReorged { old, new } => {
+            warn!(
+                "⚠️  Chain reorg detected: reverting {} old blocks, applying {} new blocks",
+                old.blocks().len(),
+                new.blocks().len()
+            );
+            exex.resume.revert_from(old.first().number());
+            if let Some(candles) = exex.candles.as_mut() {
+                candles.revert_from(old.first().number());
+            }
+            if let Some(reporter) = exex.fee_reporter.as_mut() {
+                reporter.tracker().revert_from(old.first().number());
+            }
+
+            let old_range = block_range_summary_from_numbers(old.blocks().keys().copied());
+            let new_range = block_range_summary_from_numbers(new.blocks().keys().copied());
+            let final_tip_block = new_range
+                .last_block
+                .or(old_range.last_block)
+                .unwrap_or_default();
+
+            exex.send_reorg_start(stream_seq, old_range.clone(), new_range.clone());
+
+            let mut affected_slot0_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
+            let mut affected_v2_pools = HashSet::<Address>::new();
+            let mut affected_state_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
+            let mut reorg_fluid_touched = HashSet::<Address>::new();
+
+            // Step 1: Revert old blocks
+            info!("Step 1: Reverting {} old blocks", old.blocks().len());
+            // Revert in reverse execution order: newest old block first (and,
+            // below, newest tx/log first) so inverse tick-liquidity ops
+            // un-apply in the exact reverse of how they were applied.
+            // Otherwise reverting an earlier mint before the later burn that
+            // zeroed the tick wraps `gross` through `as u128`.
+            for (block, receipts) in newest_first(old.blocks_and_receipts()) {
+                let block_number = block.number();
+                let block_timestamp = block.timestamp();
+                let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
+
+                // 🔒 Begin block
+                {
+                    let mut pool_tracker = exex.pool_tracker.write().await;
+                    pool_tracker.begin_block();
+                }
+
+                exex.send_begin_block(
+                    stream_seq,
+                    block_number,
+                    block.hash(),
+                    block.parent_hash(),
+                    block_timestamp,
+                    base_fee_per_gas,
+                    block.beneficiary(),
+                    true,
+                );
+
+                let pool_tracker = exex.pool_tracker.snapshot();
+                // Reth exposes canonical post-reorg state here, not old-fork state.
+                // Absolute full-state revert messages therefore use this final-tip
+                // snapshot; reorg epilogues below remain the definitive recovery path.
+                let state = state_at_block(provider, final_tip_block, "ChainReorged revert")?;
+                let mut events_reverted = 0;
+
+                // Reverse tx/log order, keeping the original tx/log indexes in
+                // the emitted messages.
+                // Fluid pools touched by the old block are collected into
+                // `reorg_fluid_touched` and decoded from post-reorg state after
+                // Step 2 (or dropped once new-block processing covers them).
+                let to_revert = revert_updates(
+                    &mut exex.journal,
+                    BlockNumHash::new(block_number, block.hash()),
+                    block_timestamp,
+                    receipts,
+                    state.as_ref(),
+                    &pool_tracker,
+                    &mut reorg_fluid_touched,
+                );
+                for JournaledUpdate {
+                    update: update_msg,
+                    masked,
+                } in to_revert
+                {
+                    record_affected_slot0_pool(&update_msg, &mut affected_slot0_pools);
+                    if settled_by_epilogue(
+                        &update_msg,
+                        &mut affected_v2_pools,
+                        &mut reorg_fluid_touched,
+                        &mut affected_state_pools,
+                    ) {
+                        continue;
+                    }
+                    apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
+                    events_reverted +=
+                        exex.send_pool_update_unless_masked(stream_seq, update_msg, masked);
+                }
+
+                // Overflow promotion is NOT drained here: this loop re-scrapes
+                // from the FINAL canonical tip, and Step 2 still applies the
+                // new-chain deltas afterward. Re-scraping a pool now and then
+                // applying more deltas would double-apply. The pending set is
+                // accumulated across the whole reorg and drained once, from
+                // final_state, after every delta has landed (see below).
+
+                drop(state);
+                drop(pool_tracker);
+
+                // 🔓 End block — whitelist topology (incl. removed-pool slot
+                // drop) BEFORE the block signal, as in the committed path.
+                exex.end_block_whitelist_topology(block_number).await;
+
+                exex.send_end_block(stream_seq, block_number, events_reverted);
+                exex.shadow_end_block(block_number, base_fee_per_gas, *stream_seq)
+                    .await;
+
+                if events_reverted > 0 {
+                    debug!(
+                        "Block {}: reverted {} liquidity events",
+                        block_number, events_reverted
+                    );
+                }
+            }
+
+            // Step 2: Process new blocks exactly as committed ones (events,
+            // quotes, seeds, verification), deferring overflow promotion and
+            // Fluid settlement to the final tip below.
+            info!("Step 2: Processing {} new blocks", new.blocks().len());
+            for (block, receipts) in new.blocks_and_receipts() {
+                process_committed_block(
+                    exex,
+                    provider,
+                    stream_seq,
+                    block.header(),
+                    block.hash(),
+                    receipts,
+                    Some(&mut reorg_fluid_touched),
+                )
+                .await?;
+            }
+
+            let final_state = state_at_block(provider, final_tip_block, "ChainReorged final")?;
+
+            // ── Fluid: decode pools touched in old blocks but not new ──
+            if !reorg_fluid_touched.is_empty() {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                let tip_timestamp = new
+                    .blocks()
+                    .values()
+                    .last()
+                    .map(|b| b.timestamp())
+                    .unwrap_or_default();
+                for pool_addr in &reorg_fluid_touched {
+                    if let Some(config) = pool_tracker.fluid_config(pool_addr) {
+                        match decode_fluid_pool(final_state.as_ref(), config, tip_timestamp) {
+                            Some(reserves) => {
+                                let update = ReorgEpilogueUpdate::FluidStateFinal {
+                                    pool_id: PoolIdentifier::Address(*pool_addr),
+                                    state: fluid_state_from_reserves(&reserves),
+                                };
+                                apply_epilogue_to_shadow(&mut exex.shadow, &update);
+                                exex.send_reorg_epilogue(
+                                    stream_seq,
+                                    final_tip_block,
+                                    tip_timestamp,
+                                    update,
+                                );
+                                debug!(pool = %pool_addr, "Decoded Fluid reserves post-reorg epilogue (not in new chain)");
+                            }
+                            None => {
+                                warn!(pool = %pool_addr, "Failed to decode Fluid reserves post-reorg");
+                            }
+                        }
+                    }
+                }
+                drop(pool_tracker);
+            }
+
+            let final_tip_timestamp = new
+                .blocks()
+                .values()
+                .last()
+                .map(|b| b.timestamp())
+                .unwrap_or(0);
+
+            let active_v2_pools = {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                active_affected_v2_pools(&pool_tracker, &affected_v2_pools)
+            };
+
+            // Send definitive V2 reserve overrides from the final-tip state snapshot.
+            send_v2_finals(
+                final_state.as_ref(),
+                &active_v2_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                final_tip_timestamp,
+            );
+
+            // Send definitive slot0 overrides from the final-tip state snapshot.
+            send_slot0_finals(
+                final_state.as_ref(),
+                &affected_slot0_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                final_tip_timestamp,
+            );
+            send_state_finals(
+                final_state.as_ref(),
+                &affected_state_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                final_tip_timestamp,
+            );
+            // Drain overflow promotions ONCE, now that every revert + new-chain
+            // delta has landed. Re-scraping each overflowed pool from the settled
+            // final-tip state and replacing its slot is authoritative because no
+            // further deltas are applied after this point (only the epilogue
+            // signal). Doing it per block above would re-scrape from a snapshot
+            // that later deltas then double-apply on top of.
+            {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                promote_overflowed_pools(&mut exex.shadow, &pool_tracker, final_state.as_ref());
+            }
+            // Flush the reorg epilogue writes (slot0/fluid finals + promotions)
+            // into a shadow block signal at the settled tip.
+            exex.finish_reorg(stream_seq, final_tip_block).await;
+
+            info!("✅ Reorg handled successfully");
+        }
+
+        ExExNotification::ChainReverted { old } => {
+            warn!(
+                "⚠️  Chain reverted: reverting {} blocks",
+                old.blocks().len()
+            );
+            exex.resume.revert_from(old.first().number());
+            if let Some(candles) = exex.candles.as_mut() {
+                candles.revert_from(old.first().number());
+            }
+            if let Some(reporter) = exex.fee_reporter.as_mut() {
+                reporter.tracker().revert_from(old.first().number());
+            }
+
+            let old_range = block_range_summary_from_numbers(old.blocks().keys().copied());
+            let final_tip_block = old_range
+                .first_block
+                .map(|n| n.saturating_sub(1))
+                .unwrap_or_default();
+
+            exex.send_reorg_start(
+                stream_seq,
+                old_range.clone(),
+                ReorgRange {
+                    first_block: None,
+                    last_block: None,
+                    block_count: 0,
+                },
+            );
+
+            let mut affected_slot0_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
+            let mut affected_v2_pools = HashSet::<Address>::new();
+            let mut affected_state_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
+            let mut revert_fluid_touched = HashSet::<Address>::new();
+            // Reth exposes canonical post-revert state here, not the reverted-away
+            // old blocks' state. Absolute full-state revert messages and final
+            // epilogues both read this one final-tip snapshot.
+            let final_state = state_at_block(provider, final_tip_block, "ChainReverted final")?;
+
+            // Revert in reverse execution order (see ChainReorged Step 1): newest
+            // old block first, and newest tx/log first within each block.
+            for (block, receipts) in newest_first(old.blocks_and_receipts()) {
+                let block_number = block.number();
+                let block_timestamp = block.timestamp();
+                let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
+
+                // 🔒 Begin block
+                {
+                    let mut pool_tracker = exex.pool_tracker.write().await;
+                    pool_tracker.begin_block();
+                }
+
+                exex.send_begin_block(
+                    stream_seq,
+                    block_number,
+                    block.hash(),
+                    block.parent_hash(),
+                    block_timestamp,
+                    base_fee_per_gas,
+                    block.beneficiary(),
+                    true,
+                );
+
+                let pool_tracker = exex.pool_tracker.snapshot();
+                let mut events_reverted = 0;
+
+                // Reverse tx/log order, keeping the original tx/log indexes in
+                // the emitted messages.
+                // Fluid: touched pools are collected into `revert_fluid_touched`
+                // and decoded from post-revert state after the block loop.
+                let to_revert = revert_updates(
+                    &mut exex.journal,
+                    BlockNumHash::new(block_number, block.hash()),
+                    block_timestamp,
+                    receipts,
+                    final_state.as_ref(),
+                    &pool_tracker,
+                    &mut revert_fluid_touched,
+                );
+                for JournaledUpdate {
+                    update: update_msg,
+                    masked,
+                } in to_revert
+                {
+                    record_affected_slot0_pool(&update_msg, &mut affected_slot0_pools);
+                    if settled_by_epilogue(
+                        &update_msg,
+                        &mut affected_v2_pools,
+                        &mut revert_fluid_touched,
+                        &mut affected_state_pools,
+                    ) {
+                        continue;
+                    }
+                    apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
+                    events_reverted +=
+                        exex.send_pool_update_unless_masked(stream_seq, update_msg, masked);
+                }
+
+                // Overflow promotion is drained once after the whole revert loop
+                // (see below): re-scraping from final_state mid-loop, before older
+                // reverted blocks are unapplied, would install the settled tick set
+                // and then double-apply the remaining inverse deltas on top.
+
+                drop(pool_tracker);
+
+                // 🔓 End block — whitelist topology (incl. removed-pool slot
+                // drop) BEFORE the block signal, as in the committed path.
+                exex.end_block_whitelist_topology(block_number).await;
+
+                exex.send_end_block(stream_seq, block_number, events_reverted);
+                exex.shadow_end_block(block_number, base_fee_per_gas, *stream_seq)
+                    .await;
+
+                if events_reverted > 0 {
+                    debug!(
+                        "Block {}: reverted {} liquidity events",
+                        block_number, events_reverted
+                    );
+                }
+            }
+
+            // ── Fluid: decode touched pools from post-revert state ───
+            if !revert_fluid_touched.is_empty() {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                // Provider reflects canonical state after revert
+                let tip_timestamp = old
+                    .blocks()
+                    .values()
+                    .next()
+                    .map(|b| b.timestamp())
+                    .unwrap_or_default();
+                for pool_addr in &revert_fluid_touched {
+                    if let Some(config) = pool_tracker.fluid_config(pool_addr) {
+                        match decode_fluid_pool(final_state.as_ref(), config, tip_timestamp) {
+                            Some(reserves) => {
+                                let update = ReorgEpilogueUpdate::FluidStateFinal {
+                                    pool_id: PoolIdentifier::Address(*pool_addr),
+                                    state: fluid_state_from_reserves(&reserves),
+                                };
+                                apply_epilogue_to_shadow(&mut exex.shadow, &update);
+                                exex.send_reorg_epilogue(
+                                    stream_seq,
+                                    final_tip_block,
+                                    tip_timestamp,
+                                    update,
+                                );
+                                debug!(pool = %pool_addr, "Decoded Fluid reserves post-revert epilogue");
+                            }
+                            None => {
+                                warn!(pool = %pool_addr, "Failed to decode Fluid reserves post-revert");
+                            }
+                        }
+                    }
+                }
+                drop(pool_tracker);
+            }
+
+            let active_v2_pools = {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                active_affected_v2_pools(&pool_tracker, &affected_v2_pools)
+            };
+
+            // Send definitive V2 reserve overrides from the final-tip state snapshot.
+            send_v2_finals(
+                final_state.as_ref(),
+                &active_v2_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                0,
+            );
+
+            // Send definitive slot0 overrides from the final-tip state snapshot.
+            send_slot0_finals(
+                final_state.as_ref(),
+                &affected_slot0_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                0, // No new blocks in ChainReverted
+            );
+            send_state_finals(
+                final_state.as_ref(),
+                &affected_state_pools,
+                exex,
+                stream_seq,
+                final_tip_block,
+                0,
+            );
+            // Drain overflow promotions ONCE, after every reverted block has been
+            // unapplied — re-scrape each overflowed pool from the settled
+            // post-revert tip. Doing it per block above would re-scrape from
+            // final_state before older reverts landed and double-apply on top.
+            {
+                let pool_tracker = exex.pool_tracker.snapshot();
+                promote_overflowed_pools(&mut exex.shadow, &pool_tracker, final_state.as_ref());
+            }
+            // Flush the reorg epilogue writes (slot0/fluid finals + promotions)
+            // into a shadow block signal at the settled tip.
+            exex.finish_reorg(stream_seq, final_tip_block).await;
+
+            info!("✅ Revert handled successfully");
+        }
+    }
+    Ok(())
+}
+
+/// Main ExEx entry point
+async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) -> eyre::Result<()> {
+    info!("🚀 Liquidity ExEx starting");
+
+    // Start Unix socket server
+    let socket_server = PoolUpdateSocketServer::new()?;
     let socket_tx = socket_server.get_sender();
+    let backfill_tx = socket_server.backfill_sender();
+    let released = socket_server.released();
+    health::set_up("liquidity.socket", true);
+    status::watch_socket_clients(socket_server.client_counter());
 
     // Spawn socket server task
-    tokio::spawn(async move {
+    let socket_task = tokio::spawn(async move {
         if let Err(e) = socket_server.run().await {
             warn!("Socket server error: {}", e);
         }
+        health::set_up("liquidity.socket", false);
     });
 
+    // With CONFIRMATION_DEPTH set, the ExEx writes into an internal channel and
+    // a relay task releases each block only once it is `depth` blocks deep.
+    let confirmation_depth = confirmation_buffer::ConfirmationBuffer::depth_from_env();
+    let socket_tx = if confirmation_depth > 0 {
+        let (buffered_tx, mut buffered_rx) =
+            tokio::sync::mpsc::channel::<ControlMessage>(socket_tx.max_capacity());
+        tokio::spawn(async move {
+            let mut buffer = confirmation_buffer::ConfirmationBuffer::new(confirmation_depth);
+            'relay: while let Some(msg) = buffered_rx.recv().await {
+                for released in buffer.push(msg) {
+                    if socket_tx.send(released).await.is_err() {
+                        warn!("Socket channel closed, confirmation buffer stopping");
+                        break 'relay;
+                    }
+                }
+            }
+            // Unconfirmed blocks are never released; a restart replays them
+            // from the checkpoint.
+            let unreleased = buffer.discard_unreleased();
+            if let (Some(first), Some(last)) = (unreleased.first(), unreleased.last()) {
+                warn!(
+                    blocks = unreleased.len(),
+                    first, last, "Confirmation buffer stopped with unreleased blocks"
+                );
+            }
+        });
+        info!(
+            depth = confirmation_depth,
+            "⏳ Confirmation-depth buffering enabled"
+        );
+        buffered_tx
+    } else {
+        socket_tx
+    };
+
     // Open the in-process arena writer. SHADOW_ARENA_PATH → ITE-16 diff harness;
     // SHARED_ARENA_PATH → ITE-20 production sole writer. Disabled (socket-only)
     // when neither is set — the ExEx then behaves exactly as before.
@@ -2866,6 +4837,13 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
 
     // Initialize ExEx state
     let mut exex = LiquidityExEx::new(socket_tx, shadow, curve_notifier);
+    exex.configure_from_env();
+    exex.backfill_tx = Some(backfill_tx);
+    mempool::spawn_from_env(
+        ctx.pool().clone(),
+        exex.pool_tracker.clone(),
+        exex.socket_tx.sender().clone(),
+    );
 
     info!("Socket protocol configured: v2 (cutover, legacy v1 removed)");
 
@@ -2875,7 +4853,7 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
     // Subscribe to NATS for whitelist updates
     let nats_url =
         std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
-    let chain = std::env::var("CHAIN").unwrap_or_else(|_| "ethereum".to_string());
+    let chain = chain::chain_name();
 
     info!("Connecting to NATS at {} for chain {}", nats_url, chain);
     info!("Enforcing whitelist startup barrier before block processing");
@@ -2883,12 +4861,13 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
     // Hard startup barrier:
     // 1) connect NATS
     // 2) subscribe whitelist deltas
-    // 3) request + apply full snapshot
+    // 3) request (request/reply, reseed fallback) + apply full snapshot
     // Only then continue into block processing.
     let nats_client = loop {
         match WhitelistNatsClient::connect(&nats_url).await {
             Ok(client) => {
                 info!("✅ NATS connected successfully");
+                health::watch_nats("liquidity.nats", client.client().clone());
                 break client;
             }
             Err(e) => {
@@ -2898,8 +4877,12 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
         }
     };
 
+    let whitelist_jetstream = JetStreamWhitelist::from_env(&chain);
     let subscriber = loop {
-        match nats_client.subscribe_whitelist(&chain).await {
+        match nats_client
+            .subscribe_whitelist_updates(&chain, whitelist_jetstream.as_ref())
+            .await
+        {
             Ok(subscriber) => {
                 info!(
                     "✅ Subscribed to canonical whitelist updates (.full/.add/.remove) for {}",
@@ -2930,19 +4913,47 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
         }
     };
 
-    // ── Startup: request canonical rich full whitelist snapshot ──────────
-    loop {
-        if let Err(e) = nats_client.request_reseed().await {
-            warn!(error = %e, "Failed to request whitelist reseed, retrying in 2s");
-            tokio::time::sleep(Duration::from_secs(2)).await;
-            continue;
+    // Optional pool-creations DB used to complete address-only whitelist pools.
+    let metadata_db = match pool_metadata_db::PoolMetadataDb::from_env().await {
+        Ok(db) => db.map(Arc::new),
+        Err(e) => {
+            warn!(error = %e, "Pool metadata DB unavailable, whitelist pools used as-is");
+            None
         }
+    };
 
-        match nats_client
-            .next_full_snapshot(&mut full_subscriber, Duration::from_secs(10))
+    // ── Startup: request canonical rich full whitelist snapshot ──────────
+    // Prefer direct request/reply (`whitelist.pools.{chain}.request`); fall
+    // back to reseed + waiting on the `.full` broadcast when no responder
+    // answers (older whitelist_service deployments).
+    loop {
+        let snapshot = match nats_client
+            .request_full_snapshot(&chain, Duration::from_secs(5))
             .await
         {
-            Ok(pools) => {
+            Ok(pools) => Ok(pools),
+            Err(e) => {
+                warn!(error = %e, "Whitelist snapshot request/reply failed, falling back to reseed");
+                if let Err(e) = nats_client.request_reseed().await {
+                    warn!(error = %e, "Failed to request whitelist reseed, retrying in 2s");
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    continue;
+                }
+                nats_client
+                    .next_full_snapshot(&mut full_subscriber, Duration::from_secs(10))
+                    .await
+            }
+        };
+
+        match snapshot {
+            Ok(mut pools) => {
+                let rejected = pool_metadata_db::complete(metadata_db.as_deref(), &mut pools).await;
+                if rejected > 0 {
+                    warn!(
+                        rejected,
+                        "Startup snapshot pools without token addresses not tracked"
+                    );
+                }
                 let pool_count = pools.len();
 
                 if pool_count == 0 {
@@ -3014,6 +5025,60 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
         tokio::time::sleep(Duration::from_secs(2)).await;
     }
 
+    // Runtime admin interface: operators can add/remove/list pools and query
+    // stats over NATS request/reply. Whitelist edits go through the same
+    // block-synchronized queue as canonical deltas.
+    let admin_client = nats_client.clone();
+    let admin_tracker = exex.pool_tracker.clone();
+    let admin_metadata_db = metadata_db.clone();
+    let admin_chain = chain.clone();
+    let admin_rpc_url =
+        std::env::var("RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string());
+    tokio::spawn(async move {
+        let mut admin_sub = match admin_client.subscribe_admin(&admin_chain).await {
+            Ok(sub) => sub,
+            Err(e) => {
+                warn!(error = %e, "Failed to subscribe to admin subject, admin interface disabled");
+                return;
+            }
+        };
+        while let Some(message) = admin_sub.next().await {
+            let Some(reply) = message.reply.clone() else {
+                warn!(subject = %message.subject, "Admin message without reply inbox, ignoring");
+                continue;
+            };
+            let response = match admin::parse_admin_request(&message.payload) {
+                Ok(admin::AdminCommand::Stats) => {
+                    admin::AdminResponse::stats(&admin_tracker.snapshot())
+                }
+                Ok(admin::AdminCommand::ListPools) => {
+                    admin::AdminResponse::list_pools(&admin_tracker.snapshot())
+                }
+                Ok(admin::AdminCommand::Update(mut update)) => {
+                    complete_whitelist_update(admin_metadata_db.as_deref(), &mut update).await;
+                    let count = admin::update_len(&update);
+                    let fluid_addrs = extract_fluid_addresses(&update);
+                    info!(pools = count, "🔧 Admin whitelist update queued");
+                    admin_tracker.write().await.queue_update(update);
+                    if !fluid_addrs.is_empty() {
+                        let pt = admin_tracker.clone();
+                        let rpc = admin_rpc_url.clone();
+                        tokio::spawn(async move {
+                            resolve_fluid_configs(fluid_addrs, &rpc, pt).await;
+                        });
+                    }
+                    admin::AdminResponse::queued(count)
+                }
+                Err(e) => admin::AdminResponse::error(e.to_string()),
+            };
+            let payload = serde_json::to_vec(&response).unwrap_or_default();
+            if let Err(e) = admin_client.respond(reply, payload).await {
+                warn!(error = %e, "Failed to publish admin reply");
+            }
+        }
+        warn!("Admin subscription closed");
+    });
+
     // Spawn task to handle whitelist updates with reconnect.
     let pool_tracker = exex.pool_tracker.clone();
     let chain_for_task = chain.clone();
@@ -3026,8 +5091,12 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
                 // dispatch on the suffix. The legacy `.minimal` (also matched by the
                 // wildcard subscription) returns None and is ignored.
                 let suffix = message.subject.rsplit('.').next().unwrap_or("");
-                match WhitelistNatsClient::canonical_update(suffix, &message.payload) {
-                    Ok(Some(update)) => {
+                let parsed = WhitelistNatsClient::canonical_update(suffix, &message.payload);
+                let processed = parsed.is_ok();
+                match parsed {
+                    Ok(Some(mut update)) => {
+                        complete_whitelist_update(metadata_db.as_deref(), &mut update).await;
+
                         // Extract Fluid pool addresses before queueing
                         let fluid_addrs = extract_fluid_addresses(&update);
                         pool_tracker.write().await.queue_update(update);
@@ -3038,854 +5107,177 @@ async fn liquidity_exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) ->
                             let rpc = rpc_url.clone();
                             tokio::spawn(async move {
                                 resolve_fluid_configs(fluid_addrs, &rpc, pt).await;
-                            });
-                        }
-                    }
-                    Ok(None) => {}
-                    Err(e) => {
-                        warn!("Failed to handle whitelist message: {}", e);
-                    }
-                }
-            }
-
-            // Stream closed — attempt resubscribe with backoff
-            warn!("Whitelist subscription closed, attempting resubscribe");
-            let mut backoff = Duration::from_secs(1);
-            loop {
-                tokio::time::sleep(backoff).await;
-                match nats_client.subscribe_whitelist(&chain_for_task).await {
-                    Ok(new_sub) => {
-                        info!("✅ Whitelist subscription restored");
-                        current_sub = new_sub;
-                        break;
-                    }
-                    Err(e) => {
-                        warn!(error = %e, "Failed to resubscribe, retrying in {:?}", backoff);
-                        backoff = (backoff * 2).min(Duration::from_secs(30));
-                    }
-                }
-            }
-        }
-    });
-
-    // Main event loop: receive notifications from Reth
-    while let Some(notification) = ctx.notifications.try_next().await? {
-        match &notification {
-            ExExNotification::ChainCommitted { new } => {
-                debug!(
-                    "Processing committed chain with {} blocks",
-                    new.blocks().len()
-                );
-
-                // Process each block with block boundaries.
-                for (block, receipts) in new.blocks_and_receipts() {
-                    let block_number = block.number();
-                    let block_timestamp = block.timestamp();
-                    let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
-
-                    // 🔒 Begin block - lock whitelist updates until block completes
-                    {
-                        let mut pool_tracker = exex.pool_tracker.write().await;
-                        pool_tracker.begin_block();
-                    }
-
-                    exex.send_begin_block(
-                        &mut stream_seq,
-                        block_number,
-                        block_timestamp,
-                        base_fee_per_gas,
-                        false,
-                    );
-
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    let state = state_at_block(ctx.provider(), block_number, "ChainCommitted")?;
-                    let mut events_in_block = 0;
-                    let mut logs_checked = 0;
-                    let mut logs_matched_address = 0;
-                    let mut logs_decoded = 0;
-                    let mut fluid_touched: HashSet<Address> = HashSet::new();
-
-                    for (tx_index, receipt) in receipts.iter().enumerate() {
-                        for (log_index, log) in receipt.logs().iter().enumerate() {
-                            let log_address = log.address;
-                            logs_checked += 1;
-
-                            // Quick address filter (includes V2/V3 pools + PoolManager for V4 + Liquidity Layer for Fluid)
-                            if !pool_tracker.is_tracked_address(&log_address) {
-                                continue;
-                            }
-                            logs_matched_address += 1;
-
-                            // For Fluid Liquidity Layer: pre-filter by indexed pool
-                            // address in topics[1] before full ABI decode. The
-                            // Liquidity Layer emits LogOperate for ALL protocols
-                            // (fTokens, Vaults, etc.), not just our tracked DEX pools.
-                            if log_address == pool_tracker::FLUID_LIQUIDITY_LAYER {
-                                match fluid_log_operate_pool(log) {
-                                    Some(pool) if pool_tracker.is_tracked_fluid_pool(&pool) => {
-                                        // Collect touched pool — decode reserves after log loop
-                                        fluid_touched.insert(pool);
-                                        continue;
-                                    }
-                                    _ => continue,
-                                }
-                            }
-
-                            // Decode event
-                            let decoded_event = match decode_log(log) {
-                                Some(event) => {
-                                    logs_decoded += 1;
-                                    event
-                                }
-                                None => continue,
-                            };
-
-                            // Check if we should process this specific event
-                            // For V2/V3: checks pool address
-                            // For V4: checks pool_id from event data (NOT PoolManager address)
-                            if !exex.should_process_event(&decoded_event, &pool_tracker) {
-                                continue;
-                            }
-
-                            // Create and send update
-                            if let Some(update_msg) = exex.create_pool_update(
-                                decoded_event,
-                                block_number,
-                                block_timestamp,
-                                tx_index as u64,
-                                log_index as u64,
-                                false,
-                                state.as_ref(),
-                                &pool_tracker,
-                            ) {
-                                apply_to_shadow(&mut exex.shadow, &update_msg);
-                                exex.send_pool_update(&mut stream_seq, update_msg);
-
-                                events_in_block += 1;
-                                exex.events_processed += 1;
-                            }
-                        }
-                    }
-
-                    // ── Fluid batch decode ───────────────────────────────────
-                    // For each Fluid pool touched in this block, read 8 storage
-                    // slots from the state provider and decode reserves.
-                    for pool_addr in &fluid_touched {
-                        if let Some(config) = pool_tracker.fluid_config(pool_addr) {
-                            match decode_fluid_pool(state.as_ref(), config, block_timestamp) {
-                                Some(reserves) => {
-                                    let update_msg = fluid_update_msg(
-                                        *pool_addr,
-                                        &reserves,
-                                        block_number,
-                                        block_timestamp,
-                                    );
-                                    apply_to_shadow(&mut exex.shadow, &update_msg);
-                                    exex.send_pool_update(&mut stream_seq, update_msg);
-                                    events_in_block += 1;
-                                    exex.events_processed += 1;
-                                    debug!(pool = %pool_addr, "Decoded Fluid reserves from storage");
-                                }
-                                None => {
-                                    warn!(pool = %pool_addr, "Failed to decode Fluid reserves from storage");
-                                }
-                            }
-                        } else {
-                            debug!(pool = %pool_addr, "Fluid pool touched but no config cached — skipping");
-                        }
-                    }
-
-                    // Promote any pools that overflowed their tier this block
-                    // (re-scrape + in-place re-tier) while state + tracker are held.
-                    promote_overflowed_pools(&mut exex.shadow, &pool_tracker, state.as_ref());
-
-                    // Release state/read lock before sending EndBlock and awaiting tracker writes.
-                    drop(state);
-                    drop(pool_tracker);
-
-                    // 🔓 End block — apply pending whitelist updates and drop
-                    // removed pools' arena slots BEFORE this block's EndBlock /
-                    // arena signal, so a reader synchronized on the block signal
-                    // never observes a stale active slot for a de-whitelisted
-                    // pool (see `end_block_whitelist_topology`).
-                    exex.end_block_whitelist_topology(block_number).await;
-
-                    // Hydrate pools added by this block's whitelist `.add` into the
-                    // shadow arena from current state — also before the block
-                    // signal, so the topology a reader rebuilds at this block
-                    // already contains them. Pools that cannot hydrate yet (e.g.
-                    // a Fluid config still resolving) are re-queued for the next
-                    // committed block.
-                    if exex.shadow.is_some() {
-                        let added = exex.pool_tracker.write().await.take_newly_added();
-                        if !added.is_empty() {
-                            match state_at_block(
-                                ctx.provider(),
-                                block_number,
-                                "ChainCommitted live-add hydrate",
-                            ) {
-                                Ok(add_state) => {
-                                    let (batch, unhydrated) = {
-                                        let pool_tracker = exex.pool_tracker.read().await;
-                                        // Drop additions that were removed between the
-                                        // drain and now (a failed add + later remove
-                                        // must not hydrate a stale slot).
-                                        let still_tracked: Vec<PoolMetadata> = added
-                                            .into_iter()
-                                            .filter(|p| pool_tracker.is_tracked(&p.pool_id))
-                                            .collect();
-                                        build_hydration_batch(
-                                            add_state.as_ref(),
-                                            &still_tracked,
-                                            pool_tracker.fluid_configs_map(),
-                                            block_timestamp,
-                                        )
-                                    };
-                                    if !unhydrated.is_empty() {
-                                        exex.pool_tracker
-                                            .write()
-                                            .await
-                                            .requeue_newly_added(unhydrated);
-                                    }
-                                    if let Some(shadow) = exex.shadow.as_mut() {
-                                        if !batch.is_empty() {
-                                            let added_counts = shadow.hydrate_added(&batch);
-                                            info!(
-                                                ?added_counts,
-                                                block_number,
-                                                "shadow arena: hydrated live-added pools"
-                                            );
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!(
-                                        error = %e,
-                                        block_number,
-                                        "live-add shadow hydration: no state; re-queueing"
-                                    );
-                                    exex.pool_tracker.write().await.requeue_newly_added(added);
-                                }
-                            }
-                        }
-                    }
-
-                    // Block signal LAST: the socket EndBlock and the arena block
-                    // signal / arena → curve notification are emitted only after
-                    // this block's whitelist topology (removals + additions) has
-                    // landed, so readers synchronized on them see one coherent
-                    // post-block topology.
-                    exex.send_end_block(&mut stream_seq, block_number, events_in_block);
-                    exex.shadow_end_block(block_number, base_fee_per_gas, stream_seq)
-                        .await;
-
-                    if events_in_block > 0 {
-                        info!(
-                            "Block {}: processed {} liquidity events",
-                            block_number, events_in_block
-                        );
-                    }
-
-                    // Debug logging every block for now
-                    if logs_checked > 0 || events_in_block > 0 {
-                        info!(
-                            "🔍 Block {}: checked {} logs, {} matched address, {} decoded, {} events",
-                            block_number, logs_checked, logs_matched_address, logs_decoded, events_in_block
-                        );
-                    }
-
-                    exex.blocks_processed += 1;
-
-                    // Log stats every 100 blocks
-                    if exex.blocks_processed % 100 == 0 {
-                        info!(
-                            "Stats: {} blocks, {} events processed",
-                            exex.blocks_processed, exex.events_processed
-                        );
-
-                        let pool_tracker = exex.pool_tracker.read().await;
-                        let stats = pool_tracker.stats();
-                        info!(
-                            "Tracking: {} pools ({} V2, {} V3, {} V4)",
-                            stats.total_pools, stats.v2_pools, stats.v3_pools, stats.v4_pools
-                        );
-
-                        if stats.total_pools == 0 {
-                            warn!("⚠️  No pools in whitelist! Events will be filtered out.");
-                            warn!("   Check that NATS whitelist updates are being received.");
-                        }
-                    }
-                }
-            }
-
-            ExExNotification::ChainReorged { old, new } => {
-                warn!(
-                    "⚠️  Chain reorg detected: reverting {} old blocks, applying {} new blocks",
-                    old.blocks().len(),
-                    new.blocks().len()
-                );
-
-                let old_range = block_range_summary_from_numbers(old.blocks().keys().copied());
-                let new_range = block_range_summary_from_numbers(new.blocks().keys().copied());
-                let final_tip_block = new_range
-                    .last_block
-                    .or(old_range.last_block)
-                    .unwrap_or_default();
-
-                exex.send_reorg_start(&mut stream_seq, old_range.clone(), new_range.clone());
-
-                let mut affected_slot0_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
-                let mut affected_v2_pools = HashSet::<Address>::new();
-                let mut reorg_fluid_touched = HashSet::<Address>::new();
-
-                // Step 1: Revert old blocks
-                info!("Step 1: Reverting {} old blocks", old.blocks().len());
-                // Revert in reverse execution order: newest old block first (and,
-                // below, newest tx/log first) so inverse tick-liquidity ops
-                // un-apply in the exact reverse of how they were applied.
-                // Otherwise reverting an earlier mint before the later burn that
-                // zeroed the tick wraps `gross` through `as u128`.
-                let mut reverted_blocks: Vec<_> = old.blocks_and_receipts().collect();
-                reverted_blocks.reverse();
-                for (block, receipts) in reverted_blocks {
-                    let block_number = block.number();
-                    let block_timestamp = block.timestamp();
-                    let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
-
-                    // 🔒 Begin block
-                    {
-                        let mut pool_tracker = exex.pool_tracker.write().await;
-                        pool_tracker.begin_block();
-                    }
-
-                    exex.send_begin_block(
-                        &mut stream_seq,
-                        block_number,
-                        block_timestamp,
-                        base_fee_per_gas,
-                        true,
-                    );
-
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    // Reth exposes canonical post-reorg state here, not old-fork state.
-                    // Absolute full-state revert messages therefore use this final-tip
-                    // snapshot; reorg epilogues below remain the definitive recovery path.
-                    let state =
-                        state_at_block(ctx.provider(), final_tip_block, "ChainReorged revert")?;
-                    let mut events_reverted = 0;
-
-                    // Reverse tx/log order, keeping the original tx/log indexes in
-                    // the emitted messages.
-                    for (tx_index, receipt) in receipts.iter().enumerate().rev() {
-                        for (log_index, log) in receipt.logs().iter().enumerate().rev() {
-                            let log_address = log.address;
-
-                            // Fluid: collect touched pools — will decode from
-                            // post-reorg state after Step 2 (or after new-block
-                            // processing removes them from the set).
-                            if log_address == pool_tracker::FLUID_LIQUIDITY_LAYER {
-                                if let Some(pool) = fluid_log_operate_pool(log) {
-                                    if pool_tracker.is_tracked_fluid_pool(&pool) {
-                                        reorg_fluid_touched.insert(pool);
-                                    }
-                                }
-                                continue;
-                            }
-
-                            // Quick address filter (includes V2/V3 pools + PoolManager for V4)
-                            if !pool_tracker.is_tracked_address(&log_address) {
-                                continue;
-                            }
-
-                            // Decode event first
-                            let decoded_event = match decode_log(log) {
-                                Some(event) => event,
-                                None => continue,
-                            };
-
-                            // Check if we should process this specific event
-                            // For V2/V3: checks pool address
-                            // For V4: checks pool_id from event data (NOT PoolManager address)
-                            if !exex.should_process_event(&decoded_event, &pool_tracker) {
-                                continue;
-                            }
-
-                            record_affected_v2_pool(&decoded_event, &mut affected_v2_pools);
-
-                            // Create and send revert update
-                            if let Some(update_msg) = exex.create_pool_update(
-                                decoded_event,
-                                block_number,
-                                block_timestamp,
-                                tx_index as u64,
-                                log_index as u64,
-                                true,
-                                state.as_ref(),
-                                &pool_tracker,
-                            ) {
-                                record_affected_slot0_pool(&update_msg, &mut affected_slot0_pools);
-                                apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
-                                exex.send_pool_update(&mut stream_seq, update_msg);
-
-                                events_reverted += 1;
-                            }
-                        }
-                    }
-
-                    // Overflow promotion is NOT drained here: this loop re-scrapes
-                    // from the FINAL canonical tip, and Step 2 still applies the
-                    // new-chain deltas afterward. Re-scraping a pool now and then
-                    // applying more deltas would double-apply. The pending set is
-                    // accumulated across the whole reorg and drained once, from
-                    // final_state, after every delta has landed (see below).
-
-                    drop(state);
-                    drop(pool_tracker);
-
-                    // 🔓 End block — whitelist topology (incl. removed-pool slot
-                    // drop) BEFORE the block signal, as in the committed path.
-                    exex.end_block_whitelist_topology(block_number).await;
-
-                    exex.send_end_block(&mut stream_seq, block_number, events_reverted);
-                    exex.shadow_end_block(block_number, base_fee_per_gas, stream_seq)
-                        .await;
-
-                    if events_reverted > 0 {
-                        debug!(
-                            "Block {}: reverted {} liquidity events",
-                            block_number, events_reverted
-                        );
-                    }
-                }
-
-                // Step 2: Process new blocks (same as ChainCommitted, with Fluid batch decode).
-                info!("Step 2: Processing {} new blocks", new.blocks().len());
-                for (block, receipts) in new.blocks_and_receipts() {
-                    let block_number = block.number();
-                    let block_timestamp = block.timestamp();
-                    let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
-
-                    // 🔒 Begin block
-                    {
-                        let mut pool_tracker = exex.pool_tracker.write().await;
-                        pool_tracker.begin_block();
-                    }
-
-                    exex.send_begin_block(
-                        &mut stream_seq,
-                        block_number,
-                        block_timestamp,
-                        base_fee_per_gas,
-                        false,
-                    );
-
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    let state = state_at_block(ctx.provider(), block_number, "ChainReorged apply")?;
-                    let mut events_in_block = 0;
-                    let mut fluid_touched = HashSet::<Address>::new();
-
-                    for (tx_index, receipt) in receipts.iter().enumerate() {
-                        for (log_index, log) in receipt.logs().iter().enumerate() {
-                            let log_address = log.address;
-
-                            // Fluid Liquidity Layer: pre-filter + collect touched pools
-                            if log_address == pool_tracker::FLUID_LIQUIDITY_LAYER {
-                                if let Some(pool) = fluid_log_operate_pool(log) {
-                                    if pool_tracker.is_tracked_fluid_pool(&pool) {
-                                        fluid_touched.insert(pool);
-                                    }
-                                }
-                                continue;
-                            }
-
-                            // Quick address filter (includes V2/V3 pools + PoolManager for V4)
-                            if !pool_tracker.is_tracked_address(&log_address) {
-                                continue;
-                            }
-
-                            // Decode event first
-                            let decoded_event = match decode_log(log) {
-                                Some(event) => event,
-                                None => continue,
-                            };
-
-                            // Check if we should process this specific event
-                            if !exex.should_process_event(&decoded_event, &pool_tracker) {
-                                continue;
-                            }
-
-                            // Create and send update
-                            if let Some(update_msg) = exex.create_pool_update(
-                                decoded_event,
-                                block_number,
-                                block_timestamp,
-                                tx_index as u64,
-                                log_index as u64,
-                                false,
-                                state.as_ref(),
-                                &pool_tracker,
-                            ) {
-                                apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
-                                exex.send_pool_update(&mut stream_seq, update_msg);
-
-                                events_in_block += 1;
-                                exex.events_processed += 1;
-                            }
-                        }
-                    }
-
-                    // ── Fluid batch decode (same as ChainCommitted) ──────────
-                    for pool_addr in &fluid_touched {
-                        if let Some(config) = pool_tracker.fluid_config(pool_addr) {
-                            match decode_fluid_pool(state.as_ref(), config, block_timestamp) {
-                                Some(reserves) => {
-                                    let update_msg = fluid_update_msg(
-                                        *pool_addr,
-                                        &reserves,
-                                        block_number,
-                                        block_timestamp,
-                                    );
-                                    apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
-                                    exex.send_pool_update(&mut stream_seq, update_msg);
-                                    events_in_block += 1;
-                                    exex.events_processed += 1;
-                                }
-                                None => {
-                                    warn!(pool = %pool_addr, "Failed to decode Fluid reserves during reorg reapply");
-                                }
-                            }
-                        }
-                        // Pool handled in new chain — don't re-decode after Step 2
-                        reorg_fluid_touched.remove(pool_addr);
-                    }
-
-                    // Overflow promotion is drained once at the end of the reorg
-                    // from final_state, not per new block — see Step 1's note and
-                    // the post-Step-2 drain below.
-
-                    drop(state);
-                    drop(pool_tracker);
-
-                    // 🔓 End block — whitelist topology (incl. removed-pool slot
-                    // drop) BEFORE the block signal, as in the committed path.
-                    exex.end_block_whitelist_topology(block_number).await;
-
-                    exex.send_end_block(&mut stream_seq, block_number, events_in_block);
-                    exex.shadow_end_block(block_number, base_fee_per_gas, stream_seq)
-                        .await;
-
-                    if events_in_block > 0 {
-                        debug!(
-                            "Block {}: processed {} liquidity events",
-                            block_number, events_in_block
-                        );
-                    }
-
-                    exex.blocks_processed += 1;
-                }
-
-                let final_state =
-                    state_at_block(ctx.provider(), final_tip_block, "ChainReorged final")?;
-
-                // ── Fluid: decode pools touched in old blocks but not new ──
-                if !reorg_fluid_touched.is_empty() {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    let tip_timestamp = new
-                        .blocks()
-                        .values()
-                        .last()
-                        .map(|b| b.timestamp())
-                        .unwrap_or_default();
-                    for pool_addr in &reorg_fluid_touched {
-                        if let Some(config) = pool_tracker.fluid_config(pool_addr) {
-                            match decode_fluid_pool(final_state.as_ref(), config, tip_timestamp) {
-                                Some(reserves) => {
-                                    let update = ReorgEpilogueUpdate::FluidStateFinal {
-                                        pool_id: PoolIdentifier::Address(*pool_addr),
-                                        state: fluid_state_from_reserves(&reserves),
-                                    };
-                                    apply_epilogue_to_shadow(&mut exex.shadow, &update);
-                                    exex.send_reorg_epilogue(
-                                        &mut stream_seq,
-                                        final_tip_block,
-                                        tip_timestamp,
-                                        update,
-                                    );
-                                    debug!(pool = %pool_addr, "Decoded Fluid reserves post-reorg epilogue (not in new chain)");
-                                }
-                                None => {
-                                    warn!(pool = %pool_addr, "Failed to decode Fluid reserves post-reorg");
-                                }
-                            }
-                        }
-                    }
-                    drop(pool_tracker);
-                }
-
-                let final_tip_timestamp = new
-                    .blocks()
-                    .values()
-                    .last()
-                    .map(|b| b.timestamp())
-                    .unwrap_or(0);
-
-                let active_v2_pools = {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    active_affected_v2_pools(&pool_tracker, &affected_v2_pools)
-                };
-
-                // Send definitive V2 reserve overrides from the final-tip state snapshot.
-                send_v2_finals(
-                    final_state.as_ref(),
-                    &active_v2_pools,
-                    &mut exex,
-                    &mut stream_seq,
-                    final_tip_block,
-                    final_tip_timestamp,
-                );
-
-                // Send definitive slot0 overrides from the final-tip state snapshot.
-                send_slot0_finals(
-                    final_state.as_ref(),
-                    &affected_slot0_pools,
-                    &mut exex,
-                    &mut stream_seq,
-                    final_tip_block,
-                    final_tip_timestamp,
-                );
-                // Drain overflow promotions ONCE, now that every revert + new-chain
-                // delta has landed. Re-scraping each overflowed pool from the settled
-                // final-tip state and replacing its slot is authoritative because no
-                // further deltas are applied after this point (only the epilogue
-                // signal). Doing it per block above would re-scrape from a snapshot
-                // that later deltas then double-apply on top of.
-                {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    promote_overflowed_pools(&mut exex.shadow, &pool_tracker, final_state.as_ref());
-                }
-                // Flush the reorg epilogue writes (slot0/fluid finals + promotions)
-                // into a shadow block signal at the settled tip.
-                exex.finish_reorg(&mut stream_seq, final_tip_block).await;
-
-                info!("✅ Reorg handled successfully");
-            }
-
-            ExExNotification::ChainReverted { old } => {
-                warn!(
-                    "⚠️  Chain reverted: reverting {} blocks",
-                    old.blocks().len()
-                );
-
-                let old_range = block_range_summary_from_numbers(old.blocks().keys().copied());
-                let final_tip_block = old_range
-                    .first_block
-                    .map(|n| n.saturating_sub(1))
-                    .unwrap_or_default();
-
-                exex.send_reorg_start(
-                    &mut stream_seq,
-                    old_range.clone(),
-                    ReorgRange {
-                        first_block: None,
-                        last_block: None,
-                        block_count: 0,
-                    },
-                );
-
-                let mut affected_slot0_pools: HashSet<(PoolIdentifier, Protocol)> = HashSet::new();
-                let mut affected_v2_pools = HashSet::<Address>::new();
-                let mut revert_fluid_touched = HashSet::<Address>::new();
-                // Reth exposes canonical post-revert state here, not the reverted-away
-                // old blocks' state. Absolute full-state revert messages and final
-                // epilogues both read this one final-tip snapshot.
-                let final_state =
-                    state_at_block(ctx.provider(), final_tip_block, "ChainReverted final")?;
-
-                // Revert in reverse execution order (see ChainReorged Step 1): newest
-                // old block first, and newest tx/log first within each block.
-                let mut reverted_blocks: Vec<_> = old.blocks_and_receipts().collect();
-                reverted_blocks.reverse();
-                for (block, receipts) in reverted_blocks {
-                    let block_number = block.number();
-                    let block_timestamp = block.timestamp();
-                    let base_fee_per_gas = block.base_fee_per_gas().unwrap_or(0);
-
-                    // 🔒 Begin block
-                    {
-                        let mut pool_tracker = exex.pool_tracker.write().await;
-                        pool_tracker.begin_block();
-                    }
-
-                    exex.send_begin_block(
-                        &mut stream_seq,
-                        block_number,
-                        block_timestamp,
-                        base_fee_per_gas,
-                        true,
-                    );
-
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    let mut events_reverted = 0;
-
-                    // Reverse tx/log order, keeping the original tx/log indexes in
-                    // the emitted messages.
-                    for (tx_index, receipt) in receipts.iter().enumerate().rev() {
-                        for (log_index, log) in receipt.logs().iter().enumerate().rev() {
-                            let log_address = log.address;
-
-                            // Fluid: collect touched pools — decode from
-                            // post-revert state after the block loop.
-                            if log_address == pool_tracker::FLUID_LIQUIDITY_LAYER {
-                                if let Some(pool) = fluid_log_operate_pool(log) {
-                                    if pool_tracker.is_tracked_fluid_pool(&pool) {
-                                        revert_fluid_touched.insert(pool);
-                                    }
-                                }
-                                continue;
-                            }
-
-                            if !pool_tracker.is_tracked_address(&log_address) {
-                                continue;
-                            }
-
-                            let decoded_event = match decode_log(log) {
-                                Some(event) => event,
-                                None => continue,
-                            };
-
-                            // Filter by pool_id for V4 (same as Committed/Reorged paths)
-                            if !exex.should_process_event(&decoded_event, &pool_tracker) {
-                                continue;
-                            }
-
-                            record_affected_v2_pool(&decoded_event, &mut affected_v2_pools);
-
-                            if let Some(update_msg) = exex.create_pool_update(
-                                decoded_event,
-                                block_number,
-                                block_timestamp,
-                                tx_index as u64,
-                                log_index as u64,
-                                true,
-                                final_state.as_ref(),
-                                &pool_tracker,
-                            ) {
-                                record_affected_slot0_pool(&update_msg, &mut affected_slot0_pools);
-                                apply_reorg_to_shadow(&mut exex.shadow, &update_msg);
-                                exex.send_pool_update(&mut stream_seq, update_msg);
-
-                                events_reverted += 1;
-                            }
-                        }
-                    }
-
-                    // Overflow promotion is drained once after the whole revert loop
-                    // (see below): re-scraping from final_state mid-loop, before older
-                    // reverted blocks are unapplied, would install the settled tick set
-                    // and then double-apply the remaining inverse deltas on top.
-
-                    drop(pool_tracker);
-
-                    // 🔓 End block — whitelist topology (incl. removed-pool slot
-                    // drop) BEFORE the block signal, as in the committed path.
-                    exex.end_block_whitelist_topology(block_number).await;
-
-                    exex.send_end_block(&mut stream_seq, block_number, events_reverted);
-                    exex.shadow_end_block(block_number, base_fee_per_gas, stream_seq)
-                        .await;
-
-                    if events_reverted > 0 {
-                        debug!(
-                            "Block {}: reverted {} liquidity events",
-                            block_number, events_reverted
-                        );
+                            });
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("Failed to handle whitelist message: {}", e);
                     }
                 }
+                // A queued delta is safe to ack: the queue is applied at the
+                // next block boundary.
+                message.ack(processed).await;
+            }
 
-                // ── Fluid: decode touched pools from post-revert state ───
-                if !revert_fluid_touched.is_empty() {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    // Provider reflects canonical state after revert
-                    let tip_timestamp = old
-                        .blocks()
-                        .values()
-                        .next()
-                        .map(|b| b.timestamp())
-                        .unwrap_or_default();
-                    for pool_addr in &revert_fluid_touched {
-                        if let Some(config) = pool_tracker.fluid_config(pool_addr) {
-                            match decode_fluid_pool(final_state.as_ref(), config, tip_timestamp) {
-                                Some(reserves) => {
-                                    let update = ReorgEpilogueUpdate::FluidStateFinal {
-                                        pool_id: PoolIdentifier::Address(*pool_addr),
-                                        state: fluid_state_from_reserves(&reserves),
-                                    };
-                                    apply_epilogue_to_shadow(&mut exex.shadow, &update);
-                                    exex.send_reorg_epilogue(
-                                        &mut stream_seq,
-                                        final_tip_block,
-                                        tip_timestamp,
-                                        update,
-                                    );
-                                    debug!(pool = %pool_addr, "Decoded Fluid reserves post-revert epilogue");
-                                }
-                                None => {
-                                    warn!(pool = %pool_addr, "Failed to decode Fluid reserves post-revert");
-                                }
-                            }
-                        }
+            // Stream closed — attempt resubscribe with backoff
+            warn!("Whitelist subscription closed, attempting resubscribe");
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                tokio::time::sleep(backoff).await;
+                match nats_client
+                    .subscribe_whitelist_updates(&chain_for_task, whitelist_jetstream.as_ref())
+                    .await
+                {
+                    Ok(new_sub) => {
+                        info!("✅ Whitelist subscription restored");
+                        current_sub = new_sub;
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Failed to resubscribe, retrying in {:?}", backoff);
+                        backoff = (backoff * 2).min(Duration::from_secs(30));
                     }
-                    drop(pool_tracker);
                 }
+            }
+        }
+    });
 
-                let active_v2_pools = {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    active_affected_v2_pools(&pool_tracker, &affected_v2_pools)
-                };
+    // Resume from the last fully emitted block: reth replays everything between
+    // it and the node head before live notifications, so a restart no longer
+    // silently skips the blocks processed while the ExEx was down.
+    let checkpoint_path = checkpoint::checkpoint_path(ctx.config.datadir().data_dir());
+    match checkpoint::Checkpoint::load(&checkpoint_path) {
+        Ok(Some(cp)) => {
+            info!(
+                block = cp.block_number,
+                hash = %cp.block_hash,
+                "⏮️ Resuming from emitted-block checkpoint"
+            );
+            exex.resume = checkpoint::ResumeDedupe::new(Some(&cp));
+            ctx.notifications
+                .set_with_head(ExExHead::new(BlockNumHash::new(
+                    cp.block_number,
+                    cp.block_hash,
+                )));
+        }
+        Ok(None) => {
+            info!(path = %checkpoint_path.display(), "No checkpoint, starting at node head")
+        }
+        Err(e) => warn!(
+            path = %checkpoint_path.display(),
+            "Unreadable checkpoint, starting at node head: {}", e
+        ),
+    }
 
-                // Send definitive V2 reserve overrides from the final-tip state snapshot.
-                send_v2_finals(
-                    final_state.as_ref(),
-                    &active_v2_pools,
-                    &mut exex,
-                    &mut stream_seq,
-                    final_tip_block,
-                    0,
-                );
+    // Main event loop: receive notifications from Reth until the node shuts
+    // down; a notification in progress always completes.
+    let mut finished = finished_height::FinishedHeightBatcher::from_env([SOCKET_SINK]);
+    let mut checkpointed = None;
+    let mut shutdown = shutdown::ShutdownSignal::new("liquidity", ctx.task_executor());
+    while let Some(notification) = shutdown.next(&mut ctx.notifications).await? {
+        // Re-execute the committed blocks for logless-pool swaps first; the
+        // block loops below pick them up by number.
+        if let (Some(tracer), Some(chain)) = (&exex.swap_tracer, notification.committed_chain()) {
+            let tracker = exex.pool_tracker.snapshot();
+            exex.traced_swaps =
+                tracer.trace_chain::<Node>(ctx.provider(), ctx.evm_config(), &chain, &tracker);
+        }
+        if let Some(chain) = notification.committed_chain() {
+            let tracker = exex.pool_tracker.snapshot();
+            exex.invalidated_pools =
+                pool_invalidation::invalidated_pools(ctx.provider(), &chain, &tracker);
+        }
+        handle_notification(&mut exex, ctx.provider(), &mut stream_seq, &notification).await?;
+        // Frames held back by `SOCKET_OVERFLOW=spill` go out before the
+        // notification is checkpointed; this is where the socket pushes back.
+        if let Err(e) = exex.socket_tx.drain().await {
+            warn!("Socket closed with frames held back: {}", e);
+        }
 
-                // Send definitive slot0 overrides from the final-tip state snapshot.
-                send_slot0_finals(
-                    final_state.as_ref(),
-                    &affected_slot0_pools,
-                    &mut exex,
-                    &mut stream_seq,
-                    final_tip_block,
-                    0, // No new blocks in ChainReverted
-                );
-                // Drain overflow promotions ONCE, after every reverted block has been
-                // unapplied — re-scrape each overflowed pool from the settled
-                // post-revert tip. Doing it per block above would re-scrape from
-                // final_state before older reverts landed and double-apply on top.
-                {
-                    let pool_tracker = exex.pool_tracker.read().await;
-                    promote_overflowed_pools(&mut exex.shadow, &pool_tracker, final_state.as_ref());
+        // Announce finality; the committed tip is acknowledged to reth once
+        // it is checkpointed.
+        if let Some(committed_chain) = notification.committed_chain() {
+            match ctx.provider().finalized_block_number() {
+                Ok(Some(finalized)) => {
+                    exex.send_finalized(finalized, committed_chain.tip().number())
                 }
-                // Flush the reorg epilogue writes (slot0/fluid finals + promotions)
-                // into a shadow block signal at the settled tip.
-                exex.finish_reorg(&mut stream_seq, final_tip_block).await;
-
-                info!("✅ Revert handled successfully");
+                Ok(None) => {}
+                Err(e) => debug!("Failed to read finalized block number: {}", e),
             }
         }
+        if let Some(old) = notification.reverted_chain() {
+            finished.revert_from(old.first().number());
+        }
+        if let Some(new) = notification.committed_chain() {
+            finished.commit([new.tip().num_hash()]);
+        }
 
-        // Notify Reth that we've processed this notification
-        if let Some(committed_chain) = notification.committed_chain() {
-            ctx.events
-                .send(ExExEvent::FinishedHeight(committed_chain.tip().num_hash()))?;
+        // Checkpoint the last block the socket writer received whole; with
+        // CONFIRMATION_DEPTH it trails the notification by the depth.
+        checkpoint_released(
+            &released,
+            &mut checkpointed,
+            &checkpoint_path,
+            &mut finished,
+        );
+        if let Some(height) = finished.poll(Instant::now()) {
+            ctx.events.send(ExExEvent::FinishedHeight(height))?;
         }
     }
 
-    Ok(())
+    // Every envelope sent so far is complete. Tell clients we are going away,
+    // then let the socket server drain their queues (behind any
+    // confirmation-buffer relay) before the final checkpoint.
+    if exex.socket_tx.send(ControlMessage::Shutdown).await.is_ok() {
+        match tokio::time::timeout(shutdown::FLUSH_TIMEOUT, socket_task).await {
+            Ok(_) => info!("Socket drained"),
+            Err(_) => warn!("Socket not drained within {:?}", shutdown::FLUSH_TIMEOUT),
+        }
+    }
+    health::set_up("liquidity.socket", false);
+    checkpoint_released(
+        &released,
+        &mut checkpointed,
+        &checkpoint_path,
+        &mut finished,
+    );
+    if let Some(height) = finished.flush() {
+        ctx.events.send(ExExEvent::FinishedHeight(height))?;
+    }
+
+    shutdown.finish().await
+}
+
+/// `FinishedHeight` sink of the socket writer's released blocks.
+const SOCKET_SINK: &str = "socket";
+
+/// Persist the socket writer's released block if it moved since the last
+/// checkpoint. The stream is resumed from the checkpoint: only a checkpointed
+/// block is finished.
+fn checkpoint_released(
+    released: &tokio::sync::watch::Receiver<Option<BlockNumHash>>,
+    checkpointed: &mut Option<BlockNumHash>,
+    path: &std::path::Path,
+    finished: &mut finished_height::FinishedHeightBatcher,
+) {
+    let Some(tip) = *released.borrow() else {
+        return;
+    };
+    if *checkpointed == Some(tip) {
+        return;
+    }
+    let cp = checkpoint::Checkpoint {
+        block_number: tip.number,
+        block_hash: tip.hash,
+    };
+    match cp.save(path) {
+        Ok(()) => {
+            *checkpointed = Some(tip);
+            finished.set_durable(SOCKET_SINK, tip.number);
+        }
+        Err(e) => warn!(block = tip.number, "Failed to write checkpoint: {}", e),
+    }
 }
 
 #[inline]
@@ -3912,9 +5304,10 @@ fn fluid_update_msg(
     block_timestamp: u64,
 ) -> PoolUpdateMessage {
     PoolUpdateMessage {
+        chain_id: chain::active().chain_id,
         pool_id: PoolIdentifier::Address(pool_addr),
         protocol: Protocol::Fluid,
-        update_type: UpdateType::Swap,
+        update_type: UpdateType::Sync,
         block_number,
         block_timestamp,
         tx_index: 0,
@@ -3930,7 +5323,9 @@ fn fluid_update_msg(
 fn extract_fluid_addresses(update: &pool_tracker::WhitelistUpdate) -> Vec<Address> {
     let pools = match update {
         pool_tracker::WhitelistUpdate::Add(p) | pool_tracker::WhitelistUpdate::Replace(p) => p,
-        pool_tracker::WhitelistUpdate::Remove(_) => return vec![],
+        pool_tracker::WhitelistUpdate::Remove(_)
+        | pool_tracker::WhitelistUpdate::Blacklist(_)
+        | pool_tracker::WhitelistUpdate::Unblacklist(_) => return vec![],
     };
     pools
         .iter()
@@ -3961,7 +5356,7 @@ async fn resolve_fluid_config_batch(addrs: Vec<Address>, rpc_url: &str) -> Vec<F
 async fn resolve_fluid_configs(
     addrs: Vec<Address>,
     rpc_url: &str,
-    pool_tracker: Arc<RwLock<PoolTracker>>,
+    pool_tracker: Arc<SharedPoolTracker>,
 ) {
     let configs = resolve_fluid_config_batch(addrs, rpc_url).await;
     let mut tracker = pool_tracker.write().await;
@@ -4019,15 +5414,128 @@ where
     }
 }
 
+/// ExEx subsystems that can be installed on the node.
+///
+/// Swap monitoring has no toggle of its own: it runs inside the balance
+/// monitor's block pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ExExKind {
+    Liquidity,
+    Transfers,
+    Balance,
+    Fees,
+}
+
+/// Extra CLI args selecting which ExExes share this node.
+#[derive(Debug, Clone, clap::Args)]
+struct ExExArgs {
+    /// Comma-separated ExExes to install, e.g. `--exex liquidity,transfers`.
+    #[arg(
+        long = "exex",
+        env = "EXEX_ENABLED",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "liquidity,balance"
+    )]
+    exex: Vec<ExExKind>,
+}
+
+impl ExExArgs {
+    fn enabled(&self, kind: ExExKind) -> bool {
+        self.exex.contains(&kind)
+    }
+}
+
+/// OP-stack node args plus the ExEx selection.
+#[cfg(feature = "optimism")]
+#[derive(Debug, Clone, clap::Args)]
+struct OpExExArgs {
+    #[command(flatten)]
+    rollup: reth_optimism_node::args::RollupArgs,
+    #[command(flatten)]
+    exex: ExExArgs,
+}
+
+/// Install the selected ExExes on a node builder. A macro because the
+/// Ethereum and OP builders share no nameable type; the ExExes themselves are
+/// generic over the node.
+macro_rules! install_exexes {
+    ($node_builder:expr, $args:expr) => {{
+        let args = $args;
+        info!(exex = ?args.exex, "Installing ExExes");
+        let transfers = args.enabled(ExExKind::Transfers);
+        let fees = args.enabled(ExExKind::Fees);
+        let balance = args.enabled(ExExKind::Balance);
+        // Filled by the balance handlers of the Dispatch ExEx, read by the
+        // BalanceMonitor ExEx.
+        let scans = std::sync::Arc::new(balance_monitor::scan::ScanStore::default());
+        let balance_scans = scans.clone();
+        $node_builder
+            .install_exex_if(
+                args.enabled(ExExKind::Liquidity),
+                "Liquidity",
+                async move |ctx| Ok(liquidity_exex(ctx)),
+            )
+            // Log-only subsystems share one walk per block as handlers of
+            // the Dispatch ExEx.
+            .install_exex_if(
+                transfers || fees || balance,
+                "Dispatch",
+                async move |ctx| {
+                    let mut dispatcher = dispatch::Dispatcher::default();
+                    // First, so the BalanceMonitor ExEx is not held up by
+                    // the other handlers' flushes.
+                    if balance {
+                        dispatcher.register(balance_monitor::scan::balance_scan_handler(
+                            scans.clone(),
+                        )?);
+                        dispatcher.register(swap_monitor::swap_monitor_handler(scans.clone())?);
+                    }
+                    if transfers {
+                        dispatcher.register(transfers::transfers_handler(&ctx).await?);
+                    }
+                    if fees {
+                        dispatcher.register(block_fees::block_fees_handler().await?);
+                    }
+                    Ok(dispatch::dispatch_exex(ctx, dispatcher))
+                },
+            )
+            .install_exex_if(
+                balance,
+                "BalanceMonitor",
+                async move |ctx| {
+                    Ok(balance_monitor::balance_monitor_exex(
+                        ctx,
+                        balance_scans.clone(),
+                    ))
+                },
+            )
+    }};
+}
+
+/// Steps shared by both entrypoints that must run before the CLI is parsed.
+fn pre_cli_setup() -> eyre::Result<()> {
+    // Config file settings become env vars, so this must precede CLI parsing
+    // (`--exex` falls back to EXEX_ENABLED) and every spawned thread.
+    if let Some(path) = std::env::var_os("EXEX_CONFIG") {
+        config::ExExConfig::load(path)?.apply_env();
+    }
+    exex_metrics::describe();
+    Ok(())
+}
+
+#[cfg(not(feature = "optimism"))]
 fn main() -> eyre::Result<()> {
-    reth::cli::Cli::parse_args().run(|builder, _| async move {
-        let handle = builder
-            .node(EthereumNode::default())
-            .install_exex("Liquidity", async move |ctx| Ok(liquidity_exex(ctx)))
-            // .install_exex("Transfers", async move |ctx| Ok(transfers::transfers_exex(ctx)))
-            .install_exex("BalanceMonitor", async move |ctx| {
-                Ok(balance_monitor::balance_monitor_exex(ctx))
-            })
+    pre_cli_setup()?;
+    // `exex replay ...` runs the pipeline over historical blocks, no node.
+    if std::env::args().nth(1).as_deref() == Some("replay") {
+        return replay::main(std::env::args().skip(1));
+    }
+    reth::cli::Cli::<EthereumChainSpecParser, ExExArgs>::parse().run(|builder, args| async move {
+        chain::init(builder.config().chain.chain().id());
+        health::spawn_from_env();
+        status::spawn_from_env();
+        let handle = install_exexes!(builder.node(EthereumNode::default()), args)
             .launch()
             .await?;
 
@@ -4035,6 +5543,27 @@ fn main() -> eyre::Result<()> {
     })
 }
 
+/// OP-stack (OP Mainnet, Base) entrypoint: `cargo build --features optimism`.
+#[cfg(feature = "optimism")]
+fn main() -> eyre::Result<()> {
+    use reth_optimism_cli::chainspec::OpChainSpecParser;
+    use reth_optimism_node::OpNode;
+
+    pre_cli_setup()?;
+    reth_optimism_cli::Cli::<OpChainSpecParser, OpExExArgs>::parse().run(
+        |builder, args| async move {
+            chain::init(builder.config().chain.chain().id());
+            health::spawn_from_env();
+            status::spawn_from_env();
+            let handle = install_exexes!(builder.node(OpNode::new(args.rollup)), args.exex)
+                .launch()
+                .await?;
+
+            handle.wait_for_node_exit().await
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
@@ -4125,6 +5654,7 @@ mod tests {
                 balancer_weights: None,
                 balancer_swap_fee: None,
                 balancer_version: None,
+                event_mask: None,
             }
         }
 
@@ -4144,6 +5674,183 @@ mod tests {
         assert_eq!(active, HashSet::from([v2]));
     }
 
+    /// Per-pool event mask: a `swaps_only` pool withholds liquidity event
+    /// frames but keeps swaps; unmasked state-sync events (V2 `Sync`) always
+    /// pass. Masking never removes a tracked pool's event from processing.
+    #[test]
+    fn event_mask_withholds_frames_of_tracked_pools() {
+        use crate::events::DecodedEvent;
+        use crate::pool_tracker::{PoolTracker, WhitelistUpdate};
+        use crate::types::{EventMask, PoolMetadata};
+        use alloy_primitives::Address;
+
+        let v3 = Address::from([0x44; 20]);
+        let v2 = Address::from([0x55; 20]);
+        let base = PoolMetadata {
+            pool_id: PoolIdentifier::Address(v3),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            protocol: Protocol::UniswapV3,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: None,
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: Some(EventMask::SwapsOnly),
+        };
+        let mut tracker = PoolTracker::new();
+        tracker.queue_update(WhitelistUpdate::Add(vec![
+            base.clone(),
+            PoolMetadata {
+                pool_id: PoolIdentifier::Address(v2),
+                protocol: Protocol::UniswapV2,
+                event_mask: Some(EventMask::LiquidityOnly),
+                ..base
+            },
+        ]));
+
+        let swap = DecodedEvent::V3Swap {
+            pool: v3,
+            amount0: alloy_primitives::I256::ZERO,
+            amount1: alloy_primitives::I256::ZERO,
+            sqrt_price_x96: U256::ZERO,
+            liquidity: 0,
+            tick: 0,
+        };
+        let mint = DecodedEvent::V3Mint {
+            pool: v3,
+            tick_lower: -60,
+            tick_upper: 60,
+            amount: 1,
+        };
+        let sync = DecodedEvent::V2Sync {
+            pool: v2,
+            reserve0: 1,
+            reserve1: 1,
+        };
+        for event in [&swap, &mint, &sync] {
+            assert!(LiquidityExEx::should_process_event(event, &tracker));
+        }
+        assert!(!LiquidityExEx::event_masked(&swap, &tracker));
+        assert!(LiquidityExEx::event_masked(&mint, &tracker));
+        assert!(
+            !LiquidityExEx::event_masked(&sync, &tracker),
+            "state-sync events are never masked"
+        );
+    }
+
+    /// A `swaps_only` pool's Mint writes no frame but still lands in the
+    /// arena: both range ticks carry its liquidity.
+    #[tokio::test]
+    async fn masked_mint_still_changes_arena_liquidity() {
+        use crate::events::DecodedEvent;
+        use crate::shadow_arena::UniswapV3Hydration;
+        use crate::types::{EventMask, PoolMetadata};
+        use alloy_primitives::Address;
+        use arena_layout::{AnyUniswapV3Pool, UniswapV3LowPoolData};
+        use std::sync::atomic::Ordering;
+
+        let arena_path =
+            std::env::temp_dir().join(format!("masked_mint_{}.arena", std::process::id()));
+        let _ = std::fs::remove_file(&arena_path);
+        let mut shadow = ShadowArena::open(&arena_path).expect("open arena");
+        let pool = [0x46u8; 20];
+        let mut v3 = UniswapV3LowPoolData::default();
+        v3.common.pool_id = pool;
+        v3.common.is_active.store(true, Ordering::Release);
+        v3.sqrt_price_x96 = U256::from(1_000u64);
+        v3.liquidity = 100_000;
+        v3.fee = 500;
+        v3.tick_spacing = 10;
+        shadow.hydrate_startup(
+            100,
+            &[],
+            &[UniswapV3Hydration {
+                address: pool,
+                pool: AnyUniswapV3Pool::Low(v3),
+            }],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+
+        let (socket_tx, mut socket_rx) = tokio::sync::mpsc::channel(4);
+        let mut exex = LiquidityExEx::new(socket_tx, Some(shadow), None);
+        exex.pool_tracker
+            .write()
+            .await
+            .replace_startup(vec![PoolMetadata {
+                pool_id: PoolIdentifier::Address(Address::from(pool)),
+                token0: Address::ZERO,
+                token1: Address::ZERO,
+                protocol: Protocol::UniswapV3,
+                factory: Address::ZERO,
+                tick_spacing: Some(10),
+                fee: Some(500),
+                token0_decimals: None,
+                token1_decimals: None,
+                extra_tokens: vec![],
+                twocrypto_version: None,
+                ekubo_fee: None,
+                ekubo_type_config: None,
+                balancer_weights: None,
+                balancer_swap_fee: None,
+                balancer_version: None,
+                event_mask: Some(EventMask::SwapsOnly),
+            }]);
+        let tracker = exex.pool_tracker.snapshot();
+
+        let mint = DecodedEvent::V3Mint {
+            pool: Address::from(pool),
+            tick_lower: -10,
+            tick_upper: 10,
+            amount: 5_000,
+        };
+        assert!(LiquidityExEx::should_process_event(&mint, &tracker));
+        let masked = LiquidityExEx::event_masked(&mint, &tracker);
+        assert!(masked);
+
+        let update_msg = PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
+            pool_id: PoolIdentifier::Address(Address::from(pool)),
+            protocol: Protocol::UniswapV3,
+            update_type: UpdateType::Mint,
+            block_number: 101,
+            block_timestamp: 0,
+            tx_index: 0,
+            log_index: 0,
+            is_revert: false,
+            update: PoolUpdate::V3Liquidity {
+                tick_lower: -10,
+                tick_upper: 10,
+                liquidity_delta: 5_000,
+            },
+        };
+        apply_to_shadow(&mut exex.shadow, &update_msg);
+        let mut stream_seq = 0;
+        assert_eq!(
+            exex.send_pool_update_unless_masked(&mut stream_seq, update_msg, masked),
+            0
+        );
+
+        assert!(socket_rx.try_recv().is_err(), "no frame for a masked Mint");
+        assert_eq!(stream_seq, 0);
+        let shadow = exex.shadow.as_mut().expect("shadow");
+        assert_eq!(shadow.v3_tick_gross(&pool, -10), Some(5_000));
+        assert_eq!(shadow.v3_tick_gross(&pool, 10), Some(5_000));
+        let _ = std::fs::remove_file(&arena_path);
+    }
+
     /// ITE-29 round-03 Critical regression: `end_block_whitelist_topology` —
     /// the step every per-block path (committed + both reorg loops) runs
     /// BEFORE the block's EndBlock/arena signal — applies a queued live
@@ -4196,6 +5903,7 @@ mod tests {
                 balancer_weights: None,
                 balancer_swap_fee: None,
                 balancer_version: None,
+                event_mask: None,
             }]);
             // A live `.remove` arriving mid-block stays queued until end-of-block.
             tracker.begin_block();
@@ -4233,6 +5941,7 @@ mod tests {
 
     fn slot0_event(update: PoolUpdate, protocol: Protocol) -> PoolUpdateMessage {
         PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::PoolId([0xE0; 32]),
             protocol,
             update_type: UpdateType::Burn,
@@ -4365,4 +6074,474 @@ mod tests {
         assert_eq!(determine_tier(501, 50), PoolTier::Major);
         assert_eq!(determine_tier(500, 51), PoolTier::Major);
     }
+
+    /// Parallel decoding must emit exactly what a sequential pass would, in
+    /// (tx_index, log_index) order, with untracked logs counted but dropped.
+    #[test]
+    fn parallel_block_decode_keeps_log_order() {
+        use super::{decode_block_logs, PARALLEL_DECODE_MIN_LOGS};
+        use crate::pool_tracker::PoolTracker;
+        use crate::types::PoolMetadata;
+        use alloy_primitives::{keccak256, Address, Bytes, Log};
+
+        let tracked = Address::from([0x11; 20]);
+        let untracked = Address::from([0x22; 20]);
+        let mut tracker = PoolTracker::new();
+        tracker.replace_startup(vec![PoolMetadata {
+            pool_id: PoolIdentifier::Address(tracked),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            protocol: Protocol::UniswapV2,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: None,
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }]);
+
+        let sync = |address| {
+            Log::new_unchecked(
+                address,
+                vec![keccak256("Sync(uint112,uint112)")],
+                Bytes::from(vec![0u8; 64]),
+            )
+        };
+        // Every third log is from an untracked address.
+        let receipts: Vec<alloy_consensus::Receipt> = (0..64)
+            .map(|_| alloy_consensus::Receipt {
+                status: true.into(),
+                cumulative_gas_used: 0,
+                logs: (0..6)
+                    .map(|i| sync(if i % 3 == 2 { untracked } else { tracked }))
+                    .collect(),
+            })
+            .collect();
+        assert!(
+            64 * 6 >= PARALLEL_DECODE_MIN_LOGS,
+            "exercises the parallel path"
+        );
+
+        let decoded = decode_block_logs(&receipts, None, &tracker);
+        assert_eq!(decoded.logs_checked, 64 * 6);
+        assert_eq!(decoded.logs_matched, 64 * 4);
+        assert_eq!(decoded.events.len(), 64 * 4);
+        let order: Vec<_> = decoded
+            .events
+            .iter()
+            .map(|e| (e.tx_index, e.log_index))
+            .collect();
+        let expected: Vec<_> = (0..64)
+            .flat_map(|tx| [0, 1, 3, 4].map(|log| (tx, log)))
+            .collect();
+        assert_eq!(order, expected);
+
+        let sequential = decode_block_logs(&receipts[..1], None, &tracker);
+        assert_eq!(sequential.events.len(), 4);
+    }
+
+    /// A two-block revert undoes block 11 before block 10, each newest update
+    /// first, whether the updates come from the journal (10) or are
+    /// re-decoded from receipts (11). Journaled updates are inverted as sent,
+    /// even for a pool the whitelist has since dropped.
+    #[test]
+    fn two_block_revert_runs_in_descending_ordinal_order() {
+        use super::{journaled_reverts, newest_first, revert_events};
+        use crate::pool_tracker::PoolTracker;
+        use crate::reorg_journal::{JournalBlock, JournaledUpdate, ReorgJournal};
+        use crate::types::{event_ordinal, PoolMetadata};
+        use alloy_primitives::{keccak256, Address, Bytes, Log, B256};
+
+        let pool = Address::from([0x11; 20]);
+        let mut tracker = PoolTracker::new();
+        tracker.replace_startup(vec![PoolMetadata {
+            pool_id: PoolIdentifier::Address(pool),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            protocol: Protocol::UniswapV2,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: None,
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }]);
+
+        let dropped = Address::from([0x33; 20]);
+        let journaled = |tx_index, log_index| JournaledUpdate {
+            update: PoolUpdateMessage {
+                chain_id: 1,
+                pool_id: PoolIdentifier::Address(dropped),
+                protocol: Protocol::UniswapV3,
+                update_type: UpdateType::Mint,
+                block_number: 10,
+                block_timestamp: 0,
+                tx_index,
+                log_index,
+                is_revert: false,
+                update: PoolUpdate::V3Liquidity {
+                    tick_lower: -10,
+                    tick_upper: 10,
+                    liquidity_delta: 5,
+                },
+            },
+            masked: log_index == 1,
+        };
+        let mut journal = ReorgJournal::new(8);
+        journal.record(JournalBlock {
+            block_number: 10,
+            block_hash: B256::repeat_byte(10),
+            updates: vec![journaled(0, 0), journaled(0, 1), journaled(2, 0)],
+            fluid_touched: vec![],
+        });
+
+        let sync = Log::new_unchecked(
+            pool,
+            vec![keccak256("Sync(uint112,uint112)")],
+            Bytes::from(vec![0u8; 64]),
+        );
+        let receipt = |logs: usize| alloy_consensus::Receipt {
+            status: true.into(),
+            cumulative_gas_used: 0,
+            logs: vec![sync.clone(); logs],
+        };
+        let receipts_10 = vec![];
+        let receipts_11 = vec![receipt(1), receipt(2)];
+        let old = [
+            (10u64, B256::repeat_byte(10), &receipts_10),
+            (11u64, B256::repeat_byte(11), &receipts_11),
+        ];
+
+        let mut fluid_touched = HashSet::new();
+        let mut reverted = Vec::new();
+        let mut masked = Vec::new();
+        for (block_number, block_hash, receipts) in newest_first(old.into_iter()) {
+            match journal.take(block_number, block_hash) {
+                Some(entry) => {
+                    for e in journaled_reverts(entry, &mut fluid_touched) {
+                        assert!(e.update.is_revert);
+                        assert_eq!(e.update.pool_id, PoolIdentifier::Address(dropped));
+                        reverted.push((block_number, e.update.tx_index, e.update.log_index));
+                        masked.push(e.masked);
+                    }
+                }
+                None => {
+                    for e in revert_events(receipts.as_slice(), &tracker, &mut fluid_touched) {
+                        reverted.push((block_number, e.tx_index, e.log_index));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            reverted,
+            [
+                (11, 1, 1),
+                (11, 1, 0),
+                (11, 0, 0),
+                (10, 2, 0),
+                (10, 0, 1),
+                (10, 0, 0),
+            ]
+        );
+        assert!(reverted
+            .windows(2)
+            .all(|pair| event_ordinal(pair[0].0, pair[0].1, pair[0].2)
+                > event_ordinal(pair[1].0, pair[1].1, pair[1].2)));
+        assert_eq!(masked, [false, true, false], "mask outcome kept");
+        assert!(journal.is_empty());
+    }
+
+    /// End-to-end notification handling on a `test_exex_context` node: the
+    /// same `handle_notification` the live loop runs, with no NATS, arena or
+    /// socket server. The node only has genesis state, so every synthetic
+    /// block is block 0 and the reorged fork differs only in its receipts.
+    mod harness {
+        use super::super::{handle_notification, LiquidityExEx};
+        use crate::types::{
+            ControlMessage, PoolIdentifier, PoolMetadata, PoolUpdate, Protocol,
+            ReorgEpilogueUpdate, UpdateChecksum,
+        };
+        use alloy_primitives::{keccak256, Address, Bytes, Log, B256, U256};
+        use futures::TryStreamExt;
+        use reth_ethereum_primitives::Receipt;
+        use reth_exex_test_utils::{test_exex_context, TestExExHandle};
+        use reth_provider::{Chain, ExecutionOutcome};
+        use tokio::sync::mpsc::Receiver;
+
+        const POOL: Address = Address::new([0x11; 20]);
+        const UNTRACKED: Address = Address::new([0x22; 20]);
+
+        fn sync_log(pool: Address, reserve0: u64, reserve1: u64) -> Log {
+            let mut data = B256::from(U256::from(reserve0)).to_vec();
+            data.extend_from_slice(B256::from(U256::from(reserve1)).as_slice());
+            Log::new_unchecked(
+                pool,
+                vec![keccak256("Sync(uint112,uint112)")],
+                Bytes::from(data),
+            )
+        }
+
+        /// The genesis block carrying one receipt per entry of `txs`.
+        fn chain(handle: &TestExExHandle, txs: Vec<Vec<Log>>) -> Chain {
+            let receipts = txs
+                .into_iter()
+                .map(|logs| Receipt {
+                    success: true,
+                    logs,
+                    ..Default::default()
+                })
+                .collect();
+            Chain::from_block(
+                handle.genesis.clone(),
+                ExecutionOutcome::new(Default::default(), vec![receipts], 0, vec![]),
+                Default::default(),
+            )
+        }
+
+        fn pool_metadata() -> PoolMetadata {
+            PoolMetadata {
+                pool_id: PoolIdentifier::Address(POOL),
+                token0: Address::ZERO,
+                token1: Address::ZERO,
+                protocol: Protocol::UniswapV2,
+                factory: Address::ZERO,
+                tick_spacing: None,
+                fee: None,
+                token0_decimals: None,
+                token1_decimals: None,
+                extra_tokens: vec![],
+                twocrypto_version: None,
+                ekubo_fee: None,
+                ekubo_type_config: None,
+                balancer_weights: None,
+                balancer_swap_fee: None,
+                balancer_version: None,
+                event_mask: None,
+            }
+        }
+
+        struct Harness {
+            ctx: reth_exex::ExExContext<reth_exex_test_utils::Adapter>,
+            handle: TestExExHandle,
+            exex: LiquidityExEx,
+            socket_rx: Receiver<ControlMessage>,
+            stream_seq: u64,
+        }
+
+        impl Harness {
+            async fn new() -> Self {
+                let (ctx, handle) = test_exex_context().await.expect("test context");
+                let (socket_tx, socket_rx) = tokio::sync::mpsc::channel(64);
+                let exex = LiquidityExEx::new(socket_tx, None, None);
+                exex.pool_tracker
+                    .write()
+                    .await
+                    .replace_startup(vec![pool_metadata()]);
+                Self {
+                    ctx,
+                    handle,
+                    exex,
+                    socket_rx,
+                    stream_seq: 0,
+                }
+            }
+
+            /// Handle the next notification from the node and return the
+            /// frames it produced, in order.
+            async fn step(&mut self) -> Vec<ControlMessage> {
+                let notification = self
+                    .ctx
+                    .notifications
+                    .try_next()
+                    .await
+                    .expect("notification stream")
+                    .expect("a notification");
+                handle_notification(
+                    &mut self.exex,
+                    self.ctx.provider(),
+                    &mut self.stream_seq,
+                    &notification,
+                )
+                .await
+                .expect("handled");
+                let mut frames = Vec::new();
+                while let Ok(frame) = self.socket_rx.try_recv() {
+                    frames.push(frame);
+                }
+                frames
+            }
+        }
+
+        /// One line per frame: kind, block, revert flag and payload.
+        fn describe(frames: &[ControlMessage]) -> Vec<String> {
+            frames
+                .iter()
+                .map(|frame| match frame {
+                    ControlMessage::BeginBlock {
+                        block_number,
+                        is_revert,
+                        ..
+                    } => format!("BeginBlock {block_number} revert={is_revert}"),
+                    ControlMessage::PoolUpdate { event, .. } => match &event.update {
+                        PoolUpdate::V2Sync { reserve0, reserve1 } => format!(
+                            "V2Sync {}:{} {reserve0}/{reserve1} revert={}",
+                            event.tx_index, event.log_index, event.is_revert
+                        ),
+                        other => format!("PoolUpdate {other:?}"),
+                    },
+                    ControlMessage::EndBlock {
+                        block_number,
+                        num_updates,
+                        ..
+                    } => format!("EndBlock {block_number} updates={num_updates}"),
+                    ControlMessage::ReorgStart {
+                        old_range,
+                        new_range,
+                        ..
+                    } => format!(
+                        "ReorgStart old={:?} new={:?}",
+                        old_range.last_block, new_range.last_block
+                    ),
+                    ControlMessage::ReorgEpilogue { update, .. } => match update {
+                        ReorgEpilogueUpdate::V2ReservesFinal {
+                            reserve0, reserve1, ..
+                        } => format!("V2ReservesFinal {reserve0}/{reserve1}"),
+                        other => format!("ReorgEpilogue {other:?}"),
+                    },
+                    ControlMessage::ReorgComplete {
+                        final_tip_block, ..
+                    } => format!("ReorgComplete {final_tip_block}"),
+                    other => format!("{other:?}"),
+                })
+                .collect()
+        }
+
+        /// Sequenced frames carry 1, 2, 3, ... continuing `after`.
+        fn assert_contiguous(frames: &[ControlMessage], after: u64) {
+            let seqs: Vec<u64> = frames.iter().filter_map(|f| f.stream_seq()).collect();
+            let expected: Vec<u64> = (after + 1..=after + seqs.len() as u64).collect();
+            assert_eq!(seqs, expected);
+        }
+
+        #[tokio::test]
+        async fn committed_block_is_one_envelope_in_log_order() {
+            let mut h = Harness::new().await;
+            let chain = chain(
+                &h.handle,
+                vec![
+                    vec![sync_log(POOL, 1_000, 2_000), sync_log(UNTRACKED, 1, 1)],
+                    vec![sync_log(POOL, 1_100, 1_900)],
+                ],
+            );
+            h.handle
+                .send_notification_chain_committed(chain)
+                .await
+                .unwrap();
+
+            let frames = h.step().await;
+            assert_eq!(
+                describe(&frames),
+                [
+                    "BeginBlock 0 revert=false",
+                    "V2Sync 0:0 1000/2000 revert=false",
+                    "V2Sync 1:0 1100/1900 revert=false",
+                    "EndBlock 0 updates=2",
+                ]
+            );
+            assert_contiguous(&frames, 0);
+
+            let mut checksum = UpdateChecksum::default();
+            for frame in &frames {
+                if let ControlMessage::PoolUpdate { event, .. } = frame {
+                    checksum.push(event);
+                }
+            }
+            match frames.last() {
+                Some(ControlMessage::EndBlock {
+                    updates_checksum, ..
+                }) => assert_eq!(*updates_checksum, checksum.value()),
+                other => panic!("expected EndBlock, got {other:?}"),
+            }
+        }
+
+        /// A reorg reverts the old block inside a revert-flagged envelope
+        /// (V2 `Sync` has no inverse, so it is empty), replays the new block,
+        /// then settles V2 reserves from final-tip state before completing.
+        #[tokio::test]
+        async fn reorg_reverts_then_replays_then_settles() {
+            let mut h = Harness::new().await;
+            let old = chain(&h.handle, vec![vec![sync_log(POOL, 1_000, 2_000)]]);
+            h.handle
+                .send_notification_chain_committed(old.clone())
+                .await
+                .unwrap();
+            let committed = h.step().await;
+            assert_eq!(committed.len(), 3);
+
+            let new = chain(&h.handle, vec![vec![sync_log(POOL, 900, 2_200)]]);
+            h.handle
+                .send_notification_chain_reorged(old, new)
+                .await
+                .unwrap();
+            let frames = h.step().await;
+            assert_eq!(
+                describe(&frames),
+                [
+                    "ReorgStart old=Some(0) new=Some(0)",
+                    "BeginBlock 0 revert=true",
+                    "EndBlock 0 updates=0",
+                    "BeginBlock 0 revert=false",
+                    "V2Sync 0:0 900/2200 revert=false",
+                    "EndBlock 0 updates=1",
+                    // Genesis has no pool storage: the settled reserves are 0.
+                    "V2ReservesFinal 0/0",
+                    "ReorgComplete 0",
+                ]
+            );
+            assert_contiguous(&frames, 3);
+        }
+
+        #[tokio::test]
+        async fn revert_emits_a_revert_envelope_and_settles_to_the_parent() {
+            let mut h = Harness::new().await;
+            let old = chain(&h.handle, vec![vec![sync_log(POOL, 1_000, 2_000)]]);
+            h.handle
+                .send_notification_chain_committed(old.clone())
+                .await
+                .unwrap();
+            h.step().await;
+
+            h.handle
+                .send_notification_chain_reverted(old)
+                .await
+                .unwrap();
+            let frames = h.step().await;
+            assert_eq!(
+                describe(&frames),
+                [
+                    "ReorgStart old=Some(0) new=None",
+                    "BeginBlock 0 revert=true",
+                    "EndBlock 0 updates=0",
+                    "V2ReservesFinal 0/0",
+                    "ReorgComplete 0",
+                ]
+            );
+            assert_contiguous(&frames, 3);
+        }
+    }
 }
@@ -0,0 +1,581 @@
+// Mempool Swap Watch
+//
+// Optional (`MEMPOOL_WATCH=true`): the liquidity ExEx subscribes to the node's
+// transaction pool and decodes every transaction that becomes pending for
+// swaps through tracked pools. Each hit is written to the socket as an
+// unsequenced `PendingSwap` frame, outside block envelopes, so the orderbook
+// engine sees flow that will probably land before the block carrying it.
+//
+// Nothing is simulated: pending swaps are found by calldata heuristics.
+//
+// - a direct `swap` call on a tracked V2 or V3 pool
+// - Uniswap V2-style router `swapExact*` / `swap*ForExact*` paths
+// - SwapRouter02 `exactInput{,Single}` / `exactOutput{,Single}`
+// - UniversalRouter `execute` V2/V3 swap commands
+// - one level of `multicall` around any of the router calls
+//
+// Router calls are recognized by selector, whichever contract they target.
+// A V2 hop matches every tracked V2 pool of its token pair (the router's
+// factory is not known); a V3 hop also needs the fee tier. Amounts are what
+// the calldata fixes: the input of an exact-input swap's first hop or the
+// output of an exact-output swap's last hop. V4 and aggregator routes are not
+// decoded.
+//
+// A pending transaction may be replaced, dropped or reverted; consumers should
+// treat `PendingSwap` as a hint and never apply it to pool state. Frames are
+// dropped when the socket queue is full.
+
+use crate::pool_tracker::{PoolTracker, SharedPoolTracker};
+use crate::types::{ControlMessage, PoolIdentifier, Protocol};
+use crate::{chain, exex_metrics};
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, Bytes, I256, U256};
+use alloy_sol_types::{SolCall, SolValue};
+use futures::StreamExt;
+use reth::transaction_pool::TransactionPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+mod v2_router {
+    use alloy_sol_types::sol;
+    sol! {
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline);
+        function swapTokensForExactTokens(uint256 amountOut, uint256 amountInMax, address[] path, address to, uint256 deadline);
+        function swapExactETHForTokens(uint256 amountOutMin, address[] path, address to, uint256 deadline);
+        function swapTokensForExactETH(uint256 amountOut, uint256 amountInMax, address[] path, address to, uint256 deadline);
+        function swapExactTokensForETH(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline);
+        function swapETHForExactTokens(uint256 amountOut, address[] path, address to, uint256 deadline);
+    }
+}
+
+mod v3_router {
+    use alloy_sol_types::sol;
+    sol! {
+        struct ExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint24 fee;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+            uint160 sqrtPriceLimitX96;
+        }
+        struct ExactInputParams {
+            bytes path;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+        }
+        struct ExactOutputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint24 fee;
+            address recipient;
+            uint256 amountOut;
+            uint256 amountInMaximum;
+            uint160 sqrtPriceLimitX96;
+        }
+        struct ExactOutputParams {
+            bytes path;
+            address recipient;
+            uint256 amountOut;
+            uint256 amountInMaximum;
+        }
+        function exactInputSingle(ExactInputSingleParams params);
+        function exactInput(ExactInputParams params);
+        function exactOutputSingle(ExactOutputSingleParams params);
+        function exactOutput(ExactOutputParams params);
+    }
+}
+
+// Overloads live in their own modules so each keeps its canonical name.
+
+mod multicall {
+    use alloy_sol_types::sol;
+    sol! {
+        function multicall(bytes[] data);
+    }
+}
+
+mod multicall_deadline {
+    use alloy_sol_types::sol;
+    sol! {
+        function multicall(uint256 deadline, bytes[] data);
+    }
+}
+
+mod execute {
+    use alloy_sol_types::sol;
+    sol! {
+        function execute(bytes commands, bytes[] inputs);
+    }
+}
+
+mod execute_deadline {
+    use alloy_sol_types::sol;
+    sol! {
+        function execute(bytes commands, bytes[] inputs, uint256 deadline);
+    }
+}
+
+mod v2_pool {
+    use alloy_sol_types::sol;
+    sol! {
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes data);
+    }
+}
+
+mod v3_pool {
+    use alloy_sol_types::sol;
+    sol! {
+        function swap(address recipient, bool zeroForOne, int256 amountSpecified, uint160 sqrtPriceLimitX96, bytes data);
+    }
+}
+
+// UniversalRouter command types (low 6 bits of each command byte).
+const COMMAND_TYPE_MASK: u8 = 0x3f;
+const V3_SWAP_EXACT_IN: u8 = 0x00;
+const V3_SWAP_EXACT_OUT: u8 = 0x01;
+const V2_SWAP_EXACT_IN: u8 = 0x08;
+const V2_SWAP_EXACT_OUT: u8 = 0x09;
+
+/// UniversalRouter sentinel: swap the router's whole balance.
+const CONTRACT_BALANCE: U256 = U256::from_limbs([0, 0, 0, 1 << 63]);
+
+/// One pool hop a pending transaction would swap through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapHop {
+    pub token_in: Address,
+    pub token_out: Address,
+    /// V3 fee tier; `None` for a V2 hop.
+    pub fee: Option<u32>,
+    pub amount_in: Option<U256>,
+    pub amount_out: Option<U256>,
+}
+
+type Leg = (Address, Option<u32>, Address);
+
+fn hops(legs: Vec<Leg>, amount_in: Option<U256>, amount_out: Option<U256>) -> Vec<SwapHop> {
+    let last = legs.len().saturating_sub(1);
+    legs.into_iter()
+        .enumerate()
+        .map(|(i, (token_in, fee, token_out))| SwapHop {
+            token_in,
+            token_out,
+            fee,
+            amount_in: amount_in.filter(|_| i == 0),
+            amount_out: amount_out.filter(|_| i == last),
+        })
+        .collect()
+}
+
+fn v2_legs(path: &[Address]) -> Vec<Leg> {
+    path.windows(2).map(|w| (w[0], None, w[1])).collect()
+}
+
+/// Legs of a packed V3 path, `token (fee token)*`, in path order.
+fn v3_legs(path: &[u8]) -> Vec<Leg> {
+    if path.len() < 43 || (path.len() - 20) % 23 != 0 {
+        return Vec::new();
+    }
+    path.windows(43)
+        .step_by(23)
+        .map(|leg| {
+            let fee = u32::from_be_bytes([0, leg[20], leg[21], leg[22]]);
+            (
+                Address::from_slice(&leg[..20]),
+                Some(fee),
+                Address::from_slice(&leg[23..]),
+            )
+        })
+        .collect()
+}
+
+/// Exact-output V3 paths are encoded from the output token back.
+fn reversed(legs: Vec<Leg>) -> Vec<Leg> {
+    legs.into_iter()
+        .rev()
+        .map(|(token_out, fee, token_in)| (token_in, fee, token_out))
+        .collect()
+}
+
+fn known_amount(amount: U256) -> Option<U256> {
+    (amount != CONTRACT_BALANCE && !amount.is_zero()).then_some(amount)
+}
+
+/// Hops of a router call. `value` is the transaction's ETH value, the input
+/// of the `swapExactETH*` calls.
+pub fn decode_router_call(input: &[u8], value: U256) -> Vec<SwapHop> {
+    if let Ok(call) = multicall::multicallCall::abi_decode(input) {
+        return nested_hops(&call.data, value);
+    }
+    if let Ok(call) = multicall_deadline::multicallCall::abi_decode(input) {
+        return nested_hops(&call.data, value);
+    }
+    decode_swap_call(input, value)
+}
+
+fn nested_hops(calls: &[Bytes], value: U256) -> Vec<SwapHop> {
+    calls
+        .iter()
+        .flat_map(|call| decode_swap_call(call, value))
+        .collect()
+}
+
+fn decode_swap_call(input: &[u8], value: U256) -> Vec<SwapHop> {
+    use v2_router::*;
+    use v3_router::*;
+
+    if let Ok(c) = swapExactTokensForTokensCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), Some(c.amountIn), None);
+    }
+    if let Ok(c) = swapExactTokensForETHCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), Some(c.amountIn), None);
+    }
+    if let Ok(c) = swapExactETHForTokensCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), known_amount(value), None);
+    }
+    if let Ok(c) = swapTokensForExactTokensCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), None, Some(c.amountOut));
+    }
+    if let Ok(c) = swapTokensForExactETHCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), None, Some(c.amountOut));
+    }
+    if let Ok(c) = swapETHForExactTokensCall::abi_decode(input) {
+        return hops(v2_legs(&c.path), None, Some(c.amountOut));
+    }
+    if let Ok(c) = exactInputSingleCall::abi_decode(input) {
+        let p = c.params;
+        let leg = (p.tokenIn, Some(p.fee.to::<u32>()), p.tokenOut);
+        return hops(vec![leg], known_amount(p.amountIn), None);
+    }
+    if let Ok(c) = exactInputCall::abi_decode(input) {
+        let p = c.params;
+        return hops(v3_legs(&p.path), known_amount(p.amountIn), None);
+    }
+    if let Ok(c) = exactOutputSingleCall::abi_decode(input) {
+        let p = c.params;
+        let leg = (p.tokenIn, Some(p.fee.to::<u32>()), p.tokenOut);
+        return hops(vec![leg], None, Some(p.amountOut));
+    }
+    if let Ok(c) = exactOutputCall::abi_decode(input) {
+        let p = c.params;
+        return hops(reversed(v3_legs(&p.path)), None, Some(p.amountOut));
+    }
+    if let Ok(c) = execute::executeCall::abi_decode(input) {
+        return universal_router_hops(&c.commands, &c.inputs);
+    }
+    if let Ok(c) = execute_deadline::executeCall::abi_decode(input) {
+        return universal_router_hops(&c.commands, &c.inputs);
+    }
+    Vec::new()
+}
+
+fn universal_router_hops(commands: &[u8], inputs: &[Bytes]) -> Vec<SwapHop> {
+    type V3Input = (Address, U256, U256, Bytes, bool);
+    type V2Input = (Address, U256, U256, Vec<Address>, bool);
+
+    let mut out = Vec::new();
+    for (command, input) in commands.iter().zip(inputs) {
+        match command & COMMAND_TYPE_MASK {
+            V3_SWAP_EXACT_IN => {
+                if let Ok((_, amount_in, _, path, _)) = V3Input::abi_decode_params(input) {
+                    out.extend(hops(v3_legs(&path), known_amount(amount_in), None));
+                }
+            }
+            V3_SWAP_EXACT_OUT => {
+                if let Ok((_, amount_out, _, path, _)) = V3Input::abi_decode_params(input) {
+                    out.extend(hops(reversed(v3_legs(&path)), None, Some(amount_out)));
+                }
+            }
+            V2_SWAP_EXACT_IN => {
+                if let Ok((_, amount_in, _, path, _)) = V2Input::abi_decode_params(input) {
+                    out.extend(hops(v2_legs(&path), known_amount(amount_in), None));
+                }
+            }
+            V2_SWAP_EXACT_OUT => {
+                if let Ok((_, amount_out, _, path, _)) = V2Input::abi_decode_params(input) {
+                    out.extend(hops(v2_legs(&path), None, Some(amount_out)));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// A direct `swap` call on a tracked V2/V3 pool.
+fn direct_pool_hop(tracker: &PoolTracker, to: Address, input: &[u8]) -> Option<SwapHop> {
+    let pool = tracker.get_by_address(&to)?;
+    match pool.protocol {
+        Protocol::UniswapV2 => {
+            let c = v2_pool::swapCall::abi_decode(input).ok()?;
+            let (token_in, token_out, amount_out) = if c.amount1Out.is_zero() {
+                (pool.token1, pool.token0, c.amount0Out)
+            } else {
+                (pool.token0, pool.token1, c.amount1Out)
+            };
+            Some(SwapHop {
+                token_in,
+                token_out,
+                fee: None,
+                amount_in: None,
+                amount_out: Some(amount_out),
+            })
+        }
+        Protocol::UniswapV3 => {
+            let c = v3_pool::swapCall::abi_decode(input).ok()?;
+            let (token_in, token_out) = if c.zeroForOne {
+                (pool.token0, pool.token1)
+            } else {
+                (pool.token1, pool.token0)
+            };
+            let exact_input = c.amountSpecified > I256::ZERO;
+            let amount = c.amountSpecified.unsigned_abs();
+            Some(SwapHop {
+                token_in,
+                token_out,
+                fee: pool.fee,
+                amount_in: exact_input.then_some(amount),
+                amount_out: (!exact_input).then_some(amount),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Tracked V2/V3 pools by unordered token pair, rebuilt when the tracker
+/// snapshot changes.
+#[derive(Default)]
+pub struct PairIndex {
+    built_from: Option<Arc<PoolTracker>>,
+    pools: HashMap<(Address, Address), Vec<(PoolIdentifier, Option<u32>)>>,
+}
+
+fn pair_key(a: Address, b: Address) -> (Address, Address) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl PairIndex {
+    pub fn refresh(&mut self, tracker: &Arc<PoolTracker>) {
+        if self
+            .built_from
+            .as_ref()
+            .is_some_and(|built| Arc::ptr_eq(built, tracker))
+        {
+            return;
+        }
+        self.pools.clear();
+        for pool in tracker.pools() {
+            let fee = match pool.protocol {
+                Protocol::UniswapV2 => None,
+                Protocol::UniswapV3 => pool.fee,
+                _ => continue,
+            };
+            self.pools
+                .entry(pair_key(pool.token0, pool.token1))
+                .or_default()
+                .push((pool.pool_id.clone(), fee));
+        }
+        self.built_from = Some(tracker.clone());
+    }
+
+    /// Tracked pools a pending call to `to` would swap through.
+    pub fn pending_swaps(
+        &self,
+        to: Address,
+        input: &[u8],
+        value: U256,
+    ) -> Vec<(PoolIdentifier, SwapHop)> {
+        let Some(tracker) = &self.built_from else {
+            return Vec::new();
+        };
+        if let Some(hop) = direct_pool_hop(tracker, to, input) {
+            return vec![(PoolIdentifier::Address(to), hop)];
+        }
+        let mut out = Vec::new();
+        for hop in decode_router_call(input, value) {
+            let Some(pools) = self.pools.get(&pair_key(hop.token_in, hop.token_out)) else {
+                continue;
+            };
+            for (pool_id, fee) in pools {
+                if *fee == hop.fee {
+                    out.push((pool_id.clone(), hop.clone()));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Start the watcher when `MEMPOOL_WATCH` is `1` / `true`.
+pub fn spawn_from_env<Pool>(
+    pool: Pool,
+    tracker: Arc<SharedPoolTracker>,
+    socket_tx: mpsc::Sender<ControlMessage>,
+) where
+    Pool: TransactionPool + 'static,
+{
+    let enabled = std::env::var("MEMPOOL_WATCH")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+    info!("👀 Mempool watch enabled: emitting PendingSwap for tracked pools");
+    tokio::spawn(watch(pool, tracker, socket_tx));
+}
+
+async fn watch<Pool>(
+    pool: Pool,
+    tracker: Arc<SharedPoolTracker>,
+    socket_tx: mpsc::Sender<ControlMessage>,
+) where
+    Pool: TransactionPool + 'static,
+{
+    let chain_id = chain::active().chain_id;
+    let mut pending = pool.new_pending_pool_transactions_listener();
+    let mut index = PairIndex::default();
+
+    while let Some(event) = pending.next().await {
+        let tx = &event.transaction;
+        let Some(to) = tx.transaction.to() else {
+            continue;
+        };
+        index.refresh(&tracker.snapshot());
+        let swaps = index.pending_swaps(to, tx.transaction.input(), tx.transaction.value());
+        for (pool_id, hop) in swaps {
+            let msg = ControlMessage::PendingSwap {
+                chain_id,
+                tx_hash: *tx.hash(),
+                sender: tx.sender(),
+                to,
+                pool_id,
+                token_in: hop.token_in,
+                token_out: hop.token_out,
+                amount_in: hop.amount_in,
+                amount_out: hop.amount_out,
+                max_priority_fee_per_gas: tx.transaction.max_priority_fee_per_gas(),
+            };
+            if socket_tx.try_send(msg).is_err() {
+                exex_metrics::record_sink_error("liquidity");
+                continue;
+            }
+            exex_metrics::record_liquidity_pending_swap();
+        }
+    }
+    warn!("Transaction pool listener closed, mempool watch stopping");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PoolMetadata;
+
+    fn pool(byte: u8, protocol: Protocol, token0: Address, token1: Address) -> PoolMetadata {
+        PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([byte; 20])),
+            token0,
+            token1,
+            protocol,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: (protocol == Protocol::UniswapV3).then_some(500),
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }
+    }
+
+    #[test]
+    fn router_calldata_maps_to_tracked_pools() {
+        let weth = Address::from([1u8; 20]);
+        let usdc = Address::from([2u8; 20]);
+        let dai = Address::from([3u8; 20]);
+        let router = Address::from([0xaa; 20]);
+        let mut tracker = PoolTracker::new();
+        tracker.replace_startup(vec![
+            pool(10, Protocol::UniswapV3, weth, usdc),
+            pool(11, Protocol::UniswapV2, weth, usdc),
+            pool(12, Protocol::UniswapV2, dai, usdc),
+        ]);
+        let mut index = PairIndex::default();
+        index.refresh(&Arc::new(tracker));
+        let id = |byte: u8| PoolIdentifier::Address(Address::from([byte; 20]));
+
+        // UniversalRouter V3 exact-in WETH -(500)-> USDC.
+        let mut path = weth.to_vec();
+        path.extend_from_slice(&[0x00, 0x01, 0xf4]);
+        path.extend_from_slice(usdc.as_slice());
+        let input = (
+            Address::ZERO,
+            U256::from(10u64),
+            U256::ZERO,
+            Bytes::from(path),
+            true,
+        )
+            .abi_encode_params();
+        let call = execute_deadline::executeCall {
+            commands: Bytes::from(vec![V3_SWAP_EXACT_IN]),
+            inputs: vec![input.into()],
+            deadline: U256::ZERO,
+        }
+        .abi_encode();
+        let swaps = index.pending_swaps(router, &call, U256::ZERO);
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].0, id(10));
+        assert_eq!(
+            (swaps[0].1.token_in, swaps[0].1.amount_in),
+            (weth, Some(U256::from(10u64)))
+        );
+
+        // V2 router WETH -> USDC -> DAI, wrapped in a multicall.
+        let swap = v2_router::swapExactTokensForTokensCall {
+            amountIn: U256::from(7u64),
+            amountOutMin: U256::ZERO,
+            path: vec![weth, usdc, dai],
+            to: Address::ZERO,
+            deadline: U256::ZERO,
+        }
+        .abi_encode();
+        let call = multicall::multicallCall {
+            data: vec![swap.into()],
+        }
+        .abi_encode();
+        let swaps = index.pending_swaps(router, &call, U256::ZERO);
+        let pools: Vec<_> = swaps.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(pools, vec![id(11), id(12)]);
+        assert_eq!(swaps[1].1.token_out, dai);
+        assert_eq!(swaps[1].1.amount_in, None);
+
+        // A direct V3 pool swap, zero for one, exact output.
+        let call = v3_pool::swapCall {
+            recipient: Address::ZERO,
+            zeroForOne: true,
+            amountSpecified: I256::try_from(-5i64).unwrap(),
+            sqrtPriceLimitX96: Default::default(),
+            data: Bytes::new(),
+        }
+        .abi_encode();
+        let swaps = index.pending_swaps(Address::from([10u8; 20]), &call, U256::ZERO);
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].1.amount_out, Some(U256::from(5u64)));
+
+        assert!(index
+            .pending_swaps(router, &[0xde, 0xad], U256::ZERO)
+            .is_empty());
+    }
+}
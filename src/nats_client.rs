@@ -3,12 +3,19 @@
 // Subscribes to the orchestrator's canonical pool whitelist
 // (`whitelist.pools.{chain}.{full,add,remove}`), which carries token addresses,
 // decimals, and protocol metadata the ExEx arena writer needs.
+//
+// With `WHITELIST_JETSTREAM_DURABLE` set, the deltas (`.add`, `.remove`,
+// `.blacklist`, `.unblacklist`) are instead consumed from a JetStream stream
+// through that durable pull consumer and acked once queued, so deltas
+// published while the ExEx was down are replayed on restart. `.full`
+// snapshots (and `.request`, which a stream would answer) stay on core NATS.
 
 use crate::{
-    balancer_storage,
-    types::{PoolIdentifier, PoolMetadata, Protocol, TokenMetadata},
+    balancer_storage, subjects,
+    types::{EventMask, PoolIdentifier, PoolMetadata, Protocol, TokenMetadata},
 };
 use alloy_primitives::Address;
+use async_nats::jetstream::{self, consumer::pull, AckKind};
 use async_nats::Client;
 use eyre::Result;
 use futures::StreamExt;
@@ -42,13 +49,46 @@ fn canonical_token_to_metadata(t: &CanonicalToken) -> Option<TokenMetadata> {
     })
 }
 
+/// `token0` / `token1` as they appear on the wire: the rich `{address,
+/// decimals}` object, or a bare address string from the older flat
+/// full-metadata schema (`WhitelistPoolMessage`). Bare tokens carry no
+/// decimals, so arena hydration skips those pools (data-integrity rule) but
+/// protocol / tokens / fee / tick spacing are still tracked correctly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum WireToken {
+    Rich(CanonicalToken),
+    Bare(String),
+}
+
+impl WireToken {
+    fn address(&self) -> &str {
+        match self {
+            WireToken::Rich(t) => &t.address,
+            WireToken::Bare(address) => address,
+        }
+    }
+
+    fn decimals(&self) -> Option<u8> {
+        match self {
+            WireToken::Rich(t) => Some(t.decimals),
+            WireToken::Bare(_) => None,
+        }
+    }
+}
+
 /// Pool entry in the rich whitelist (orchestrator `WhitelistPool`).
 #[derive(Debug, Clone, Deserialize)]
 struct CanonicalPool {
     address: String,
     protocol: String,
-    token0: CanonicalToken,
-    token1: CanonicalToken,
+    /// Optional so address-only adds parse; missing tokens are filled from
+    /// the pool-creations DB when configured, and pools still without them
+    /// are rejected before tracking (see `pool_metadata_db::complete`).
+    #[serde(default)]
+    token0: Option<WireToken>,
+    #[serde(default)]
+    token1: Option<WireToken>,
     #[serde(default)]
     fee: Option<u32>,
     #[serde(default)]
@@ -65,6 +105,8 @@ struct CanonicalPool {
     ekubo_type_config: Option<u32>,
     #[serde(default)]
     additional_data: Option<serde_json::Value>,
+    #[serde(default)]
+    event_mask: Option<EventMask>,
 }
 
 /// Full rich-snapshot envelope (`whitelist.pools.{chain}.full`).
@@ -74,18 +116,20 @@ struct FullSnapshotMessage {
     pools: Vec<CanonicalPool>,
 }
 
-/// Map a whitelist protocol string to the ExEx `Protocol`.
+/// Map a whitelist protocol string to the ExEx `Protocol`. Accepts the
+/// canonical snake_case names and the `Protocol` variant names used by the
+/// older flat full-metadata schema (e.g. `"UniswapV3"`).
 fn protocol_from_str(s: &str) -> Option<Protocol> {
     Some(match s {
-        "v2" | "uniswap_v2" => Protocol::UniswapV2,
-        "v3" | "uniswap_v3" => Protocol::UniswapV3,
-        "v4" | "uniswap_v4" => Protocol::UniswapV4,
-        "ekubo" => Protocol::Ekubo,
-        "curve_stable" => Protocol::CurveStable,
-        "curve_twocrypto" => Protocol::CurveTwoCrypto,
-        "curve_tricrypto" => Protocol::CurveTricrypto,
-        "balancer_v2_weighted" => Protocol::BalancerV2Weighted,
-        "fluid" => Protocol::Fluid,
+        "v2" | "uniswap_v2" | "UniswapV2" => Protocol::UniswapV2,
+        "v3" | "uniswap_v3" | "UniswapV3" => Protocol::UniswapV3,
+        "v4" | "uniswap_v4" | "UniswapV4" => Protocol::UniswapV4,
+        "ekubo" | "Ekubo" => Protocol::Ekubo,
+        "curve_stable" | "CurveStable" => Protocol::CurveStable,
+        "curve_twocrypto" | "CurveTwoCrypto" => Protocol::CurveTwoCrypto,
+        "curve_tricrypto" | "CurveTricrypto" => Protocol::CurveTricrypto,
+        "balancer_v2_weighted" | "BalancerV2Weighted" => Protocol::BalancerV2Weighted,
+        "fluid" | "Fluid" => Protocol::Fluid,
         _ => return None,
     })
 }
@@ -106,8 +150,14 @@ fn parse_pool_identifier(address: &str, pool_id: Option<&str>) -> Option<PoolIde
 fn canonical_pool_to_metadata(p: &CanonicalPool) -> Option<PoolMetadata> {
     let protocol = protocol_from_str(&p.protocol)?;
     let pool_id = parse_pool_identifier(&p.address, p.pool_id.as_deref())?;
-    let token0 = Address::from_str(&p.token0.address).ok()?;
-    let token1 = Address::from_str(&p.token1.address).ok()?;
+    let token0 = match &p.token0 {
+        Some(t) => Address::from_str(t.address()).ok()?,
+        None => Address::ZERO,
+    };
+    let token1 = match &p.token1 {
+        Some(t) => Address::from_str(t.address()).ok()?,
+        None => Address::ZERO,
+    };
     let factory = p
         .factory
         .as_deref()
@@ -155,8 +205,8 @@ fn canonical_pool_to_metadata(p: &CanonicalPool) -> Option<PoolMetadata> {
         factory,
         tick_spacing: p.tick_spacing,
         fee: p.fee,
-        token0_decimals: Some(p.token0.decimals),
-        token1_decimals: Some(p.token1.decimals),
+        token0_decimals: p.token0.as_ref().and_then(WireToken::decimals),
+        token1_decimals: p.token1.as_ref().and_then(WireToken::decimals),
         extra_tokens,
         twocrypto_version,
         ekubo_fee: p.ekubo_fee,
@@ -164,6 +214,7 @@ fn canonical_pool_to_metadata(p: &CanonicalPool) -> Option<PoolMetadata> {
         balancer_weights,
         balancer_swap_fee,
         balancer_version,
+        event_mask: p.event_mask,
     })
 }
 
@@ -234,7 +285,98 @@ pub fn parse_remove_snapshot(payload: &[u8]) -> Result<Vec<PoolIdentifier>> {
     Ok(ids)
 }
 
+/// Delta suffixes captured by the JetStream stream.
+const DELTA_SUFFIXES: [&str; 4] = ["add", "remove", "blacklist", "unblacklist"];
+
+/// JetStream source for whitelist deltas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JetStreamWhitelist {
+    /// Stream capturing the chain's delta subjects; created if missing.
+    pub stream: String,
+    /// Durable consumer name: its ack floor survives restarts.
+    pub durable: String,
+}
+
+impl JetStreamWhitelist {
+    /// `WHITELIST_JETSTREAM_DURABLE` enables JetStream;
+    /// `WHITELIST_JETSTREAM_STREAM` defaults to `WHITELIST_{CHAIN}`.
+    pub fn from_env(chain: &str) -> Option<Self> {
+        let durable = std::env::var("WHITELIST_JETSTREAM_DURABLE")
+            .ok()
+            .filter(|s| !s.is_empty())?;
+        let stream = std::env::var("WHITELIST_JETSTREAM_STREAM")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| format!("WHITELIST_{}", chain.to_uppercase()));
+        Some(Self { stream, durable })
+    }
+}
+
+/// Live whitelist updates: the core wildcard subscription, or `.full` over
+/// core NATS plus the deltas from a JetStream durable consumer.
+pub enum WhitelistSubscription {
+    Core(async_nats::Subscriber),
+    JetStream {
+        full: async_nats::Subscriber,
+        deltas: pull::Stream,
+    },
+}
+
+impl WhitelistSubscription {
+    /// Next update; `None` once the subscription is closed.
+    pub async fn next(&mut self) -> Option<WhitelistMessage> {
+        match self {
+            Self::Core(subscriber) => subscriber.next().await.map(WhitelistMessage::Core),
+            Self::JetStream { full, deltas } => loop {
+                tokio::select! {
+                    message = full.next() => return message.map(WhitelistMessage::Core),
+                    message = deltas.next() => match message? {
+                        Ok(message) => return Some(WhitelistMessage::JetStream(message)),
+                        Err(e) => warn!(error = %e, "JetStream whitelist consumer error"),
+                    },
+                }
+            },
+        }
+    }
+}
+
+/// A whitelist update message; JetStream deltas must be acked.
+pub enum WhitelistMessage {
+    Core(async_nats::Message),
+    JetStream(jetstream::Message),
+}
+
+impl std::ops::Deref for WhitelistMessage {
+    type Target = async_nats::Message;
+
+    fn deref(&self) -> &async_nats::Message {
+        match self {
+            Self::Core(message) => message,
+            Self::JetStream(message) => &message.message,
+        }
+    }
+}
+
+impl WhitelistMessage {
+    /// Ack a JetStream delta once it is queued; one that failed to parse is
+    /// terminated so it is not redelivered forever. No-op for core messages.
+    pub async fn ack(&self, processed: bool) {
+        let Self::JetStream(message) = self else {
+            return;
+        };
+        let result = if processed {
+            message.ack().await
+        } else {
+            message.ack_with(AckKind::Term).await
+        };
+        if let Err(e) = result {
+            warn!(subject = %message.subject, error = %e, "Failed to ack whitelist delta");
+        }
+    }
+}
+
 /// NATS client for whitelist subscriptions
+#[derive(Clone)]
 pub struct WhitelistNatsClient {
     client: Client,
 }
@@ -247,6 +389,11 @@ impl WhitelistNatsClient {
         Ok(Self { client })
     }
 
+    /// The underlying connection.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
     /// Subscribe to the canonical per-chain whitelist for live deltas.
     ///
     /// Subscribes to the wildcard `whitelist.pools.{chain}.*` and the caller
@@ -254,32 +401,110 @@ impl WhitelistNatsClient {
     /// [`WhitelistNatsClient::canonical_update`], ignoring the legacy `.minimal`
     /// topic. These carry enriched metadata (token decimals + protocol fields).
     pub async fn subscribe_whitelist(&self, chain: &str) -> Result<async_nats::Subscriber> {
-        let subject = format!("whitelist.pools.{}.*", chain);
+        let subject = subjects::whitelist_pools(chain, "*");
         let subscriber = self.client.subscribe(subject.clone()).await?;
         info!("Subscribed to NATS subject: {}", subject);
         Ok(subscriber)
     }
 
+    /// Subscribe to live whitelist updates: [`Self::subscribe_whitelist`], or
+    /// with `jetstream` set, `.full` over core NATS and the deltas from the
+    /// durable consumer. A new consumer starts at new messages (the startup
+    /// snapshot covers the past); an existing one resumes at its ack floor.
+    pub async fn subscribe_whitelist_updates(
+        &self,
+        chain: &str,
+        jetstream: Option<&JetStreamWhitelist>,
+    ) -> Result<WhitelistSubscription> {
+        let Some(js) = jetstream else {
+            return Ok(WhitelistSubscription::Core(
+                self.subscribe_whitelist(chain).await?,
+            ));
+        };
+        let context = jetstream::new(self.client.clone());
+        let stream = context
+            .get_or_create_stream(jetstream::stream::Config {
+                name: js.stream.clone(),
+                subjects: DELTA_SUFFIXES
+                    .iter()
+                    .map(|suffix| subjects::whitelist_pools(chain, suffix))
+                    .collect(),
+                ..Default::default()
+            })
+            .await?;
+        let consumer = stream
+            .get_or_create_consumer(
+                &js.durable,
+                pull::Config {
+                    durable_name: Some(js.durable.clone()),
+                    deliver_policy: jetstream::consumer::DeliverPolicy::New,
+                    ack_policy: jetstream::consumer::AckPolicy::Explicit,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let deltas = consumer.messages().await?;
+        let full = self.subscribe_full_whitelist(chain).await?;
+        info!(
+            stream = %js.stream,
+            durable = %js.durable,
+            "Consuming whitelist deltas from JetStream"
+        );
+        Ok(WhitelistSubscription::JetStream { full, deltas })
+    }
+
     /// Subscribe to the canonical rich full whitelist subject.
     ///
     /// Startup hydration uses this with `request_reseed()` so ExEx receives the
     /// same `WhitelistPool` payload as arena readers: token addresses, decimals,
     /// fee/tick metadata, and protocol-specific fields.
     pub async fn subscribe_full_whitelist(&self, chain: &str) -> Result<async_nats::Subscriber> {
-        let subject = format!("whitelist.pools.{}.full", chain);
+        let subject = subjects::whitelist_pools(chain, "full");
         let subscriber = self.client.subscribe(subject.clone()).await?;
         info!("Subscribed to rich whitelist subject: {}", subject);
         Ok(subscriber)
     }
 
+    /// Request the current rich full snapshot directly via NATS request/reply
+    /// on `whitelist.pools.{chain}.request`. The reply carries the same
+    /// `.full` payload, so startup does not have to wait for a reseed
+    /// broadcast (or the next periodic publish) to arrive.
+    pub async fn request_full_snapshot(
+        &self,
+        chain: &str,
+        timeout: Duration,
+    ) -> Result<Vec<PoolMetadata>> {
+        let subject = subjects::whitelist_pools(chain, "request");
+        let message = tokio::time::timeout(timeout, self.client.request(subject, "".into()))
+            .await
+            .map_err(|_| eyre::eyre!("timed out waiting for whitelist snapshot reply"))??;
+        parse_full_snapshot(&message.payload)
+    }
+
     /// Ask whitelist_service to re-publish cached full snapshots on the standard
     /// subjects (`whitelist.pools.{chain}.full`, minimal, HL perps).
     pub async fn request_reseed(&self) -> Result<()> {
-        self.client.publish("whitelist.reseed", "".into()).await?;
+        self.client
+            .publish(subjects::whitelist_reseed(), "".into())
+            .await?;
         info!("Requested whitelist reseed");
         Ok(())
     }
 
+    /// Subscribe to the runtime admin request subject (`exex.admin.{chain}`).
+    pub async fn subscribe_admin(&self, chain: &str) -> Result<async_nats::Subscriber> {
+        let subject = crate::admin::admin_subject(chain);
+        let subscriber = self.client.subscribe(subject.clone()).await?;
+        info!("Subscribed to admin subject: {}", subject);
+        Ok(subscriber)
+    }
+
+    /// Publish a reply to a NATS request's reply inbox.
+    pub async fn respond(&self, reply: async_nats::Subject, payload: Vec<u8>) -> Result<()> {
+        self.client.publish(reply, payload.into()).await?;
+        Ok(())
+    }
+
     /// Wait for one rich full snapshot from a `.full` subscription and parse it.
     pub async fn next_full_snapshot(
         &self,
@@ -294,10 +519,10 @@ impl WhitelistNatsClient {
         parse_full_snapshot(&message.payload)
     }
 
-    /// Dispatch a canonical whitelist message (by `.full` / `.add` / `.remove`
-    /// subject suffix) into a `WhitelistUpdate` carrying enriched metadata
-    /// (token addresses + decimals + protocol fields). Returns `Ok(None)` for
-    /// ignored subjects (e.g. the legacy `.minimal`).
+    /// Dispatch a canonical whitelist message (by `.full` / `.add` / `.remove` /
+    /// `.blacklist` / `.unblacklist` subject suffix) into a `WhitelistUpdate`
+    /// carrying enriched metadata (token addresses + decimals + protocol
+    /// fields). Returns `Ok(None)` for ignored subjects (e.g. the legacy `.minimal`).
     pub fn canonical_update(
         subject_suffix: &str,
         payload: &[u8],
@@ -308,6 +533,9 @@ impl WhitelistNatsClient {
             "full" => Update::Replace(parse_full_snapshot(payload)?),
             "add" => Update::Add(parse_full_snapshot(payload)?),
             "remove" => Update::Remove(parse_remove_snapshot(payload)?),
+            // Blacklist envelopes share the `.remove` shape (chain + pool_addresses).
+            "blacklist" => Update::Blacklist(parse_remove_snapshot(payload)?),
+            "unblacklist" => Update::Unblacklist(parse_remove_snapshot(payload)?),
             _ => return Ok(None),
         };
         Ok(Some(update))
@@ -445,6 +673,38 @@ mod tests {
         assert_ne!(pools[0].extra_tokens[0].address, Address::ZERO);
     }
 
+    #[test]
+    fn parse_full_snapshot_carries_event_mask() {
+        let json = br#"{"chain":"ethereum","pools":[
+            {"address":"0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc","protocol":"v3","event_mask":"swaps_only","token0":{"address":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","decimals":6},"token1":{"address":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","decimals":18}},
+            {"address":"0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640","protocol":"v3","token0":{"address":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","decimals":6},"token1":{"address":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","decimals":18}}]}"#;
+        let pools = super::parse_full_snapshot(json).expect("parse full snapshot");
+        assert_eq!(pools[0].event_mask, Some(EventMask::SwapsOnly));
+        assert_eq!(pools[1].event_mask, None);
+    }
+
+    /// The older flat full-metadata schema (bare token strings, `Protocol`
+    /// variant names) keeps its protocol, tokens, fee and tick spacing rather
+    /// than being rejected or flattened to a default protocol.
+    #[test]
+    fn parse_full_snapshot_accepts_flat_metadata_schema() {
+        let json = br#"{"chain":"ethereum","timestamp":"2025-01-01T00:00:00Z","pools":[
+            {"address":"0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640","token0":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","token1":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","protocol":"UniswapV3","factory":"0x1F98431c8aD98523631AE4a59f267346ea31F984","tick_spacing":10,"fee":500},
+            {"address":"0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc","token0":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","token1":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","protocol":"UniswapV2","factory":"0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"}]}"#;
+        let pools = super::parse_full_snapshot(json).expect("parse flat snapshot");
+        assert_eq!(pools.len(), 2);
+        assert_eq!(pools[0].protocol, Protocol::UniswapV3);
+        assert_eq!(pools[0].fee, Some(500));
+        assert_eq!(pools[0].tick_spacing, Some(10));
+        assert_ne!(pools[0].token0, Address::ZERO);
+        assert_eq!(
+            pools[0].token0_decimals, None,
+            "bare tokens carry no decimals"
+        );
+        assert_eq!(pools[1].protocol, Protocol::UniswapV2);
+        assert_ne!(pools[1].factory, Address::ZERO);
+    }
+
     const FULL_V2: &[u8] = br#"{"snapshot_id":1,"chain":"ethereum","pools":[{"address":"0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc","protocol":"v2","token0":{"address":"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48","symbol":"USDC","decimals":6},"token1":{"address":"0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2","symbol":"WETH","decimals":18}}]}"#;
 
     #[test]
@@ -464,6 +724,14 @@ mod tests {
             WhitelistUpdate::Add(p) => assert_eq!(p.len(), 1),
             other => panic!("expected Add, got {other:?}"),
         }
+        let blacklist = br#"{"chain":"ethereum","pool_addresses":["0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"]}"#;
+        match WhitelistNatsClient::canonical_update("blacklist", blacklist)
+            .unwrap()
+            .unwrap()
+        {
+            WhitelistUpdate::Blacklist(ids) => assert_eq!(ids.len(), 1),
+            other => panic!("expected Blacklist, got {other:?}"),
+        }
         // Legacy/unknown subjects are ignored.
         assert!(WhitelistNatsClient::canonical_update("minimal", FULL_V2)
             .unwrap()
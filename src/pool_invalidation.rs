@@ -0,0 +1,141 @@
+// Dead Pool Detection
+//
+// A tracked pool contract that self-destructs, or whose code changes (a
+// CREATE2 redeploy at the same address after a pre-Cancun self-destruct), no
+// longer behaves like the pool the whitelist describes: quoting against its
+// last known state would price a pool that cannot be traded. After each
+// committed chain, the state diff (`ExecutionOutcome` reverts) names every
+// account whose info changed in each block; tracked address-keyed pools among
+// them have their code hash compared between the parent state and the block's
+// post-state.
+//
+// Each dead pool is announced with an unsequenced `PoolInvalidated` frame just
+// before the `EndBlock` of the block that killed it, and blacklisted in the
+// `PoolTracker` at that block's end, so the usual removal path drops its arena
+// slot and later whitelist snapshots do not re-track it. A reorg that undoes
+// the block does not lift the blacklist; an `.unblacklist` whitelist message
+// does. Pools inside singletons (V4, Balancer, Ekubo) share the singleton's
+// code and are not checked. `exex replay` does not check.
+
+use crate::pool_tracker::PoolTracker;
+use crate::types::{InvalidationReason, PoolIdentifier, Protocol};
+use alloy_consensus::constants::KECCAK_EMPTY;
+use alloy_primitives::{Address, B256};
+use reth::providers::StateProviderFactory;
+use reth_node_api::NodePrimitives;
+use reth_provider::{Chain, StateProvider};
+use reth_revm::revm::database::states::reverts::AccountInfoRevert;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// A tracked pool that died in a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invalidation {
+    pub pool_id: PoolIdentifier,
+    pub protocol: Protocol,
+    pub reason: InvalidationReason,
+}
+
+/// How a contract's code hash moved across a block, if that kills a pool.
+/// `None` and `KECCAK_EMPTY` both mean no code.
+pub fn classify(before: Option<B256>, after: Option<B256>) -> Option<InvalidationReason> {
+    let code = |hash: Option<B256>| hash.filter(|hash| *hash != KECCAK_EMPTY);
+    match (code(before), code(after)) {
+        (Some(_), None) => Some(InvalidationReason::Destroyed),
+        (Some(before), Some(after)) if before != after => Some(InvalidationReason::CodeChanged),
+        _ => None,
+    }
+}
+
+/// Pools of `tracker` that died in each block of a committed chain, by block
+/// number. A block whose state cannot be read is logged and skipped.
+pub fn invalidated_pools<N: NodePrimitives>(
+    provider: &impl StateProviderFactory,
+    chain: &Chain<N>,
+    tracker: &PoolTracker,
+) -> BTreeMap<u64, Vec<Invalidation>> {
+    let outcome = chain.execution_outcome();
+    let mut invalidated = BTreeMap::new();
+    for (offset, reverts) in outcome.bundle.reverts.iter().enumerate() {
+        let block_number = outcome.first_block() + offset as u64;
+        // Storage writes leave the account info alone; only balance, nonce
+        // and code changes (or a wipe) make a pool a candidate.
+        let candidates: Vec<Address> = reverts
+            .iter()
+            .filter(|(address, revert)| {
+                (revert.wipe_storage || !matches!(revert.account, AccountInfoRevert::DoNothing))
+                    && tracker.is_tracked_address(address)
+            })
+            .map(|(address, _)| *address)
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        match dead_pools(provider, block_number, &candidates, tracker) {
+            Ok(dead) if !dead.is_empty() => {
+                invalidated.insert(block_number, dead);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(block = block_number, "Pool code check failed: {}", e),
+        }
+    }
+    invalidated
+}
+
+fn dead_pools(
+    provider: &impl StateProviderFactory,
+    block_number: u64,
+    candidates: &[Address],
+    tracker: &PoolTracker,
+) -> eyre::Result<Vec<Invalidation>> {
+    let parent = provider.history_by_block_number(block_number.saturating_sub(1))?;
+    let post = provider.history_by_block_number(block_number)?;
+    let code_hash = |state: &dyn StateProvider, address: &Address| -> eyre::Result<Option<B256>> {
+        Ok(state
+            .basic_account(address)?
+            .and_then(|account| account.bytecode_hash))
+    };
+    let mut dead = Vec::new();
+    for address in candidates {
+        let before = code_hash(parent.as_ref(), address)?;
+        let after = code_hash(post.as_ref(), address)?;
+        let (Some(reason), Some(protocol)) =
+            (classify(before, after), tracker.get_protocol(address))
+        else {
+            continue;
+        };
+        dead.push(Invalidation {
+            pool_id: PoolIdentifier::Address(*address),
+            protocol,
+            reason,
+        });
+    }
+    Ok(dead)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_hash_changes_classify() {
+        let pool_code = B256::repeat_byte(0x11);
+        let redeployed = B256::repeat_byte(0x22);
+        assert_eq!(
+            classify(Some(pool_code), None),
+            Some(InvalidationReason::Destroyed)
+        );
+        assert_eq!(
+            classify(Some(pool_code), Some(KECCAK_EMPTY)),
+            Some(InvalidationReason::Destroyed)
+        );
+        assert_eq!(
+            classify(Some(pool_code), Some(redeployed)),
+            Some(InvalidationReason::CodeChanged)
+        );
+        assert_eq!(classify(Some(pool_code), Some(pool_code)), None);
+        // A pool created in the block was not live before it.
+        assert_eq!(classify(None, Some(pool_code)), None);
+        assert_eq!(classify(Some(KECCAK_EMPTY), Some(pool_code)), None);
+    }
+}
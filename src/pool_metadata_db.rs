@@ -0,0 +1,298 @@
+// Pool Metadata Lookup (pool-creations database)
+//
+// Whitelist adds may carry only a pool address + protocol. The pool-creations
+// indexer writes every factory-created pool into `network_{chain_id}_dex_pools_cryo`
+// (factory, tokens, fee, tick spacing), so the ExEx can complete `PoolMetadata`
+// from there without changing the whitelist producer. Every whitelist path
+// (startup snapshot, `.full` reseeds, adds, admin edits) goes through
+// `complete`.
+//
+// Enabled only when `POOL_METADATA_DATABASE_URL` is set. Lookup failures are
+// logged and leave the pools as they arrived. A pool still missing a token
+// address afterwards is rejected, never tracked with `Address::ZERO` tokens;
+// the next snapshot carrying it (or a DB that has caught up) tracks it.
+
+use crate::types::{PoolIdentifier, PoolMetadata};
+use alloy_primitives::Address;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Default pool-creations table of the active chain.
+fn default_table() -> String {
+    format!("network_{}_dex_pools_cryo", crate::chain::active().chain_id)
+}
+
+/// One row of the pool-creations table, reduced to the fields we fill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolCreationRow {
+    pub factory: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: Option<u32>,
+    pub tick_spacing: Option<i32>,
+}
+
+/// Read-only handle on the pool-creations table.
+pub struct PoolMetadataDb {
+    pool: PgPool,
+    table: String,
+}
+
+impl PoolMetadataDb {
+    /// Connect using `POOL_METADATA_DATABASE_URL` (table override:
+    /// `POOL_METADATA_TABLE`). Returns `Ok(None)` when the lookup is not
+    /// configured.
+    pub async fn from_env() -> eyre::Result<Option<Self>> {
+        let Ok(database_url) = std::env::var("POOL_METADATA_DATABASE_URL") else {
+            return Ok(None);
+        };
+        let table = std::env::var("POOL_METADATA_TABLE").unwrap_or_else(|_| default_table());
+        // The table name is interpolated into SQL; only accept identifiers.
+        if !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            eyre::bail!("invalid POOL_METADATA_TABLE {table:?}");
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect(&database_url)
+            .await?;
+        info!(table = %table, "Connected to pool-creations metadata DB");
+        Ok(Some(Self { pool, table }))
+    }
+
+    /// Look up pool-creation rows for a batch of pool addresses.
+    pub async fn lookup(
+        &self,
+        addresses: &[Address],
+    ) -> eyre::Result<HashMap<Address, PoolCreationRow>> {
+        if addresses.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let keys: Vec<String> = addresses.iter().map(|a| format!("{a:#x}")).collect();
+        // fee / tick_spacing are INTEGER, BIGINT or NUMERIC depending on the
+        // indexer version; read their text form and parse it (see `parse_int`).
+        let sql = format!(
+            "SELECT address, factory, token0, token1, fee::text AS fee, \
+             tick_spacing::text AS tick_spacing \
+             FROM {} WHERE lower(address) = ANY($1)",
+            self.table
+        );
+        let rows = sqlx::query(&sql).bind(&keys).fetch_all(&self.pool).await?;
+
+        let mut out = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let address: String = row.try_get("address")?;
+            let parse = |col: &str| -> eyre::Result<Address> {
+                let value: Option<String> = row.try_get(col)?;
+                Ok(value
+                    .and_then(|v| Address::from_str(&v).ok())
+                    .unwrap_or(Address::ZERO))
+            };
+            let Ok(address) = Address::from_str(&address) else {
+                continue;
+            };
+            let fee: Option<String> = row.try_get("fee")?;
+            let tick_spacing: Option<String> = row.try_get("tick_spacing")?;
+            out.insert(
+                address,
+                PoolCreationRow {
+                    factory: parse("factory")?,
+                    token0: parse("token0")?,
+                    token1: parse("token1")?,
+                    fee: fee.as_deref().and_then(parse_int),
+                    tick_spacing: tick_spacing.as_deref().and_then(parse_int),
+                },
+            );
+        }
+        Ok(out)
+    }
+
+    /// Fill missing factory/token/fee/tick-spacing fields of address-keyed
+    /// pools in place. Returns the number of pools enriched.
+    pub async fn enrich(&self, pools: &mut [PoolMetadata]) -> eyre::Result<usize> {
+        let missing: Vec<Address> = pools
+            .iter()
+            .filter(|p| needs_enrichment(p))
+            .filter_map(|p| p.pool_id.as_address())
+            .collect();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+        let rows = self.lookup(&missing).await?;
+        let mut enriched = 0;
+        for pool in pools.iter_mut() {
+            let PoolIdentifier::Address(addr) = pool.pool_id else {
+                continue;
+            };
+            if let Some(row) = rows.get(&addr) {
+                if apply_row(pool, row) {
+                    enriched += 1;
+                }
+            }
+        }
+        debug!(
+            requested = missing.len(),
+            found = rows.len(),
+            enriched,
+            "Pool metadata DB lookup"
+        );
+        Ok(enriched)
+    }
+}
+
+/// Complete `pools` from the DB (when configured), then drop the ones still
+/// missing a token address. Returns the number of pools rejected.
+pub async fn complete(db: Option<&PoolMetadataDb>, pools: &mut Vec<PoolMetadata>) -> usize {
+    if let Some(db) = db {
+        if let Err(e) = db.enrich(pools).await {
+            warn!(error = %e, "Pool metadata DB lookup failed");
+        }
+    }
+    reject_incomplete(pools)
+}
+
+/// Drop pools without both token addresses: they can be neither hydrated nor
+/// quoted. Returns the number dropped.
+fn reject_incomplete(pools: &mut Vec<PoolMetadata>) -> usize {
+    let before = pools.len();
+    pools.retain(|pool| {
+        let complete = pool.token0 != Address::ZERO && pool.token1 != Address::ZERO;
+        if !complete {
+            warn!(pool = ?pool.pool_id, protocol = ?pool.protocol, "Rejecting whitelist pool without token addresses");
+        }
+        complete
+    });
+    before - pools.len()
+}
+
+/// Integer column text (`3000`, or NUMERIC's `3000.0`) into the target type;
+/// fractional or out-of-range values are treated as absent.
+fn parse_int<T: TryFrom<i64>>(text: &str) -> Option<T> {
+    let value = rust_decimal::Decimal::from_str(text.trim()).ok()?;
+    if !value.fract().is_zero() {
+        return None;
+    }
+    let value: i64 = value.try_into().ok()?;
+    T::try_from(value).ok()
+}
+
+/// A pool needs a DB lookup when the whitelist did not carry its tokens or factory.
+fn needs_enrichment(pool: &PoolMetadata) -> bool {
+    pool.token0 == Address::ZERO || pool.token1 == Address::ZERO || pool.factory == Address::ZERO
+}
+
+/// Fill only fields the whitelist left empty; whitelist values always win.
+fn apply_row(pool: &mut PoolMetadata, row: &PoolCreationRow) -> bool {
+    let mut changed = false;
+    if pool.token0 == Address::ZERO && row.token0 != Address::ZERO {
+        pool.token0 = row.token0;
+        changed = true;
+    }
+    if pool.token1 == Address::ZERO && row.token1 != Address::ZERO {
+        pool.token1 = row.token1;
+        changed = true;
+    }
+    if pool.factory == Address::ZERO && row.factory != Address::ZERO {
+        pool.factory = row.factory;
+        changed = true;
+    }
+    if pool.fee.is_none() && row.fee.is_some() {
+        pool.fee = row.fee;
+        changed = true;
+    }
+    if pool.tick_spacing.is_none() && row.tick_spacing.is_some() {
+        pool.tick_spacing = row.tick_spacing;
+        changed = true;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Protocol;
+
+    #[test]
+    fn apply_row_fills_only_missing_fields() {
+        let mut pool = PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([1u8; 20])),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            protocol: Protocol::UniswapV3,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: Some(3000),
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        };
+        assert!(needs_enrichment(&pool));
+
+        let row = PoolCreationRow {
+            factory: Address::from([0xFA; 20]),
+            token0: Address::from([0xA0; 20]),
+            token1: Address::from([0xA1; 20]),
+            fee: Some(500),
+            tick_spacing: Some(10),
+        };
+        assert!(apply_row(&mut pool, &row));
+        assert_eq!(pool.token0, row.token0);
+        assert_eq!(pool.token1, row.token1);
+        assert_eq!(pool.factory, row.factory);
+        assert_eq!(pool.fee, Some(3000), "whitelist fee wins");
+        assert_eq!(pool.tick_spacing, Some(10));
+        assert!(!needs_enrichment(&pool));
+        assert!(!apply_row(&mut pool, &row), "second apply is a no-op");
+    }
+
+    #[test]
+    fn integer_columns_parse_from_any_numeric_text() {
+        assert_eq!(parse_int::<u32>("3000"), Some(3000));
+        assert_eq!(parse_int::<u32>("3000.000"), Some(3000));
+        assert_eq!(parse_int::<i32>("-60"), Some(-60));
+        assert_eq!(parse_int::<u32>("-60"), None);
+        assert_eq!(parse_int::<u32>("0.5"), None);
+        assert_eq!(parse_int::<u32>("99999999999"), None);
+    }
+
+    #[test]
+    fn pools_without_tokens_are_rejected() {
+        let pool = |id: u8, token1: Address| PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([id; 20])),
+            token0: Address::from([0xA0; 20]),
+            token1,
+            protocol: Protocol::UniswapV2,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: None,
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        };
+        let mut pools = vec![pool(1, Address::from([0xA1; 20])), pool(2, Address::ZERO)];
+        assert_eq!(reject_incomplete(&mut pools), 1);
+        assert_eq!(pools.len(), 1);
+        assert_eq!(
+            pools[0].pool_id,
+            PoolIdentifier::Address(Address::from([1; 20]))
+        );
+    }
+}
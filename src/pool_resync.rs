@@ -0,0 +1,124 @@
+//! Periodic pool-state resync, spread across blocks.
+//!
+//! With `POOL_SNAPSHOT_INTERVAL_BLOCKS` set, the absolute state of every
+//! tracked pool is re-emitted once per interval, so a consumer that dropped
+//! messages converges without reconnecting. Reading every pool's state inside
+//! a single block would stall the block loop on a large whitelist, so each
+//! block re-seeds only the next `ceil(pools / interval)` pools round-robin
+//! (like the state verifier's sample): every pool is still covered within one
+//! interval, at a bounded per-block cost.
+
+use crate::types::PoolMetadata;
+
+/// Round-robin resync of every tracked pool over `interval_blocks` blocks.
+#[derive(Debug)]
+pub struct PoolResync {
+    interval_blocks: u64,
+    /// Position of the next batch in the stable pool order; 0 starts a pass.
+    cursor: usize,
+}
+
+impl PoolResync {
+    /// `None` unless `POOL_SNAPSHOT_INTERVAL_BLOCKS` is set (> 0).
+    pub fn from_env() -> Option<Self> {
+        std::env::var("POOL_SNAPSHOT_INTERVAL_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .and_then(Self::new)
+    }
+
+    pub fn new(interval_blocks: u64) -> Option<Self> {
+        (interval_blocks > 0).then_some(Self {
+            interval_blocks,
+            cursor: 0,
+        })
+    }
+
+    pub fn interval_blocks(&self) -> u64 {
+        self.interval_blocks
+    }
+
+    /// This block's batch of `pools` to re-seed, and whether it completes a
+    /// pass over all of them.
+    pub fn next_batch<'a>(
+        &mut self,
+        pools: impl Iterator<Item = &'a PoolMetadata>,
+    ) -> (Vec<PoolMetadata>, bool) {
+        let mut pools: Vec<&PoolMetadata> = pools.collect();
+        if pools.is_empty() {
+            return (Vec::new(), false);
+        }
+        // Stable order across blocks so the cursor walks every pool.
+        pools.sort_by_cached_key(|p| format!("{:?}", p.pool_id));
+        let take = pools.len().div_ceil(self.interval_blocks as usize);
+        // The whitelist may have shrunk since the last batch.
+        let start = self.cursor.min(pools.len());
+        let end = (start + take).min(pools.len());
+        let completes_pass = end == pools.len();
+        self.cursor = if completes_pass { 0 } else { end };
+        let batch = pools[start..end].iter().map(|p| (*p).clone()).collect();
+        (batch, completes_pass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PoolIdentifier, Protocol};
+    use alloy_primitives::Address;
+
+    fn pool(byte: u8) -> PoolMetadata {
+        PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::repeat_byte(byte)),
+            token0: Address::repeat_byte(0xa0),
+            token1: Address::repeat_byte(0xa1),
+            protocol: Protocol::UniswapV2,
+            factory: Address::ZERO,
+            tick_spacing: None,
+            fee: None,
+            token0_decimals: Some(18),
+            token1_decimals: Some(18),
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        }
+    }
+
+    #[test]
+    fn every_pool_is_resynced_once_per_interval() {
+        let pools: Vec<PoolMetadata> = (1..=10).map(pool).collect();
+        let mut resync = PoolResync::new(4).unwrap();
+
+        let mut seen = Vec::new();
+        let mut passes = Vec::new();
+        for _ in 0..4 {
+            let (batch, completes_pass) = resync.next_batch(pools.iter());
+            assert!(batch.len() <= 3);
+            seen.extend(batch.into_iter().map(|p| p.pool_id));
+            passes.push(completes_pass);
+        }
+        assert_eq!(passes, [false, false, false, true]);
+        assert_eq!(seen.len(), pools.len());
+        for pool in &pools {
+            assert!(
+                seen.contains(&pool.pool_id),
+                "{:?} never resynced",
+                pool.pool_id
+            );
+        }
+
+        // The next interval starts over from the first pool.
+        let (batch, _) = resync.next_batch(pools.iter());
+        assert_eq!(batch[0].pool_id, seen[0]);
+    }
+
+    #[test]
+    fn zero_interval_disables_resync() {
+        assert!(PoolResync::new(0).is_none());
+    }
+}
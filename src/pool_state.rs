@@ -0,0 +1,546 @@
+// In-ExEx Pool State Engine
+//
+// Optional (`POOL_STATE_MODE`): keeps the absolute state of seeded pools in
+// memory — V2 reserves, V3/V4 slot0 + active liquidity + per-tick liquidity_net —
+// and folds every outgoing socket update into it. For concentrated-liquidity
+// pools it produces an absolute `ConcentratedState` update, so a thin consumer
+// can track price and tick liquidity without reimplementing mint/burn math.
+//
+// A pool only has a known base once a seed (`V2Sync`, `V3Snapshot`,
+// `V4Snapshot`) has passed through the engine. With the engine enabled, every
+// tracked V2/V3/V4 pool without a base (the startup snapshot, a `.full`
+// replacement, a live add, a pool whose base was dropped) is seeded from the
+// post-state of the next committed block. Until then its updates are emitted
+// unchanged: no absolute frame is ever built for a pool without a base.
+//
+// Modes:
+//   off        (default) engine disabled, deltas only
+//   alongside  every delta followed by its absolute post-state
+//   absolute   absolute post-state replaces the delta when the pool has a base
+
+use crate::chain;
+use crate::types::{
+    PoolIdentifier, PoolMetadata, PoolUpdate, PoolUpdateMessage, Protocol, ReorgEpilogueUpdate,
+    Slot0State, TickSnapshot,
+};
+use alloy_primitives::U256;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::warn;
+
+/// How absolute-state updates are mixed into the socket stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PoolStateMode {
+    #[default]
+    Off,
+    Alongside,
+    Absolute,
+}
+
+impl PoolStateMode {
+    /// Parse `POOL_STATE_MODE` (`off` | `alongside` | `absolute`).
+    pub fn from_env() -> Self {
+        match std::env::var("POOL_STATE_MODE").as_deref() {
+            Ok("alongside") => PoolStateMode::Alongside,
+            Ok("absolute") => PoolStateMode::Absolute,
+            Ok("off") | Err(_) => PoolStateMode::Off,
+            Ok(other) => {
+                warn!(
+                    mode = other,
+                    "Unknown POOL_STATE_MODE, state engine disabled"
+                );
+                PoolStateMode::Off
+            }
+        }
+    }
+}
+
+/// Concentrated-liquidity pool state (V3/V4).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConcentratedState {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    /// Net liquidity per initialized tick; zero entries are removed.
+    pub liquidity_net: BTreeMap<i32, i128>,
+}
+
+impl ConcentratedState {
+    fn from_snapshot(snapshot: &TickSnapshot) -> Self {
+        Self {
+            sqrt_price_x96: snapshot.sqrt_price_x96,
+            tick: snapshot.tick,
+            liquidity: snapshot.liquidity,
+            liquidity_net: snapshot
+                .ticks
+                .iter()
+                .filter(|(_, _, net)| *net != 0)
+                .map(|(tick, _, net)| (*tick, *net))
+                .collect(),
+        }
+    }
+
+    fn slot0(&self) -> Slot0State {
+        Slot0State {
+            sqrt_price_x96: self.sqrt_price_x96,
+            liquidity: self.liquidity,
+            tick: self.tick,
+        }
+    }
+
+    /// Fold a position change into the tick map and active liquidity. Returns
+    /// `None` if the fold would overflow (the base is then unknown).
+    fn apply_liquidity(&mut self, tick_lower: i32, tick_upper: i32, delta: i128) -> Option<()> {
+        for (tick, d) in [(tick_lower, delta), (tick_upper, delta.checked_neg()?)] {
+            let net = self.liquidity_net.get(&tick).copied().unwrap_or(0);
+            let net = net.checked_add(d)?;
+            if net == 0 {
+                self.liquidity_net.remove(&tick);
+            } else {
+                self.liquidity_net.insert(tick, net);
+            }
+        }
+        if (tick_lower..tick_upper).contains(&self.tick) {
+            self.liquidity = self.liquidity.checked_add_signed(delta)?;
+        }
+        Some(())
+    }
+
+    fn net_at(&self, tick: i32) -> i128 {
+        self.liquidity_net.get(&tick).copied().unwrap_or(0)
+    }
+
+    /// Active liquidity in each of the `2 * spacings + 1` tick-spacing-wide
+    /// ranges centred on the current one, as `(range_lower_tick, liquidity)`
+    /// in ascending tick order. Walks the tick map outward from the current
+    /// range, crossing one initialized tick boundary per range. `None` if the
+    /// walk under/overflows (inconsistent base).
+    pub fn depth(&self, tick_spacing: i32, spacings: u32) -> Option<Vec<(i32, u128)>> {
+        if tick_spacing <= 0 {
+            return None;
+        }
+        let current = self.tick.div_euclid(tick_spacing) * tick_spacing;
+        let n = spacings as i32;
+
+        let mut below = Vec::with_capacity(spacings as usize);
+        let mut liquidity = self.liquidity;
+        for k in 0..n {
+            // Leaving range `current - k*spacing` downward crosses its lower tick.
+            let boundary = current - k * tick_spacing;
+            liquidity = liquidity.checked_add_signed(self.net_at(boundary).checked_neg()?)?;
+            below.push((boundary - tick_spacing, liquidity));
+        }
+
+        let mut above = Vec::with_capacity(spacings as usize);
+        let mut liquidity = self.liquidity;
+        for k in 1..=n {
+            let boundary = current + k * tick_spacing;
+            liquidity = liquidity.checked_add_signed(self.net_at(boundary))?;
+            above.push((boundary, liquidity));
+        }
+
+        below.reverse();
+        below.push((current, self.liquidity));
+        below.extend(above);
+        Some(below)
+    }
+}
+
+/// Absolute state of one pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolState {
+    V2 { reserve0: u128, reserve1: u128 },
+    Concentrated(ConcentratedState),
+}
+
+/// In-memory pool state folded from the outgoing update stream.
+#[derive(Debug, Default)]
+pub struct PoolStateEngine {
+    mode: PoolStateMode,
+    pools: HashMap<PoolIdentifier, PoolState>,
+    /// Concentrated pools with a base that changed since the last
+    /// `take_touched` (drives per-block depth snapshots).
+    touched: HashSet<PoolIdentifier>,
+    /// Pools handed out by `take_unseeded` whose seed has not landed yet; not
+    /// handed out again (a pool whose seed cannot be read is not re-read every
+    /// block) until `retry_seeds` or its base is dropped.
+    seeding: HashSet<PoolIdentifier>,
+}
+
+impl PoolStateEngine {
+    pub fn new(mode: PoolStateMode) -> Self {
+        Self {
+            mode,
+            pools: HashMap::new(),
+            touched: HashSet::new(),
+            seeding: HashSet::new(),
+        }
+    }
+
+    /// Tracked pools the engine models but has no base for, to seed from
+    /// provider state.
+    pub fn take_unseeded<'a>(
+        &mut self,
+        pools: impl IntoIterator<Item = &'a PoolMetadata>,
+    ) -> Vec<PoolMetadata> {
+        pools
+            .into_iter()
+            .filter(|pool| {
+                matches!(
+                    pool.protocol,
+                    Protocol::UniswapV2 | Protocol::UniswapV3 | Protocol::UniswapV4
+                ) && !self.pools.contains_key(&pool.pool_id)
+                    && self.seeding.insert(pool.pool_id.clone())
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Hand every pool still without a base out again (a full resync).
+    pub fn retry_seeds(&mut self) {
+        self.seeding.clear();
+    }
+
+    pub fn mode(&self) -> PoolStateMode {
+        self.mode
+    }
+
+    pub fn state(&self, pool_id: &PoolIdentifier) -> Option<&PoolState> {
+        self.pools.get(pool_id)
+    }
+
+    /// Number of pools with a known base.
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Fold one outgoing update into the engine. Returns the absolute post-state
+    /// update to emit for it, or `None` when the update is already absolute, is
+    /// a seed, or the pool has no known base.
+    pub fn apply(&mut self, msg: &PoolUpdateMessage) -> Option<PoolUpdate> {
+        match &msg.update {
+            PoolUpdate::V2Sync { reserve0, reserve1 } => {
+                if !msg.is_revert {
+                    self.seeding.remove(&msg.pool_id);
+                    self.pools.insert(
+                        msg.pool_id.clone(),
+                        PoolState::V2 {
+                            reserve0: *reserve0,
+                            reserve1: *reserve1,
+                        },
+                    );
+                }
+                None
+            }
+            PoolUpdate::V3Snapshot { snapshot } | PoolUpdate::V4Snapshot { snapshot } => {
+                self.seeding.remove(&msg.pool_id);
+                self.pools.insert(
+                    msg.pool_id.clone(),
+                    PoolState::Concentrated(ConcentratedState::from_snapshot(snapshot)),
+                );
+                None
+            }
+            PoolUpdate::V3Swap {
+                sqrt_price_x96,
+                liquidity,
+                tick,
+            }
+            | PoolUpdate::V4Swap {
+                sqrt_price_x96,
+                liquidity,
+                tick,
+            } => {
+                // A reverted swap's pre-state is unknown here; the reorg
+                // epilogue's `Slot0Final` restores it.
+                if msg.is_revert {
+                    return None;
+                }
+                let Some(PoolState::Concentrated(state)) = self.pools.get_mut(&msg.pool_id) else {
+                    return None;
+                };
+                state.sqrt_price_x96 = *sqrt_price_x96;
+                state.liquidity = *liquidity;
+                state.tick = *tick;
+                self.touched.insert(msg.pool_id.clone());
+                Some(PoolUpdate::ConcentratedState {
+                    state: state.slot0(),
+                    ticks: Vec::new(),
+                })
+            }
+            PoolUpdate::V3Liquidity {
+                tick_lower,
+                tick_upper,
+                liquidity_delta,
+            }
+            | PoolUpdate::V4Liquidity {
+                tick_lower,
+                tick_upper,
+                liquidity_delta,
+            } => {
+                let Some(PoolState::Concentrated(state)) = self.pools.get_mut(&msg.pool_id) else {
+                    return None;
+                };
+                let delta = if msg.is_revert {
+                    liquidity_delta.checked_neg()
+                } else {
+                    Some(*liquidity_delta)
+                };
+                if delta
+                    .and_then(|d| state.apply_liquidity(*tick_lower, *tick_upper, d))
+                    .is_none()
+                {
+                    warn!(pool_id = ?msg.pool_id, "Pool state overflow, dropping base");
+                    self.pools.remove(&msg.pool_id);
+                    self.seeding.remove(&msg.pool_id);
+                    return None;
+                }
+                self.touched.insert(msg.pool_id.clone());
+                Some(PoolUpdate::ConcentratedState {
+                    state: state.slot0(),
+                    ticks: vec![
+                        (*tick_lower, state.net_at(*tick_lower)),
+                        (*tick_upper, state.net_at(*tick_upper)),
+                    ],
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Drain the concentrated pools changed since the last call.
+    pub fn take_touched(&mut self) -> Vec<PoolIdentifier> {
+        self.touched.drain().collect()
+    }
+
+    /// Whether the engine's state for a pool disagrees with a freshly read
+    /// seed (`V2Sync` / `V3Snapshot` / `V4Snapshot`) of it. Pools without a
+    /// base, and non-seed updates, never diverge.
+    pub fn diverges(&self, seed: &PoolUpdateMessage) -> bool {
+        let Some(current) = self.pools.get(&seed.pool_id) else {
+            return false;
+        };
+        match (&seed.update, current) {
+            (
+                PoolUpdate::V2Sync { reserve0, reserve1 },
+                PoolState::V2 {
+                    reserve0: r0,
+                    reserve1: r1,
+                },
+            ) => reserve0 != r0 || reserve1 != r1,
+            (
+                PoolUpdate::V3Snapshot { snapshot } | PoolUpdate::V4Snapshot { snapshot },
+                PoolState::Concentrated(state),
+            ) => *state != ConcentratedState::from_snapshot(snapshot),
+            (
+                PoolUpdate::V2Sync { .. }
+                | PoolUpdate::V3Snapshot { .. }
+                | PoolUpdate::V4Snapshot { .. },
+                _,
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// Fold a reorg-epilogue final state into pools with a known base.
+    pub fn apply_epilogue(&mut self, update: &ReorgEpilogueUpdate) {
+        match update {
+            ReorgEpilogueUpdate::Slot0Final { pool_id, state, .. } => {
+                if let Some(PoolState::Concentrated(pool)) = self.pools.get_mut(pool_id) {
+                    pool.sqrt_price_x96 = state.sqrt_price_x96;
+                    pool.liquidity = state.liquidity;
+                    pool.tick = state.tick;
+                }
+            }
+            ReorgEpilogueUpdate::V2ReservesFinal {
+                pool_id,
+                reserve0,
+                reserve1,
+            } => {
+                self.seeding.remove(pool_id);
+                self.pools.insert(
+                    pool_id.clone(),
+                    PoolState::V2 {
+                        reserve0: *reserve0,
+                        reserve1: *reserve1,
+                    },
+                );
+            }
+            ReorgEpilogueUpdate::FluidStateFinal { .. }
+            | ReorgEpilogueUpdate::PoolStateFinal { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Protocol, UpdateType};
+    use alloy_primitives::Address;
+
+    fn msg(update: PoolUpdate, is_revert: bool) -> PoolUpdateMessage {
+        PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
+            pool_id: PoolIdentifier::Address(Address::from([3u8; 20])),
+            protocol: Protocol::UniswapV3,
+            update_type: UpdateType::Mint,
+            block_number: 1,
+            block_timestamp: 1,
+            tx_index: 0,
+            log_index: 0,
+            is_revert,
+            update,
+        }
+    }
+
+    #[test]
+    fn liquidity_deltas_fold_into_absolute_state() {
+        let mut engine = PoolStateEngine::new(PoolStateMode::Absolute);
+        let mint = PoolUpdate::V3Liquidity {
+            tick_lower: -60,
+            tick_upper: 60,
+            liquidity_delta: 1_000,
+        };
+        assert!(
+            engine.apply(&msg(mint.clone(), false)).is_none(),
+            "no base before seed"
+        );
+
+        let seed = PoolUpdate::V3Snapshot {
+            snapshot: TickSnapshot {
+                sqrt_price_x96: U256::from(1u128 << 96),
+                tick: 0,
+                liquidity: 500,
+                tick_bitmaps: vec![],
+                ticks: vec![(-120, 500, 500), (120, 500, -500)],
+            },
+        };
+        assert!(engine.apply(&msg(seed, false)).is_none());
+
+        let Some(PoolUpdate::ConcentratedState { state, ticks }) =
+            engine.apply(&msg(mint.clone(), false))
+        else {
+            panic!("expected absolute state");
+        };
+        assert_eq!(
+            state.liquidity, 1_500,
+            "in-range mint raises active liquidity"
+        );
+        assert_eq!(ticks, vec![(-60, 1_000), (60, -1_000)]);
+
+        // Reverting the mint restores the seeded base exactly.
+        let revert = msg(mint, true);
+        engine.apply(&revert);
+        let Some(PoolState::Concentrated(pool)) = engine.state(&revert.pool_id) else {
+            panic!("expected concentrated state");
+        };
+        assert_eq!(pool.liquidity, 500);
+        assert_eq!(pool.liquidity_net.len(), 2);
+    }
+
+    #[test]
+    fn unseeded_pools_are_handed_out_once_and_get_no_absolute_state() {
+        let mut engine = PoolStateEngine::new(PoolStateMode::Absolute);
+        let swap = msg(
+            PoolUpdate::V3Swap {
+                sqrt_price_x96: U256::from(1u128 << 96),
+                liquidity: 500,
+                tick: 0,
+            },
+            false,
+        );
+        let pool = PoolMetadata {
+            pool_id: swap.pool_id.clone(),
+            token0: Address::ZERO,
+            token1: Address::ZERO,
+            protocol: Protocol::UniswapV3,
+            factory: Address::ZERO,
+            tick_spacing: Some(60),
+            fee: Some(3000),
+            token0_decimals: None,
+            token1_decimals: None,
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        };
+        let curve = PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([4u8; 20])),
+            protocol: Protocol::CurveStable,
+            ..pool.clone()
+        };
+
+        assert!(
+            engine.apply(&swap).is_none(),
+            "no absolute frame without a base"
+        );
+        let unseeded = engine.take_unseeded([&pool, &curve]);
+        assert_eq!(unseeded.len(), 1);
+        assert_eq!(unseeded[0].pool_id, pool.pool_id);
+        assert!(engine.take_unseeded([&pool]).is_empty(), "seed in flight");
+        engine.retry_seeds();
+        assert_eq!(engine.take_unseeded([&pool]).len(), 1);
+
+        engine.apply(&msg(
+            PoolUpdate::V3Snapshot {
+                snapshot: TickSnapshot {
+                    sqrt_price_x96: U256::from(1u128 << 96),
+                    tick: 0,
+                    liquidity: 500,
+                    tick_bitmaps: vec![],
+                    ticks: vec![],
+                },
+            },
+            false,
+        ));
+        engine.retry_seeds();
+        assert!(engine.take_unseeded([&pool]).is_empty(), "seeded");
+        assert!(engine.apply(&swap).is_some());
+    }
+
+    #[test]
+    fn depth_walks_tick_map_outward() {
+        let state = ConcentratedState {
+            sqrt_price_x96: U256::ZERO,
+            tick: 5,
+            liquidity: 150,
+            // A position over [-10, 20) plus a wider one over [-20, 30).
+            liquidity_net: BTreeMap::from([(-20, 50), (-10, 100), (20, -100), (30, -50)]),
+        };
+        let depth = state.depth(10, 2).unwrap();
+        assert_eq!(
+            depth,
+            vec![(-20, 50), (-10, 150), (0, 150), (10, 150), (20, 50)],
+            "current range [0, 10) carries the active liquidity"
+        );
+    }
+
+    #[test]
+    fn diverges_compares_against_fresh_seed() {
+        let mut engine = PoolStateEngine::new(PoolStateMode::Alongside);
+        let seed = |liquidity| {
+            msg(
+                PoolUpdate::V3Snapshot {
+                    snapshot: TickSnapshot {
+                        sqrt_price_x96: U256::from(1u128 << 96),
+                        tick: 0,
+                        liquidity,
+                        tick_bitmaps: vec![],
+                        ticks: vec![(-120, 500, 500), (120, 500, -500)],
+                    },
+                },
+                false,
+            )
+        };
+        assert!(!engine.diverges(&seed(500)), "no base, nothing to verify");
+        engine.apply(&seed(500));
+        assert!(!engine.diverges(&seed(500)));
+        assert!(engine.diverges(&seed(501)));
+    }
+}
@@ -4,12 +4,19 @@
 // 1. Differential updates (add/remove) instead of full replacement
 // 2. Block-synchronized updates - changes applied between blocks to prevent event loss
 // 3. Pending update queue - whitelist changes queued and applied atomically
+// 4. Lock-free reads - writers publish an immutable snapshot (`SharedPoolTracker`)
+//    that the block loop loads once per block instead of holding a read lock
 
-use crate::events::{BALANCER_V2_VAULT, EKUBO_CORE};
+use crate::chain;
 use crate::fluid_decoder::FluidPoolConfig;
 use crate::types::{PoolIdentifier, PoolMetadata, Protocol};
-use alloy_primitives::{address, Address};
+use alloy_primitives::{address, Address, Bloom, BloomInput};
+use arc_swap::ArcSwap;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
 use tracing::{info, warn};
 
 // ============================================================================
@@ -26,6 +33,9 @@ pub const UNISWAP_V4_POOL_MANAGER: Address = address!("000000000004444c5dc75cb35
 /// Deployed: https://etherscan.io/address/0x52Aa899454998Be5b000Ad077a46Bbe360F4e497
 pub const FLUID_LIQUIDITY_LAYER: Address = address!("52Aa899454998Be5b000Ad077a46Bbe360F4e497");
 
+/// Largest whitelist (tracked addresses) that gets logs-bloom pre-filtering.
+pub const BLOOM_PREFILTER_MAX_ADDRESSES: usize = 64;
+
 /// Differential whitelist update operations
 #[derive(Debug, Clone)]
 pub enum WhitelistUpdate {
@@ -39,15 +49,25 @@ pub enum WhitelistUpdate {
     /// metadata in place. Startup uses [`PoolTracker::replace_startup`], which
     /// installs the snapshot without surfacing deltas.
     Replace(Vec<PoolMetadata>),
+    /// Blacklist pools (e.g. detected as malicious). Overrides whitelist
+    /// membership: a tracked pool is removed (surfacing via `newly_removed`),
+    /// and later `.add` / `.full` messages will not re-track it.
+    Blacklist(Vec<PoolIdentifier>),
+    /// Lift a blacklist entry. The pool is NOT re-tracked by this alone; it
+    /// returns with the next whitelist add or full snapshot that lists it.
+    Unblacklist(Vec<PoolIdentifier>),
 }
 
 /// Tracks which pools we should monitor for events
+#[derive(Clone)]
 pub struct PoolTracker {
     /// Map of pool address -> metadata (for V2/V3)
-    pools_by_address: HashMap<Address, PoolMetadata>,
+    /// Metadata is shared: publishing a snapshot clones the maps, not every
+    /// pool's metadata.
+    pools_by_address: HashMap<Address, Arc<PoolMetadata>>,
 
     /// Map of pool_id (bytes32) -> metadata (for V4)
-    pools_by_id: HashMap<[u8; 32], PoolMetadata>,
+    pools_by_id: HashMap<[u8; 32], Arc<PoolMetadata>>,
 
     /// Set of tracked addresses for fast lookup
     tracked_addresses: HashSet<Address>,
@@ -64,6 +84,10 @@ pub struct PoolTracker {
     /// pool address and map it back to the poolId for the arena fee update.
     balancer_pools_by_addr: HashMap<Address, [u8; 32]>,
 
+    /// Pools that must never be tracked, regardless of whitelist membership.
+    /// Survives whitelist replacement (startup and live).
+    blacklist: HashSet<PoolIdentifier>,
+
     /// Pending whitelist updates (applied between blocks)
     pending_updates: VecDeque<WhitelistUpdate>,
 
@@ -72,6 +96,12 @@ pub struct PoolTracker {
     /// from current state, so live `.add` pools are written without a restart.
     newly_added: Vec<PoolMetadata>,
 
+    /// Pools added since the last `take_pending_seed` drain that still need an
+    /// initial absolute-state message on the socket. Independent of
+    /// `newly_added`: it is drained exactly once per live add, whether or not the
+    /// shadow arena is enabled or the pool hydrates.
+    pending_seed: Vec<PoolMetadata>,
+
     /// Pools removed since the last `take_newly_removed` drain. The ExEx drains
     /// this at each committed block boundary and removes their shadow-arena
     /// slots, so live `.remove` (and live `.full` replace) cannot leave stale
@@ -81,6 +111,17 @@ pub struct PoolTracker {
     /// Whether we're currently processing a block
     in_block: bool,
 
+    /// Bumped whenever anything a reader can observe changes (membership,
+    /// metadata, Fluid configs). `SharedPoolTracker` republishes its snapshot
+    /// only when this moves, so per-block begin/end is free.
+    generation: u64,
+
+    /// Bloom of each tracked address at `generation`, for skipping blocks and
+    /// receipts whose logs bloom rules out every tracked address. Built by
+    /// [`PoolTracker::index_address_blooms`]; absent or stale, nothing is
+    /// skipped.
+    address_blooms: Option<(u64, Arc<[Bloom]>)>,
+
     /// Statistics
     v2_count: usize,
     v3_count: usize,
@@ -102,10 +143,14 @@ impl PoolTracker {
             tracked_pool_ids: HashSet::new(),
             fluid_configs: HashMap::new(),
             balancer_pools_by_addr: HashMap::new(),
+            blacklist: HashSet::new(),
             pending_updates: VecDeque::new(),
             newly_added: Vec::new(),
+            pending_seed: Vec::new(),
             newly_removed: Vec::new(),
             in_block: false,
+            generation: 0,
+            address_blooms: None,
             v2_count: 0,
             v3_count: 0,
             v4_count: 0,
@@ -143,6 +188,12 @@ impl PoolTracker {
             WhitelistUpdate::Replace(pools) => {
                 info!("Queuing replace: {} pools", pools.len());
             }
+            WhitelistUpdate::Blacklist(pools) => {
+                info!("Queuing blacklist: {} pools", pools.len());
+            }
+            WhitelistUpdate::Unblacklist(pools) => {
+                info!("Queuing unblacklist: {} pools", pools.len());
+            }
         }
 
         self.pending_updates.push_back(update);
@@ -163,12 +214,15 @@ impl PoolTracker {
             "Applying {} pending whitelist updates",
             self.pending_updates.len()
         );
+        self.generation += 1;
 
         while let Some(update) = self.pending_updates.pop_front() {
             match update {
                 WhitelistUpdate::Add(pools) => self.add_pools(pools, true),
                 WhitelistUpdate::Remove(pool_ids) => self.remove_pools(pool_ids),
                 WhitelistUpdate::Replace(pools) => self.replace_all(pools),
+                WhitelistUpdate::Blacklist(pool_ids) => self.blacklist_pools(pool_ids),
+                WhitelistUpdate::Unblacklist(pool_ids) => self.unblacklist_pools(pool_ids),
             }
         }
 
@@ -208,43 +262,53 @@ impl PoolTracker {
                 continue; // Skip duplicates
             }
 
+            if self.blacklist.contains(&pool.pool_id) {
+                warn!(pool_id = ?pool.pool_id, "Skipping blacklisted pool");
+                continue;
+            }
+
             // Add to tracking
+            let shared = Arc::new(pool);
+            let pool = shared.as_ref();
             match &pool.pool_id {
                 PoolIdentifier::Address(addr) => {
                     self.tracked_addresses.insert(*addr);
-                    self.pools_by_address.insert(*addr, pool.clone());
+                    self.pools_by_address.insert(*addr, Arc::clone(&shared));
                 }
                 PoolIdentifier::PoolId(id) => {
                     // For V4/Ekubo pools, track the poolId AND the singleton address
                     self.tracked_pool_ids.insert(*id);
-                    self.pools_by_id.insert(*id, pool.clone());
+                    self.pools_by_id.insert(*id, Arc::clone(&shared));
 
                     // Track singleton contract addresses so we receive their events
                     match pool.protocol {
                         Protocol::UniswapV4 => {
-                            if !self.tracked_addresses.contains(&UNISWAP_V4_POOL_MANAGER) {
-                                self.tracked_addresses.insert(UNISWAP_V4_POOL_MANAGER);
+                            let pool_manager = chain::active().uniswap_v4_pool_manager;
+                            if !self.tracked_addresses.contains(&pool_manager) {
+                                self.tracked_addresses.insert(pool_manager);
                                 info!(
                                     "🔧 Added PoolManager address for V4 events: {:?}",
-                                    UNISWAP_V4_POOL_MANAGER
+                                    pool_manager
                                 );
                             }
                         }
                         Protocol::Ekubo => {
-                            if !self.tracked_addresses.contains(&EKUBO_CORE) {
-                                self.tracked_addresses.insert(EKUBO_CORE);
+                            let ekubo_core = chain::active().ekubo_core;
+                            if !self.tracked_addresses.contains(&ekubo_core) {
+                                self.tracked_addresses.insert(ekubo_core);
                                 info!(
                                     "🔧 Added Ekubo Core address for Ekubo events: {:?}",
-                                    EKUBO_CORE
+                                    ekubo_core
                                 );
                             }
                         }
                         Protocol::BalancerV2Weighted => {
-                            if !self.tracked_addresses.contains(&BALANCER_V2_VAULT) {
-                                self.tracked_addresses.insert(BALANCER_V2_VAULT);
+                            let vault = chain::active().balancer_v2_vault;
+                            if !self.tracked_addresses.contains(&vault) {
+                                self.tracked_addresses.insert(vault);
                                 info!(
                                     "🔧 Added Balancer V2 Vault for Swap/PoolBalanceChanged events: {:?}",
-                                    BALANCER_V2_VAULT
+                                    vault
                                 );
                             }
                             // Also track the POOL contract address: SwapFeePercentage-
@@ -276,17 +340,19 @@ impl PoolTracker {
             // ExEx at the next committed block boundary). Startup/full replace is
             // hydrated separately from the frozen anchor and must not surface here.
             if surface_newly_added {
-                self.newly_added.push(pool);
+                self.pending_seed.push(pool.clone());
+                self.newly_added.push(pool.clone());
             }
             added += 1;
         }
 
         // Ensure Liquidity Layer address is tracked when any Fluid pools exist
-        if self.fluid_count > 0 && !self.tracked_addresses.contains(&FLUID_LIQUIDITY_LAYER) {
-            self.tracked_addresses.insert(FLUID_LIQUIDITY_LAYER);
+        let liquidity_layer = chain::active().fluid_liquidity_layer;
+        if self.fluid_count > 0 && !self.tracked_addresses.contains(&liquidity_layer) {
+            self.tracked_addresses.insert(liquidity_layer);
             info!(
                 "🔧 Added Fluid Liquidity Layer to tracked addresses for LogOperate events: {:?}",
-                FLUID_LIQUIDITY_LAYER
+                liquidity_layer
             );
         }
 
@@ -301,6 +367,7 @@ impl PoolTracker {
             // Drop any not-yet-hydrated `.add` for this pool: a failed add followed
             // by a remove must not later hydrate a stale arena slot.
             self.newly_added.retain(|p| p.pool_id != pool_id);
+            self.pending_seed.retain(|p| p.pool_id != pool_id);
             match pool_id {
                 PoolIdentifier::Address(addr) => {
                     if let Some(pool) = self.pools_by_address.remove(&addr) {
@@ -367,6 +434,35 @@ impl PoolTracker {
         info!("Removed {} pools from whitelist", removed);
     }
 
+    /// Blacklist pools: remember them and untrack any that are currently
+    /// tracked. Removal goes through `remove_pools`, so dropped pools surface
+    /// via `take_newly_removed` for arena-slot removal like a `.remove`.
+    fn blacklist_pools(&mut self, pool_ids: Vec<PoolIdentifier>) {
+        for pool_id in &pool_ids {
+            self.blacklist.insert(pool_id.clone());
+        }
+        warn!(
+            "Blacklisted {} pools (total blacklisted: {})",
+            pool_ids.len(),
+            self.blacklist.len()
+        );
+        self.remove_pools(pool_ids);
+    }
+
+    /// Lift blacklist entries. Does not re-track the pools.
+    fn unblacklist_pools(&mut self, pool_ids: Vec<PoolIdentifier>) {
+        let lifted = pool_ids
+            .iter()
+            .filter(|id| self.blacklist.remove(id))
+            .count();
+        info!("Removed {} pools from blacklist", lifted);
+    }
+
+    /// Whether a pool identifier is blacklisted.
+    pub fn is_blacklisted(&self, pool_id: &PoolIdentifier) -> bool {
+        self.blacklist.contains(pool_id)
+    }
+
     /// Live full replacement of the whitelist (a `.full` snapshot on the live
     /// subscription). Applied as a topology DELTA against the current tracker:
     /// pools absent from the new snapshot are removed (surfacing via
@@ -409,7 +505,7 @@ impl PoolTracker {
             };
             if let Some(existing) = existing {
                 if existing.protocol == pool.protocol {
-                    *existing = pool.clone();
+                    *existing = Arc::new(pool.clone());
                 } else {
                     warn!(
                         pool_id = ?pool.pool_id,
@@ -434,6 +530,7 @@ impl PoolTracker {
     /// there is nothing to remove either.
     pub fn replace_startup(&mut self, pools: Vec<PoolMetadata>) {
         warn!("Startup whitelist replacement with {} pools", pools.len());
+        self.generation += 1;
 
         // Clear existing
         self.pools_by_address.clear();
@@ -462,6 +559,33 @@ impl PoolTracker {
         self.tracked_addresses.contains(address)
     }
 
+    /// Index the tracked addresses for [`PoolTracker::may_contain_tracked`].
+    /// Past `BLOOM_PREFILTER_MAX_ADDRESSES` a bloom nearly always matches
+    /// and testing each address costs more than scanning the logs, so the
+    /// index is dropped instead.
+    pub fn index_address_blooms(&mut self) {
+        self.address_blooms =
+            (self.tracked_addresses.len() <= BLOOM_PREFILTER_MAX_ADDRESSES).then(|| {
+                let blooms = self
+                    .tracked_addresses
+                    .iter()
+                    .map(|address| Bloom::from(BloomInput::Raw(address.as_slice())))
+                    .collect();
+                (self.generation, blooms)
+            });
+    }
+
+    /// Whether a block or receipt with this logs bloom may hold a log from a
+    /// tracked address. `true` when the index is absent or stale.
+    pub fn may_contain_tracked(&self, bloom: &Bloom) -> bool {
+        match &self.address_blooms {
+            Some((generation, blooms)) if *generation == self.generation => {
+                blooms.iter().any(|address| bloom.contains(address))
+            }
+            _ => true,
+        }
+    }
+
     /// Check if a pool ID is tracked
     pub fn is_tracked_pool_id(&self, pool_id: &[u8; 32]) -> bool {
         self.tracked_pool_ids.contains(pool_id)
@@ -469,12 +593,12 @@ impl PoolTracker {
 
     /// Full metadata for an address-keyed pool (V2/V3/Curve/Fluid), for re-scrape.
     pub fn pool_metadata(&self, address: &Address) -> Option<&PoolMetadata> {
-        self.pools_by_address.get(address)
+        self.pools_by_address.get(address).map(Arc::as_ref)
     }
 
     /// Full metadata for a pool-id-keyed pool (V4/Ekubo/Balancer/FluidV2).
     pub fn pool_metadata_by_id(&self, pool_id: &[u8; 32]) -> Option<&PoolMetadata> {
-        self.pools_by_id.get(pool_id)
+        self.pools_by_id.get(pool_id).map(Arc::as_ref)
     }
 
     /// Get the protocol of a pool tracked by address.
@@ -485,13 +609,18 @@ impl PoolTracker {
     /// Get pool metadata by address
     #[allow(dead_code)]
     pub fn get_by_address(&self, address: &Address) -> Option<&PoolMetadata> {
-        self.pools_by_address.get(address)
+        self.pools_by_address.get(address).map(Arc::as_ref)
     }
 
     /// Get pool metadata by pool ID
     #[allow(dead_code)]
     pub fn get_by_pool_id(&self, pool_id: &[u8; 32]) -> Option<&PoolMetadata> {
-        self.pools_by_id.get(pool_id)
+        self.pools_by_id.get(pool_id).map(Arc::as_ref)
+    }
+
+    /// Iterate over every tracked pool's metadata (address- and id-keyed).
+    pub fn pools(&self) -> impl Iterator<Item = &PoolMetadata> {
+        self.pools_by_address.values().chain(self.pools_by_id.values()).map(Arc::as_ref)
     }
 
     /// Get all tracked addresses
@@ -529,6 +658,7 @@ impl PoolTracker {
             "Registered Fluid pool config"
         );
         self.fluid_configs.insert(config.pool_address, config);
+        self.generation += 1;
     }
 
     /// Get a Fluid pool's cached config for storage reads + decoding.
@@ -593,6 +723,13 @@ impl PoolTracker {
         std::mem::take(&mut self.newly_added)
     }
 
+    /// Drain the live-added pools that have not been seeded yet. The ExEx reads
+    /// their absolute state at the committed block boundary and emits it inside
+    /// the block envelope, so consumers never apply deltas to an unknown base.
+    pub fn take_pending_seed(&mut self) -> Vec<PoolMetadata> {
+        std::mem::take(&mut self.pending_seed)
+    }
+
     /// Drain the pools removed since the last call. The ExEx removes their
     /// shadow-arena slots at the committed block boundary so a live `.remove`
     /// (or a live `.full` replace that drops pools) cannot leave stale active
@@ -602,7 +739,7 @@ impl PoolTracker {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 #[allow(dead_code)]
 pub struct PoolTrackerStats {
     pub total_pools: usize,
@@ -623,6 +760,90 @@ impl Default for PoolTracker {
     }
 }
 
+// ============================================================================
+// SHARED TRACKER (lock-free snapshot for the block loop)
+// ============================================================================
+
+/// Shared `PoolTracker` handle.
+///
+/// Writers (NATS whitelist task, admin, Fluid config resolution, block
+/// begin/end) serialize on a mutex. Whenever a write changes reader-visible
+/// state, the guard publishes a fresh immutable clone on drop. The block loop
+/// calls [`SharedPoolTracker::snapshot`] once per block and filters every log
+/// against that `Arc` without touching the lock, so the NATS task never
+/// contends with the hot path.
+///
+/// Because `begin_block` defers queued updates until `end_block`, a snapshot
+/// loaded after `begin_block` is exactly the membership the block is filtered
+/// against.
+pub struct SharedPoolTracker {
+    writer: Mutex<PoolTracker>,
+    snapshot: ArcSwap<PoolTracker>,
+}
+
+impl SharedPoolTracker {
+    pub fn new(mut tracker: PoolTracker) -> Self {
+        tracker.index_address_blooms();
+        Self {
+            snapshot: ArcSwap::from_pointee(tracker.clone()),
+            writer: Mutex::new(tracker),
+        }
+    }
+
+    /// Cheap `Arc` clone of the latest published tracker state.
+    pub fn snapshot(&self) -> Arc<PoolTracker> {
+        self.snapshot.load_full()
+    }
+
+    /// Exclusive write access. Republishes the snapshot on drop if the write
+    /// changed anything readers can observe.
+    pub async fn write(&self) -> PoolTrackerWriteGuard<'_> {
+        let guard = self.writer.lock().await;
+        let generation = guard.generation;
+        PoolTrackerWriteGuard {
+            guard,
+            snapshot: &self.snapshot,
+            generation,
+        }
+    }
+}
+
+impl Default for SharedPoolTracker {
+    fn default() -> Self {
+        Self::new(PoolTracker::new())
+    }
+}
+
+/// Write guard returned by [`SharedPoolTracker::write`].
+pub struct PoolTrackerWriteGuard<'a> {
+    guard: MutexGuard<'a, PoolTracker>,
+    snapshot: &'a ArcSwap<PoolTracker>,
+    generation: u64,
+}
+
+impl Deref for PoolTrackerWriteGuard<'_> {
+    type Target = PoolTracker;
+
+    fn deref(&self) -> &PoolTracker {
+        &self.guard
+    }
+}
+
+impl DerefMut for PoolTrackerWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PoolTracker {
+        &mut self.guard
+    }
+}
+
+impl Drop for PoolTrackerWriteGuard<'_> {
+    fn drop(&mut self) {
+        if self.guard.generation != self.generation {
+            self.guard.index_address_blooms();
+            self.snapshot.store(Arc::new(self.guard.clone()));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -645,6 +866,7 @@ mod tests {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         }
     }
 
@@ -662,6 +884,22 @@ mod tests {
         assert_eq!(tracker.stats().v3_pools, 1);
     }
 
+    #[test]
+    fn snapshot_clones_share_pool_metadata() {
+        let addr = Address::from([3u8; 20]);
+        let mut tracker = PoolTracker::new();
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            addr,
+            Protocol::UniswapV3,
+        )]));
+
+        let snapshot = tracker.clone();
+        assert!(Arc::ptr_eq(
+            &tracker.pools_by_address[&addr],
+            &snapshot.pools_by_address[&addr]
+        ));
+    }
+
     /// ITE-16 round-18: added pools surface via `take_newly_added` (for live-add
     /// shadow hydration); full replace/startup does not surface the whole snapshot,
     /// the drain empties it, dedup of duplicate adds holds, and `requeue_newly_added`
@@ -702,6 +940,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn address_blooms_rule_out_untracked_logs_until_stale() {
+        let tracked = Address::from([1u8; 20]);
+        let other = Address::from([2u8; 20]);
+        let bloom_of = |address: Address| Bloom::from(BloomInput::Raw(address.as_slice()));
+        let mut tracker = PoolTracker::new();
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            tracked,
+            Protocol::UniswapV2,
+        )]));
+
+        // Not indexed yet: nothing is ruled out.
+        assert!(tracker.may_contain_tracked(&bloom_of(other)));
+        tracker.index_address_blooms();
+        assert!(tracker.may_contain_tracked(&bloom_of(tracked)));
+        assert!(!tracker.may_contain_tracked(&bloom_of(other)));
+
+        // A membership change makes the index stale until rebuilt.
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            other,
+            Protocol::UniswapV2,
+        )]));
+        assert!(tracker.may_contain_tracked(&bloom_of(other)));
+    }
+
     /// Round-19 Warning: an `.add` removed before it hydrates must not linger in
     /// `newly_added` (else it would later hydrate a stale/untracked slot).
     #[test]
@@ -719,6 +982,40 @@ mod tests {
         );
     }
 
+    /// Live adds surface exactly once for state seeding, independent of the
+    /// hydration requeue; startup replace and removed-before-seed pools do not.
+    #[test]
+    fn pending_seed_drains_once_per_live_add() {
+        let mut tracker = PoolTracker::new();
+        let a = Address::from([1u8; 20]);
+        let b = Address::from([2u8; 20]);
+        tracker.replace_startup(vec![create_test_pool(a, Protocol::UniswapV2)]);
+        assert!(
+            tracker.take_pending_seed().is_empty(),
+            "startup is not seeded"
+        );
+
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            b,
+            Protocol::UniswapV2,
+        )]));
+        let hydrate = tracker.take_newly_added();
+        tracker.requeue_newly_added(hydrate);
+        assert_eq!(tracker.take_pending_seed().len(), 1);
+        assert!(
+            tracker.take_pending_seed().is_empty(),
+            "hydration requeue does not re-seed"
+        );
+
+        let c = Address::from([3u8; 20]);
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            c,
+            Protocol::UniswapV2,
+        )]));
+        tracker.queue_update(WhitelistUpdate::Remove(vec![PoolIdentifier::Address(c)]));
+        assert!(tracker.take_pending_seed().is_empty());
+    }
+
     /// Round-19 Critical: a Balancer pool tracks its CONTRACT address (`pool_id[..20]`)
     /// so pool-emitted SwapFeePercentageChanged logs pass the filter, and maps it
     /// back to the poolId. Removal untracks the address and clears the mapping.
@@ -973,6 +1270,85 @@ mod tests {
         );
     }
 
+    /// A blacklisted pool is untracked (surfacing for arena removal), is not
+    /// re-tracked by a later add, and the blacklist is applied at the block
+    /// boundary like any other update.
+    #[test]
+    fn blacklist_overrides_whitelist_membership() {
+        let mut tracker = PoolTracker::new();
+        let a = Address::from([0xDD; 20]);
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            a,
+            Protocol::UniswapV2,
+        )]));
+        let _ = tracker.take_newly_added();
+
+        tracker.begin_block();
+        tracker.queue_update(WhitelistUpdate::Blacklist(vec![PoolIdentifier::Address(a)]));
+        assert!(tracker.is_tracked_address(&a), "deferred until block end");
+        tracker.end_block();
+
+        assert!(!tracker.is_tracked_address(&a));
+        assert!(tracker.is_blacklisted(&PoolIdentifier::Address(a)));
+        assert_eq!(
+            tracker.take_newly_removed(),
+            vec![PoolIdentifier::Address(a)]
+        );
+
+        // Whitelist re-add (and live full replace) cannot resurrect it.
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            a,
+            Protocol::UniswapV2,
+        )]));
+        tracker.queue_update(WhitelistUpdate::Replace(vec![create_test_pool(
+            a,
+            Protocol::UniswapV2,
+        )]));
+        assert!(!tracker.is_tracked_address(&a));
+        assert!(tracker.take_newly_added().is_empty());
+
+        // Unblacklist alone does not re-track; the next add does.
+        tracker.queue_update(WhitelistUpdate::Unblacklist(vec![PoolIdentifier::Address(
+            a,
+        )]));
+        assert!(!tracker.is_tracked_address(&a));
+        tracker.queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+            a,
+            Protocol::UniswapV2,
+        )]));
+        assert!(tracker.is_tracked_address(&a));
+    }
+
+    /// Snapshots are immutable: a block-loop snapshot taken before an update is
+    /// unaffected by it, the update is visible to the next snapshot, and a
+    /// deferred (in-block) update only publishes once it applies at end_block.
+    #[tokio::test]
+    async fn shared_tracker_publishes_snapshot_on_change() {
+        let shared = SharedPoolTracker::default();
+        let a = Address::from([0xEE; 20]);
+
+        shared.write().await.begin_block();
+        let before = shared.snapshot();
+        shared
+            .write()
+            .await
+            .queue_update(WhitelistUpdate::Add(vec![create_test_pool(
+                a,
+                Protocol::UniswapV2,
+            )]));
+        assert!(
+            !shared.snapshot().is_tracked_address(&a),
+            "queued in-block update not yet published"
+        );
+
+        shared.write().await.end_block();
+        assert!(shared.snapshot().is_tracked_address(&a));
+        assert!(
+            !before.is_tracked_address(&a),
+            "earlier snapshot is unaffected"
+        );
+    }
+
     #[test]
     fn test_fluid_pool_remove() {
         let mut tracker = PoolTracker::new();
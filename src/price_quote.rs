@@ -0,0 +1,217 @@
+// Swap Price Normalization and USD Quoting
+//
+// Optional (`SWAP_QUOTES`): after every forward V3/V4 swap the ExEx emits a
+// `SwapQuote` update for the same pool and log position, carrying the
+// post-swap price as token1 per token0 in whole-token units, the absolute
+// amounts swapped, and — when `PRICE_FEED_DATABASE_URL` points at a database
+// with the `token_metadata` price feed — the approximate USD notional. Every
+// consumer otherwise repeats the same decimal math.
+//
+// Pools without known decimals for both tokens get no quote. The notional
+// uses token0's USD price when known, else token1's. Reverts are not quoted:
+// the quote is informational and has no inverse.
+
+use crate::types::{PoolMetadata, PoolUpdate};
+use alloy_primitives::{Address, I256, U256};
+use arc_swap::ArcSwap;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Default price-feed refresh interval.
+const DEFAULT_REFRESH_SECS: u64 = 60;
+
+/// Builds `SwapQuote` updates from swap amounts, pool decimals, and a
+/// periodically refreshed USD price map.
+#[derive(Debug, Default)]
+pub struct SwapQuoter {
+    prices: Arc<ArcSwap<HashMap<Address, f64>>>,
+}
+
+impl SwapQuoter {
+    /// `None` unless `SWAP_QUOTES` is `1` / `true`. Starts the price-feed
+    /// refresh task when `PRICE_FEED_DATABASE_URL` is set.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("SWAP_QUOTES")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let quoter = Self::default();
+        if let Ok(database_url) = std::env::var("PRICE_FEED_DATABASE_URL") {
+            let refresh = std::env::var("PRICE_FEED_REFRESH_SECS")
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_REFRESH_SECS);
+            tokio::spawn(refresh_prices(
+                database_url,
+                Duration::from_secs(refresh.max(1)),
+                quoter.prices.clone(),
+            ));
+        }
+        Some(quoter)
+    }
+
+    /// Quote a forward V3/V4 swap. `None` for other updates or when either
+    /// token's decimals are unknown.
+    pub fn quote(
+        &self,
+        pool: &PoolMetadata,
+        update: &PoolUpdate,
+        amount0: I256,
+        amount1: I256,
+    ) -> Option<PoolUpdate> {
+        let sqrt_price_x96 = match update {
+            PoolUpdate::V3Swap { sqrt_price_x96, .. }
+            | PoolUpdate::V4Swap { sqrt_price_x96, .. } => *sqrt_price_x96,
+            _ => return None,
+        };
+        let decimals0 = pool.token0_decimals?;
+        let decimals1 = pool.token1_decimals?;
+        let amount0 = normalize_amount(amount0, decimals0);
+        let amount1 = normalize_amount(amount1, decimals1);
+        let prices = self.prices.load();
+        let usd_notional = prices
+            .get(&pool.token0)
+            .map(|usd| amount0 * usd)
+            .or_else(|| prices.get(&pool.token1).map(|usd| amount1 * usd));
+        Some(PoolUpdate::SwapQuote {
+            price: normalized_price(sqrt_price_x96, decimals0, decimals1),
+            amount0,
+            amount1,
+            usd_notional,
+        })
+    }
+}
+
+/// token1 per token0 in whole-token units from a Q64.96 sqrt price.
+pub fn normalized_price(sqrt_price_x96: U256, decimals0: u8, decimals1: u8) -> f64 {
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96);
+    sqrt_price * sqrt_price * 10f64.powi(i32::from(decimals0) - i32::from(decimals1))
+}
+
+/// token1 per token0 in whole-token units from V2-style reserves. `None` if
+/// either reserve is empty.
+pub fn reserves_price(reserve0: u128, reserve1: u128, decimals0: u8, decimals1: u8) -> Option<f64> {
+    if reserve0 == 0 || reserve1 == 0 {
+        return None;
+    }
+    let amount0 = reserve0 as f64 / 10f64.powi(i32::from(decimals0));
+    let amount1 = reserve1 as f64 / 10f64.powi(i32::from(decimals1));
+    Some(amount1 / amount0)
+}
+
+/// Absolute raw token amount in whole-token units.
+pub fn normalize_amount(amount: I256, decimals: u8) -> f64 {
+    f64::from(amount.unsigned_abs()) / 10f64.powi(i32::from(decimals))
+}
+
+/// Reload `token_metadata.price_usd` into the shared map every `interval`.
+/// Failures keep the previous map.
+async fn refresh_prices(
+    database_url: String,
+    interval: Duration,
+    prices: Arc<ArcSwap<HashMap<Address, f64>>>,
+) {
+    let pool = match PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_secs(10))
+        .connect(&database_url)
+        .await
+    {
+        Ok(pool) => pool,
+        Err(e) => {
+            warn!(error = %e, "Price feed DB unavailable, swap quotes carry no USD notional");
+            return;
+        }
+    };
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let rows =
+            sqlx::query("SELECT token_address, price_usd FROM token_metadata WHERE price_usd > 0")
+                .fetch_all(&pool)
+                .await;
+        match rows {
+            Ok(rows) => {
+                let map: HashMap<Address, f64> = rows
+                    .iter()
+                    .filter_map(|row| {
+                        let address: String = row.try_get("token_address").ok()?;
+                        let price: f64 = row.try_get("price_usd").ok()?;
+                        Some((Address::from_str(&address).ok()?, price))
+                    })
+                    .collect();
+                if prices.load().is_empty() {
+                    info!(tokens = map.len(), "💲 Loaded token price feed");
+                }
+                prices.store(Arc::new(map));
+            }
+            Err(e) => warn!(error = %e, "Price feed refresh failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PoolIdentifier, Protocol};
+
+    #[test]
+    fn quotes_normalize_decimals_and_usd() {
+        let usdc = Address::from([1u8; 20]);
+        let weth = Address::from([2u8; 20]);
+        let pool = PoolMetadata {
+            pool_id: PoolIdentifier::Address(Address::from([3u8; 20])),
+            token0: usdc,
+            token1: weth,
+            protocol: Protocol::UniswapV3,
+            factory: Address::ZERO,
+            tick_spacing: Some(10),
+            fee: Some(500),
+            token0_decimals: Some(6),
+            token1_decimals: Some(18),
+            extra_tokens: vec![],
+            twocrypto_version: None,
+            ekubo_fee: None,
+            ekubo_type_config: None,
+            balancer_weights: None,
+            balancer_swap_fee: None,
+            balancer_version: None,
+            event_mask: None,
+        };
+        let quoter = SwapQuoter::default();
+        quoter.prices.store(Arc::new(HashMap::from([(usdc, 1.0)])));
+
+        // Raw price 1e12 (sqrt = 1e6 * 2^96) scaled by 10^(6-18) is 1 token1
+        // per token0.
+        let update = PoolUpdate::V3Swap {
+            sqrt_price_x96: U256::from(1_000_000u64) << 96,
+            liquidity: 1,
+            tick: 0,
+        };
+        let Some(PoolUpdate::SwapQuote {
+            price,
+            amount0,
+            amount1,
+            usd_notional,
+        }) = quoter.quote(
+            &pool,
+            &update,
+            I256::try_from(2_500_000_000i64).unwrap(),
+            I256::try_from(-1_000_000_000_000_000_000i64).unwrap(),
+        )
+        else {
+            panic!("expected a quote");
+        };
+        assert!((price - 1.0).abs() < 1e-9);
+        assert!((amount0 - 2_500.0).abs() < 1e-9);
+        assert!((amount1 - 1.0).abs() < 1e-9);
+        assert_eq!(usd_notional, Some(2_500.0));
+    }
+}
@@ -0,0 +1,171 @@
+// Per-block Reorg Journal
+//
+// The reorg/revert paths used to rebuild the inverse stream by re-decoding the
+// old chain's receipts. If those receipts are unavailable, or the decoder or
+// whitelist changed since the block was emitted, the revert is lossy. The
+// journal keeps, for the last N committed blocks, exactly the `PoolUpdate`
+// messages the forward path emitted (event updates, Fluid reserves, seeds and
+// verifier corrections, with their event-mask outcome) plus the Fluid pools it
+// touched, keyed by block hash. A revert inverts those messages in exact
+// reverse emission order, without re-deciding against the current whitelist
+// or re-reading state.
+//
+// Bounded by `REORG_JOURNAL_BLOCKS` (default 128, 0 disables). Blocks older
+// than the window fall back to re-decoding receipts.
+
+use crate::events::DecodedEvent;
+use crate::types::PoolUpdateMessage;
+use alloy_primitives::{Address, B256};
+use std::collections::VecDeque;
+
+/// Default number of most recent blocks kept.
+pub const DEFAULT_JOURNAL_BLOCKS: usize = 128;
+
+/// A decoded event with its original position in the block.
+#[derive(Debug, Clone)]
+pub struct JournaledEvent {
+    pub tx_index: u64,
+    pub log_index: u64,
+    pub event: DecodedEvent,
+}
+
+/// One update the forward path sent. `masked` updates moved the state engine
+/// but wrote no frame; their inverse is withheld the same way.
+#[derive(Debug, Clone)]
+pub struct JournaledUpdate {
+    pub update: PoolUpdateMessage,
+    pub masked: bool,
+}
+
+/// Everything the forward path emitted for one block.
+#[derive(Debug, Clone)]
+pub struct JournalBlock {
+    pub block_number: u64,
+    pub block_hash: B256,
+    /// In emission order.
+    pub updates: Vec<JournaledUpdate>,
+    pub fluid_touched: Vec<Address>,
+}
+
+/// Bounded per-block journal of emitted events.
+#[derive(Debug)]
+pub struct ReorgJournal {
+    blocks: VecDeque<JournalBlock>,
+    capacity: usize,
+}
+
+impl ReorgJournal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Capacity from `REORG_JOURNAL_BLOCKS`.
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("REORG_JOURNAL_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_JOURNAL_BLOCKS);
+        Self::new(capacity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Record a forward-applied block. A block at height N supersedes any
+    /// journaled block at N or above (those were reorged away).
+    pub fn record(&mut self, block: JournalBlock) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self
+            .blocks
+            .back()
+            .is_some_and(|b| b.block_number >= block.block_number)
+        {
+            self.blocks.pop_back();
+        }
+        self.blocks.push_back(block);
+        while self.blocks.len() > self.capacity {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Remove and return the journaled block with this exact number and hash.
+    pub fn take(&mut self, block_number: u64, block_hash: B256) -> Option<JournalBlock> {
+        let pos = self
+            .blocks
+            .iter()
+            .position(|b| b.block_number == block_number && b.block_hash == block_hash)?;
+        self.blocks.remove(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PoolIdentifier, PoolUpdate, Protocol, UpdateType};
+
+    fn block(block_number: u64, hash_byte: u8) -> JournalBlock {
+        JournalBlock {
+            block_number,
+            block_hash: B256::repeat_byte(hash_byte),
+            updates: vec![JournaledUpdate {
+                update: PoolUpdateMessage {
+                    chain_id: 1,
+                    pool_id: PoolIdentifier::Address(Address::from([hash_byte; 20])),
+                    protocol: Protocol::UniswapV2,
+                    update_type: UpdateType::Sync,
+                    block_number,
+                    block_timestamp: 0,
+                    tx_index: 0,
+                    log_index: 0,
+                    is_revert: false,
+                    update: PoolUpdate::V2Sync {
+                        reserve0: 1,
+                        reserve1: 2,
+                    },
+                },
+                masked: false,
+            }],
+            fluid_touched: vec![],
+        }
+    }
+
+    #[test]
+    fn journal_is_bounded_and_keyed_by_hash() {
+        let mut journal = ReorgJournal::new(2);
+        journal.record(block(1, 0x01));
+        journal.record(block(2, 0x02));
+        journal.record(block(3, 0x03));
+        assert_eq!(journal.len(), 2, "oldest block evicted");
+        assert!(journal.take(1, B256::repeat_byte(0x01)).is_none());
+
+        // A different fork's block at the same height is not a match.
+        assert!(journal.take(3, B256::repeat_byte(0xEE)).is_none());
+        let taken = journal.take(3, B256::repeat_byte(0x03)).unwrap();
+        assert_eq!(taken.updates.len(), 1);
+        assert!(
+            journal.take(3, B256::repeat_byte(0x03)).is_none(),
+            "taken once"
+        );
+    }
+
+    #[test]
+    fn new_block_supersedes_same_or_higher_heights() {
+        let mut journal = ReorgJournal::new(8);
+        journal.record(block(10, 0x0A));
+        journal.record(block(11, 0x0B));
+        journal.record(block(11, 0xBB));
+        assert_eq!(journal.len(), 2);
+        assert!(journal.take(11, B256::repeat_byte(0x0B)).is_none());
+        assert!(journal.take(11, B256::repeat_byte(0xBB)).is_some());
+    }
+}
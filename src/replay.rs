@@ -0,0 +1,245 @@
+// Replay / Backtest Runner
+//
+//   exex replay --from <block> --to <block> (--datadir <path> | --rpc-url <url>)
+//
+// Drives the liquidity pipeline over a historical block range without a live
+// node. Headers, receipts and state come either from a node's datadir (MDBX +
+// static files, opened read-only, so the node may keep running) or from an
+// archive RPC endpoint, and every block goes through the same
+// `process_committed_block` as the live ExEx. Given the same whitelist and
+// env configuration, the socket stream (BeginBlock … EndBlock, seeds, quotes,
+// depth snapshots) is the one the live node emitted for those blocks, which
+// makes known blocks usable as regression fixtures for consumers.
+//
+// The whitelist is a JSON array of `PoolMetadata` (`--whitelist`) or, without
+// one, the usual NATS startup snapshot. The range is canonical history, so no
+// reorg is ever replayed; the arena writer, checkpoint and confirmation
+// buffer are live-only and stay off.
+
+use crate::chain;
+use crate::nats_client::WhitelistNatsClient;
+use crate::pool_metadata_db::{self, PoolMetadataDb};
+use crate::shutdown::FLUSH_TIMEOUT;
+use crate::socket::PoolUpdateSocketServer;
+use crate::types::{ControlMessage, PoolMetadata, Protocol};
+use crate::{process_committed_block, resolve_fluid_config_batch, LiquidityExEx};
+use alloy_consensus::TxReceipt;
+use alloy_primitives::{Address, Log};
+use clap::Parser;
+use reth::chainspec::{ChainSpec, EthChainSpec, HOLESKY, HOODI, MAINNET, SEPOLIA};
+use reth::providers::StateProviderFactory;
+use reth_node_ethereum::EthereumNode;
+use reth_provider::providers::{BlockchainProvider, ReadOnlyConfig};
+use reth_provider::BlockReader;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// `exex replay` arguments.
+#[derive(Debug, Parser)]
+#[command(
+    name = "exex replay",
+    about = "Replay historical blocks through the liquidity pipeline"
+)]
+pub struct ReplayArgs {
+    /// First block to replay.
+    #[arg(long)]
+    pub from: u64,
+    /// Last block to replay (inclusive).
+    #[arg(long)]
+    pub to: u64,
+    /// Read blocks and state from this reth datadir.
+    #[arg(long, required_unless_present = "rpc_url", conflicts_with = "rpc_url")]
+    pub datadir: Option<PathBuf>,
+    /// Read blocks and state from this archive RPC endpoint.
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+    /// Chain of the datadir / endpoint: mainnet, sepolia, holesky or hoodi.
+    #[arg(long, default_value = "mainnet")]
+    pub chain: String,
+    /// Pools to track, as a JSON array of `PoolMetadata`. Without it the
+    /// whitelist snapshot is requested over NATS (`NATS_URL`).
+    #[arg(long)]
+    pub whitelist: Option<PathBuf>,
+    /// Socket to serve the stream on (default `EXEX_SOCKET`), so a replay
+    /// can run beside a live node.
+    #[arg(long)]
+    pub socket: Option<String>,
+    /// Clients to wait for before the first block; 0 starts at once.
+    #[arg(long, default_value_t = 1)]
+    pub wait_clients: usize,
+}
+
+/// Entry point for `exex replay`: `args` are the process arguments after the
+/// binary name.
+pub fn main(args: impl IntoIterator<Item = String>) -> eyre::Result<()> {
+    use reth_tracing::{RethTracer, Tracer};
+
+    let args = ReplayArgs::parse_from(args);
+    let _guard = RethTracer::new().init()?;
+    if args.from > args.to {
+        eyre::bail!("--from {} is past --to {}", args.from, args.to);
+    }
+    if let Some(socket) = &args.socket {
+        // Before the runtime starts: no other thread reads the environment yet.
+        std::env::set_var("EXEX_SOCKET", socket);
+    }
+    tokio::runtime::Runtime::new()?.block_on(run(args))
+}
+
+async fn run(args: ReplayArgs) -> eyre::Result<()> {
+    let chain_spec = chain_spec(&args.chain)?;
+    chain::init(chain_spec.chain().id());
+    let whitelist = load_whitelist(&args).await?;
+
+    if let Some(datadir) = &args.datadir {
+        let factory = EthereumNode::provider_factory_builder()
+            .open_read_only(chain_spec, ReadOnlyConfig::from_datadir(datadir))?;
+        replay(BlockchainProvider::new(factory)?, whitelist, &args).await
+    } else if let Some(rpc_url) = &args.rpc_url {
+        let rpc = alloy_provider::ProviderBuilder::new()
+            .network::<alloy_network::AnyNetwork>()
+            .connect(rpc_url)
+            .await?;
+        let provider = reth_storage_rpc_provider::RpcBlockchainProvider::<
+            _,
+            EthereumNode,
+            alloy_network::AnyNetwork,
+        >::new(rpc);
+        replay(provider, whitelist, &args).await
+    } else {
+        unreachable!("clap requires --datadir or --rpc-url")
+    }
+}
+
+fn chain_spec(name: &str) -> eyre::Result<Arc<ChainSpec>> {
+    Ok(match name {
+        "mainnet" => MAINNET.clone(),
+        "sepolia" => SEPOLIA.clone(),
+        "holesky" => HOLESKY.clone(),
+        "hoodi" => HOODI.clone(),
+        other => eyre::bail!("unsupported --chain {other:?}"),
+    })
+}
+
+async fn load_whitelist(args: &ReplayArgs) -> eyre::Result<Vec<PoolMetadata>> {
+    let mut pools: Vec<PoolMetadata> = match &args.whitelist {
+        Some(path) => serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| eyre::eyre!("invalid whitelist {}: {e}", path.display()))?,
+        None => {
+            let nats_url =
+                std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+            WhitelistNatsClient::connect(&nats_url)
+                .await?
+                .request_full_snapshot(&chain::chain_name(), Duration::from_secs(5))
+                .await?
+        }
+    };
+    let metadata_db = PoolMetadataDb::from_env().await?;
+    let rejected = pool_metadata_db::complete(metadata_db.as_ref(), &mut pools).await;
+    if rejected > 0 {
+        warn!(
+            rejected,
+            "Whitelist pools without token addresses not replayed"
+        );
+    }
+    if pools.is_empty() {
+        eyre::bail!("whitelist is empty, nothing to replay");
+    }
+    Ok(pools)
+}
+
+/// Replay `args.from..=args.to` from `provider` onto the socket.
+async fn replay<P>(provider: P, pools: Vec<PoolMetadata>, args: &ReplayArgs) -> eyre::Result<()>
+where
+    P: StateProviderFactory + BlockReader + Clone + Send + Sync + 'static,
+    P::Receipt: TxReceipt<Log = Log> + Sync,
+{
+    let socket_server = PoolUpdateSocketServer::new()?;
+    let socket_tx = socket_server.get_sender();
+    let backfill_tx = socket_server.backfill_sender();
+    let clients = socket_server.client_counter();
+    let socket_task = tokio::spawn(async move {
+        if let Err(e) = socket_server.run().await {
+            warn!("Socket server error: {}", e);
+        }
+    });
+
+    let mut exex = LiquidityExEx::new(socket_tx, None, None);
+    exex.configure_from_env();
+    exex.backfill_tx = Some(backfill_tx);
+    // Replayed blocks are old by definition; emit them as the live node did
+    // when they were new.
+    exex.catch_up = None;
+    let fluid_addrs: Vec<Address> = pools
+        .iter()
+        .filter(|p| p.protocol == Protocol::Fluid)
+        .filter_map(|p| p.pool_id.as_address())
+        .collect();
+    let fluid_configs = if fluid_addrs.is_empty() {
+        Vec::new()
+    } else {
+        let rpc_url = args.rpc_url.clone().unwrap_or_else(|| {
+            std::env::var("RPC_URL").unwrap_or_else(|_| "http://localhost:8545".to_string())
+        });
+        resolve_fluid_config_batch(fluid_addrs, &rpc_url).await
+    };
+    {
+        let mut tracker = exex.pool_tracker.write().await;
+        tracker.replace_startup(pools);
+        for config in fluid_configs {
+            tracker.register_fluid_config(config);
+        }
+    }
+
+    if args.wait_clients > 0 {
+        info!(clients = args.wait_clients, "Waiting for socket clients");
+        while clients() < args.wait_clients {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    info!(from = args.from, to = args.to, "⏯️ Replaying blocks");
+    let started = Instant::now();
+    let mut stream_seq: u64 = 0;
+    for block_number in args.from..=args.to {
+        let header = provider
+            .sealed_header(block_number)?
+            .ok_or_else(|| eyre::eyre!("block {block_number} not found"))?;
+        let receipts = provider
+            .receipts_by_block(block_number.into())?
+            .ok_or_else(|| eyre::eyre!("receipts for block {block_number} not found"))?;
+
+        // The live path drops frames on a full channel; a replay has no
+        // deadline, so it lets the socket catch up before every block.
+        while exex.socket_tx.sender().capacity() < exex.socket_tx.sender().max_capacity() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        process_committed_block(
+            &mut exex,
+            &provider,
+            &mut stream_seq,
+            header.header(),
+            header.hash(),
+            &receipts,
+            None,
+        )
+        .await?;
+    }
+    info!(
+        blocks = args.to - args.from + 1,
+        events = exex.events_processed,
+        elapsed = ?started.elapsed(),
+        "✅ Replay complete"
+    );
+
+    if exex.socket_tx.send(ControlMessage::Shutdown).await.is_ok()
+        && tokio::time::timeout(FLUSH_TIMEOUT, socket_task)
+            .await
+            .is_err()
+    {
+        warn!("Socket not drained within {:?}", FLUSH_TIMEOUT);
+    }
+    Ok(())
+}
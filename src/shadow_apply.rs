@@ -111,7 +111,14 @@ struct LiquidityChange {
 fn extract_liquidity(event: &PoolUpdateMessage) -> Option<LiquidityChange> {
     match event.update_type {
         UpdateType::Mint | UpdateType::Burn => {}
-        UpdateType::Swap => return None,
+        UpdateType::Swap
+        | UpdateType::Sync
+        | UpdateType::Collect
+        | UpdateType::Flash
+        | UpdateType::Donate
+        | UpdateType::Initialize
+        | UpdateType::Seed
+        | UpdateType::ParamChange => return None,
     }
     match &event.update {
         PoolUpdate::V3Liquidity {
@@ -489,6 +496,18 @@ pub fn apply_live_event(
             }
         }
 
+        // ── Live-add seeds: consumer-facing only. The shadow arena hydrates
+        // added pools itself from the same block state (`hydrate_added`).
+        PoolUpdate::V3Snapshot { .. } | PoolUpdate::V4Snapshot { .. } => return Ok(false),
+
+        // ── State-engine output: the shadow applies the source delta instead.
+        PoolUpdate::ConcentratedState { .. } => return Ok(false),
+
+        // ── Swap quote / depth enrichment: informational only.
+        PoolUpdate::SwapQuote { .. }
+        | PoolUpdate::LiquidityDepth { .. }
+        | PoolUpdate::TracedSwap { .. } => return Ok(false),
+
         // ── Fluid DEX: absolute reserve snapshot ────────────────────────
         PoolUpdate::FluidState { state } => {
             if let PoolIdentifier::Address(addr) = &event.pool_id {
@@ -585,6 +604,26 @@ pub fn apply_reorg_epilogue(
             }
             writer.update_v2_reserves(addr, *reserve0, *reserve1)?;
         }
+        ReorgEpilogueUpdate::PoolStateFinal {
+            pool_id,
+            protocol,
+            update,
+        } => {
+            // Same absolute write as the forward update it settles.
+            let event = PoolUpdateMessage {
+                chain_id: crate::chain::active().chain_id,
+                pool_id: pool_id.clone(),
+                protocol: *protocol,
+                update_type: UpdateType::Sync,
+                block_number: 0,
+                block_timestamp: 0,
+                tx_index: 0,
+                log_index: 0,
+                is_revert: false,
+                update: update.clone(),
+            };
+            return apply_live_event(writer, &event, &mut false);
+        }
     }
     Ok(true)
 }
@@ -16,6 +16,7 @@
 //! a rich whitelist plus anchor-pinned storage reads. Live per-block apply lands
 //! in 3c; reorg writes in 3d.
 
+use crate::chain;
 use crate::types::{PoolIdentifier, PoolUpdateMessage, Protocol, ReorgEpilogueUpdate};
 use arena_layout::{
     AnyEkuboPool, AnyUniswapV3Pool, AnyUniswapV4Pool, CurveStablePoolData, CurveTricryptoPoolData,
@@ -435,6 +436,19 @@ impl ShadowArena {
         self.arena.region().header.get_slot_version()
     }
 
+    /// Gross liquidity at `tick` of a Low-tier V3 pool slot (test/diagnostic use).
+    #[allow(dead_code)] // used by the masked-event regression test
+    pub(crate) fn v3_tick_gross(&mut self, address: &[u8; 20], tick: i32) -> Option<u128> {
+        let writer = SharedArenaWriter::new(self.arena.region_mut());
+        match writer.get_v3_pool(address)? {
+            AnyUniswapV3Pool::Low(pool) => pool.ticks[..pool.tick_count as usize]
+                .iter()
+                .find(|(t, _, _)| *t == tick)
+                .map(|(_, gross, _)| *gross),
+            _ => None,
+        }
+    }
+
     /// Open (creating if needed) the shadow arena at `path` and reset it to a
     /// fresh state — matching arena_service, which resets header + slot
     /// assignments on start so tracker state and topology begin in sync.
@@ -786,6 +800,7 @@ impl ShadowArena {
                 ReorgEpilogueUpdate::Slot0Final { pool_id, .. } => pool_id,
                 ReorgEpilogueUpdate::FluidStateFinal { pool_id, .. } => pool_id,
                 ReorgEpilogueUpdate::V2ReservesFinal { pool_id, .. } => pool_id,
+                ReorgEpilogueUpdate::PoolStateFinal { pool_id, .. } => pool_id,
             };
             self.updated_this_block.push(to_wire_ident(pool_id));
         }
@@ -1152,6 +1167,7 @@ mod tests {
 
     fn v2_delta_event(pool: [u8; 20], block: u64, a0: i64, a1: i64) -> PoolUpdateMessage {
         PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::Address(Address::from(pool)),
             protocol: Protocol::UniswapV2,
             update_type: UpdateType::Swap,
@@ -1174,6 +1190,7 @@ mod tests {
         reserve1: u128,
     ) -> PoolUpdateMessage {
         PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::Address(Address::from(pool)),
             protocol: Protocol::UniswapV2,
             update_type: UpdateType::Swap,
@@ -1314,6 +1331,7 @@ mod tests {
         );
 
         let ev = PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::Address(Address::from(addr(0xA3))),
             protocol: Protocol::UniswapV3,
             update_type: UpdateType::Swap,
@@ -1378,6 +1396,7 @@ mod tests {
         );
 
         let ev = PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::PoolId(ekubo_id),
             protocol: Protocol::Ekubo,
             update_type: UpdateType::Mint,
@@ -1651,6 +1670,7 @@ mod tests {
         is_revert: bool,
     ) -> PoolUpdateMessage {
         PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::PoolId(pool_id),
             protocol: Protocol::Ekubo,
             update_type: if delta >= 0 {
@@ -1834,6 +1854,7 @@ mod tests {
         is_revert: bool,
     ) -> PoolUpdateMessage {
         PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::Address(Address::from(address)),
             protocol: Protocol::UniswapV3,
             update_type: if delta >= 0 {
@@ -1983,6 +2004,7 @@ mod tests {
         // Low holds 50 ticks; 26 distinct mints (2 new ticks each) overflow it.
         for i in 0..26i32 {
             let ev = PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(Address::from(a)),
                 protocol: Protocol::UniswapV3,
                 update_type: UpdateType::Mint,
@@ -2171,6 +2193,7 @@ mod tests {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         let mut tracker = PoolTracker::new();
@@ -2353,6 +2376,7 @@ mod tests {
         // bypasses the replay guard yet still records the overflow for promotion.
         for i in 0..26i32 {
             let ev = PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
                 pool_id: PoolIdentifier::Address(Address::from(a)),
                 protocol: Protocol::UniswapV3,
                 update_type: UpdateType::Mint,
@@ -2544,6 +2568,7 @@ mod tests {
         );
 
         let mk = |pool: [u8; 20], block: u64, i: i32| PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::Address(Address::from(pool)),
             protocol: Protocol::UniswapV3,
             update_type: UpdateType::Mint,
@@ -2670,6 +2695,7 @@ mod tests {
         hydrate_balancer_two_token(&mut shadow, a);
 
         let fee_ev = |fee: u64, is_revert: bool| PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::PoolId(a),
             protocol: Protocol::BalancerV2Weighted,
             update_type: UpdateType::Swap,
@@ -2715,6 +2741,7 @@ mod tests {
 
         // Event order reversed: token B then token A; +500 to B, -300 from A.
         let ev = PoolUpdateMessage {
+            chain_id: chain::active().chain_id,
             pool_id: PoolIdentifier::PoolId(a),
             protocol: Protocol::BalancerV2Weighted,
             update_type: UpdateType::Mint,
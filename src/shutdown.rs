@@ -0,0 +1,86 @@
+// Graceful Shutdown
+//
+// On SIGTERM / node shutdown reth fires its shutdown signal and waits (with a
+// timeout) for every outstanding `GracefulShutdown` guard before the runtime
+// drops its tasks. `ShutdownSignal` takes a guard the moment the signal fires,
+// from a watcher task, so an ExEx in the middle of a block is not cut off: it
+// finishes the notification, sees the shutdown before the next one, flushes
+// (socket frames, DB batches, persisted state) and only then releases the
+// guard.
+//
+// An ExEx must not return while the node runs — reth treats that as a crash —
+// so `finish` parks until the runtime stops.
+
+use futures::{TryStream, TryStreamExt};
+use reth::tasks::shutdown::GracefulShutdown;
+use reth::tasks::TaskExecutor;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::info;
+
+/// Upper bound for any single flush step, so a dead consumer or database
+/// cannot hold the node's shutdown for reth's whole timeout.
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct ShutdownSignal {
+    name: &'static str,
+    guard_rx: oneshot::Receiver<GracefulShutdown>,
+    /// Held while flushing; dropping it lets the node finish shutting down.
+    guard: Option<GracefulShutdown>,
+    fired: bool,
+}
+
+impl ShutdownSignal {
+    pub fn new(name: &'static str, executor: &TaskExecutor) -> Self {
+        let signal = executor.on_shutdown_signal().clone();
+        let (guard_tx, guard_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = guard_tx.send(signal.await);
+        });
+        Self {
+            name,
+            guard_rx,
+            guard: None,
+            fired: false,
+        }
+    }
+
+    /// Resolves once the node is shutting down (immediately after that).
+    /// Cancel safe, so it can sit in a `select!`.
+    pub async fn wait(&mut self) {
+        if !self.fired {
+            // A dropped watcher means the runtime is going away: shut down too.
+            self.guard = (&mut self.guard_rx).await.ok();
+            self.fired = true;
+            info!(exex = self.name, "Shutdown signal received, flushing");
+        }
+    }
+
+    /// The next notification, or `None` once the node is shutting down or
+    /// the stream ended.
+    pub async fn next<S>(&mut self, notifications: &mut S) -> eyre::Result<Option<S::Ok>>
+    where
+        S: TryStream<Error = eyre::Report> + Unpin,
+    {
+        if self.fired {
+            return Ok(None);
+        }
+        tokio::select! {
+            biased;
+            () = self.wait() => Ok(None),
+            next = notifications.try_next() => next,
+        }
+    }
+
+    /// Release the guard after the flush. On shutdown this never returns;
+    /// when the notification stream ended instead, it returns at once.
+    pub async fn finish(self) -> eyre::Result<()> {
+        let fired = self.fired;
+        drop(self.guard);
+        if fired {
+            info!(exex = self.name, "Flushed, waiting for the node to stop");
+            std::future::pending::<()>().await;
+        }
+        Ok(())
+    }
+}
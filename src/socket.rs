@@ -2,13 +2,25 @@
 //
 // Sends pool state updates to connected orderbook engine clients
 
+use crate::exex_metrics;
 use crate::types::ControlMessage;
+use alloy_eips::BlockNumHash;
 use eyre::Result;
+use std::collections::VecDeque;
+use std::io::{self, IoSlice};
 use std::path::Path;
+use std::sync::Mutex;
 use tokio::{
-    io::AsyncWriteExt,
+    io::{AsyncWrite, AsyncWriteExt},
     net::{UnixListener, UnixStream},
-    sync::{broadcast, mpsc},
+    sync::{
+        broadcast,
+        mpsc::{
+            self,
+            error::{SendError, TrySendError},
+        },
+        watch,
+    },
 };
 use tracing::{error, info, warn};
 
@@ -26,15 +38,233 @@ pub fn socket_path_from_env() -> String {
 
 /// Bounded channel capacity between ExEx producer and socket broadcast loop.
 /// 50k messages ≈ several thousand blocks worth of events. If exceeded, the
-/// ExEx drops messages rather than accumulating unbounded memory.
+/// ExEx drops (or, with `SOCKET_OVERFLOW=spill`, holds back) messages rather
+/// than accumulating unbounded memory. Override with `SOCKET_CHANNEL_CAPACITY`.
 const CHANNEL_CAPACITY: usize = 50_000;
 
+/// Backfill frames queued ahead of the broadcast loop. Kept small: the replay
+/// blocks on it, and the loop only drains it between block envelopes.
+const BACKFILL_CHANNEL_CAPACITY: usize = 1_024;
+
+/// Frames held back by `SOCKET_OVERFLOW=spill` unless `SOCKET_SPILL_MAX` is set.
+const DEFAULT_SPILL_MAX: usize = 500_000;
+
+fn channel_capacity_from_env() -> usize {
+    std::env::var("SOCKET_CHANNEL_CAPACITY")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&capacity| capacity > 0)
+        .unwrap_or(CHANNEL_CAPACITY)
+}
+
+/// What the ExEx does with a frame when the socket channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketOverflow {
+    /// Drop it (default): the ExEx never waits on the socket.
+    Drop,
+    /// Hold up to `max` frames back in stream order; they are sent, waiting
+    /// on the socket, after each notification. Past `max` frames are dropped.
+    Spill { max: usize },
+}
+
+impl SocketOverflow {
+    /// `SOCKET_OVERFLOW` (`drop` | `spill`) and `SOCKET_SPILL_MAX`.
+    pub fn from_env() -> Self {
+        match std::env::var("SOCKET_OVERFLOW").as_deref() {
+            Ok("spill") => Self::Spill {
+                max: std::env::var("SOCKET_SPILL_MAX")
+                    .ok()
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .unwrap_or(DEFAULT_SPILL_MAX),
+            },
+            Ok("drop") | Err(_) => Self::Drop,
+            Ok(other) => {
+                warn!(
+                    "Unknown SOCKET_OVERFLOW {:?}, dropping frames on overflow",
+                    other
+                );
+                Self::Drop
+            }
+        }
+    }
+}
+
+/// The ExEx's end of the socket channel, applying its `SocketOverflow`.
+/// Every dropped frame is counted in `exex_liquidity_socket_send_failures_total`.
+pub struct SocketSender {
+    tx: mpsc::Sender<ControlMessage>,
+    overflow: SocketOverflow,
+    spill: Mutex<VecDeque<ControlMessage>>,
+}
+
+impl SocketSender {
+    pub fn new(tx: mpsc::Sender<ControlMessage>, overflow: SocketOverflow) -> Self {
+        Self {
+            tx,
+            overflow,
+            spill: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn set_overflow(&mut self, overflow: SocketOverflow) {
+        self.overflow = overflow;
+    }
+
+    /// The raw channel, for unsequenced producers (mempool) and pacing.
+    pub fn sender(&self) -> &mpsc::Sender<ControlMessage> {
+        &self.tx
+    }
+
+    /// Frames queued in the channel plus those held back.
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity() + self.spilled()
+    }
+
+    pub fn spilled(&self) -> usize {
+        self.spill.lock().unwrap().len()
+    }
+
+    /// Send without waiting. An error means the frame was dropped.
+    pub fn try_send(&self, msg: ControlMessage) -> Result<(), TrySendError<ControlMessage>> {
+        let result = self.push(msg);
+        if result.is_err() {
+            exex_metrics::record_socket_send_failure();
+        }
+        result
+    }
+
+    fn push(&self, msg: ControlMessage) -> Result<(), TrySendError<ControlMessage>> {
+        let SocketOverflow::Spill { max } = self.overflow else {
+            return self.tx.try_send(msg);
+        };
+        let mut spill = self.spill.lock().unwrap();
+        // Held-back frames go first, so the stream stays in order.
+        while let Some(frame) = spill.pop_front() {
+            match self.tx.try_send(frame) {
+                Ok(()) => {}
+                Err(TrySendError::Full(frame)) => {
+                    spill.push_front(frame);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    spill.clear();
+                    return Err(TrySendError::Closed(msg));
+                }
+            }
+        }
+        let msg = if spill.is_empty() {
+            match self.tx.try_send(msg) {
+                Err(TrySendError::Full(msg)) => msg,
+                result => return result,
+            }
+        } else {
+            msg
+        };
+        if spill.len() >= max {
+            return Err(TrySendError::Full(msg));
+        }
+        spill.push_back(msg);
+        Ok(())
+    }
+
+    /// Send the held-back frames, waiting on the socket.
+    pub async fn drain(&self) -> Result<(), SendError<ControlMessage>> {
+        loop {
+            let Some(frame) = self.spill.lock().unwrap().pop_front() else {
+                return Ok(());
+            };
+            self.tx.send(frame).await?;
+        }
+    }
+
+    /// Send after the held-back frames, waiting on the socket.
+    pub async fn send(&self, msg: ControlMessage) -> Result<(), SendError<ControlMessage>> {
+        self.drain().await?;
+        self.tx.send(msg).await
+    }
+}
+
+/// How long a `Shutdown` waits for clients to receive their queued frames.
+const CLIENT_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// The last block envelope the broadcast loop received whole: the position a
+/// restart may resume from. An envelope with a `stream_seq` gap (a frame
+/// dropped on overflow) does not count. A revert envelope moves the position
+/// back to the reverted block's parent.
+#[derive(Debug, Default)]
+struct ReleasedBlocks {
+    /// Position the open envelope's `EndBlock` releases, while none of its
+    /// frames is missing.
+    open: Option<BlockNumHash>,
+    next_seq: Option<u64>,
+}
+
+impl ReleasedBlocks {
+    /// Feed one frame in broadcast order; returns the new position when it
+    /// completes an envelope.
+    fn observe(&mut self, message: &ControlMessage) -> Option<BlockNumHash> {
+        let seq = message.stream_seq()?;
+        let contiguous = self.next_seq.is_none_or(|next| next == seq);
+        self.next_seq = Some(seq + 1);
+        match message {
+            ControlMessage::BeginBlock {
+                block_number,
+                block_hash,
+                parent_hash,
+                is_revert,
+                ..
+            } => {
+                self.open = Some(if *is_revert {
+                    BlockNumHash::new(block_number.saturating_sub(1), *parent_hash)
+                } else {
+                    BlockNumHash::new(*block_number, *block_hash)
+                });
+                None
+            }
+            ControlMessage::EndBlock { .. } if contiguous => self.open.take(),
+            _ => {
+                if !contiguous {
+                    self.open = None;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Whether the broadcast loop is inside a block or reorg envelope, which
+/// backfill frames must not split.
+#[derive(Debug, Default)]
+struct OpenEnvelope {
+    block: bool,
+    reorg: bool,
+}
+
+impl OpenEnvelope {
+    fn observe(&mut self, message: &ControlMessage) {
+        match message {
+            ControlMessage::BeginBlock { .. } => self.block = true,
+            ControlMessage::EndBlock { .. } => self.block = false,
+            ControlMessage::ReorgStart { .. } => self.reorg = true,
+            ControlMessage::ReorgComplete { .. } => self.reorg = false,
+            _ => {}
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.block || self.reorg
+    }
+}
+
 /// Unix socket server that broadcasts pool updates to connected clients
 pub struct PoolUpdateSocketServer {
     listener: UnixListener,
     message_tx: mpsc::Sender<ControlMessage>,
     message_rx: mpsc::Receiver<ControlMessage>,
+    backfill_tx: mpsc::Sender<ControlMessage>,
+    backfill_rx: mpsc::Receiver<ControlMessage>,
     broadcast_tx: broadcast::Sender<ControlMessage>,
+    released_tx: watch::Sender<Option<BlockNumHash>>,
 }
 
 impl PoolUpdateSocketServer {
@@ -68,14 +298,19 @@ impl PoolUpdateSocketServer {
 
         info!("Unix socket server listening on {}", socket_path_str);
 
-        let (message_tx, message_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let (message_tx, message_rx) = mpsc::channel(channel_capacity_from_env());
+        let (backfill_tx, backfill_rx) = mpsc::channel(BACKFILL_CHANNEL_CAPACITY);
         let (broadcast_tx, _) = broadcast::channel(BUFFER_SIZE);
+        let (released_tx, _) = watch::channel(None);
 
         Ok(Self {
             listener,
             message_tx,
             message_rx,
+            backfill_tx,
+            backfill_rx,
             broadcast_tx,
+            released_tx,
         })
     }
 
@@ -84,6 +319,25 @@ impl PoolUpdateSocketServer {
         self.message_tx.clone()
     }
 
+    /// Sender for backfill frames. They have their own channel, so a replay
+    /// waits on it without taking capacity from the live stream, and the
+    /// broadcast loop only releases them between block envelopes.
+    pub fn backfill_sender(&self) -> mpsc::Sender<ControlMessage> {
+        self.backfill_tx.clone()
+    }
+
+    /// Connected-client count, still readable once `run` owns the server.
+    pub fn client_counter(&self) -> impl Fn() -> usize + Send + Sync + 'static {
+        let broadcast_tx = self.broadcast_tx.clone();
+        move || broadcast_tx.receiver_count()
+    }
+
+    /// Last block whose envelope reached the broadcast loop whole, behind any
+    /// confirmation buffer and overflow drops: what the checkpoint records.
+    pub fn released(&self) -> watch::Receiver<Option<BlockNumHash>> {
+        self.released_tx.subscribe()
+    }
+
     /// Run the server, accepting connections and broadcasting messages
     pub async fn run(mut self) -> Result<()> {
         info!("Pool update socket server starting");
@@ -114,12 +368,45 @@ impl PoolUpdateSocketServer {
             }
         });
 
-        // Main broadcast loop - receive from message_rx and broadcast to all clients
+        // Main broadcast loop - receive from message_rx and broadcast to all clients.
+        // Live frames take priority; backfill frames go out only while no
+        // envelope is open.
         info!("Socket server broadcast loop starting");
-        while let Some(message) = self.message_rx.recv().await {
+        let mut released = ReleasedBlocks::default();
+        let mut envelope = OpenEnvelope::default();
+        loop {
+            let message = tokio::select! {
+                biased;
+                message = self.message_rx.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                Some(message) = self.backfill_rx.recv(), if !envelope.is_open() => message,
+            };
+            envelope.observe(&message);
+            let shutdown = matches!(message, ControlMessage::Shutdown);
+            let released_block = released.observe(&message);
             // Broadcast to all connected clients
             // Ignore errors - clients may disconnect
             let _ = self.broadcast_tx.send(message);
+            if let Some(block) = released_block {
+                self.released_tx.send_replace(Some(block));
+            }
+            if shutdown {
+                // Every client handler writes out its backlog, ending with
+                // the Shutdown frame, then drops its receiver.
+                let deadline = tokio::time::Instant::now() + CLIENT_DRAIN_TIMEOUT;
+                while self.broadcast_tx.receiver_count() > 0
+                    && tokio::time::Instant::now() < deadline
+                {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+                let undrained = self.broadcast_tx.receiver_count();
+                if undrained > 0 {
+                    warn!("{} clients not drained before shutdown", undrained);
+                }
+                break;
+            }
         }
 
         info!("Socket server shutting down");
@@ -128,10 +415,16 @@ impl PoolUpdateSocketServer {
 }
 
 /// Handle a single client connection
+/// A client's serialization buffer is reused across messages, but one that
+/// grew past this (a large snapshot) is released rather than kept around.
+const FRAME_BUFFER_RETAIN: usize = 1 << 20;
+
 async fn handle_client(
     mut stream: UnixStream,
     mut broadcast_rx: broadcast::Receiver<ControlMessage>,
 ) -> Result<()> {
+    let mut body = Vec::with_capacity(4096);
+
     // Receive messages from broadcast channel and send to this client
     loop {
         let message = match broadcast_rx.recv().await {
@@ -149,23 +442,17 @@ async fn handle_client(
             }
         };
 
-        // Serialize message with bincode
-        let serialized = match bincode::serialize(&message) {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                error!("Failed to serialize message: {}", e);
-                continue;
-            }
-        };
-
-        // Send length prefix (4 bytes) + message as a single write
-        // to prevent partial frames if the process crashes mid-send.
-        let len = serialized.len() as u32;
-        let mut frame = Vec::with_capacity(4 + serialized.len());
-        frame.extend_from_slice(&len.to_le_bytes());
-        frame.extend_from_slice(&serialized);
+        // Serialize message with bincode into the reused buffer
+        if body.capacity() > FRAME_BUFFER_RETAIN {
+            body = Vec::with_capacity(4096);
+        }
+        body.clear();
+        if let Err(e) = bincode::serialize_into(&mut body, &message) {
+            error!("Failed to serialize message: {}", e);
+            continue;
+        }
 
-        if let Err(e) = stream.write_all(&frame).await {
+        if let Err(e) = write_frame(&mut stream, &body).await {
             error!("Failed to write framed message: {}", e);
             break;
         }
@@ -174,12 +461,34 @@ async fn handle_client(
             error!("Failed to flush stream: {}", e);
             break;
         }
+
+        if matches!(message, ControlMessage::Shutdown) {
+            let _ = stream.shutdown().await;
+            break;
+        }
     }
 
     info!("Client disconnected");
     Ok(())
 }
 
+/// Write one frame: the 4-byte little-endian length prefix and the body go
+/// out together in a vectored write, so a frame normally costs one syscall
+/// and no copy. A partial write continues where it stopped.
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, body: &[u8]) -> io::Result<()> {
+    let len = (body.len() as u32).to_le_bytes();
+    let mut slices = [IoSlice::new(&len), IoSlice::new(body)];
+    let mut remaining = &mut slices[..];
+    while !remaining.is_empty() {
+        let written = writer.write_vectored(remaining).await?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut remaining, written);
+    }
+    Ok(())
+}
+
 /// Simple broadcaster that clones messages to all client channels
 /// This is a simplified version - in production use tokio::sync::broadcast
 pub struct MessageBroadcaster {
@@ -233,4 +542,162 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(socket_path_from_env());
     }
+
+    #[tokio::test]
+    async fn frames_are_length_prefixed_from_a_reused_buffer() {
+        let mut out = Vec::new();
+        let mut body = Vec::new();
+        for block_number in [1u64, 2] {
+            body.clear();
+            bincode::serialize_into(&mut body, &ControlMessage::Finalized { block_number })
+                .unwrap();
+            write_frame(&mut out, &body).await.unwrap();
+        }
+
+        let mut rest = &out[..];
+        for expected in [1u64, 2] {
+            let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+            let message: ControlMessage = bincode::deserialize(&rest[4..4 + len]).unwrap();
+            assert!(matches!(
+                message,
+                ControlMessage::Finalized { block_number } if block_number == expected
+            ));
+            rest = &rest[4 + len..];
+        }
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn released_blocks_skip_envelopes_with_a_dropped_frame() {
+        use alloy_primitives::{Address, B256};
+
+        let begin = |stream_seq, block_number: u64, is_revert| ControlMessage::BeginBlock {
+            stream_seq,
+            chain_id: 1,
+            block_number,
+            block_hash: B256::with_last_byte(block_number as u8),
+            parent_hash: B256::with_last_byte(block_number as u8 - 1),
+            block_timestamp: 0,
+            base_fee_per_gas: 0,
+            fee_recipient: Address::ZERO,
+            builder: None,
+            is_revert,
+            is_historical: false,
+        };
+        let end = |stream_seq, block_number| ControlMessage::EndBlock {
+            stream_seq,
+            block_number,
+            num_updates: 0,
+            updates_checksum: B256::ZERO,
+        };
+        let mut released = ReleasedBlocks::default();
+
+        assert_eq!(released.observe(&begin(0, 10, false)), None);
+        assert_eq!(
+            released.observe(&ControlMessage::Finalized { block_number: 9 }),
+            None
+        );
+        assert_eq!(
+            released.observe(&end(1, 10)),
+            Some(BlockNumHash::new(10, B256::with_last_byte(10)))
+        );
+
+        // Seq 3 (a PoolUpdate of block 11) was dropped.
+        released.observe(&begin(2, 11, false));
+        assert_eq!(released.observe(&end(4, 11)), None);
+
+        // A revert moves the position back to the parent.
+        released.observe(&begin(5, 11, true));
+        assert_eq!(
+            released.observe(&end(6, 11)),
+            Some(BlockNumHash::new(10, B256::with_last_byte(10)))
+        );
+    }
+
+    #[test]
+    fn backfill_waits_for_block_and_reorg_envelopes_to_close() {
+        use crate::types::ReorgRange;
+        use alloy_primitives::{Address, B256};
+
+        let begin = |block_number: u64| ControlMessage::BeginBlock {
+            stream_seq: 0,
+            chain_id: 1,
+            block_number,
+            block_hash: B256::ZERO,
+            parent_hash: B256::ZERO,
+            block_timestamp: 0,
+            base_fee_per_gas: 0,
+            fee_recipient: Address::ZERO,
+            builder: None,
+            is_revert: false,
+            is_historical: false,
+        };
+        let end = |block_number| ControlMessage::EndBlock {
+            stream_seq: 0,
+            block_number,
+            num_updates: 0,
+            updates_checksum: B256::ZERO,
+        };
+        let range = ReorgRange {
+            first_block: Some(10),
+            last_block: Some(10),
+            block_count: 1,
+        };
+        let mut envelope = OpenEnvelope::default();
+        assert!(!envelope.is_open());
+
+        envelope.observe(&begin(10));
+        envelope.observe(&ControlMessage::Finalized { block_number: 9 });
+        assert!(envelope.is_open());
+        envelope.observe(&end(10));
+        assert!(!envelope.is_open());
+
+        // Between a reorg's blocks the reorg itself is still open.
+        envelope.observe(&ControlMessage::ReorgStart {
+            stream_seq: 0,
+            old_range: range.clone(),
+            new_range: range,
+        });
+        envelope.observe(&begin(10));
+        envelope.observe(&end(10));
+        assert!(envelope.is_open());
+        envelope.observe(&ControlMessage::ReorgComplete {
+            stream_seq: 0,
+            final_tip_block: 10,
+        });
+        assert!(!envelope.is_open());
+    }
+
+    #[tokio::test]
+    async fn spill_keeps_order_and_drops_past_its_bound() {
+        let frame = |block_number| ControlMessage::Finalized { block_number };
+        let block = |msg: ControlMessage| match msg {
+            ControlMessage::Finalized { block_number } => block_number,
+            other => panic!("unexpected frame {other:?}"),
+        };
+        let (tx, mut rx) = mpsc::channel(2);
+        let sender = SocketSender::new(tx, SocketOverflow::Spill { max: 3 });
+
+        // Two fit the channel, three are held back, the sixth is dropped.
+        for n in 1..=5 {
+            sender.try_send(frame(n)).unwrap();
+        }
+        assert!(sender.try_send(frame(6)).is_err());
+        assert_eq!((sender.spilled(), sender.depth()), (3, 5));
+
+        // Room in the channel goes to held-back frames first.
+        assert_eq!(block(rx.recv().await.unwrap()), 1);
+        sender.try_send(frame(7)).unwrap();
+        assert_eq!(sender.spilled(), 3);
+
+        let (drained, received) = tokio::join!(sender.drain(), async {
+            let mut received = Vec::new();
+            for _ in 0..5 {
+                received.push(block(rx.recv().await.unwrap()));
+            }
+            received
+        });
+        drained.unwrap();
+        assert_eq!(received, [2, 3, 4, 5, 7]);
+    }
 }
@@ -0,0 +1,107 @@
+//! Live-add state seeding.
+//!
+//! When the whitelist adds a pool mid-stream, the consumer has no base to apply
+//! the pool's next incremental update to. At the committed block boundary where
+//! the add lands, the ExEx reads the pool's absolute state from the block
+//! post-state and emits it inside that block's envelope, ahead of any delta —
+//! so the consumer never has to race the stream with its own RPC calls. The
+//! same messages, built for a round-robin batch of tracked pools each block,
+//! form the periodic resync (`POOL_SNAPSHOT_INTERVAL_BLOCKS`, see `pool_resync`).
+//!
+//! Reuses the storage readers the shadow-arena hydration path already uses
+//! (`read_v2_reserves`, `read_v3_full_state`, `read_v4_full_state`), so the
+//! seed and the hydrated arena slot are read identically.
+
+use crate::chain;
+use crate::types::{
+    PoolMetadata, PoolUpdate, PoolUpdateMessage, Protocol, TickSnapshot, UpdateType,
+};
+use crate::{
+    pool_address, pool_id_32, read_v2_reserves, read_v3_full_state, read_v4_full_state,
+    singleton_contract_or, v3_factory, TickBitmapSnapshot,
+};
+use reth_provider::StateProvider;
+use tracing::warn;
+
+/// Build the seed update for every added pool whose protocol has a seed reader,
+/// as a `Seed` update stamped after the block's last log: it carries the
+/// block's post-state. Pools that cannot be read (missing tick spacing, empty slot0) are skipped
+/// with a warning; their first incremental update is still emitted as usual.
+pub fn seed_update_messages(
+    state: &dyn StateProvider,
+    pools: &[PoolMetadata],
+    block_number: u64,
+    block_timestamp: u64,
+) -> Vec<PoolUpdateMessage> {
+    pools
+        .iter()
+        .filter_map(|pool| {
+            let update = seed_update(state, pool)?;
+            Some(PoolUpdateMessage {
+                chain_id: chain::active().chain_id,
+                pool_id: pool.pool_id.clone(),
+                protocol: pool.protocol,
+                update_type: UpdateType::Seed,
+                block_number,
+                block_timestamp,
+                tx_index: u64::MAX,
+                log_index: u64::MAX,
+                is_revert: false,
+                update,
+            })
+        })
+        .collect()
+}
+
+fn seed_update(state: &dyn StateProvider, pool: &PoolMetadata) -> Option<PoolUpdate> {
+    match pool.protocol {
+        Protocol::UniswapV2 => {
+            let (reserve0, reserve1) = read_v2_reserves(state, pool_address(pool)?);
+            Some(PoolUpdate::V2Sync { reserve0, reserve1 })
+        }
+        Protocol::UniswapV3 => {
+            let addr = pool_address(pool)?;
+            let Some(tick_spacing) = pool.tick_spacing else {
+                warn!(pool = %addr, "Skipping V3 seed: missing tick spacing");
+                return None;
+            };
+            let Some(snapshot) = read_v3_full_state(state, addr, tick_spacing, v3_factory(pool))
+            else {
+                warn!(pool = %addr, "Skipping V3 seed: slot0 not initialized");
+                return None;
+            };
+            Some(PoolUpdate::V3Snapshot {
+                snapshot: tick_snapshot(snapshot),
+            })
+        }
+        Protocol::UniswapV4 => {
+            let pool_id = pool_id_32(pool)?;
+            let Some(tick_spacing) = pool.tick_spacing else {
+                warn!(pool_id = ?pool_id, "Skipping V4 seed: missing tick spacing");
+                return None;
+            };
+            // V4 state lives in the PoolManager singleton, keyed by pool id:
+            // slot0 at `pools[id]`, liquidity at +3, ticks at +4, bitmap at +5.
+            let pool_manager = singleton_contract_or(pool, chain::active().uniswap_v4_pool_manager);
+            let Some(snapshot) = read_v4_full_state(state, pool_manager, &pool_id, tick_spacing)
+            else {
+                warn!(pool_id = ?pool_id, "Skipping V4 seed: pool not initialized");
+                return None;
+            };
+            Some(PoolUpdate::V4Snapshot {
+                snapshot: tick_snapshot(snapshot),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn tick_snapshot(snapshot: TickBitmapSnapshot) -> TickSnapshot {
+    TickSnapshot {
+        sqrt_price_x96: snapshot.sqrt_price_x96,
+        tick: snapshot.tick,
+        liquidity: snapshot.liquidity,
+        tick_bitmaps: snapshot.tick_bitmaps,
+        ticks: snapshot.ticks,
+    }
+}
@@ -0,0 +1,112 @@
+//! On-chain state verification for the pool state engine.
+//!
+//! With the state engine enabled, the absolute state it emits is only as good
+//! as its folding of every delta. Every `STATE_VERIFY_INTERVAL_BLOCKS` blocks,
+//! and in every replacement block of a reorg (where it most likely diverged),
+//! the verifier takes the next `STATE_VERIFY_SAMPLE` pools with a known base
+//! (round-robin, so every pool is eventually covered), reads their state
+//! directly from the block post-state with the same readers the live-add seed
+//! uses, and compares. A diverging pool is logged, counted, and gets the fresh
+//! read emitted as a corrective seed inside the block envelope — which also
+//! rebases the engine.
+
+use crate::pool_state::PoolStateEngine;
+use crate::state_seed::seed_update_messages;
+use crate::types::{PoolMetadata, PoolUpdateMessage};
+use reth_provider::StateProvider;
+use tracing::warn;
+
+/// Default number of pools checked per verification pass.
+const DEFAULT_SAMPLE_SIZE: usize = 16;
+
+/// Periodic engine-vs-storage cross-check.
+#[derive(Debug)]
+pub struct StateVerifier {
+    interval_blocks: u64,
+    sample_size: usize,
+    /// Round-robin position into the pools with a known base.
+    cursor: usize,
+    /// Pools found diverged since startup.
+    divergences: u64,
+}
+
+impl StateVerifier {
+    /// `None` unless `STATE_VERIFY_INTERVAL_BLOCKS` is set (> 0).
+    pub fn from_env() -> Option<Self> {
+        let interval_blocks = std::env::var("STATE_VERIFY_INTERVAL_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+        if interval_blocks == 0 {
+            return None;
+        }
+        let sample_size = std::env::var("STATE_VERIFY_SAMPLE")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_SAMPLE_SIZE);
+        Some(Self {
+            interval_blocks,
+            sample_size,
+            cursor: 0,
+            divergences: 0,
+        })
+    }
+
+    pub fn due(&self, block_number: u64) -> bool {
+        block_number % self.interval_blocks == 0
+    }
+
+    /// Pick the next round-robin sample of tracked pools the engine has a
+    /// base for.
+    pub fn sample<'a>(
+        &mut self,
+        engine: &PoolStateEngine,
+        pools: impl Iterator<Item = &'a PoolMetadata>,
+    ) -> Vec<PoolMetadata> {
+        let mut candidates: Vec<&PoolMetadata> = pools
+            .filter(|p| engine.state(&p.pool_id).is_some())
+            .collect();
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        // Stable order across blocks so the cursor walks every pool.
+        candidates.sort_by_key(|p| format!("{:?}", p.pool_id));
+        let take = self.sample_size.min(candidates.len());
+        let start = self.cursor % candidates.len();
+        self.cursor = start + take;
+        candidates
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(take)
+            .map(|p| (*p).clone())
+            .collect()
+    }
+
+    /// Read the sampled pools from `state` and return a corrective seed for
+    /// every pool whose engine state diverges from storage.
+    pub fn verify(
+        &mut self,
+        engine: &PoolStateEngine,
+        state: &dyn StateProvider,
+        pools: &[PoolMetadata],
+        block_number: u64,
+        block_timestamp: u64,
+    ) -> Vec<PoolUpdateMessage> {
+        let corrections: Vec<PoolUpdateMessage> =
+            seed_update_messages(state, pools, block_number, block_timestamp)
+                .into_iter()
+                .filter(|seed| engine.diverges(seed))
+                .collect();
+        for seed in &corrections {
+            self.divergences += 1;
+            warn!(
+                pool_id = ?seed.pool_id,
+                block_number,
+                total_divergences = self.divergences,
+                "🩺 Pool state diverged from storage, emitting corrective snapshot"
+            );
+        }
+        corrections
+    }
+}
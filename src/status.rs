@@ -0,0 +1,196 @@
+// ExEx Status Publishing
+//
+// Every `STATUS_INTERVAL_SECS` (default 30, 0 disables) a JSON status message
+// is published to `status.exex.<chain>`: per ExEx its last block and age
+// (from the health registry), blocks and events processed, tracked pools or
+// tokens and sink errors (failed socket sends, NATS publishes or store
+// writes), plus the socket client count. Dashboards subscribe instead of
+// scraping logs.
+//
+// The totals mirror what `exex_metrics` records: its recorders update them
+// here, since the `metrics` facade cannot be read back.
+
+use crate::{health, subjects};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+static TOTALS: LazyLock<Mutex<BTreeMap<&'static str, ExExTotals>>> = LazyLock::new(Mutex::default);
+
+type ClientCounter = Box<dyn Fn() -> usize + Send + Sync>;
+static SOCKET_CLIENTS: OnceLock<ClientCounter> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExExTotals {
+    pub blocks: u64,
+    /// Liquidity: events decoded; balance monitor: updates published;
+    /// transfers: rows inserted.
+    pub events: u64,
+    /// Pools (liquidity) or tokens (balance monitor) tracked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracked: Option<u64>,
+    pub sink_errors: u64,
+}
+
+/// One ExEx in the status message.
+#[derive(Debug, Serialize)]
+pub struct ExExStatus {
+    pub exex: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_block: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_block_age_secs: Option<u64>,
+    #[serde(flatten)]
+    pub totals: ExExTotals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusMessage {
+    pub chain: String,
+    pub ts: u64,
+    pub uptime_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_clients: Option<usize>,
+    pub exexes: Vec<ExExStatus>,
+}
+
+fn update(exex: &'static str, f: impl FnOnce(&mut ExExTotals)) {
+    f(TOTALS.lock().unwrap().entry(exex).or_default());
+}
+
+pub fn record_block(exex: &'static str, events: u64) {
+    update(exex, |t| {
+        t.blocks += 1;
+        t.events += events;
+    });
+}
+
+pub fn record_events(exex: &'static str, events: u64) {
+    update(exex, |t| t.events += events);
+}
+
+pub fn set_tracked(exex: &'static str, tracked: usize) {
+    update(exex, |t| t.tracked = Some(tracked as u64));
+}
+
+pub fn record_sink_error(exex: &'static str) {
+    update(exex, |t| t.sink_errors += 1);
+}
+
+/// Report the liquidity socket's client count; only the first call counts.
+pub fn watch_socket_clients(counter: impl Fn() -> usize + Send + Sync + 'static) {
+    let _ = SOCKET_CLIENTS.set(Box::new(counter));
+}
+
+/// The current status.
+pub fn snapshot(chain: String, started: Instant) -> StatusMessage {
+    let blocks: BTreeMap<_, _> = health::blocks()
+        .into_iter()
+        .map(|b| (b.exex, (b.block_number, b.age_secs)))
+        .collect();
+    let totals = TOTALS.lock().unwrap().clone();
+    StatusMessage {
+        chain,
+        ts: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        uptime_secs: started.elapsed().as_secs(),
+        socket_clients: SOCKET_CLIENTS.get().map(|count| count()),
+        exexes: merge(blocks, totals),
+    }
+}
+
+fn merge(
+    blocks: BTreeMap<&'static str, (u64, u64)>,
+    mut totals: BTreeMap<&'static str, ExExTotals>,
+) -> Vec<ExExStatus> {
+    let mut names: Vec<&'static str> = blocks.keys().chain(totals.keys()).copied().collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|exex| ExExStatus {
+            exex,
+            last_block: blocks.get(exex).map(|&(number, _)| number),
+            last_block_age_secs: blocks.get(exex).map(|&(_, age)| age),
+            totals: totals.remove(exex).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Start the publisher unless `STATUS_INTERVAL_SECS` is 0.
+pub fn spawn_from_env() {
+    let interval = match std::env::var("STATUS_INTERVAL_SECS") {
+        Ok(raw) => match raw.parse::<u64>() {
+            Ok(0) => return,
+            Ok(secs) => Duration::from_secs(secs),
+            Err(e) => {
+                warn!("Invalid STATUS_INTERVAL_SECS {:?}: {}", raw, e);
+                return;
+            }
+        },
+        Err(_) => DEFAULT_INTERVAL,
+    };
+    let nats_url =
+        std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+    let started = Instant::now();
+
+    tokio::spawn(async move {
+        let client = match async_nats::connect(&nats_url).await {
+            Ok(client) => client,
+            Err(e) => {
+                warn!(
+                    "Status publisher could not connect to NATS at {}: {}",
+                    nats_url, e
+                );
+                return;
+            }
+        };
+        let chain = crate::chain::chain_name();
+        let subject = subjects::status(&chain);
+        info!(subject = %subject, ?interval, "Publishing ExEx status");
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let status = snapshot(chain.clone(), started);
+            let payload = serde_json::to_vec(&status).expect("StatusMessage serializes");
+            if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+                warn!("Failed to publish ExEx status: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_and_totals_are_merged_per_exex() {
+        let blocks = BTreeMap::from([("liquidity", (100, 3))]);
+        let totals = BTreeMap::from([(
+            "transfers",
+            ExExTotals {
+                blocks: 2,
+                events: 7,
+                ..Default::default()
+            },
+        )]);
+        let merged = merge(blocks, totals);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].exex, "liquidity");
+        assert_eq!(merged[0].last_block, Some(100));
+        assert_eq!(merged[0].totals, ExExTotals::default());
+        assert_eq!(merged[1].exex, "transfers");
+        assert_eq!(merged[1].last_block, None);
+        assert_eq!(merged[1].totals.events, 7);
+    }
+}
@@ -0,0 +1,136 @@
+// NATS Subjects
+//
+// Every subject the ExExes publish or subscribe to is built here. When
+// `NATS_SUBJECT_PREFIX` is set (e.g. `staging`), it is prepended to all of
+// them (`staging.whitelist.pools.ethereum.full`), so several environments can
+// share one NATS cluster; the services on the other side must use the same
+// prefix. Unset, the subjects are unchanged.
+
+use std::sync::OnceLock;
+
+static PREFIX: OnceLock<Option<String>> = OnceLock::new();
+
+/// `NATS_SUBJECT_PREFIX`, read once.
+fn prefix() -> Option<&'static str> {
+    PREFIX
+        .get_or_init(|| {
+            std::env::var("NATS_SUBJECT_PREFIX")
+                .ok()
+                .map(|p| p.trim_matches('.').to_string())
+                .filter(|p| !p.is_empty())
+        })
+        .as_deref()
+}
+
+fn with_prefix(prefix: Option<&str>, subject: String) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}.{subject}"),
+        None => subject,
+    }
+}
+
+fn subject(subject: String) -> String {
+    with_prefix(prefix(), subject)
+}
+
+/// `whitelist.pools.{chain}.{kind}`: `full`, `add`, `remove`, `request`, or
+/// `*` for all of them.
+pub fn whitelist_pools(chain: &str, kind: &str) -> String {
+    subject(format!("whitelist.pools.{chain}.{kind}"))
+}
+
+pub fn whitelist_reseed() -> String {
+    subject("whitelist.reseed".to_string())
+}
+
+pub fn whitelist_snapshot_request(chain: &str) -> String {
+    subject(format!("whitelist.snapshot.request.{chain}"))
+}
+
+pub fn admin(chain: &str) -> String {
+    subject(format!("exex.admin.{chain}"))
+}
+
+/// `balances.chain.{chain_id}`, or with a suffix (`finalized`, `request`).
+pub fn balances(chain_id: &str, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) => subject(format!("balances.chain.{chain_id}.{suffix}")),
+        None => subject(format!("balances.chain.{chain_id}")),
+    }
+}
+
+pub fn balance_alerts(chain_id: &str) -> String {
+    subject(format!("alerts.balances.{chain_id}"))
+}
+
+pub fn upgrade_alerts(chain_id: &str) -> String {
+    subject(format!("alerts.upgrades.{chain_id}"))
+}
+
+pub fn allowances(chain_id: &str) -> String {
+    subject(format!("allowances.chain.{chain_id}"))
+}
+
+pub fn gas(chain_id: &str) -> String {
+    subject(format!("gas.chain.{chain_id}"))
+}
+
+pub fn token_list(chain_id: &str) -> String {
+    subject(format!("tokens.chain.{chain_id}"))
+}
+
+/// `swap.{kind}.{chain_id}`: `confirmed` or `failed`.
+pub fn swap(kind: &str, chain_id: &str) -> String {
+    subject(format!("swap.{kind}.{chain_id}"))
+}
+
+pub fn depeg_alerts(chain: &str) -> String {
+    subject(format!("alerts.depeg.{chain}"))
+}
+
+pub fn transfer_alerts(chain: &str) -> String {
+    subject(format!("alerts.transfers.{chain}"))
+}
+
+pub fn status(chain: &str) -> String {
+    subject(format!("status.exex.{chain}"))
+}
+
+pub fn dead_letters(chain: &str) -> String {
+    subject(format!("deadletter.liquidity.{chain}"))
+}
+
+pub fn arb_signals(chain: &str) -> String {
+    subject(format!("arb.signals.{chain}"))
+}
+
+pub fn fee_reports(chain: &str) -> String {
+    subject(format!("fees.pools.{chain}"))
+}
+
+pub fn block_fees(chain: &str) -> String {
+    subject(format!("fees.blocks.{chain}"))
+}
+
+/// `candles.{chain}.{interval}`: `1s`, `1m`, ... or `revert`.
+pub fn candles(chain: &str, interval: &str) -> String {
+    subject(format!("candles.{chain}.{interval}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_is_prepended_when_set() {
+        let subject = || "whitelist.pools.ethereum.full".to_string();
+        assert_eq!(
+            with_prefix(None, subject()),
+            "whitelist.pools.ethereum.full"
+        );
+        assert_eq!(
+            with_prefix(Some("staging"), subject()),
+            "staging.whitelist.pools.ethereum.full"
+        );
+    }
+}
@@ -1,13 +1,43 @@
 //! Swap Monitor — detects swap events in transactions from the executor address.
 //!
-//! Publishes `SwapConfirmation` to NATS for hedger correlation via tx_hash.
-//! Integrated into the balance_monitor ExEx — single pass per block.
+//! Publishes `SwapConfirmation` to NATS (`swap.confirmed.<chain_id>`) for
+//! hedger correlation via tx_hash. `token0`/`token1` come from `PoolTokens`,
+//! filled from the whitelist and, for pools it does not cover, the
+//! pool-creations DB.
+//!
+//! Trades routed through an aggregator, where the executor is not the
+//! pool-level sender or recipient, are confirmed too, tagged with
+//! `aggregator`: fills reported by the aggregator's own event (1inch
+//! `Swapped`, 0x `TransformedERC20` / `RfqOrderFilled` / `LimitOrderFilled` /
+//! `OtcOrderFilled`) with the executor as taker, and pool swaps made by a
+//! UniversalRouter or 1inch router in a transaction the executor sent.
+//!
+//! Each direct pool swap is also checked for a sandwich: a swap on the same
+//! pool earlier in the block in the same direction, and one later in the
+//! opposite direction, both from the same account, that together took more
+//! out of the pool than they put in. Suspects are flagged in the
+//! confirmation's `sandwich` (see `detect_sandwiches`).
+//!
+//! Executor transactions that did not trade — reverted, or a trade call that
+//! succeeded without a swap log involving the executor — are published as
+//! `SwapFailed` on `swap.failed.<chain_id>`, so pending hedges can be
+//! cancelled.
+//!
+//! Decoding runs on the dispatch walk (`SwapMonitorHandler`); the balance
+//! monitor fills in tokens and prices and publishes (see `balance_monitor::scan`).
 
-use alloy_consensus::TxReceipt;
-use alloy_primitives::{Address, Log, I256, U256};
+use crate::balance_monitor::{self, now_ms, scan::ScanStore};
+use crate::chain;
+use crate::dispatch::{BlockHandler, BlockInfo, TxInfo};
+use crate::price_quote::normalize_amount;
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{address, Address, Log, B256, I256, U256};
 use alloy_sol_types::SolEvent;
+use futures::future::BoxFuture;
 use serde::Serialize;
-use tracing::debug;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info};
 
 // Re-use the sol! event definitions from events.rs (same crate).
 // We need the full event structs with sender/recipient for swap detection.
@@ -62,9 +92,75 @@ mod v4_swap {
     }
 }
 
+mod aggregator_events {
+    use alloy_sol_types::sol;
+    sol! {
+        // 1inch AggregationRouterV4.
+        event Swapped(
+            address sender,
+            address srcToken,
+            address dstToken,
+            address dstReceiver,
+            uint256 spentAmount,
+            uint256 returnAmount
+        );
+        // 0x Exchange Proxy.
+        event TransformedERC20(
+            address indexed taker,
+            address inputToken,
+            address outputToken,
+            uint256 inputTokenAmount,
+            uint256 outputTokenAmount
+        );
+        event RfqOrderFilled(
+            bytes32 orderHash,
+            address maker,
+            address taker,
+            address makerToken,
+            address takerToken,
+            uint128 takerTokenFilledAmount,
+            uint128 makerTokenFilledAmount,
+            bytes32 pool
+        );
+        event LimitOrderFilled(
+            bytes32 orderHash,
+            address maker,
+            address taker,
+            address feeRecipient,
+            address makerToken,
+            address takerToken,
+            uint128 takerTokenFilledAmount,
+            uint128 makerTokenFilledAmount,
+            uint128 takerTokenFeeFilledAmount,
+            uint256 protocolFeePaid,
+            bytes32 pool
+        );
+        event OtcOrderFilled(
+            bytes32 orderHash,
+            address maker,
+            address taker,
+            address makerToken,
+            address takerToken,
+            uint128 makerTokenFilledAmount,
+            uint128 takerTokenFilledAmount
+        );
+    }
+}
+
+/// 1inch AggregationRouter V5 and V6 (same address on every chain).
+const ONEINCH_ROUTERS: [Address; 2] = [
+    address!("1111111254EEB25477B68fb85Ed929f73A960582"),
+    address!("111111125421cA6dc452d289314280a0f8842A65"),
+];
+
 /// A confirmed swap extracted from block logs.
+///
+/// For an aggregator fill decoded from the aggregator's event, `pool` is the
+/// aggregator contract, `token0` the token sold (`amount0` negative) and
+/// `token1` the token bought.
 #[derive(Debug, Clone, Serialize)]
 pub struct SwapConfirmation {
+    pub chain_id: u64,
     pub tx_hash: String,
     pub pool: String,
     pub protocol: String,
@@ -76,6 +172,112 @@ pub struct SwapConfirmation {
     pub tx_index: u64,
     pub log_index: u64,
     pub ts: u64,
+    /// Aggregator the trade was routed through, if not made directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregator: Option<String>,
+    /// Gas used by the whole transaction, and what it cost (gas used ×
+    /// effective gas price, wei). Shared by every swap of the transaction.
+    pub gas_used: u64,
+    pub raw_gas_cost_wei: String,
+    /// `|amount1| / |amount0|`, decimal-normalized (token1 per token0);
+    /// `None` while either token's decimals are unknown.
+    pub effective_price: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandwich: Option<SuspectedSandwich>,
+}
+
+/// A suspected sandwich around an executor swap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SuspectedSandwich {
+    /// Sender of the front-run transaction.
+    pub attacker: String,
+    /// Contract both legs called, when they called the same one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attacker_contract: Option<String>,
+    pub front_tx_hash: String,
+    pub back_tx_hash: String,
+    /// The attacker's gross profit, as the estimated loss: what the back-run
+    /// took out of the pool minus what the front-run put in, in raw units of
+    /// `loss_token` (`token0` or `token1`), the token the executor sold.
+    pub raw_estimated_loss: String,
+    pub loss_token: String,
+}
+
+/// One pool swap of a block, amounts from the pool's side (positive: paid
+/// into the pool).
+#[derive(Debug, Clone)]
+pub struct BlockSwap {
+    pub pool: String,
+    pub tx_index: u64,
+    pub log_index: u64,
+    pub tx_hash: String,
+    pub tx_sender: Address,
+    pub tx_to: Option<Address>,
+    pub amount0: I256,
+    pub amount1: I256,
+}
+
+impl SwapConfirmation {
+    /// Set `effective_price` from the token decimals.
+    pub fn price_with_decimals(&mut self, decimals0: u8, decimals1: u8) {
+        let (Ok(amount0), Ok(amount1)) = (
+            I256::from_dec_str(&self.amount0),
+            I256::from_dec_str(&self.amount1),
+        ) else {
+            return;
+        };
+        let sold = normalize_amount(amount0, decimals0);
+        if sold > 0.0 {
+            self.effective_price = Some(normalize_amount(amount1, decimals1) / sold);
+        }
+    }
+}
+
+/// An executor transaction that did not trade.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapFailed {
+    pub chain_id: u64,
+    pub tx_hash: String,
+    pub reason: SwapFailureReason,
+    pub block_number: u64,
+    pub tx_index: u64,
+    pub ts: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapFailureReason {
+    /// The receipt status is false.
+    Reverted,
+    /// A trade call succeeded without a swap log involving the executor.
+    NoSwap,
+}
+
+/// Executor calls that are not trades: ERC-20 `approve` / `transfer`, WETH
+/// `deposit` / `withdraw`.
+const NON_TRADE_SELECTORS: [[u8; 4]; 4] = [
+    [0x09, 0x5e, 0xa7, 0xb3],
+    [0xa9, 0x05, 0x9c, 0xbb],
+    [0xd0, 0xe3, 0x0d, 0xb0],
+    [0x2e, 0x1a, 0x7d, 0x4d],
+];
+
+/// Why an executor transaction with calldata `input` did not trade, given
+/// its receipt status and the swap logs found in it; `None` if it traded or
+/// was not a trade. Plain transfers (no calldata) and non-trade calls only
+/// count when they revert.
+pub fn executor_tx_failure(
+    success: bool,
+    input: &[u8],
+    swaps_found: usize,
+) -> Option<SwapFailureReason> {
+    if !success {
+        return Some(SwapFailureReason::Reverted);
+    }
+    let is_trade = input
+        .get(..4)
+        .is_some_and(|selector| !NON_TRADE_SELECTORS.iter().any(|s| s == selector));
+    (is_trade && swaps_found == 0).then_some(SwapFailureReason::NoSwap)
 }
 
 /// Try to decode a log as a swap event involving the executor address.
@@ -109,6 +311,8 @@ pub fn decode_executor_swap(log: &Log, executor: Address) -> Option<DecodedSwap>
             protocol: "v2".to_string(),
             amount0: amount0.to_string(),
             amount1: amount1.to_string(),
+            tokens: None,
+            aggregator: None,
         });
     }
 
@@ -124,6 +328,8 @@ pub fn decode_executor_swap(log: &Log, executor: Address) -> Option<DecodedSwap>
             protocol: "v3".to_string(),
             amount0: event.data.amount0.to_string(),
             amount1: event.data.amount1.to_string(),
+            tokens: None,
+            aggregator: None,
         });
     }
 
@@ -141,6 +347,8 @@ pub fn decode_executor_swap(log: &Log, executor: Address) -> Option<DecodedSwap>
                 protocol: "v4".to_string(),
                 amount0: event.amount0.to_string(),
                 amount1: event.amount1.to_string(),
+                tokens: None,
+                aggregator: None,
             });
         }
     }
@@ -148,6 +356,216 @@ pub fn decode_executor_swap(log: &Log, executor: Address) -> Option<DecodedSwap>
     None
 }
 
+/// Try to decode a log as an aggregator fill with the executor as taker.
+/// Amounts are signed from the executor's side: sold negative, bought
+/// positive.
+pub fn decode_aggregator_fill(log: &Log, executor: Address) -> Option<DecodedSwap> {
+    use aggregator_events::*;
+
+    let fill = |aggregator: &'static str,
+                protocol: &str,
+                sold: (Address, U256),
+                bought: (Address, U256)| DecodedSwap {
+        pool: format!("{:#x}", log.address),
+        protocol: protocol.to_string(),
+        amount0: (-I256::try_from(sold.1).unwrap_or(I256::MAX)).to_string(),
+        amount1: I256::try_from(bought.1).unwrap_or(I256::MAX).to_string(),
+        tokens: Some((sold.0, bought.0)),
+        aggregator: Some(aggregator),
+    };
+
+    match log.topics().first().copied()? {
+        Swapped::SIGNATURE_HASH => {
+            let event = Swapped::decode_log_data(&log.data).ok()?;
+            (event.sender == executor || event.dstReceiver == executor).then(|| {
+                fill(
+                    "1inch",
+                    "1inch",
+                    (event.srcToken, event.spentAmount),
+                    (event.dstToken, event.returnAmount),
+                )
+            })
+        }
+        TransformedERC20::SIGNATURE_HASH => {
+            let event = TransformedERC20::decode_log_data(&log.data).ok()?;
+            (event.taker == executor).then(|| {
+                fill(
+                    "0x",
+                    "0x_transform",
+                    (event.inputToken, event.inputTokenAmount),
+                    (event.outputToken, event.outputTokenAmount),
+                )
+            })
+        }
+        RfqOrderFilled::SIGNATURE_HASH => {
+            let event = RfqOrderFilled::decode_log_data(&log.data).ok()?;
+            (event.taker == executor).then(|| {
+                fill(
+                    "0x",
+                    "0x_rfq",
+                    (event.takerToken, U256::from(event.takerTokenFilledAmount)),
+                    (event.makerToken, U256::from(event.makerTokenFilledAmount)),
+                )
+            })
+        }
+        LimitOrderFilled::SIGNATURE_HASH => {
+            let event = LimitOrderFilled::decode_log_data(&log.data).ok()?;
+            (event.taker == executor).then(|| {
+                fill(
+                    "0x",
+                    "0x_limit",
+                    (event.takerToken, U256::from(event.takerTokenFilledAmount)),
+                    (event.makerToken, U256::from(event.makerTokenFilledAmount)),
+                )
+            })
+        }
+        OtcOrderFilled::SIGNATURE_HASH => {
+            let event = OtcOrderFilled::decode_log_data(&log.data).ok()?;
+            (event.taker == executor).then(|| {
+                fill(
+                    "0x",
+                    "0x_otc",
+                    (event.takerToken, U256::from(event.takerTokenFilledAmount)),
+                    (event.makerToken, U256::from(event.makerTokenFilledAmount)),
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// A pool swap made by a known router (UniversalRouter of the active chain,
+/// 1inch V5/V6) as sender or recipient.
+fn decode_router_swap(log: &Log) -> Option<DecodedSwap> {
+    let routers = chain::active()
+        .universal_routers
+        .iter()
+        .map(|router| (*router, "universal_router"))
+        .chain(ONEINCH_ROUTERS.iter().map(|router| (*router, "1inch")));
+    for (router, aggregator) in routers {
+        if let Some(mut decoded) = decode_executor_swap(log, router) {
+            decoded.aggregator = Some(aggregator);
+            return Some(decoded);
+        }
+    }
+    None
+}
+
+/// Decode any V2/V3/V4 pool swap: `(pool, amount0, amount1)` from the
+/// pool's side.
+pub fn decode_pool_swap(log: &Log) -> Option<(String, I256, I256)> {
+    let signed = |amount: U256| I256::try_from(amount).unwrap_or(I256::MAX);
+    if let Ok(event) = v2_swap::Swap::decode_log(log) {
+        let data = &event.data;
+        return Some((
+            format!("{:#x}", log.address),
+            signed(data.amount0In) - signed(data.amount0Out),
+            signed(data.amount1In) - signed(data.amount1Out),
+        ));
+    }
+    if let Ok(event) = v3_swap::Swap::decode_log(log) {
+        return Some((
+            format!("{:#x}", log.address),
+            event.data.amount0,
+            event.data.amount1,
+        ));
+    }
+    if log.topics().len() >= 3 && log.topics()[0] == v4_swap::Swap::SIGNATURE_HASH {
+        let event = v4_swap::Swap::decode_log_data(&log.data).ok()?;
+        // V4 deltas are the swapper's: negative is paid into the pool.
+        return Some((
+            format!("{:#x}", log.topics()[1]),
+            -I256::try_from(event.amount0).ok()?,
+            -I256::try_from(event.amount1).ok()?,
+        ));
+    }
+    None
+}
+
+/// Flag suspected sandwiches around the executor's pool swaps, given every
+/// pool swap of the block they were made in. Aggregator-event fills name no
+/// pool and are skipped.
+pub fn detect_sandwiches(
+    confirmations: &mut [SwapConfirmation],
+    block_swaps: &[BlockSwap],
+    executor: Address,
+) {
+    for confirmation in confirmations {
+        let Some(own) = block_swaps.iter().find(|s| {
+            s.tx_index == confirmation.tx_index
+                && s.log_index == confirmation.log_index
+                && s.pool == confirmation.pool
+        }) else {
+            continue;
+        };
+        // The executor sold whichever token it paid into the pool.
+        let (sold, sold_token): (fn(&BlockSwap) -> I256, _) = if own.amount0 > I256::ZERO {
+            (|s| s.amount0, "token0")
+        } else if own.amount1 > I256::ZERO {
+            (|s| s.amount1, "token1")
+        } else {
+            continue;
+        };
+        let same_pool = |s: &&BlockSwap| s.pool == own.pool && s.tx_sender != executor;
+
+        // Nearest front-run first.
+        let fronts = block_swaps
+            .iter()
+            .filter(same_pool)
+            .filter(|s| s.tx_index < own.tx_index && sold(s) > I256::ZERO)
+            .rev();
+        for front in fronts {
+            let back = block_swaps.iter().filter(same_pool).find(|s| {
+                s.tx_index > own.tx_index && sold(s) < I256::ZERO && same_attacker(front, s)
+            });
+            let Some(back) = back else {
+                continue;
+            };
+            let profit = -sold(back) - sold(front);
+            if profit <= I256::ZERO {
+                continue;
+            }
+            confirmation.sandwich = Some(SuspectedSandwich {
+                attacker: format!("{:#x}", front.tx_sender),
+                attacker_contract: front
+                    .tx_to
+                    .filter(|to| back.tx_to == Some(*to))
+                    .map(|to| format!("{to:#x}")),
+                front_tx_hash: front.tx_hash.clone(),
+                back_tx_hash: back.tx_hash.clone(),
+                raw_estimated_loss: profit.to_string(),
+                loss_token: sold_token.to_string(),
+            });
+            break;
+        }
+    }
+}
+
+/// Same sender, or the same contract called when that is not a public
+/// router (where unrelated users meet).
+fn same_attacker(front: &BlockSwap, back: &BlockSwap) -> bool {
+    if front.tx_sender == back.tx_sender {
+        return true;
+    }
+    let public = |to: &Address| {
+        chain::active().universal_routers.contains(to) || ONEINCH_ROUTERS.contains(to)
+    };
+    matches!((front.tx_to, back.tx_to), (Some(a), Some(b)) if a == b && !public(&a))
+}
+
+/// Gas of the transaction being scanned.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxGas {
+    pub gas_used: u64,
+    pub effective_gas_price: u128,
+}
+
+impl TxGas {
+    pub fn cost_wei(&self) -> U256 {
+        U256::from(self.gas_used) * U256::from(self.effective_gas_price)
+    }
+}
+
 /// Intermediate decoded swap before we have tx context.
 #[derive(Debug)]
 pub struct DecodedSwap {
@@ -155,47 +573,263 @@ pub struct DecodedSwap {
     pub protocol: String,
     pub amount0: String,
     pub amount1: String,
+    /// `(token0, token1)` when the event names them (aggregator fills).
+    pub tokens: Option<(Address, Address)>,
+    pub aggregator: Option<&'static str>,
 }
 
-/// Scan a transaction's receipt logs for swaps involving the executor.
-/// Returns SwapConfirmations with tx_hash and block context filled in.
-pub fn scan_receipt_for_swaps<R: TxReceipt<Log = Log>>(
-    receipt: &R,
+/// Decode a receipt log as a swap involving the executor. `tx_sender`
+/// attributes router-made pool swaps of the executor's own transactions.
+/// Returns a SwapConfirmation with tx_hash, gas and block context filled in.
+#[allow(clippy::too_many_arguments)]
+pub fn decode_swap_confirmation(
+    log: &Log,
+    log_index: u64,
     executor: Address,
+    tx_sender: Address,
     tx_hash: &str,
+    gas: TxGas,
     block_number: u64,
     tx_index: u64,
     ts: u64,
-) -> Vec<SwapConfirmation> {
-    let mut confirmations = Vec::new();
-
-    for (log_index, log) in receipt.logs().iter().enumerate() {
-        if let Some(decoded) = decode_executor_swap(log, executor) {
-            debug!(
-                tx_hash = %tx_hash,
-                pool = %decoded.pool,
-                protocol = %decoded.protocol,
-                "swap confirmation detected"
-            );
-            confirmations.push(SwapConfirmation {
-                tx_hash: tx_hash.to_string(),
-                pool: decoded.pool,
-                protocol: decoded.protocol,
-                amount0: decoded.amount0,
-                amount1: decoded.amount1,
-                // token0/token1 not available from swap event alone — filled as empty.
-                // Hedger correlates by tx_hash, doesn't need tokens from here.
-                token0: String::new(),
-                token1: String::new(),
-                block_number,
-                tx_index,
+) -> Option<SwapConfirmation> {
+    let decoded = decode_executor_swap(log, executor)
+        .or_else(|| decode_aggregator_fill(log, executor))
+        .or_else(|| {
+            if tx_sender == executor {
+                decode_router_swap(log)
+            } else {
+                None
+            }
+        })?;
+    debug!(
+        tx_hash = %tx_hash,
+        pool = %decoded.pool,
+        protocol = %decoded.protocol,
+        aggregator = ?decoded.aggregator,
+        "swap confirmation detected"
+    );
+    let (token0, token1) = decoded
+        .tokens
+        .map(|(t0, t1)| (format!("{t0:#x}"), format!("{t1:#x}")))
+        .unwrap_or_default();
+    Some(SwapConfirmation {
+        chain_id: chain::active().chain_id,
+        tx_hash: tx_hash.to_string(),
+        pool: decoded.pool,
+        protocol: decoded.protocol,
+        amount0: decoded.amount0,
+        amount1: decoded.amount1,
+        // Pool swaps do not name their tokens; see `PoolTokens::fill`.
+        token0,
+        token1,
+        block_number,
+        tx_index,
+        log_index,
+        ts,
+        aggregator: decoded.aggregator.map(str::to_string),
+        gas_used: gas.gas_used,
+        raw_gas_cost_wei: gas.cost_wei().to_string(),
+        // Needs token decimals; see `price_with_decimals`.
+        effective_price: None,
+        // Needs the block's other swaps; see `detect_sandwiches`.
+        sandwich: None,
+    })
+}
+
+/// Token pair per pool, keyed like `SwapConfirmation.pool`: the lowercase
+/// `0x` pool address, or the V4 pool id.
+#[derive(Debug, Default)]
+pub struct PoolTokens {
+    by_pool: HashMap<String, (Address, Address)>,
+}
+
+impl PoolTokens {
+    pub fn insert(&mut self, pool: &str, token0: Address, token1: Address) {
+        self.by_pool
+            .insert(pool.to_ascii_lowercase(), (token0, token1));
+    }
+
+    /// Set `token0`/`token1` of every confirmation whose pool is known.
+    /// Returns the address-keyed pools still unknown, for a DB lookup.
+    pub fn fill(&self, confirmations: &mut [SwapConfirmation]) -> Vec<Address> {
+        let mut missing = Vec::new();
+        for confirmation in confirmations {
+            if !confirmation.token0.is_empty() {
+                continue;
+            }
+            match self.by_pool.get(&confirmation.pool) {
+                Some((token0, token1)) => {
+                    confirmation.token0 = format!("{token0:#x}");
+                    confirmation.token1 = format!("{token1:#x}");
+                }
+                None => {
+                    if let Ok(pool) = confirmation.pool.parse::<Address>() {
+                        if !missing.contains(&pool) {
+                            missing.push(pool);
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+}
+
+/// Every topic0 the swap monitor decodes: pool swaps and aggregator fills.
+pub fn swap_topics() -> Vec<B256> {
+    use aggregator_events::*;
+    vec![
+        v2_swap::Swap::SIGNATURE_HASH,
+        v3_swap::Swap::SIGNATURE_HASH,
+        v4_swap::Swap::SIGNATURE_HASH,
+        Swapped::SIGNATURE_HASH,
+        TransformedERC20::SIGNATURE_HASH,
+        RfqOrderFilled::SIGNATURE_HASH,
+        LimitOrderFilled::SIGNATURE_HASH,
+        OtcOrderFilled::SIGNATURE_HASH,
+    ]
+}
+
+/// One block's executor swaps and executor transactions that did not trade.
+#[derive(Debug, Default)]
+pub struct BlockSwaps {
+    pub confirmations: Vec<SwapConfirmation>,
+    pub failures: Vec<SwapFailed>,
+}
+
+/// Swap monitoring as a dispatch handler: swaps and failed trades of the
+/// executor are decoded on the shared walk and handed, per block, to the
+/// balance monitor, which fills in tokens and prices and publishes them.
+pub struct SwapMonitorHandler {
+    executor: Address,
+    scans: Arc<ScanStore>,
+    /// The block being walked.
+    current: BlockSwaps,
+    /// Every pool swap of the block being walked, for sandwich detection.
+    block_swaps: Vec<BlockSwap>,
+    /// The executor transaction being walked and the swaps found in it.
+    executor_tx: Option<(TxInfo, usize)>,
+    /// Walked blocks waiting for the flush.
+    pending: Vec<(BlockNumHash, BlockSwaps)>,
+}
+
+/// Build the swap monitor handler for the balance monitor's executor.
+pub fn swap_monitor_handler(scans: Arc<ScanStore>) -> eyre::Result<Box<dyn BlockHandler>> {
+    let executor = balance_monitor::executor_address()?;
+    info!(executor = %executor, "Swap monitor handler starting");
+    Ok(Box::new(SwapMonitorHandler {
+        executor,
+        scans,
+        current: BlockSwaps::default(),
+        block_swaps: Vec::new(),
+        executor_tx: None,
+        pending: Vec::new(),
+    }))
+}
+
+impl SwapMonitorHandler {
+    /// Report the executor transaction just walked if it did not trade.
+    fn finish_tx(&mut self, block: &BlockInfo) {
+        let Some((tx, swaps_found)) = self.executor_tx.take() else {
+            return;
+        };
+        let input = tx
+            .selector
+            .as_ref()
+            .map_or(&[][..], |selector| &selector[..]);
+        if let Some(reason) = executor_tx_failure(tx.success, input, swaps_found) {
+            self.current.failures.push(SwapFailed {
+                chain_id: chain::active().chain_id,
+                tx_hash: format!("{:#x}", tx.hash),
+                reason,
+                block_number: block.number,
+                tx_index: tx.index as u64,
+                ts: now_ms(),
+            });
+        }
+    }
+}
+
+impl BlockHandler for SwapMonitorHandler {
+    fn name(&self) -> &'static str {
+        "swap_monitor"
+    }
+
+    fn topics(&self) -> Vec<B256> {
+        swap_topics()
+    }
+
+    fn on_tx(&mut self, block: &BlockInfo, tx: &TxInfo) {
+        self.finish_tx(block);
+        if tx.from == self.executor {
+            self.executor_tx = Some((tx.clone(), 0));
+        }
+    }
+
+    fn on_log(&mut self, block: &BlockInfo, tx: &TxInfo, log_index: usize, log: &Log) {
+        let tx_hash = format!("{:#x}", tx.hash);
+        if let Some((pool, amount0, amount1)) = decode_pool_swap(log) {
+            self.block_swaps.push(BlockSwap {
+                pool,
+                tx_index: tx.index as u64,
                 log_index: log_index as u64,
-                ts,
+                tx_hash: tx_hash.clone(),
+                tx_sender: tx.from,
+                tx_to: tx.to,
+                amount0,
+                amount1,
             });
         }
+        let gas = TxGas {
+            gas_used: tx.gas_used,
+            effective_gas_price: u128::from(block.base_fee_per_gas.unwrap_or(0))
+                + tx.priority_fee_per_gas,
+        };
+        let confirmation = decode_swap_confirmation(
+            log,
+            log_index as u64,
+            self.executor,
+            tx.from,
+            &tx_hash,
+            gas,
+            block.number,
+            tx.index as u64,
+            now_ms(),
+        );
+        if let Some(confirmation) = confirmation {
+            if let Some((_, swaps_found)) = &mut self.executor_tx {
+                *swaps_found += 1;
+            }
+            self.current.confirmations.push(confirmation);
+        }
+    }
+
+    fn end_block(&mut self, block: &BlockInfo) {
+        self.finish_tx(block);
+        detect_sandwiches(
+            &mut self.current.confirmations,
+            &self.block_swaps,
+            self.executor,
+        );
+        self.block_swaps.clear();
+        self.pending.push((
+            BlockNumHash::new(block.number, block.hash),
+            std::mem::take(&mut self.current),
+        ));
     }
 
-    confirmations
+    fn on_revert(&mut self, blocks: &[u64]) {
+        self.pending
+            .retain(|(block, _)| !blocks.contains(&block.number));
+    }
+
+    fn flush(&mut self, _committed_tip: Option<u64>) -> BoxFuture<'_, eyre::Result<()>> {
+        for (block, swaps) in std::mem::take(&mut self.pending) {
+            self.scans.publish_swaps(block, swaps);
+        }
+        Box::pin(async { Ok(()) })
+    }
 }
 
 #[cfg(test)]
@@ -253,4 +887,247 @@ mod tests {
         let result = decode_executor_swap(&log, EXECUTOR);
         assert!(result.is_none());
     }
+
+    fn receipt(logs: Vec<Log>) -> alloy_consensus::Receipt {
+        alloy_consensus::Receipt {
+            status: true.into(),
+            cumulative_gas_used: 0,
+            logs,
+        }
+    }
+
+    /// Every swap confirmation in `receipt`, log by log as the walk decodes
+    /// them.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_receipt_for_swaps(
+        receipt: &alloy_consensus::Receipt,
+        executor: Address,
+        tx_sender: Address,
+        tx_hash: &str,
+        gas: TxGas,
+        block_number: u64,
+        tx_index: u64,
+        ts: u64,
+    ) -> Vec<SwapConfirmation> {
+        receipt
+            .logs
+            .iter()
+            .enumerate()
+            .filter_map(|(log_index, log)| {
+                decode_swap_confirmation(
+                    log,
+                    log_index as u64,
+                    executor,
+                    tx_sender,
+                    tx_hash,
+                    gas,
+                    block_number,
+                    tx_index,
+                    ts,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zero_x_fill_with_executor_as_taker_is_confirmed() {
+        const PROXY: Address = address!("Def1C0ded9bec7F1a1670819833240f027b25EfF");
+        const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let event = aggregator_events::TransformedERC20 {
+            taker: EXECUTOR,
+            inputToken: USDC,
+            outputToken: WETH,
+            inputTokenAmount: U256::from(3_000_000_000u64),
+            outputTokenAmount: U256::from(10u64).pow(U256::from(18)),
+        };
+        let log = Log {
+            address: PROXY,
+            data: event.encode_log_data(),
+        };
+
+        let swaps = scan_receipt_for_swaps(
+            &receipt(vec![log.clone()]),
+            EXECUTOR,
+            OTHER,
+            "0xabc",
+            TxGas::default(),
+            1,
+            0,
+            0,
+        );
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].aggregator.as_deref(), Some("0x"));
+        assert_eq!(swaps[0].token0, format!("{USDC:#x}"));
+        assert_eq!(swaps[0].amount0, "-3000000000");
+        assert_eq!(swaps[0].amount1, "1000000000000000000");
+
+        // Someone else's fill.
+        assert!(decode_aggregator_fill(&log, OTHER).is_none());
+    }
+
+    #[test]
+    fn router_swaps_are_attributed_only_in_executor_transactions() {
+        let router = chain::active().universal_routers[0];
+        let log = make_v3_swap_log(POOL, router, router);
+
+        let own = scan_receipt_for_swaps(
+            &receipt(vec![log.clone()]),
+            EXECUTOR,
+            EXECUTOR,
+            "0xabc",
+            TxGas::default(),
+            1,
+            0,
+            0,
+        );
+        assert_eq!(own.len(), 1);
+        assert_eq!(own[0].aggregator.as_deref(), Some("universal_router"));
+        assert_eq!(own[0].pool, format!("{POOL:#x}"));
+
+        let foreign = scan_receipt_for_swaps(
+            &receipt(vec![log]),
+            EXECUTOR,
+            OTHER,
+            "0xabc",
+            TxGas::default(),
+            1,
+            0,
+            0,
+        );
+        assert!(foreign.is_empty());
+    }
+
+    #[test]
+    fn confirmation_carries_gas_cost_and_effective_price() {
+        // 1000 units of token0 (3 decimals) for 500 of token1 (0 decimals).
+        let log = make_v3_swap_log(POOL, EXECUTOR, OTHER);
+        let gas = TxGas {
+            gas_used: 150_000,
+            effective_gas_price: 20_000_000_000,
+        };
+        let mut swaps = scan_receipt_for_swaps(
+            &receipt(vec![log]),
+            EXECUTOR,
+            EXECUTOR,
+            "0xabc",
+            gas,
+            1,
+            0,
+            0,
+        );
+        assert_eq!(swaps[0].gas_used, 150_000);
+        assert_eq!(swaps[0].raw_gas_cost_wei, "3000000000000000");
+        assert_eq!(swaps[0].effective_price, None);
+
+        swaps[0].price_with_decimals(3, 0);
+        assert_eq!(swaps[0].effective_price, Some(500.0));
+    }
+
+    #[test]
+    fn same_account_swaps_around_the_executor_are_flagged() {
+        let attacker = address!("a77ac4e700000000000000000000000000000000");
+        let swap = |tx_index: u64, tx_sender, amount0: i64, amount1: i64| BlockSwap {
+            pool: format!("{POOL:#x}"),
+            tx_index,
+            log_index: 0,
+            tx_hash: format!("0x{tx_index:02x}"),
+            tx_sender,
+            tx_to: None,
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+        };
+        let mut swaps = scan_receipt_for_swaps(
+            &receipt(vec![make_v3_swap_log(POOL, EXECUTOR, OTHER)]),
+            EXECUTOR,
+            EXECUTOR,
+            "0x05",
+            TxGas::default(),
+            1,
+            5,
+            0,
+        );
+        // The executor pays 1000 token0 into the pool at tx 5.
+        let mut block = vec![
+            swap(4, attacker, 300, -160),
+            swap(5, EXECUTOR, 1000, -500),
+            swap(6, OTHER, -50, 25),
+            swap(7, attacker, -320, 160),
+        ];
+        detect_sandwiches(&mut swaps, &block, EXECUTOR);
+        let sandwich = swaps[0].sandwich.clone().expect("sandwich flagged");
+        assert_eq!(sandwich.attacker, format!("{attacker:#x}"));
+        assert_eq!(
+            (
+                sandwich.front_tx_hash.as_str(),
+                sandwich.back_tx_hash.as_str()
+            ),
+            ("0x04", "0x07")
+        );
+        assert_eq!(sandwich.raw_estimated_loss, "20");
+        assert_eq!(sandwich.loss_token, "token0");
+
+        // An unprofitable round trip is not a sandwich.
+        swaps[0].sandwich = None;
+        block[3] = swap(7, attacker, -290, 160);
+        detect_sandwiches(&mut swaps, &block, EXECUTOR);
+        assert_eq!(swaps[0].sandwich, None);
+    }
+
+    #[test]
+    fn executor_tx_failures_are_classified() {
+        let trade = [0x12, 0x34, 0x56, 0x78, 0x00];
+        let approve = [0x09, 0x5e, 0xa7, 0xb3, 0x00];
+        assert_eq!(executor_tx_failure(true, &trade, 1), None);
+        assert_eq!(
+            executor_tx_failure(false, &trade, 0),
+            Some(SwapFailureReason::Reverted)
+        );
+        assert_eq!(
+            executor_tx_failure(true, &trade, 0),
+            Some(SwapFailureReason::NoSwap)
+        );
+        // Not trades: only a revert is reported.
+        assert_eq!(executor_tx_failure(true, &approve, 0), None);
+        assert_eq!(executor_tx_failure(true, &[], 0), None);
+        assert_eq!(
+            executor_tx_failure(false, &approve, 0),
+            Some(SwapFailureReason::Reverted)
+        );
+        assert_eq!(
+            serde_json::to_value(SwapFailureReason::NoSwap).unwrap(),
+            "no_swap"
+        );
+    }
+
+    #[test]
+    fn pool_tokens_fill_known_pools_and_report_the_rest() {
+        const UNKNOWN: Address = address!("1111111111111111111111111111111111111111");
+        let mut confirmations = Vec::new();
+        for pool in [POOL, UNKNOWN, UNKNOWN] {
+            let log = make_v3_swap_log(pool, EXECUTOR, OTHER);
+            confirmations.extend(scan_receipt_for_swaps(
+                &alloy_consensus::Receipt {
+                    status: true.into(),
+                    cumulative_gas_used: 0,
+                    logs: vec![log],
+                },
+                EXECUTOR,
+                EXECUTOR,
+                "0xabc",
+                TxGas::default(),
+                1,
+                0,
+                0,
+            ));
+        }
+
+        let mut tokens = PoolTokens::default();
+        // Whitelist addresses arrive checksummed.
+        tokens.insert(&POOL.to_checksum(None), OTHER, EXECUTOR);
+        assert_eq!(tokens.fill(&mut confirmations), vec![UNKNOWN]);
+        assert_eq!(confirmations[0].token0, format!("{OTHER:#x}"));
+        assert_eq!(confirmations[0].token1, format!("{EXECUTOR:#x}"));
+        assert!(confirmations[1].token0.is_empty());
+    }
 }
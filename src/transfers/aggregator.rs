@@ -24,7 +24,7 @@ pub fn spawn_cleanup(db: Arc<TransferDb>) {
         loop {
             tick.tick().await;
             match db.cleanup_old_transfers().await {
-                Ok(deleted) => info!("Cleanup: deleted {} old transfers", deleted),
+                Ok(dropped) => info!("Cleanup: dropped {} expired transfer partitions", dropped),
                 Err(e) => warn!("Cleanup failed: {}", e),
             }
         }
@@ -0,0 +1,160 @@
+use super::db::{TransferDb, TransferQuery};
+use alloy_primitives::Address;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Rows returned when `limit` is not given, and the most ever returned.
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1_000;
+
+/// Serve the read-only transfers API on `addr`, so downstream services stop
+/// issuing raw SQL against the production tables:
+///
+///   GET /transfers/address/{address}   sent or received by an address
+///   GET /transfers/token/{token}       of one token
+///   GET /transfers/blocks              in a block range (from_block required)
+///   GET /tokens/top                    from the top_transferred_tokens view
+///
+/// The transfer endpoints take optional `from_block`, `to_block` and `limit`
+/// query parameters and return newest first.
+pub fn spawn_api(db: Arc<TransferDb>, addr: SocketAddr) {
+    let app = Router::new()
+        .route("/transfers/address/{address}", get(by_address))
+        .route("/transfers/token/{token}", get(by_token))
+        .route("/transfers/blocks", get(by_blocks))
+        .route("/tokens/top", get(top_tokens))
+        .with_state(db);
+
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Transfers API failed to bind {}: {}", addr, e);
+                return;
+            }
+        };
+        info!("Transfers API listening on {}", addr);
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!("Transfers API stopped: {}", e);
+        }
+    });
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RangeParams {
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    limit: Option<i64>,
+}
+
+impl RangeParams {
+    fn into_query(self) -> TransferQuery {
+        TransferQuery {
+            from_block: self.from_block,
+            to_block: self.to_block,
+            limit: clamp_limit(self.limit),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LimitParams {
+    limit: Option<i64>,
+}
+
+fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Lowercase `0x` hex, matching the stored format; `None` if not an address.
+fn normalize_address(raw: &str) -> Option<String> {
+    raw.parse::<Address>()
+        .ok()
+        .map(|addr| format!("0x{}", hex::encode(addr.0 .0)))
+}
+
+fn bad_request(msg: &str) -> Response {
+    (StatusCode::BAD_REQUEST, msg.to_string()).into_response()
+}
+
+fn db_result<T: serde::Serialize>(result: eyre::Result<T>) -> Response {
+    match result {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => {
+            warn!("Transfers API query failed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "query failed").into_response()
+        }
+    }
+}
+
+async fn by_address(
+    State(db): State<Arc<TransferDb>>,
+    Path(address): Path<String>,
+    Query(params): Query<RangeParams>,
+) -> Response {
+    let Some(address) = normalize_address(&address) else {
+        return bad_request("invalid address");
+    };
+    let query = TransferQuery {
+        address: Some(address),
+        ..params.into_query()
+    };
+    db_result(db.query_transfers(&query).await)
+}
+
+async fn by_token(
+    State(db): State<Arc<TransferDb>>,
+    Path(token): Path<String>,
+    Query(params): Query<RangeParams>,
+) -> Response {
+    let Some(token) = normalize_address(&token) else {
+        return bad_request("invalid token");
+    };
+    let query = TransferQuery {
+        token: Some(token),
+        ..params.into_query()
+    };
+    db_result(db.query_transfers(&query).await)
+}
+
+async fn by_blocks(
+    State(db): State<Arc<TransferDb>>,
+    Query(params): Query<RangeParams>,
+) -> Response {
+    // An unbounded range would walk every partition.
+    if params.from_block.is_none() {
+        return bad_request("from_block is required");
+    }
+    db_result(db.query_transfers(&params.into_query()).await)
+}
+
+async fn top_tokens(
+    State(db): State<Arc<TransferDb>>,
+    Query(params): Query<LimitParams>,
+) -> Response {
+    db_result(db.top_tokens(clamp_limit(params.limit)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_normalize_to_stored_format() {
+        assert_eq!(
+            normalize_address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").as_deref(),
+            Some("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")
+        );
+        assert_eq!(normalize_address("usdc"), None);
+        assert_eq!(clamp_limit(None), DEFAULT_LIMIT);
+        assert_eq!(clamp_limit(Some(50_000)), MAX_LIMIT);
+    }
+}
@@ -0,0 +1,114 @@
+use super::db::TransferRow;
+use super::filter::TransferFilter;
+use super::push_receipt_transfers;
+use super::store::TransferStore;
+use alloy_consensus::{transaction::TxHashRef, BlockHeader, TxReceipt};
+use alloy_primitives::Log;
+use reth_provider::BlockReader;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Blocks read per insert. Large enough for COPY to pay off, small enough to
+/// keep a chunk's rows in memory.
+const CHUNK_BLOCKS: u64 = 1_000;
+
+/// Index ERC20 transfers for `from_block..=to_block` from the node's receipts.
+/// Reads run on blocking tasks a chunk at a time; inserts are idempotent, so
+/// overlap with the live stream (or a rerun) is harmless. Blocks older than
+/// the retention window are dropped again by the next cleanup.
+pub fn spawn_backfill<P, S>(
+    provider: P,
+    store: Arc<S>,
+    filter: Arc<TransferFilter>,
+    from_block: u64,
+    to_block: u64,
+) where
+    P: BlockReader + Clone + Send + Sync + 'static,
+    P::Receipt: TxReceipt<Log = Log>,
+    P::Transaction: TxHashRef,
+    S: TransferStore,
+{
+    tokio::spawn(async move {
+        info!(
+            "Transfers backfill of blocks {}..={} started",
+            from_block, to_block
+        );
+        let mut total = 0u64;
+        let mut next = from_block;
+        while next <= to_block {
+            let end = next.saturating_add(CHUNK_BLOCKS - 1).min(to_block);
+            let (provider, filter) = (provider.clone(), filter.clone());
+            let rows = match tokio::task::spawn_blocking(move || {
+                read_transfers(&provider, &filter, next, end)
+            })
+            .await
+            {
+                Ok(Ok(rows)) => rows,
+                Ok(Err(e)) => {
+                    warn!("Transfers backfill aborted at block {}: {}", next, e);
+                    return;
+                }
+                Err(e) => {
+                    warn!("Transfers backfill task panicked at block {}: {}", next, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = store.insert_transfers(&rows).await {
+                warn!("Transfers backfill aborted at block {}: {}", next, e);
+                return;
+            }
+            total += rows.len() as u64;
+            info!(
+                "Backfill: blocks {}..={} done, {} transfers so far",
+                from_block, end, total
+            );
+            next = end + 1;
+        }
+        info!(
+            "Transfers backfill of blocks {}..={} complete: {} transfers",
+            from_block, to_block, total
+        );
+    });
+}
+
+fn read_transfers<P>(
+    provider: &P,
+    filter: &TransferFilter,
+    from_block: u64,
+    to_block: u64,
+) -> eyre::Result<Vec<TransferRow>>
+where
+    P: BlockReader,
+    P::Receipt: TxReceipt<Log = Log>,
+    P::Transaction: TxHashRef,
+{
+    let mut rows = Vec::new();
+    for block_number in from_block..=to_block {
+        let Some(receipts) = provider.receipts_by_block(block_number.into())? else {
+            continue;
+        };
+        let Some(header) = provider.header_by_number(block_number)? else {
+            continue;
+        };
+        let transactions = provider
+            .transactions_by_block(block_number.into())?
+            .unwrap_or_default();
+
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let tx_hash: [u8; 32] = transactions
+                .get(tx_index)
+                .map(|tx| tx.tx_hash().0)
+                .unwrap_or_default();
+            push_receipt_transfers(
+                &mut rows,
+                filter,
+                block_number,
+                header.timestamp(),
+                tx_hash,
+                receipt.logs(),
+            );
+        }
+    }
+    Ok(rows)
+}
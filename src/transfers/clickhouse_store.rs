@@ -0,0 +1,216 @@
+use super::db::{EthTransferRow, TransferRow};
+use super::store::TransferStore;
+use alloy_primitives::U256;
+use clickhouse::{Client, Row};
+use serde::Serialize;
+use tracing::info;
+
+/// `erc20_transfers` row in RowBinary layout. `amount` is a little-endian
+/// `UInt256`, so analytics can sum it without casting.
+#[derive(Row, Serialize)]
+struct ClickHouseTransfer<'a> {
+    chain_id: u64,
+    block_number: u64,
+    tx_hash: &'a str,
+    log_index: u32,
+    token_address: &'a str,
+    from_address: &'a str,
+    to_address: &'a str,
+    amount: [u8; 32],
+    block_timestamp: u64,
+    kind: &'static str,
+}
+
+/// `eth_transfers` row in RowBinary layout.
+#[derive(Row, Serialize)]
+struct ClickHouseEthTransfer<'a> {
+    chain_id: u64,
+    block_number: u64,
+    tx_hash: &'a str,
+    from_address: &'a str,
+    to_address: &'a str,
+    amount: [u8; 32],
+    block_timestamp: u64,
+}
+
+pub struct ClickHouseStore {
+    client: Client,
+    /// Chain these rows belong to, stored in `chain_id`.
+    chain_id: u64,
+}
+
+impl ClickHouseStore {
+    /// `retention_days` sets the table TTL when the tables are created.
+    pub async fn new(
+        url: &str,
+        database: &str,
+        chain_id: u64,
+        retention_days: u64,
+    ) -> eyre::Result<Self> {
+        let mut client = Client::default().with_url(url).with_database(database);
+        if let Ok(user) = std::env::var("CLICKHOUSE_USER") {
+            client = client.with_user(user);
+        }
+        if let Ok(password) = std::env::var("CLICKHOUSE_PASSWORD") {
+            client = client.with_password(password);
+        }
+
+        let store = Self { client, chain_id };
+        store.init_schema(retention_days.max(1)).await?;
+        Ok(store)
+    }
+
+    async fn init_schema(&self, retention_days: u64) -> eyre::Result<()> {
+        // ReplacingMergeTree on the natural key dedups retried / replayed
+        // blocks on merge; the TTL replaces the Postgres cleanup task.
+        self.client
+            .query(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS erc20_transfers (
+                    chain_id        UInt64,
+                    block_number    UInt64,
+                    tx_hash         String,
+                    log_index       UInt32,
+                    token_address   LowCardinality(String),
+                    from_address    String,
+                    to_address      String,
+                    amount          UInt256,
+                    block_timestamp UInt64,
+                    kind            LowCardinality(String)
+                )
+                ENGINE = ReplacingMergeTree
+                PARTITION BY toYYYYMMDD(toDateTime(block_timestamp))
+                ORDER BY (chain_id, token_address, block_timestamp, tx_hash, log_index)
+                TTL toDateTime(block_timestamp) + INTERVAL {retention_days} DAY
+                "#
+            ))
+            .execute()
+            .await?;
+
+        // Tables created before `kind`: old rows read the default.
+        self.client
+            .query(
+                "ALTER TABLE erc20_transfers ADD COLUMN IF NOT EXISTS kind LowCardinality(String) DEFAULT 'transfer'",
+            )
+            .execute()
+            .await?;
+
+        self.client
+            .query(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS eth_transfers (
+                    chain_id        UInt64,
+                    block_number    UInt64,
+                    tx_hash         String,
+                    from_address    String,
+                    to_address      String,
+                    amount          UInt256,
+                    block_timestamp UInt64
+                )
+                ENGINE = ReplacingMergeTree
+                PARTITION BY toYYYYMMDD(toDateTime(block_timestamp))
+                ORDER BY (chain_id, block_timestamp, tx_hash)
+                TTL toDateTime(block_timestamp) + INTERVAL {retention_days} DAY
+                "#
+            ))
+            .execute()
+            .await?;
+
+        // Tables created before `chain_id`: their rows belong to this chain.
+        // The sort key cannot change in place, so those tables keep theirs.
+        for table in ["erc20_transfers", "eth_transfers"] {
+            self.client
+                .query(&format!(
+                    "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS chain_id UInt64 DEFAULT {}",
+                    self.chain_id
+                ))
+                .execute()
+                .await?;
+        }
+
+        info!("ClickHouse schema initialized");
+        Ok(())
+    }
+}
+
+impl TransferStore for ClickHouseStore {
+    async fn insert_transfers(&self, transfers: &[TransferRow]) -> eyre::Result<()> {
+        if transfers.is_empty() {
+            return Ok(());
+        }
+
+        // One INSERT per block: ClickHouse prefers few large inserts.
+        let mut insert = self.client.insert("erc20_transfers")?;
+        for t in transfers {
+            let amount: U256 = t.amount_str.parse()?;
+            insert
+                .write(&ClickHouseTransfer {
+                    chain_id: self.chain_id,
+                    block_number: t.block_number,
+                    tx_hash: &t.tx_hash,
+                    log_index: t.log_index,
+                    token_address: &t.token_address,
+                    from_address: &t.from_address,
+                    to_address: &t.to_address,
+                    amount: amount.to_le_bytes(),
+                    block_timestamp: t.block_timestamp,
+                    kind: t.kind.as_str(),
+                })
+                .await?;
+        }
+        insert.end().await?;
+        Ok(())
+    }
+
+    async fn insert_eth_transfers(&self, transfers: &[EthTransferRow]) -> eyre::Result<()> {
+        if transfers.is_empty() {
+            return Ok(());
+        }
+
+        let mut insert = self.client.insert("eth_transfers")?;
+        for t in transfers {
+            let amount: U256 = t.amount_str.parse()?;
+            insert
+                .write(&ClickHouseEthTransfer {
+                    chain_id: self.chain_id,
+                    block_number: t.block_number,
+                    tx_hash: &t.tx_hash,
+                    from_address: &t.from_address,
+                    to_address: &t.to_address,
+                    amount: amount.to_le_bytes(),
+                    block_timestamp: t.block_timestamp,
+                })
+                .await?;
+        }
+        insert.end().await?;
+        Ok(())
+    }
+
+    async fn delete_block(&self, block_number: u64) -> eyre::Result<u64> {
+        let mut deleted = 0;
+        for table in ["erc20_transfers", "eth_transfers"] {
+            // Lightweight DELETE reports no row count, so count first.
+            let count: u64 = self
+                .client
+                .query(&format!(
+                    "SELECT count() FROM {table} WHERE block_number = ? AND chain_id = ?"
+                ))
+                .bind(block_number)
+                .bind(self.chain_id)
+                .fetch_one()
+                .await?;
+            if count > 0 {
+                self.client
+                    .query(&format!(
+                        "DELETE FROM {table} WHERE block_number = ? AND chain_id = ?"
+                    ))
+                    .bind(block_number)
+                    .bind(self.chain_id)
+                    .execute()
+                    .await?;
+            }
+            deleted += count;
+        }
+        Ok(deleted)
+    }
+}
@@ -1,8 +1,42 @@
+use alloy_primitives::Address;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Mutex;
 use std::time::Duration;
 use tracing::info;
 
+/// Supply effect of a transfer, from the zero address on either side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    Mint,
+    Burn,
+    #[default]
+    Transfer,
+}
+
+impl TransferKind {
+    pub fn of(from: Address, to: Address) -> Self {
+        if from.is_zero() {
+            Self::Mint
+        } else if to.is_zero() {
+            Self::Burn
+        } else {
+            Self::Transfer
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mint => "mint",
+            Self::Burn => "burn",
+            Self::Transfer => "transfer",
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct TransferRow {
     pub block_number: u64,
     pub tx_hash: String,
@@ -12,28 +46,140 @@ pub struct TransferRow {
     pub to_address: String,
     pub amount_str: String, // U256.to_string() decimal representation
     pub block_timestamp: u64,
+    #[serde(default)]
+    pub kind: TransferKind,
 }
 
+/// Plain ETH value transfer (top-level `tx.value`), stored in `eth_transfers`.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct EthTransferRow {
+    pub block_number: u64,
+    pub tx_hash: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub amount_str: String, // wei, U256.to_string()
+    pub block_timestamp: u64,
+}
+
+/// Filters for `query_transfers`. Addresses are lowercase `0x` hex, as stored.
+#[derive(Debug, Default)]
+pub struct TransferQuery {
+    /// Sender or receiver.
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub limit: i64,
+}
+
+/// A stored transfer as returned by the query API.
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct TransferRecord {
+    pub block_number: i64,
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub token_address: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: String,
+    pub block_timestamp: i64,
+    pub kind: String,
+}
+
+/// A `top_transferred_tokens` row.
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct TopToken {
+    pub token_address: String,
+    pub ranking_score: f64,
+    pub transfer_count_24h: i64,
+    pub transfer_count_7d: i64,
+    pub unique_senders_24h: i64,
+    pub unique_receivers_24h: i64,
+    pub volume_usd_24h: f64,
+    pub volume_usd_7d: f64,
+    pub updated_at: i64,
+}
+
+/// `symbol` / `decimals` resolved on-chain for `token_metadata`.
+pub struct TokenMetadataRow {
+    pub token_address: String,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+/// Width of a transfer table partition.
+const PARTITION_SECS: i64 = 86400;
+
+/// Tables partitioned daily on block_timestamp.
+const PARTITIONED_TABLES: [&str; 2] = ["erc20_transfers", "eth_transfers"];
+
+/// Aggregation reads the hourly tables over 7 days, so they are kept at
+/// least that long whatever the raw retention.
+const AGGREGATION_WINDOW_SECS: i64 = 7 * 86400;
+
 pub struct TransferDb {
     pool: PgPool,
+    /// Chain these rows belong to, stored in `chain_id`.
+    chain_id: u64,
+    /// Raw transfers older than this are dropped.
+    retention_secs: i64,
+    /// (table, day start) partitions known to exist.
+    partitions: Mutex<HashSet<(&'static str, i64)>>,
 }
 
 impl TransferDb {
-    pub async fn new(database_url: &str) -> eyre::Result<Self> {
+    /// Connect with every table in Postgres schema `schema` (created if
+    /// missing), so ExEx instances for different chains can share a database
+    /// with one schema each.
+    pub async fn new(
+        database_url: &str,
+        chain_id: u64,
+        schema: &str,
+        retention_days: u64,
+    ) -> eyre::Result<Self> {
+        if schema.is_empty()
+            || !schema
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            eyre::bail!("invalid transfers schema {schema:?} (expected [a-z0-9_]+)");
+        }
+        let search_path = format!("SET search_path TO {schema}");
         let pool = PgPoolOptions::new()
             .max_connections(20)
             .min_connections(2)
             .acquire_timeout(Duration::from_secs(60))
             .idle_timeout(Duration::from_secs(300))
             .max_lifetime(Duration::from_secs(1800))
+            .after_connect(move |conn, _meta| {
+                let search_path = search_path.clone();
+                Box::pin(async move {
+                    sqlx::query(&search_path).execute(conn).await?;
+                    Ok(())
+                })
+            })
             .connect(database_url)
             .await?;
 
-        let db = Self { pool };
+        sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {schema}"))
+            .execute(&pool)
+            .await?;
+
+        let db = Self {
+            pool,
+            chain_id,
+            retention_secs: retention_days.max(1) as i64 * 86400,
+            partitions: Mutex::new(HashSet::new()),
+        };
         db.init_schema().await?;
         Ok(db)
     }
 
+    /// Connection pool, for the readiness probe.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
     async fn init_schema(&self) -> eyre::Result<()> {
         // Migration: drop old BYTEA-based tables if they exist
         sqlx::query(
@@ -43,7 +189,8 @@ impl TransferDb {
                 -- Check if erc20_transfers exists with BYTEA columns (old schema)
                 IF EXISTS (
                     SELECT 1 FROM information_schema.columns
-                    WHERE table_name = 'erc20_transfers'
+                    WHERE table_schema = current_schema()
+                      AND table_name = 'erc20_transfers'
                       AND column_name = 'tx_hash'
                       AND data_type = 'bytea'
                 ) THEN
@@ -59,9 +206,47 @@ impl TransferDb {
         .execute(&self.pool)
         .await?;
 
+        // Migration: move an unpartitioned table aside so the partitioned one
+        // can take its name; its rows are copied over once partitions can be
+        // created below. Its indexes (and with them the pkey constraint) are
+        // renamed too, or the new table's would clash or be skipped.
+        sqlx::query(
+            r#"
+            DO $$
+            DECLARE
+                idx RECORD;
+            BEGIN
+                IF EXISTS (
+                    SELECT 1 FROM pg_class
+                    WHERE relname = 'erc20_transfers' AND relkind = 'r'
+                      AND relnamespace = current_schema()::regnamespace
+                ) THEN
+                    ALTER TABLE erc20_transfers RENAME TO erc20_transfers_unpartitioned;
+                    FOR idx IN
+                        SELECT indexname FROM pg_indexes
+                        WHERE schemaname = current_schema()
+                          AND tablename = 'erc20_transfers_unpartitioned'
+                    LOOP
+                        EXECUTE format('ALTER INDEX %I RENAME TO %I',
+                            idx.indexname, idx.indexname || '_unpartitioned');
+                    END LOOP;
+                    RAISE NOTICE 'Renamed unpartitioned erc20_transfers';
+                END IF;
+            END
+            $$
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Daily range partitions on block_timestamp, so retention drops whole
+        // partitions instead of DELETE + vacuum. The partition key has to be
+        // part of the primary key; a tx's logs all share its block timestamp,
+        // so uniqueness is unchanged.
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS erc20_transfers (
+                chain_id        BIGINT NOT NULL,
                 block_number    BIGINT NOT NULL,
                 tx_hash         TEXT NOT NULL,
                 log_index       INTEGER NOT NULL,
@@ -70,13 +255,153 @@ impl TransferDb {
                 to_address      TEXT NOT NULL,
                 amount          NUMERIC NOT NULL,
                 block_timestamp BIGINT NOT NULL,
-                CONSTRAINT erc20_transfers_pkey PRIMARY KEY (tx_hash, log_index)
-            )
+                kind            TEXT NOT NULL DEFAULT 'transfer',
+                CONSTRAINT erc20_transfers_pkey PRIMARY KEY (tx_hash, log_index, block_timestamp)
+            ) PARTITION BY RANGE (block_timestamp)
             "#,
         )
         .execute(&self.pool)
         .await?;
 
+        // Migration: add `kind` to tables created before it, backfilling
+        // mints and burns from the zero address.
+        sqlx::query(
+            r#"
+            DO $$
+            BEGIN
+                IF NOT EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_schema = current_schema()
+                      AND table_name = 'erc20_transfers' AND column_name = 'kind'
+                ) THEN
+                    ALTER TABLE erc20_transfers ADD COLUMN kind TEXT NOT NULL DEFAULT 'transfer';
+                    UPDATE erc20_transfers SET kind = 'mint'
+                        WHERE from_address = '0x0000000000000000000000000000000000000000';
+                    UPDATE erc20_transfers SET kind = 'burn'
+                        WHERE kind = 'transfer'
+                          AND to_address = '0x0000000000000000000000000000000000000000';
+                    RAISE NOTICE 'Added kind to erc20_transfers';
+                END IF;
+            END
+            $$
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Native ETH value transfers, partitioned the same way. One row per
+        // transaction, so tx_hash alone identifies it.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS eth_transfers (
+                chain_id        BIGINT NOT NULL,
+                block_number    BIGINT NOT NULL,
+                tx_hash         TEXT NOT NULL,
+                from_address    TEXT NOT NULL,
+                to_address      TEXT NOT NULL,
+                amount          NUMERIC NOT NULL,
+                block_timestamp BIGINT NOT NULL,
+                CONSTRAINT eth_transfers_pkey PRIMARY KEY (tx_hash, block_timestamp)
+            ) PARTITION BY RANGE (block_timestamp)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_eth_transfers_block_number ON eth_transfers (block_number)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Migration: add `chain_id` to tables created before it; their rows
+        // are this instance's chain.
+        for table in PARTITIONED_TABLES {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS chain_id BIGINT NOT NULL DEFAULT {}",
+                self.chain_id
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
+
+        // Creates the partition of `parent` for the UTC day starting at
+        // `day_start`, named `<parent>_YYYYMMDD`.
+        sqlx::query(
+            r#"
+            CREATE OR REPLACE FUNCTION transfers_ensure_partition(parent TEXT, day_start BIGINT)
+            RETURNS void AS $$
+            BEGIN
+                EXECUTE format(
+                    'CREATE TABLE IF NOT EXISTS %I PARTITION OF %I FOR VALUES FROM (%s) TO (%s)',
+                    parent || '_' || to_char(to_timestamp(day_start) AT TIME ZONE 'UTC', 'YYYYMMDD'),
+                    parent,
+                    day_start,
+                    day_start + 86400
+                );
+            END
+            $$ LANGUAGE plpgsql
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Migration: copy the renamed unpartitioned table's rows into daily
+        // partitions, then drop it. Rows from before `kind` / `chain_id` get
+        // the same backfill as the column migrations above. The hourly and
+        // participant aggregates were built from these rows and are kept.
+        sqlx::query(&format!(
+            r#"
+            DO $$
+            DECLARE
+                kind_expr TEXT := 'kind';
+                chain_expr TEXT := 'chain_id';
+            BEGIN
+                IF to_regclass('erc20_transfers_unpartitioned') IS NULL THEN
+                    RETURN;
+                END IF;
+                IF NOT EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_schema = current_schema()
+                      AND table_name = 'erc20_transfers_unpartitioned' AND column_name = 'kind'
+                ) THEN
+                    kind_expr := 'CASE'
+                        || ' WHEN from_address = ''0x0000000000000000000000000000000000000000'' THEN ''mint'''
+                        || ' WHEN to_address = ''0x0000000000000000000000000000000000000000'' THEN ''burn'''
+                        || ' ELSE ''transfer'' END';
+                END IF;
+                IF NOT EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_schema = current_schema()
+                      AND table_name = 'erc20_transfers_unpartitioned' AND column_name = 'chain_id'
+                ) THEN
+                    chain_expr := '{chain_id}';
+                END IF;
+
+                PERFORM transfers_ensure_partition('erc20_transfers', day_start)
+                FROM (
+                    SELECT DISTINCT block_timestamp - block_timestamp % {PARTITION_SECS} AS day_start
+                    FROM erc20_transfers_unpartitioned
+                ) days;
+
+                EXECUTE format(
+                    'INSERT INTO erc20_transfers (chain_id, block_number, tx_hash, log_index,'
+                    || ' token_address, from_address, to_address, amount, block_timestamp, kind)'
+                    || ' SELECT %s, block_number, tx_hash, log_index, token_address, from_address,'
+                    || ' to_address, amount, block_timestamp, %s FROM erc20_transfers_unpartitioned'
+                    || ' ON CONFLICT DO NOTHING',
+                    chain_expr, kind_expr
+                );
+                DROP TABLE erc20_transfers_unpartitioned;
+                RAISE NOTICE 'Copied unpartitioned erc20_transfers into partitions';
+            END
+            $$
+            "#,
+            chain_id = self.chain_id,
+        ))
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_transfers_block_timestamp ON erc20_transfers (block_timestamp)",
         )
@@ -95,13 +420,66 @@ impl TransferDb {
         .execute(&self.pool)
         .await?;
 
+        // Supply-change queries: mints and burns only, a small fraction of rows.
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_transfers_kind_token ON erc20_transfers (kind, token_address, block_timestamp) WHERE kind <> 'transfer'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Incremental aggregates, maintained per block by `insert_transfers` /
+        // `delete_block` so `run_aggregation` never scans `erc20_transfers`.
+        // `hour` is the block timestamp truncated to the hour.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS token_transfer_hourly (
+                token_address   TEXT NOT NULL,
+                hour            BIGINT NOT NULL,
+                transfer_count  BIGINT NOT NULL DEFAULT 0,
+                volume_raw      NUMERIC NOT NULL DEFAULT 0,
+                CONSTRAINT token_transfer_hourly_pkey PRIMARY KEY (token_address, hour)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_transfer_hourly_hour ON token_transfer_hourly (hour)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Distinct senders / receivers per token and hour, for the unique
+        // counts (which are not additive across hours).
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS token_transfer_participants (
+                token_address   TEXT NOT NULL,
+                hour            BIGINT NOT NULL,
+                address         TEXT NOT NULL,
+                is_sender       BOOLEAN NOT NULL,
+                CONSTRAINT token_transfer_participants_pkey
+                    PRIMARY KEY (token_address, hour, address, is_sender)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_transfer_participants_hour ON token_transfer_participants (hour)",
+        )
+        .execute(&self.pool)
+        .await?;
+
         // Token metadata — populated by an external service (price feed)
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS token_metadata (
                 token_address   TEXT PRIMARY KEY,
                 symbol          TEXT,
-                decimals        INTEGER NOT NULL DEFAULT 18,
+                decimals        INTEGER,
                 price_usd       DOUBLE PRECISION NOT NULL DEFAULT 0,
                 market_cap_usd  DOUBLE PRECISION NOT NULL DEFAULT 0,
                 updated_at      BIGINT NOT NULL DEFAULT 0
@@ -111,6 +489,14 @@ impl TransferDb {
         .execute(&self.pool)
         .await?;
 
+        // Unresolved decimals are NULL (readers fall back to 18), not a
+        // stored 18 that would never be corrected.
+        sqlx::query(
+            "ALTER TABLE token_metadata ALTER COLUMN decimals DROP NOT NULL, ALTER COLUMN decimals DROP DEFAULT",
+        )
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS token_transfer_stats (
@@ -169,47 +555,185 @@ impl TransferDb {
         Ok(())
     }
 
-    /// Batch insert transfers for a block. Idempotent via ON CONFLICT DO NOTHING.
+    /// Bulk insert transfers for a block. Idempotent via ON CONFLICT DO NOTHING.
+    ///
+    /// Rows are streamed into a transaction-scoped staging table with binary
+    /// `COPY IN` — no parameter limit, no per-row bind overhead — then moved
+    /// into `erc20_transfers` by one statement that also folds the rows
+    /// actually inserted into the hourly aggregates, so a retried or replayed
+    /// block is never counted twice.
     pub async fn insert_transfers(&self, transfers: &[TransferRow]) -> eyre::Result<()> {
         if transfers.is_empty() {
             return Ok(());
         }
 
-        // Chunk to stay under Postgres parameter limits (65535 params / 8 cols ≈ 8191 rows)
-        for chunk in transfers.chunks(1000) {
-            let mut qb = sqlx::QueryBuilder::new(
-                "INSERT INTO erc20_transfers (block_number, tx_hash, log_index, token_address, from_address, to_address, amount, block_timestamp) ",
-            );
-
-            qb.push_values(chunk, |mut b, t| {
-                b.push_bind(t.block_number as i64)
-                    .push_bind(&t.tx_hash)
-                    .push_bind(t.log_index as i32)
-                    .push_bind(&t.token_address)
-                    .push_bind(&t.from_address)
-                    .push_bind(&t.to_address)
-                    .push_bind(&t.amount_str)
-                    .push_unseparated("::NUMERIC")
-                    .push_bind(t.block_timestamp as i64);
-            });
-
-            qb.push(" ON CONFLICT (tx_hash, log_index) DO NOTHING");
-            qb.build().execute(&self.pool).await?;
+        self.ensure_partitions(
+            "erc20_transfers",
+            transfers.iter().map(|t| t.block_timestamp),
+        )
+        .await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        // `amount` is staged as TEXT: binary NUMERIC is not worth hand-encoding,
+        // and the cast happens server-side in the move below.
+        sqlx::query(
+            r#"
+            CREATE TEMP TABLE erc20_transfers_staging (
+                block_number    BIGINT NOT NULL,
+                tx_hash         TEXT NOT NULL,
+                log_index       INTEGER NOT NULL,
+                token_address   TEXT NOT NULL,
+                from_address    TEXT NOT NULL,
+                to_address      TEXT NOT NULL,
+                amount          TEXT NOT NULL,
+                block_timestamp BIGINT NOT NULL,
+                kind            TEXT NOT NULL
+            ) ON COMMIT DROP
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut copy = tx
+            .copy_in_raw("COPY erc20_transfers_staging FROM STDIN (FORMAT binary)")
+            .await?;
+        copy.send(encode_copy_binary(transfers)).await?;
+        copy.finish().await?;
+
+        sqlx::query(
+            r#"
+            WITH inserted AS (
+                INSERT INTO erc20_transfers (chain_id, block_number, tx_hash, log_index, token_address, from_address, to_address, amount, block_timestamp, kind)
+                SELECT $1, block_number, tx_hash, log_index, token_address, from_address, to_address, amount::NUMERIC, block_timestamp, kind
+                FROM erc20_transfers_staging
+                ON CONFLICT (tx_hash, log_index, block_timestamp) DO NOTHING
+                RETURNING token_address, from_address, to_address, amount, block_timestamp / 3600 * 3600 AS hour
+            ),
+            hourly AS (
+                INSERT INTO token_transfer_hourly (token_address, hour, transfer_count, volume_raw)
+                SELECT token_address, hour, COUNT(*), SUM(amount)
+                FROM inserted
+                GROUP BY token_address, hour
+                ON CONFLICT (token_address, hour) DO UPDATE SET
+                    transfer_count = token_transfer_hourly.transfer_count + EXCLUDED.transfer_count,
+                    volume_raw = token_transfer_hourly.volume_raw + EXCLUDED.volume_raw
+            )
+            INSERT INTO token_transfer_participants (token_address, hour, address, is_sender)
+            SELECT token_address, hour, from_address, TRUE FROM inserted
+            UNION
+            SELECT token_address, hour, to_address, FALSE FROM inserted
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(self.chain_id as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Insert a block's ETH value transfers. Idempotent via ON CONFLICT DO NOTHING.
+    pub async fn insert_eth_transfers(&self, transfers: &[EthTransferRow]) -> eyre::Result<()> {
+        if transfers.is_empty() {
+            return Ok(());
         }
 
+        self.ensure_partitions("eth_transfers", transfers.iter().map(|t| t.block_timestamp))
+            .await?;
+
+        // At most one row per transaction, so one statement stays well under
+        // the parameter limit.
+        let mut qb = sqlx::QueryBuilder::new(
+            "INSERT INTO eth_transfers (chain_id, block_number, tx_hash, from_address, to_address, amount, block_timestamp) ",
+        );
+        qb.push_values(transfers, |mut b, t| {
+            b.push_bind(self.chain_id as i64)
+                .push_bind(t.block_number as i64)
+                .push_bind(&t.tx_hash)
+                .push_bind(&t.from_address)
+                .push_bind(&t.to_address)
+                .push_bind(&t.amount_str)
+                .push_unseparated("::NUMERIC")
+                .push_bind(t.block_timestamp as i64);
+        });
+        qb.push(" ON CONFLICT (tx_hash, block_timestamp) DO NOTHING");
+        qb.build().execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Create the daily partitions of `table` that `timestamps` fall into, if
+    /// not already known.
+    async fn ensure_partitions(
+        &self,
+        table: &'static str,
+        timestamps: impl Iterator<Item = u64>,
+    ) -> eyre::Result<()> {
+        let missing: HashSet<i64> = {
+            let known = self.partitions.lock().expect("partition cache poisoned");
+            timestamps
+                .map(|ts| ts as i64 / PARTITION_SECS * PARTITION_SECS)
+                .filter(|day| !known.contains(&(table, *day)))
+                .collect()
+        };
+
+        for day_start in missing {
+            sqlx::query("SELECT transfers_ensure_partition($1, $2)")
+                .bind(table)
+                .bind(day_start)
+                .execute(&self.pool)
+                .await?;
+            self.partitions
+                .lock()
+                .expect("partition cache poisoned")
+                .insert((table, day_start));
+        }
         Ok(())
     }
 
-    /// Delete all transfers for a block (reorg handling).
+    /// Delete all ERC20 and ETH transfers for a block (reorg handling),
+    /// subtracting the ERC20 ones from the hourly aggregates. Participants are
+    /// left in place: a reorged-out address can overcount an hour's unique
+    /// senders/receivers by one.
     pub async fn delete_block(&self, block_number: u64) -> eyre::Result<u64> {
-        let result = sqlx::query("DELETE FROM erc20_transfers WHERE block_number = $1")
-            .bind(block_number as i64)
-            .execute(&self.pool)
-            .await?;
-        Ok(result.rows_affected())
+        let deleted: i64 = sqlx::query_scalar(
+            r#"
+            WITH deleted AS (
+                DELETE FROM erc20_transfers WHERE block_number = $1 AND chain_id = $2
+                RETURNING token_address, amount, block_timestamp / 3600 * 3600 AS hour
+            ),
+            adjusted AS (
+                UPDATE token_transfer_hourly h SET
+                    transfer_count = h.transfer_count - d.transfer_count,
+                    volume_raw = h.volume_raw - d.volume_raw
+                FROM (
+                    SELECT token_address, hour, COUNT(*) AS transfer_count, SUM(amount) AS volume_raw
+                    FROM deleted
+                    GROUP BY token_address, hour
+                ) d
+                WHERE h.token_address = d.token_address AND h.hour = d.hour
+            )
+            SELECT COUNT(*) FROM deleted
+            "#,
+        )
+        .bind(block_number as i64)
+        .bind(self.chain_id as i64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let eth =
+            sqlx::query("DELETE FROM eth_transfers WHERE block_number = $1 AND chain_id = $2")
+                .bind(block_number as i64)
+                .bind(self.chain_id as i64)
+                .execute(&self.pool)
+                .await?;
+        Ok(deleted as u64 + eth.rows_affected())
     }
 
-    /// Aggregate token stats, join against token_metadata for USD volume and mcap ratio.
+    /// Aggregate token stats from the hourly tables, join against
+    /// token_metadata for USD volume and mcap ratio. Windows are whole hours,
+    /// so each may include up to an hour more than 24h / 7d.
     ///
     /// Ranking score:
     ///   transfer_count_24h * 0.3
@@ -223,11 +747,51 @@ impl TransferDb {
         let now_ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
-        let ts_24h = now_ts - 86400;
-        let ts_7d = now_ts - 604800;
+        let hour_24h = (now_ts - 86400) / 3600 * 3600;
+        let hour_7d = (now_ts - 604800) / 3600 * 3600;
 
         sqlx::query(
             r#"
+            WITH counts AS (
+                SELECT
+                    token_address,
+                    COALESCE(SUM(transfer_count) FILTER (WHERE hour >= $1), 0) AS count_24h,
+                    SUM(transfer_count) AS count_7d,
+                    COALESCE(SUM(volume_raw) FILTER (WHERE hour >= $1), 0) AS volume_raw_24h,
+                    SUM(volume_raw) AS volume_raw_7d
+                FROM token_transfer_hourly
+                WHERE hour >= $2
+                GROUP BY token_address
+                HAVING SUM(transfer_count) > 0
+            ),
+            uniques AS (
+                SELECT
+                    token_address,
+                    COUNT(DISTINCT address) FILTER (WHERE is_sender AND hour >= $1) AS senders_24h,
+                    COUNT(DISTINCT address) FILTER (WHERE is_sender) AS senders_7d,
+                    COUNT(DISTINCT address) FILTER (WHERE NOT is_sender AND hour >= $1) AS receivers_24h,
+                    COUNT(DISTINCT address) FILTER (WHERE NOT is_sender) AS receivers_7d
+                FROM token_transfer_participants
+                WHERE hour >= $2
+                GROUP BY token_address
+            ),
+            stats AS (
+                SELECT
+                    c.token_address,
+                    c.count_24h,
+                    c.count_7d,
+                    COALESCE(u.senders_24h, 0) AS senders_24h,
+                    COALESCE(u.senders_7d, 0) AS senders_7d,
+                    COALESCE(u.receivers_24h, 0) AS receivers_24h,
+                    COALESCE(u.receivers_7d, 0) AS receivers_7d,
+                    -- volume_usd: raw_amount / 10^decimals * price_usd
+                    c.volume_raw_24h / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0) AS volume_usd_24h,
+                    c.volume_raw_7d / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0) AS volume_usd_7d,
+                    COALESCE(m.market_cap_usd, 0) AS market_cap_usd
+                FROM counts c
+                LEFT JOIN uniques u ON u.token_address = c.token_address
+                LEFT JOIN token_metadata m ON m.token_address = c.token_address
+            )
             INSERT INTO token_transfer_stats (
                 token_address,
                 transfer_count_24h, transfer_count_7d,
@@ -238,45 +802,24 @@ impl TransferDb {
                 ranking_score, updated_at
             )
             SELECT
-                t.token_address,
-                COUNT(*) FILTER (WHERE t.block_timestamp >= $1),
-                COUNT(*),
-                COUNT(DISTINCT t.from_address) FILTER (WHERE t.block_timestamp >= $1),
-                COUNT(DISTINCT t.from_address),
-                COUNT(DISTINCT t.to_address) FILTER (WHERE t.block_timestamp >= $1),
-                COUNT(DISTINCT t.to_address),
-                -- volume_usd: raw_amount / 10^decimals * price_usd
-                COALESCE(SUM(t.amount / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0))
-                    FILTER (WHERE t.block_timestamp >= $1), 0),
-                COALESCE(SUM(t.amount / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0)), 0),
+                token_address,
+                count_24h, count_7d,
+                senders_24h, senders_7d,
+                receivers_24h, receivers_7d,
+                volume_usd_24h, volume_usd_7d,
                 -- volume_mcap_ratio: volume_usd / market_cap (0 if no mcap data)
-                CASE WHEN COALESCE(m.market_cap_usd, 0) > 0
-                    THEN COALESCE(SUM(t.amount / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0))
-                        FILTER (WHERE t.block_timestamp >= $1), 0) / m.market_cap_usd
-                    ELSE 0
-                END,
-                CASE WHEN COALESCE(m.market_cap_usd, 0) > 0
-                    THEN COALESCE(SUM(t.amount / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0)), 0)
-                        / m.market_cap_usd
-                    ELSE 0
-                END,
+                CASE WHEN market_cap_usd > 0 THEN volume_usd_24h / market_cap_usd ELSE 0 END,
+                CASE WHEN market_cap_usd > 0 THEN volume_usd_7d / market_cap_usd ELSE 0 END,
                 -- ranking_score
-                (COUNT(*) FILTER (WHERE t.block_timestamp >= $1) * 0.3 +
-                 COUNT(DISTINCT t.from_address) FILTER (WHERE t.block_timestamp >= $1) * 0.15 +
-                 COUNT(DISTINCT t.to_address) FILTER (WHERE t.block_timestamp >= $1) * 0.15 +
-                 CASE WHEN COALESCE(m.market_cap_usd, 0) > 0
-                     THEN COALESCE(SUM(t.amount / pow(10, COALESCE(m.decimals, 18)) * COALESCE(m.price_usd, 0))
-                         FILTER (WHERE t.block_timestamp >= $1), 0) / m.market_cap_usd * 1000 * 0.2
-                     ELSE 0
-                 END +
-                 COUNT(*) * 0.1 +
-                 COUNT(DISTINCT t.from_address) * 0.05 +
-                 COUNT(DISTINCT t.to_address) * 0.05),
+                (count_24h * 0.3 +
+                 senders_24h * 0.15 +
+                 receivers_24h * 0.15 +
+                 CASE WHEN market_cap_usd > 0 THEN volume_usd_24h / market_cap_usd * 1000 * 0.2 ELSE 0 END +
+                 count_7d * 0.1 +
+                 senders_7d * 0.05 +
+                 receivers_7d * 0.05),
                 $3
-            FROM erc20_transfers t
-            LEFT JOIN token_metadata m ON t.token_address = m.token_address
-            WHERE t.block_timestamp >= $2
-            GROUP BY t.token_address, m.decimals, m.price_usd, m.market_cap_usd
+            FROM stats
             ON CONFLICT (token_address)
             DO UPDATE SET
                 transfer_count_24h = EXCLUDED.transfer_count_24h,
@@ -293,8 +836,8 @@ impl TransferDb {
                 updated_at = EXCLUDED.updated_at
             "#,
         )
-        .bind(ts_24h)
-        .bind(ts_7d)
+        .bind(hour_24h)
+        .bind(hour_7d)
         .bind(now_ts)
         .execute(&self.pool)
         .await?;
@@ -307,17 +850,233 @@ impl TransferDb {
         Ok(())
     }
 
-    /// Delete transfers older than 7 days.
+    /// Transfers matching `query`, newest first.
+    pub async fn query_transfers(
+        &self,
+        query: &TransferQuery,
+    ) -> eyre::Result<Vec<TransferRecord>> {
+        let mut qb = sqlx::QueryBuilder::new(
+            "SELECT block_number, tx_hash, log_index, token_address, from_address, to_address, amount::TEXT AS amount, block_timestamp, kind FROM erc20_transfers WHERE chain_id = ",
+        );
+        qb.push_bind(self.chain_id as i64);
+        if let Some(address) = &query.address {
+            qb.push(" AND (from_address = ")
+                .push_bind(address)
+                .push(" OR to_address = ")
+                .push_bind(address)
+                .push(")");
+        }
+        if let Some(token) = &query.token {
+            qb.push(" AND token_address = ").push_bind(token);
+        }
+        if let Some(from_block) = query.from_block {
+            qb.push(" AND block_number >= ")
+                .push_bind(from_block as i64);
+        }
+        if let Some(to_block) = query.to_block {
+            qb.push(" AND block_number <= ").push_bind(to_block as i64);
+        }
+        qb.push(" ORDER BY block_number DESC, log_index DESC LIMIT ")
+            .push_bind(query.limit);
+
+        let rows = qb
+            .build_query_as::<TransferRecord>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Top tokens from the `top_transferred_tokens` view, best first.
+    pub async fn top_tokens(&self, limit: i64) -> eyre::Result<Vec<TopToken>> {
+        let rows = sqlx::query_as::<_, TopToken>(
+            r#"
+            SELECT token_address, ranking_score,
+                   transfer_count_24h, transfer_count_7d,
+                   unique_senders_24h, unique_receivers_24h,
+                   volume_usd_24h, volume_usd_7d, updated_at
+            FROM top_transferred_tokens
+            ORDER BY ranking_score DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Tokens with transfers in the aggregation window but no `token_metadata`
+    /// row, then those whose decimals are still unresolved. Reads the hourly
+    /// table rather than scanning `erc20_transfers`.
+    pub async fn tokens_missing_metadata(&self, limit: i64) -> eyre::Result<Vec<String>> {
+        let tokens = sqlx::query_scalar(
+            r#"
+            SELECT h.token_address
+            FROM token_transfer_hourly h
+            LEFT JOIN token_metadata m ON m.token_address = h.token_address
+            WHERE m.token_address IS NULL OR m.decimals IS NULL
+            GROUP BY h.token_address
+            ORDER BY bool_and(m.token_address IS NOT NULL)
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tokens)
+    }
+
+    /// Insert on-chain metadata. Existing values (e.g. from the price feed)
+    /// win; unresolved decimals stay NULL and are filled once resolved.
+    pub async fn upsert_token_metadata(&self, rows: &[TokenMetadataRow]) -> eyre::Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let now_ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let mut qb = sqlx::QueryBuilder::new(
+            "INSERT INTO token_metadata (token_address, symbol, decimals, updated_at) ",
+        );
+        qb.push_values(rows, |mut b, r| {
+            b.push_bind(&r.token_address)
+                .push_bind(&r.symbol)
+                .push_bind(r.decimals.map(i32::from))
+                .push_bind(now_ts);
+        });
+        qb.push(
+            " ON CONFLICT (token_address) DO UPDATE SET \
+             symbol = COALESCE(token_metadata.symbol, EXCLUDED.symbol), \
+             decimals = COALESCE(token_metadata.decimals, EXCLUDED.decimals)",
+        );
+        qb.build().execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Drop daily partitions that lie entirely before the retention cutoff.
+    /// Returns the number of partitions dropped.
     pub async fn cleanup_old_transfers(&self) -> eyre::Result<u64> {
-        let cutoff = std::time::SystemTime::now()
+        let now_ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64
-            - 604800;
+            .as_secs() as i64;
+        let cutoff = now_ts - self.retention_secs;
 
-        let result = sqlx::query("DELETE FROM erc20_transfers WHERE block_timestamp < $1")
+        // Partition names sort by day, so everything named before the cutoff's
+        // day ends at or before its start.
+        let mut dropped = 0u64;
+        for table in PARTITIONED_TABLES {
+            let expired: Vec<String> = sqlx::query_scalar(
+                r#"
+                SELECT c.relname::TEXT
+                FROM pg_inherits i
+                JOIN pg_class c ON c.oid = i.inhrelid
+                WHERE i.inhparent = $1::TEXT::regclass
+                  AND c.relname < $1 || '_' || to_char(to_timestamp($2) AT TIME ZONE 'UTC', 'YYYYMMDD')
+                ORDER BY c.relname
+                "#,
+            )
+            .bind(table)
             .bind(cutoff)
+            .fetch_all(&self.pool)
+            .await?;
+
+            for name in &expired {
+                sqlx::query(&format!("DROP TABLE IF EXISTS \"{name}\""))
+                    .execute(&self.pool)
+                    .await?;
+            }
+            dropped += expired.len() as u64;
+        }
+        {
+            let cutoff_day = cutoff / PARTITION_SECS * PARTITION_SECS;
+            self.partitions
+                .lock()
+                .expect("partition cache poisoned")
+                .retain(|(_, day)| *day >= cutoff_day);
+        }
+
+        // Aggregate hours outside both the retention and aggregation windows.
+        let cutoff_hour = (now_ts - self.retention_secs.max(AGGREGATION_WINDOW_SECS)) / 3600 * 3600;
+        sqlx::query("DELETE FROM token_transfer_hourly WHERE hour < $1")
+            .bind(cutoff_hour)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM token_transfer_participants WHERE hour < $1")
+            .bind(cutoff_hour)
             .execute(&self.pool)
             .await?;
-        Ok(result.rows_affected())
+
+        Ok(dropped)
+    }
+}
+
+/// Encode rows in Postgres binary COPY format, column order matching
+/// `erc20_transfers_staging`.
+fn encode_copy_binary(transfers: &[TransferRow]) -> Vec<u8> {
+    fn push_field(buf: &mut Vec<u8>, bytes: &[u8]) {
+        buf.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+        buf.extend_from_slice(bytes);
+    }
+
+    let mut buf = Vec::with_capacity(19 + transfers.len() * 256 + 2);
+    // Header: signature, flags, header extension length
+    buf.extend_from_slice(b"PGCOPY\n\xff\r\n\0");
+    buf.extend_from_slice(&0i32.to_be_bytes());
+    buf.extend_from_slice(&0i32.to_be_bytes());
+
+    for t in transfers {
+        buf.extend_from_slice(&9i16.to_be_bytes());
+        push_field(&mut buf, &(t.block_number as i64).to_be_bytes());
+        push_field(&mut buf, t.tx_hash.as_bytes());
+        push_field(&mut buf, &(t.log_index as i32).to_be_bytes());
+        push_field(&mut buf, t.token_address.as_bytes());
+        push_field(&mut buf, t.from_address.as_bytes());
+        push_field(&mut buf, t.to_address.as_bytes());
+        push_field(&mut buf, t.amount_str.as_bytes());
+        push_field(&mut buf, &(t.block_timestamp as i64).to_be_bytes());
+        push_field(&mut buf, t.kind.as_str().as_bytes());
+    }
+
+    // Trailer
+    buf.extend_from_slice(&(-1i16).to_be_bytes());
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_from_zero_address() {
+        let a = Address::repeat_byte(1);
+        assert_eq!(TransferKind::of(Address::ZERO, a), TransferKind::Mint);
+        assert_eq!(TransferKind::of(a, Address::ZERO), TransferKind::Burn);
+        assert_eq!(TransferKind::of(a, a), TransferKind::Transfer);
+    }
+
+    #[test]
+    fn copy_binary_layout() {
+        let row = TransferRow {
+            block_number: 1,
+            tx_hash: "0xab".to_string(),
+            log_index: 2,
+            token_address: "0x01".to_string(),
+            from_address: "0x02".to_string(),
+            to_address: "0x03".to_string(),
+            amount_str: "1000".to_string(),
+            block_timestamp: 3,
+            kind: TransferKind::Transfer,
+        };
+        let buf = encode_copy_binary(&[row]);
+
+        assert_eq!(&buf[..11], b"PGCOPY\n\xff\r\n\0");
+        // Tuple: field count, then block_number as length-prefixed BIGINT
+        assert_eq!(&buf[19..21], &9i16.to_be_bytes());
+        assert_eq!(&buf[21..25], &8i32.to_be_bytes());
+        assert_eq!(&buf[25..33], &1i64.to_be_bytes());
+        // 9 length prefixes + 8 + 4 + 4 + 4 + 4 + 4 + 4 + 8 + 8 bytes of data
+        assert_eq!(buf.len(), 19 + 2 + 9 * 4 + 48 + 2);
+        assert_eq!(&buf[buf.len() - 2..], &(-1i16).to_be_bytes());
     }
 }
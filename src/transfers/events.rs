@@ -32,3 +32,25 @@ pub fn decode_transfer(log: &Log) -> Option<DecodedTransfer> {
         value: decoded.data.value,
     })
 }
+
+/// Top-level ETH value transfer of a transaction, with the zero address as
+/// the token. None for zero value, contract creations (`to` is None), and
+/// reverted transactions (which move no value). Internal transfers from calls
+/// are not covered: they would need traces.
+pub fn decode_eth_transfer(
+    sender: Address,
+    to: Option<Address>,
+    value: U256,
+    success: bool,
+) -> Option<DecodedTransfer> {
+    let to = to?;
+    if value.is_zero() || !success {
+        return None;
+    }
+    Some(DecodedTransfer {
+        token: Address::ZERO,
+        from: sender,
+        to,
+        value,
+    })
+}
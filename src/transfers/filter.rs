@@ -0,0 +1,129 @@
+use super::events::DecodedTransfer;
+use alloy_primitives::{Address, U256};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Which decoded transfers get stored. Applied before building `TransferRow`,
+/// so deployments that only care about a subset never store the firehose.
+/// An empty filter keeps everything.
+#[derive(Debug, Default)]
+pub struct TransferFilter {
+    /// Only these tokens, if set.
+    tokens: Option<HashSet<Address>>,
+    /// Only transfers from or to one of these addresses, if set.
+    addresses: Option<HashSet<Address>>,
+    /// Minimum raw amount.
+    min_amount: U256,
+}
+
+impl TransferFilter {
+    /// Read `TRANSFERS_TOKEN_ALLOWLIST` and `TRANSFERS_ADDRESS_ALLOWLIST`
+    /// (comma-separated addresses) and `TRANSFERS_MIN_AMOUNT` (raw units).
+    /// ETH value transfers use the zero address as their token.
+    pub fn from_env() -> Self {
+        let tokens = std::env::var("TRANSFERS_TOKEN_ALLOWLIST")
+            .ok()
+            .map(|raw| parse_addresses("TRANSFERS_TOKEN_ALLOWLIST", &raw));
+        let addresses = std::env::var("TRANSFERS_ADDRESS_ALLOWLIST")
+            .ok()
+            .map(|raw| parse_addresses("TRANSFERS_ADDRESS_ALLOWLIST", &raw));
+        let min_amount = match std::env::var("TRANSFERS_MIN_AMOUNT") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid TRANSFERS_MIN_AMOUNT {:?}", raw);
+                U256::ZERO
+            }),
+            Err(_) => U256::ZERO,
+        };
+
+        let filter = Self {
+            tokens,
+            addresses,
+            min_amount,
+        };
+        if !filter.is_empty() {
+            info!(
+                "Transfer filter: {} tokens, {} addresses, min amount {}",
+                filter
+                    .tokens
+                    .as_ref()
+                    .map_or("all".to_string(), |t| t.len().to_string()),
+                filter
+                    .addresses
+                    .as_ref()
+                    .map_or("all".to_string(), |a| a.len().to_string()),
+                filter.min_amount
+            );
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_none() && self.addresses.is_none() && self.min_amount.is_zero()
+    }
+
+    pub fn matches(&self, transfer: &DecodedTransfer) -> bool {
+        if transfer.value < self.min_amount {
+            return false;
+        }
+        if let Some(tokens) = &self.tokens {
+            if !tokens.contains(&transfer.token) {
+                return false;
+            }
+        }
+        if let Some(addresses) = &self.addresses {
+            if !addresses.contains(&transfer.from) && !addresses.contains(&transfer.to) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn parse_addresses(var: &str, raw: &str) -> HashSet<Address> {
+    let mut addresses = HashSet::new();
+    for entry in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match entry.parse::<Address>() {
+            Ok(addr) => {
+                addresses.insert(addr);
+            }
+            Err(_) => warn!("Ignoring invalid address {:?} in {}", entry, var),
+        }
+    }
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(token: u8, from: u8, to: u8, value: u64) -> DecodedTransfer {
+        DecodedTransfer {
+            token: Address::repeat_byte(token),
+            from: Address::repeat_byte(from),
+            to: Address::repeat_byte(to),
+            value: U256::from(value),
+        }
+    }
+
+    #[test]
+    fn filter_applies_every_configured_condition() {
+        let filter = TransferFilter {
+            tokens: Some(HashSet::from([Address::repeat_byte(1)])),
+            addresses: Some(HashSet::from([Address::repeat_byte(9)])),
+            min_amount: U256::from(100u64),
+        };
+
+        assert!(filter.matches(&transfer(1, 9, 2, 100)));
+        assert!(filter.matches(&transfer(1, 2, 9, 500)), "receiver matches");
+        assert!(
+            !filter.matches(&transfer(2, 9, 2, 100)),
+            "token not allowed"
+        );
+        assert!(
+            !filter.matches(&transfer(1, 2, 3, 100)),
+            "no allowed address"
+        );
+        assert!(!filter.matches(&transfer(1, 9, 2, 99)), "below minimum");
+        assert!(TransferFilter::default().matches(&transfer(7, 7, 7, 0)));
+    }
+}
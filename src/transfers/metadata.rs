@@ -0,0 +1,139 @@
+use super::db::{TokenMetadataRow, TransferDb};
+use alloy_primitives::Address;
+use alloy_sol_types::{sol, SolCall};
+use reth::providers::{BlockNumReader, HeaderProvider, StateProviderFactory};
+use reth_evm::{ConfigureEvm, Evm};
+use reth_node_api::FullNodeComponents;
+use reth_revm::database::StateProviderDatabase;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+
+sol! {
+    function symbol() external view returns (string);
+    function decimals() external view returns (uint8);
+}
+
+/// Tokens resolved per pass.
+const BATCH_SIZE: i64 = 200;
+
+/// Spawn the metadata worker — every minute, fills `token_metadata` for tokens
+/// seen in transfers but missing there, with `symbol()` / `decimals()` called
+/// against Reth's latest state. Price and market cap stay with the price feed.
+pub fn spawn_metadata_worker<Node: FullNodeComponents>(
+    db: Arc<TransferDb>,
+    provider: Node::Provider,
+    evm_config: Node::Evm,
+) {
+    tokio::spawn(async move {
+        let mut tick = interval(Duration::from_secs(60));
+        loop {
+            tick.tick().await;
+            let tokens = match db.tokens_missing_metadata(BATCH_SIZE).await {
+                Ok(tokens) if tokens.is_empty() => continue,
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    warn!("Metadata lookup failed: {}", e);
+                    continue;
+                }
+            };
+
+            let provider = provider.clone();
+            let evm_config = evm_config.clone();
+            let rows = match tokio::task::spawn_blocking(move || {
+                call_metadata::<Node>(&provider, &evm_config, &tokens)
+            })
+            .await
+            {
+                Ok(Ok(rows)) => rows,
+                Ok(Err(e)) => {
+                    warn!("Metadata calls failed: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Metadata task panicked: {}", e);
+                    continue;
+                }
+            };
+
+            match db.upsert_token_metadata(&rows).await {
+                Ok(()) => info!("Enriched metadata for {} tokens", rows.len()),
+                Err(e) => warn!("Metadata upsert failed: {}", e),
+            }
+        }
+    });
+}
+
+/// `symbol()` and `decimals()` of each token against the latest block's
+/// state. A reverting or undecodable call leaves that field `None`; the row is
+/// still returned, so a token without decimals is only retried after tokens
+/// with no metadata at all.
+fn call_metadata<Node: FullNodeComponents>(
+    provider: &Node::Provider,
+    evm_config: &Node::Evm,
+    tokens: &[String],
+) -> eyre::Result<Vec<TokenMetadataRow>> {
+    let number = provider.best_block_number()?;
+    let header = provider
+        .header_by_number(number)?
+        .ok_or_else(|| eyre::eyre!("missing header for block {number}"))?;
+    let db = StateProviderDatabase::new(provider.latest()?);
+    let mut evm = evm_config.evm_with_env(db, evm_config.evm_env(&header)?);
+
+    let mut call = |token: Address, data: Vec<u8>| -> Option<Vec<u8>> {
+        let result = evm
+            .transact_system_call(Address::ZERO, token, data.into())
+            .ok()?
+            .result;
+        result
+            .is_success()
+            .then(|| result.output().cloned().unwrap_or_default().to_vec())
+    };
+
+    let mut rows = Vec::with_capacity(tokens.len());
+    for token_address in tokens {
+        let Ok(token) = token_address.parse::<Address>() else {
+            continue;
+        };
+        let symbol = call(token, symbolCall {}.abi_encode()).and_then(|out| decode_symbol(&out));
+        let decimals = call(token, decimalsCall {}.abi_encode())
+            .and_then(|out| decimalsCall::abi_decode_returns(&out).ok());
+        rows.push(TokenMetadataRow {
+            token_address: token_address.clone(),
+            symbol,
+            decimals,
+        });
+    }
+    Ok(rows)
+}
+
+/// Decode a `symbol()` return: ABI string, or `bytes32` for older tokens
+/// (MKR, SAI). NULs are stripped since Postgres TEXT rejects them.
+fn decode_symbol(output: &[u8]) -> Option<String> {
+    let symbol = match symbolCall::abi_decode_returns(output) {
+        Ok(symbol) => symbol,
+        Err(_) if output.len() == 32 => String::from_utf8_lossy(output).into_owned(),
+        Err(_) => return None,
+    };
+    let symbol: String = symbol.chars().filter(|c| *c != '\0').collect();
+    (!symbol.is_empty()).then_some(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::SolValue;
+
+    #[test]
+    fn decodes_string_and_bytes32_symbols() {
+        let string = ("USDC".to_string(),).abi_encode_params();
+        assert_eq!(decode_symbol(&string), Some("USDC".to_string()));
+
+        let mut bytes32 = [0u8; 32];
+        bytes32[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_symbol(&bytes32), Some("MKR".to_string()));
+
+        assert_eq!(decode_symbol(&[0u8; 32]), None);
+        assert_eq!(decode_symbol(&[1, 2, 3]), None);
+    }
+}
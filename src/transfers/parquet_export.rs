@@ -0,0 +1,211 @@
+use super::db::TransferRow;
+use arrow::array::{ArrayRef, StringArray, UInt32Array, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Blocks a range must be behind the tip before its file is written, so
+/// reorgs only ever touch buffered rows.
+const REORG_DEPTH: u64 = 64;
+
+/// Buffers transfers per block range and writes each range to
+/// `<dir>/transfers_<first>_<last>.parquet` once it is `REORG_DEPTH` blocks
+/// behind the tip. Ranges are aligned to `blocks_per_file` (300 ≈ one hour on
+/// mainnet). Buffered blocks are not durable: the handler holds FinishedHeight
+/// below `first_buffered_block`, so after a restart reth replays them.
+pub struct ParquetExporter {
+    dir: PathBuf,
+    /// Written as the `chain_id` column of every file.
+    chain_id: u64,
+    blocks_per_file: u64,
+    /// Range start → (last block seen, rows).
+    pending: BTreeMap<u64, (u64, Vec<TransferRow>)>,
+    /// First block seen per range.
+    first_block: BTreeMap<u64, u64>,
+}
+
+impl ParquetExporter {
+    pub fn new(dir: PathBuf, blocks_per_file: u64, chain_id: u64) -> eyre::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            chain_id,
+            blocks_per_file: blocks_per_file.max(1),
+            pending: BTreeMap::new(),
+            first_block: BTreeMap::new(),
+        })
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn range_start(&self, block_number: u64) -> u64 {
+        block_number / self.blocks_per_file * self.blocks_per_file
+    }
+
+    /// Buffer a block's transfers (possibly none, so the range is still
+    /// written).
+    pub fn push_block(&mut self, block_number: u64, rows: &[TransferRow]) {
+        let start = self.range_start(block_number);
+        self.first_block.entry(start).or_insert(block_number);
+        let (last, buffered) = self.pending.entry(start).or_default();
+        *last = block_number;
+        buffered.extend(rows.iter().cloned());
+    }
+
+    /// Drop buffered rows at or above `block_number` (reorg handling).
+    pub fn revert_from(&mut self, block_number: u64) {
+        for (last, rows) in self.pending.values_mut() {
+            rows.retain(|r| r.block_number < block_number);
+            *last = (*last).min(block_number.saturating_sub(1));
+        }
+        self.first_block.retain(|_, first| *first < block_number);
+        let first_block = &self.first_block;
+        self.pending
+            .retain(|start, _| first_block.contains_key(start));
+    }
+
+    /// The oldest block whose rows are buffered and not yet taken.
+    pub fn first_buffered_block(&self) -> Option<u64> {
+        let (&start, _) = self.pending.first_key_value()?;
+        Some(self.first_block.get(&start).copied().unwrap_or(start))
+    }
+
+    /// Take the ranges that ended at least `REORG_DEPTH` blocks before `tip`,
+    /// with the path each should be written to.
+    pub fn take_ready(&mut self, tip: u64) -> Vec<(PathBuf, Vec<TransferRow>)> {
+        let Some(cutoff) = tip.checked_sub(REORG_DEPTH) else {
+            return Vec::new();
+        };
+        let mut ready = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() + self.blocks_per_file > cutoff {
+                break;
+            }
+            let start = *entry.key();
+            let (last, rows) = entry.remove();
+            let first = self.first_block.remove(&start).unwrap_or(start);
+            let path = self.dir.join(format!("transfers_{first}_{last}.parquet"));
+            ready.push((path, rows));
+        }
+        ready
+    }
+}
+
+/// Write `rows` to `path` (zstd), tagged with `chain_id`. Atomic: written to
+/// `.tmp`, then renamed.
+pub fn write_parquet(path: &Path, chain_id: u64, rows: &[TransferRow]) -> eyre::Result<()> {
+    // amount stays a decimal string: U256 does not fit any Parquet integer.
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("chain_id", DataType::UInt64, false),
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("tx_hash", DataType::Utf8, false),
+        Field::new("log_index", DataType::UInt32, false),
+        Field::new("token_address", DataType::Utf8, false),
+        Field::new("from_address", DataType::Utf8, false),
+        Field::new("to_address", DataType::Utf8, false),
+        Field::new("amount", DataType::Utf8, false),
+        Field::new("block_timestamp", DataType::UInt64, false),
+        Field::new("kind", DataType::Utf8, false),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(std::iter::repeat_n(
+            chain_id,
+            rows.len(),
+        ))),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.block_number),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.tx_hash),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|r| r.log_index),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.token_address),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.from_address),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.to_address),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.amount_str),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.block_timestamp),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.kind.as_str()),
+        )),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let tmp_path = path.with_extension("tmp");
+    let file = std::fs::File::create(&tmp_path)?;
+    let mut writer = ArrowWriter::try_new(file, schema, Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfers::db::TransferKind;
+
+    fn row(block_number: u64) -> TransferRow {
+        TransferRow {
+            block_number,
+            tx_hash: format!("0x{block_number:064x}"),
+            log_index: 0,
+            token_address: "0x01".to_string(),
+            from_address: "0x02".to_string(),
+            to_address: "0x03".to_string(),
+            amount_str: "1".to_string(),
+            block_timestamp: block_number * 12,
+            kind: TransferKind::Transfer,
+        }
+    }
+
+    #[test]
+    fn ranges_are_written_once_past_reorg_depth() {
+        let dir = std::env::temp_dir().join(format!("parquet_export_test_{}", std::process::id()));
+        let mut exporter = ParquetExporter::new(dir.clone(), 100, 1).unwrap();
+        for block in 150..=260 {
+            exporter.push_block(block, &[row(block)]);
+        }
+        exporter.revert_from(255);
+        exporter.push_block(255, &[row(255)]);
+
+        assert!(
+            exporter.take_ready(263).is_empty(),
+            "range 100..199 ends too recently"
+        );
+        let ready = exporter.take_ready(264);
+        assert_eq!(ready.len(), 1);
+        let (path, rows) = &ready[0];
+        assert_eq!(path, &dir.join("transfers_150_199.parquet"));
+        assert_eq!(rows.len(), 50);
+
+        let (_, pending) = &exporter.pending[&200];
+        assert_eq!(pending.len(), 56, "200..=255 after the revert");
+
+        write_parquet(path, exporter.chain_id(), rows).unwrap();
+        assert!(std::fs::metadata(path).unwrap().len() > 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use super::db::{EthTransferRow, TransferDb, TransferRow};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Everything stored for one block.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BlockBatch {
+    pub transfers: Vec<TransferRow>,
+    pub eth_transfers: Vec<EthTransferRow>,
+}
+
+impl BlockBatch {
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty() && self.eth_transfers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.transfers.len() + self.eth_transfers.len()
+    }
+}
+
+/// Storage backend for decoded transfers. Postgres (`TransferDb`) backs the
+/// ranking aggregation; ClickHouse (`ClickHouseStore`) serves analytics scans.
+pub trait TransferStore: Send + Sync + 'static {
+    /// Insert a block's transfers. Must be idempotent: blocks are retried and
+    /// replayed.
+    fn insert_transfers(
+        &self,
+        transfers: &[TransferRow],
+    ) -> impl Future<Output = eyre::Result<()>> + Send;
+
+    /// Insert a block's ETH value transfers. Idempotent, like `insert_transfers`.
+    fn insert_eth_transfers(
+        &self,
+        transfers: &[EthTransferRow],
+    ) -> impl Future<Output = eyre::Result<()>> + Send;
+
+    /// Delete all transfers for a block (reorg handling). Returns the number
+    /// of rows removed.
+    fn delete_block(&self, block_number: u64) -> impl Future<Output = eyre::Result<u64>> + Send;
+}
+
+impl TransferStore for TransferDb {
+    async fn insert_transfers(&self, transfers: &[TransferRow]) -> eyre::Result<()> {
+        TransferDb::insert_transfers(self, transfers).await
+    }
+
+    async fn insert_eth_transfers(&self, transfers: &[EthTransferRow]) -> eyre::Result<()> {
+        TransferDb::insert_eth_transfers(self, transfers).await
+    }
+
+    async fn delete_block(&self, block_number: u64) -> eyre::Result<u64> {
+        TransferDb::delete_block(self, block_number).await
+    }
+}
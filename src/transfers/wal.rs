@@ -0,0 +1,124 @@
+use super::store::BlockBatch;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Disk-backed write-ahead log of blocks whose insert failed.
+///
+/// Each block is one `block_<number>.json` file, fsynced before the ExEx
+/// reports the block finished, and replayed in block order once the store is
+/// reachable again. Inserts are idempotent, so a replay racing a partially
+/// applied insert is harmless.
+pub struct TransferWal {
+    dir: PathBuf,
+}
+
+impl TransferWal {
+    pub fn open(dir: PathBuf) -> eyre::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, block_number: u64) -> PathBuf {
+        // Zero-padded so lexical order is block order.
+        self.dir.join(format!("block_{block_number:012}.json"))
+    }
+
+    /// Durably record a block's batch: write `.tmp`, fsync, rename, fsync dir.
+    pub fn append(&self, block_number: u64, batch: &BlockBatch) -> eyre::Result<()> {
+        let path = self.path(block_number);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(batch)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &path)?;
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+
+    /// Logged blocks in block order.
+    pub fn pending(&self) -> eyre::Result<Vec<u64>> {
+        let mut blocks: Vec<u64> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| parse_block_number(&entry.path()))
+            .collect();
+        blocks.sort_unstable();
+        Ok(blocks)
+    }
+
+    pub fn read(&self, block_number: u64) -> eyre::Result<BlockBatch> {
+        let content = std::fs::read(self.path(block_number))?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Drop a block once stored (or reverted).
+    pub fn remove(&self, block_number: u64) -> eyre::Result<()> {
+        match std::fs::remove_file(self.path(block_number)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Drop logged blocks at or above `block_number` (reorg handling).
+    pub fn remove_from(&self, block_number: u64) -> eyre::Result<()> {
+        for block in self.pending()? {
+            if block >= block_number {
+                self.remove(block)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_block_number(path: &Path) -> Option<u64> {
+    if path.extension()? != "json" {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("block_")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfers::db::{TransferKind, TransferRow};
+
+    fn batch(block_number: u64) -> BlockBatch {
+        let row = TransferRow {
+            block_number,
+            tx_hash: "0xab".to_string(),
+            log_index: 0,
+            token_address: "0x01".to_string(),
+            from_address: "0x02".to_string(),
+            to_address: "0x03".to_string(),
+            amount_str: "1000".to_string(),
+            block_timestamp: 12,
+            kind: TransferKind::Transfer,
+        };
+        BlockBatch {
+            transfers: vec![row],
+            eth_transfers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn wal_roundtrip_in_block_order() {
+        let dir = std::env::temp_dir().join(format!("transfer_wal_test_{}", std::process::id()));
+        let wal = TransferWal::open(dir.clone()).unwrap();
+        wal.append(1_000, &batch(1_000)).unwrap();
+        wal.append(99, &batch(99)).unwrap();
+        wal.append(1_001, &batch(1_001)).unwrap();
+
+        assert_eq!(wal.pending().unwrap(), vec![99, 1_000, 1_001]);
+        assert_eq!(wal.read(1_000).unwrap().transfers[0].block_number, 1_000);
+
+        wal.remove_from(1_000).unwrap();
+        wal.remove(99).unwrap();
+        assert!(wal.pending().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
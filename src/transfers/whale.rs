@@ -0,0 +1,244 @@
+use super::db::TransferRow;
+use alloy_primitives::{Address, U256};
+use arc_swap::ArcSwap;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Large-transfer alert, published on `alerts.transfers.<chain>`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LargeTransferAlert {
+    pub chain: String,
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub tx_hash: String,
+    pub log_index: u32,
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    pub raw_amount: String,
+    /// Set when the token has a price in the feed.
+    pub usd_notional: Option<f64>,
+    pub ts: u64,
+}
+
+/// Flags transfers above a per-token raw threshold
+/// (`TRANSFERS_WHALE_THRESHOLDS`, `token:raw_amount` comma-separated) or a
+/// USD threshold (`TRANSFERS_WHALE_USD`) for tokens priced in the
+/// `token_metadata` feed at `PRICE_FEED_DATABASE_URL`.
+pub struct WhaleWatch {
+    chain: String,
+    chain_id: u64,
+    raw_thresholds: HashMap<Address, U256>,
+    usd_threshold: Option<f64>,
+    /// token → (decimals, price_usd)
+    prices: Arc<ArcSwap<HashMap<Address, (u8, f64)>>>,
+}
+
+impl WhaleWatch {
+    /// `None` unless a raw or USD threshold is configured. Starts the price
+    /// refresh task when a USD threshold and a price feed are set.
+    pub fn from_env(chain: String, chain_id: u64) -> Option<Self> {
+        let raw_thresholds = match std::env::var("TRANSFERS_WHALE_THRESHOLDS") {
+            Ok(raw) => {
+                let (thresholds, invalid) = parse_raw_thresholds(&raw);
+                for entry in invalid {
+                    warn!(
+                        "Ignoring invalid TRANSFERS_WHALE_THRESHOLDS entry {:?}",
+                        entry
+                    );
+                }
+                thresholds
+            }
+            Err(_) => HashMap::new(),
+        };
+        let usd_threshold = std::env::var("TRANSFERS_WHALE_USD")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|usd| *usd > 0.0);
+        if raw_thresholds.is_empty() && usd_threshold.is_none() {
+            return None;
+        }
+
+        let watch = Self {
+            chain,
+            chain_id,
+            raw_thresholds,
+            usd_threshold,
+            prices: Arc::default(),
+        };
+        if watch.usd_threshold.is_some() {
+            match std::env::var("PRICE_FEED_DATABASE_URL") {
+                Ok(database_url) => {
+                    tokio::spawn(refresh_prices(database_url, watch.prices.clone()));
+                }
+                Err(_) => warn!("TRANSFERS_WHALE_USD set without PRICE_FEED_DATABASE_URL"),
+            }
+        }
+        info!(
+            "Whale watch: {} token thresholds, USD threshold {:?}",
+            watch.raw_thresholds.len(),
+            watch.usd_threshold
+        );
+        Some(watch)
+    }
+
+    pub fn subject(&self) -> String {
+        crate::subjects::transfer_alerts(&self.chain)
+    }
+
+    /// Alert for `row` if it crosses its token's raw threshold or the USD
+    /// threshold.
+    pub fn check(&self, row: &TransferRow) -> Option<LargeTransferAlert> {
+        let token = row.token_address.parse::<Address>().ok()?;
+        let amount = row.amount_str.parse::<U256>().ok()?;
+        let usd_notional =
+            self.prices.load().get(&token).map(|(decimals, price)| {
+                f64::from(amount) / 10f64.powi(i32::from(*decimals)) * price
+            });
+
+        let over_raw = self
+            .raw_thresholds
+            .get(&token)
+            .is_some_and(|threshold| amount >= *threshold);
+        let over_usd = matches!(
+            (usd_notional, self.usd_threshold),
+            (Some(usd), Some(threshold)) if usd >= threshold
+        );
+        if !over_raw && !over_usd {
+            return None;
+        }
+
+        Some(LargeTransferAlert {
+            chain: self.chain.clone(),
+            chain_id: self.chain_id,
+            block_number: row.block_number,
+            tx_hash: row.tx_hash.clone(),
+            log_index: row.log_index,
+            token: row.token_address.clone(),
+            from: row.from_address.clone(),
+            to: row.to_address.clone(),
+            raw_amount: row.amount_str.clone(),
+            usd_notional,
+            ts: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Parse `TRANSFERS_WHALE_THRESHOLDS`. Invalid entries are returned
+/// separately so the caller can log them.
+fn parse_raw_thresholds(raw: &str) -> (HashMap<Address, U256>, Vec<String>) {
+    let mut thresholds = HashMap::new();
+    let mut invalid = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let parsed = entry
+            .split_once(':')
+            .and_then(|(token, amount)| Some((token.parse().ok()?, amount.parse().ok()?)));
+        match parsed {
+            Some((token, amount)) => {
+                thresholds.insert(token, amount);
+            }
+            None => invalid.push(entry.to_string()),
+        }
+    }
+    (thresholds, invalid)
+}
+
+/// Reload decimals and `price_usd` from `token_metadata` every minute.
+/// Failures keep the previous map.
+async fn refresh_prices(database_url: String, prices: Arc<ArcSwap<HashMap<Address, (u8, f64)>>>) {
+    let pool = match PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_secs(10))
+        .connect(&database_url)
+        .await
+    {
+        Ok(pool) => pool,
+        Err(e) => {
+            warn!(
+                "Price feed DB unavailable, whale watch uses raw thresholds only: {}",
+                e
+            );
+            return;
+        }
+    };
+    let mut ticker = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        ticker.tick().await;
+        let rows = sqlx::query(
+            "SELECT token_address, decimals, price_usd FROM token_metadata WHERE price_usd > 0",
+        )
+        .fetch_all(&pool)
+        .await;
+        match rows {
+            Ok(rows) => {
+                let map: HashMap<Address, (u8, f64)> = rows
+                    .iter()
+                    .filter_map(|row| {
+                        let address: String = row.try_get("token_address").ok()?;
+                        let decimals: i32 = row.try_get("decimals").ok()?;
+                        let price: f64 = row.try_get("price_usd").ok()?;
+                        Some((address.parse().ok()?, (u8::try_from(decimals).ok()?, price)))
+                    })
+                    .collect();
+                prices.store(Arc::new(map));
+            }
+            Err(e) => warn!("Whale watch price refresh failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfers::db::TransferKind;
+
+    fn row(token: Address, amount: u64) -> TransferRow {
+        TransferRow {
+            block_number: 1,
+            tx_hash: "0xab".to_string(),
+            log_index: 0,
+            token_address: format!("{token:#x}"),
+            from_address: "0x02".to_string(),
+            to_address: "0x03".to_string(),
+            amount_str: amount.to_string(),
+            block_timestamp: 12,
+            kind: TransferKind::Transfer,
+        }
+    }
+
+    #[test]
+    fn flags_raw_and_usd_thresholds() {
+        let usdc = Address::repeat_byte(1);
+        let weth = Address::repeat_byte(2);
+        let (raw_thresholds, invalid) =
+            parse_raw_thresholds(&format!("{usdc:#x}:1000000000, nope"));
+        assert_eq!(invalid, vec!["nope".to_string()]);
+
+        let watch = WhaleWatch {
+            chain: "ethereum".to_string(),
+            raw_thresholds,
+            usd_threshold: Some(10_000.0),
+            prices: Arc::new(ArcSwap::from_pointee(HashMap::from([(
+                weth,
+                (18, 2_000.0),
+            )]))),
+        };
+
+        assert!(watch.check(&row(usdc, 999_999_999)).is_none());
+        assert!(watch.check(&row(usdc, 1_000_000_000)).is_some());
+
+        assert!(
+            watch.check(&row(weth, 6 * 10u64.pow(17))).is_none(),
+            "$1,200"
+        );
+        let alert = watch.check(&row(weth, 10u64.pow(18) * 18)).unwrap();
+        assert_eq!(alert.usd_notional, Some(36_000.0));
+    }
+}
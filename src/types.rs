@@ -2,12 +2,15 @@
 //
 // This module defines all message types sent over Unix socket from ExEx to Orderbook Engine
 
-use alloy_primitives::{Address, I256, U256};
+use alloy_primitives::{keccak256, Address, B256, I256, U256};
 use serde::{Deserialize, Serialize};
 
 /// Main envelope for all pool update messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolUpdateMessage {
+    /// Chain the update belongs to (EIP-155 chain id)
+    pub chain_id: u64,
+
     /// Pool identifier (contract address for V2/V3, poolId for V4)
     pub pool_id: PoolIdentifier,
 
@@ -33,6 +36,49 @@ pub struct PoolUpdateMessage {
     pub update: PoolUpdate,
 }
 
+impl PoolUpdateMessage {
+    /// Chain position of the log this update was decoded from, see
+    /// [`event_ordinal`]. Forward blocks emit their updates in ascending
+    /// ordinal order, reverted blocks in descending order. Frames sharing an
+    /// ordinal (an absolute update or quote next to its delta, or block-level
+    /// seeds and depth at the end-of-block marker) and Fluid reserves, which
+    /// are not tied to a log, are ordered by the `stream_seq` of their
+    /// `PoolUpdate` frame.
+    pub fn ordinal(&self) -> u128 {
+        event_ordinal(self.block_number, self.tx_index, self.log_index)
+    }
+}
+
+/// Rolling integrity hash of one block's pool updates, carried in
+/// `EndBlock.updates_checksum`. Starting from zero, every update in stream
+/// order folds in as `keccak256(checksum ‖ bincode(PoolUpdateMessage))`, so a
+/// consumer recomputing it over the frames it buffered knows the block arrived
+/// complete, uncorrupted and in order. Only the event is hashed, not its
+/// `stream_seq`, which a confirmation buffer may renumber.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateChecksum(B256);
+
+impl UpdateChecksum {
+    pub fn push(&mut self, event: &PoolUpdateMessage) {
+        let mut preimage = self.0.to_vec();
+        bincode::serialize_into(&mut preimage, event).expect("PoolUpdateMessage serializes");
+        self.0 = keccak256(&preimage);
+    }
+
+    pub fn value(&self) -> B256 {
+        self.0
+    }
+}
+
+/// `(block_number, tx_index, log_index)` packed into one totally ordered
+/// value: block in the high 64 bits, then 32 bits each for the transaction
+/// and log index. Indexes past `u32::MAX` (the end-of-block marker
+/// `u64::MAX`) saturate, so they still sort last within their block.
+pub fn event_ordinal(block_number: u64, tx_index: u64, log_index: u64) -> u128 {
+    let saturate = |index: u64| index.min(u32::MAX as u64) as u128;
+    ((block_number as u128) << 64) | (saturate(tx_index) << 32) | saturate(log_index)
+}
+
 /// Pool identifier - can be address (V2/V3) or bytes32 (V4)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PoolIdentifier {
@@ -55,6 +101,14 @@ impl PoolIdentifier {
             PoolIdentifier::PoolId(id) => Some(*id),
         }
     }
+
+    /// `0x`-prefixed lowercase hex of the address or pool id.
+    pub fn to_hex(&self) -> String {
+        match self {
+            PoolIdentifier::Address(addr) => format!("{addr:#x}"),
+            PoolIdentifier::PoolId(id) => format!("{:#x}", B256::from(*id)),
+        }
+    }
 }
 
 /// Protocol type
@@ -77,6 +131,66 @@ pub enum UpdateType {
     Swap,
     Mint,
     Burn,
+    /// Absolute state rather than an event delta: V2 `Sync`, Fluid reserves,
+    /// the state engine's absolute frames and end-of-block liquidity depth.
+    Sync,
+    /// Position fees withdrawn (V3 `Collect`): no price or liquidity change.
+    Collect,
+    /// Flash loan (V3 `Flash`): fee growth changes, price does not.
+    Flash,
+    /// Donation to in-range liquidity (V4 `Donate`).
+    Donate,
+    /// Pool initialized at its starting price (V3/V4 `Initialize`).
+    Initialize,
+    /// Absolute state read from the block post-state rather than decoded from
+    /// a log: live-add seeds, periodic resyncs and verifier corrections.
+    /// Stamped with the end-of-block marker `tx_index`/`log_index` `u64::MAX`,
+    /// after every event of its block.
+    Seed,
+    /// Pool parameter change with no price or liquidity move: Curve `A`
+    /// ramps and fee changes, TwoCrypto/Tricrypto parameter updates and
+    /// Balancer swap-fee changes.
+    ParamChange,
+}
+
+/// Why a tracked pool contract stopped being a usable pool.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InvalidationReason {
+    /// The contract self-destructed: no code at the address.
+    Destroyed,
+    /// Different code now sits at the address.
+    CodeChanged,
+}
+
+/// Coarse class of a decoded pool event, used for per-pool event masks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventClass {
+    Swap,
+    Liquidity,
+}
+
+/// Per-pool event mask from the whitelist. Lets high-churn pools skip the
+/// frames of the event class the consumer ignores; masked events still update
+/// the arena and state engine. State-sync and parameter events (V2
+/// `Sync`, Fluid `LogOperate`, Curve ramps/fees, Balancer fee changes) are
+/// never masked: they carry absolute state the consumer cannot rebuild.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventMask {
+    #[default]
+    All,
+    SwapsOnly,
+    LiquidityOnly,
+}
+
+impl EventMask {
+    pub fn allows(self, class: EventClass) -> bool {
+        match self {
+            EventMask::All => true,
+            EventMask::SwapsOnly => class == EventClass::Swap,
+            EventMask::LiquidityOnly => class == EventClass::Liquidity,
+        }
+    }
 }
 
 /// Slot0-like post-state shared by swap and reorg-epilogue messages.
@@ -87,6 +201,21 @@ pub struct Slot0State {
     pub tick: i32,
 }
 
+/// Full concentrated-liquidity state read from storage: slot0, active
+/// liquidity, every populated tick-bitmap word and the initialized ticks under it.
+/// Emitted once when a pool is live-added so the consumer can initialize it
+/// without racing the stream with its own RPC calls.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TickSnapshot {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    /// `(word_pos, bitmap)` for each non-zero bitmap word.
+    pub tick_bitmaps: Vec<(i16, [u8; 32])>,
+    /// `(tick, liquidity_gross, liquidity_net)` for each initialized tick.
+    pub ticks: Vec<(i32, u128, i128)>,
+}
+
 /// Full Fluid reserve snapshot.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct FluidState {
@@ -267,6 +396,16 @@ pub enum PoolUpdate {
         amount_out: U256,
     },
 
+    /// Swap reconstructed from a call trace for a pool that emits no swap
+    /// log (`TRACE_SWAP_POOLS`): the raw token amounts the pool received and
+    /// paid out. Informational; carries no post-state.
+    TracedSwap {
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        amount_out: U256,
+    },
+
     /// Balancer V2 PoolBalanceChanged (join/exit).
     /// Signed deltas per token (positive = entering pool, negative = leaving).
     /// `tokens` is parallel to `deltas` (Vault event order); apply matches tokens
@@ -289,6 +428,43 @@ pub enum PoolUpdate {
     /// Uniswap V2 absolute reserve post-state from `Sync`.
     /// Canonical forward-path update for V2 pools.
     V2Sync { reserve0: u128, reserve1: u128 },
+
+    /// Uniswap V3 full-state seed for a newly tracked pool, read from the pool
+    /// contract's storage at the block where the whitelist add lands.
+    V3Snapshot { snapshot: TickSnapshot },
+
+    /// Uniswap V4 full-state seed for a newly tracked pool id, read from the
+    /// PoolManager's `pools[id]` storage (extsload layout) at the add block.
+    V4Snapshot { snapshot: TickSnapshot },
+
+    /// Absolute V3/V4 post-state from the in-ExEx state engine
+    /// (`POOL_STATE_MODE`): slot0 plus the absolute `liquidity_net` of each
+    /// tick the triggering event changed (empty for swaps).
+    ConcentratedState {
+        state: Slot0State,
+        ticks: Vec<(i32, i128)>,
+    },
+
+    /// Decimal-normalized quote for the preceding V3/V4 swap (`SWAP_QUOTES`):
+    /// post-swap price as token1 per token0, the absolute token amounts
+    /// swapped, and their approximate USD value when the price feed knows
+    /// either token.
+    SwapQuote {
+        price: f64,
+        amount0: f64,
+        amount1: f64,
+        usd_notional: Option<f64>,
+    },
+
+    /// End-of-block liquidity depth for a V3/V4 pool the block touched
+    /// (`LIQUIDITY_DEPTH_SPACINGS`): active liquidity per tick-spacing-wide
+    /// range around the current tick, as `(range_lower_tick, liquidity)` in
+    /// ascending order. Emitted just before `EndBlock` with `tx_index` and
+    /// `log_index` set to `u64::MAX`.
+    LiquidityDepth {
+        tick_spacing: i32,
+        ranges: Vec<(i32, u128)>,
+    },
 }
 
 /// Reorg-epilogue-only canonical state updates.
@@ -314,6 +490,15 @@ pub enum ReorgEpilogueUpdate {
         reserve0: u128,
         reserve1: u128,
     },
+
+    /// Definitive final Curve full state (`CurveLiquidity`, `TwoCryptoState`
+    /// or `TricryptoState`) for pools whose full-state updates were reverted:
+    /// a reverted absolute state has no inverse of its own.
+    PoolStateFinal {
+        pool_id: PoolIdentifier,
+        protocol: Protocol,
+        update: PoolUpdate,
+    },
 }
 
 /// Token metadata from the rich whitelist.
@@ -381,6 +566,11 @@ pub struct PoolMetadata {
     /// published `balancer_swap_fee` is then the only trusted fee source.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub balancer_version: Option<String>,
+
+    /// Optional event mask from whitelist `event_mask` (`swaps_only` /
+    /// `liquidity_only`). `None` emits frames for every event class.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_mask: Option<EventMask>,
 }
 
 /// Whitelist control message sent from dynamicWhitelist to ExEx
@@ -410,12 +600,26 @@ pub enum ControlMessage {
     /// Block boundary start with monotonic stream sequence.
     BeginBlock {
         stream_seq: u64,
+        /// EIP-155 chain id, so one consumer can tell streams apart.
+        chain_id: u64,
         block_number: u64,
+        /// Hash of this block and of its parent, so a consumer can key its
+        /// per-block buffers by hash and detect reorgs on its own (a forward
+        /// block whose parent is not the last applied one).
+        block_hash: B256,
+        parent_hash: B256,
         block_timestamp: u64,
         /// EIP-1559 base fee in wei. Always present post-London.
         base_fee_per_gas: u64,
+        /// Header beneficiary: under PBS, usually the winning builder.
+        fee_recipient: Address,
+        /// `BUILDER_LABELS` name of `fee_recipient`, if listed.
+        builder: Option<String>,
         /// If true, this block's events are reverts (from ChainReorged or ChainReverted)
         is_revert: bool,
+        /// The block is far behind the chain tip (initial sync, restart
+        /// backlog): apply it, but do not quote off it.
+        is_historical: bool,
     },
 
     /// Pool update wrapper with monotonic stream sequence.
@@ -430,6 +634,8 @@ pub enum ControlMessage {
         block_number: u64,
         /// Number of pool updates sent for this block (for validation)
         num_updates: u64,
+        /// [`UpdateChecksum`] over this block's `PoolUpdate` events.
+        updates_checksum: B256,
     },
 
     /// Heartbeat / keepalive
@@ -457,6 +663,82 @@ pub enum ControlMessage {
         stream_seq: u64,
         final_tip_block: u64,
     },
+
+    /// Historical backfill for pools added mid-stream (`BACKFILL_BLOCKS`).
+    /// Backfill frames are unsequenced and only ever sent between block and
+    /// reorg envelopes, never inside one; a consumer buffers them per pool
+    /// until the matching `BackfillComplete`. The range ends at the block whose
+    /// post-state the live-add seed carries.
+    BackfillStart {
+        pool_ids: Vec<PoolIdentifier>,
+        from_block: u64,
+        to_block: u64,
+    },
+
+    /// One replayed historical update for a pool in an open backfill.
+    BackfillUpdate {
+        event: PoolUpdateMessage,
+    },
+
+    /// Backfill finished; `num_updates` is the count of `BackfillUpdate` frames.
+    /// `incomplete` lists the pools whose history has a gap: a block the node
+    /// had no receipts or state for (pruned) was skipped.
+    BackfillComplete {
+        pool_ids: Vec<PoolIdentifier>,
+        to_block: u64,
+        num_updates: u64,
+        incomplete: Vec<PoolIdentifier>,
+    },
+
+    /// The node's finalized head advanced. Unsequenced and outside block
+    /// envelopes: blocks at or below `block_number` will never be reverted, so a
+    /// consumer can prune its revert buffers and checkpoint up to it.
+    Finalized {
+        block_number: u64,
+    },
+
+    /// The stream is live again. Unsequenced, emitted just before the
+    /// `BeginBlock` of `block_number`, the first block within reach of the
+    /// chain tip after `is_historical` ones (or at startup).
+    CaughtUp {
+        block_number: u64,
+    },
+
+    /// A pending transaction would swap through a tracked pool
+    /// (`MEMPOOL_WATCH`). Unsequenced and outside block envelopes; a hint
+    /// only, never applied to pool state: the transaction may be replaced,
+    /// dropped or revert. Amounts are those fixed by the calldata.
+    PendingSwap {
+        chain_id: u64,
+        tx_hash: B256,
+        sender: Address,
+        /// Contract the transaction calls: a router or the pool itself.
+        to: Address,
+        pool_id: PoolIdentifier,
+        token_in: Address,
+        token_out: Address,
+        /// Input of an exact-input swap's first hop.
+        amount_in: Option<U256>,
+        /// Output of an exact-output swap's last hop.
+        amount_out: Option<U256>,
+        max_priority_fee_per_gas: Option<u128>,
+    },
+
+    /// A tracked pool contract died in `block_number` (self-destruct or code
+    /// change). Unsequenced, emitted just before that block's `EndBlock`; the
+    /// pool is blacklisted from the same block on and gets no more updates.
+    PoolInvalidated {
+        chain_id: u64,
+        block_number: u64,
+        pool_id: PoolIdentifier,
+        protocol: Protocol,
+        reason: InvalidationReason,
+    },
+
+    /// The ExEx is stopping. Unsequenced and the last frame on the
+    /// connection: every block envelope before it was complete, and the
+    /// stream resumes after the last `EndBlock` on restart.
+    Shutdown,
 }
 
 impl ControlMessage {
@@ -470,9 +752,17 @@ impl ControlMessage {
             | ControlMessage::ReorgStart { stream_seq, .. }
             | ControlMessage::ReorgEpilogue { stream_seq, .. }
             | ControlMessage::ReorgComplete { stream_seq, .. } => Some(*stream_seq),
-            ControlMessage::UpdateWhitelist(_) | ControlMessage::Ping | ControlMessage::Pong => {
-                None
-            }
+            ControlMessage::UpdateWhitelist(_)
+            | ControlMessage::Ping
+            | ControlMessage::Pong
+            | ControlMessage::BackfillStart { .. }
+            | ControlMessage::BackfillUpdate { .. }
+            | ControlMessage::BackfillComplete { .. }
+            | ControlMessage::Finalized { .. }
+            | ControlMessage::CaughtUp { .. }
+            | ControlMessage::PendingSwap { .. }
+            | ControlMessage::PoolInvalidated { .. }
+            | ControlMessage::Shutdown => None,
         }
     }
 }
@@ -497,17 +787,107 @@ mod tests {
         assert_eq!(id.as_address(), None);
     }
 
+    #[test]
+    fn event_mask_allows_matching_class_only() {
+        assert!(EventMask::All.allows(EventClass::Swap));
+        assert!(EventMask::All.allows(EventClass::Liquidity));
+        assert!(EventMask::SwapsOnly.allows(EventClass::Swap));
+        assert!(!EventMask::SwapsOnly.allows(EventClass::Liquidity));
+        assert!(!EventMask::LiquidityOnly.allows(EventClass::Swap));
+        assert!(EventMask::LiquidityOnly.allows(EventClass::Liquidity));
+    }
+
+    #[test]
+    fn update_type_tags_are_append_only() {
+        let tag = |t: UpdateType| bincode::serialize(&t).unwrap()[0];
+        assert_eq!(
+            [
+                UpdateType::Swap,
+                UpdateType::Mint,
+                UpdateType::Burn,
+                UpdateType::Sync,
+                UpdateType::Collect,
+                UpdateType::Flash,
+                UpdateType::Donate,
+                UpdateType::Initialize,
+                UpdateType::Seed,
+                UpdateType::ParamChange,
+            ]
+            .map(tag),
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn event_ordinal_orders_by_block_then_tx_then_log() {
+        let ordered = [
+            event_ordinal(99, u64::MAX, u64::MAX),
+            event_ordinal(100, 0, 0),
+            event_ordinal(100, 0, 7),
+            event_ordinal(100, 1, 0),
+            event_ordinal(100, 1, u64::MAX),
+            event_ordinal(100, u64::MAX, u64::MAX),
+            event_ordinal(101, 0, 0),
+        ];
+        assert!(ordered.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn update_checksum_depends_on_content_and_order() {
+        let update = |log_index| PoolUpdateMessage {
+            chain_id: 1,
+            pool_id: PoolIdentifier::Address(Address::ZERO),
+            protocol: Protocol::UniswapV2,
+            update_type: UpdateType::Swap,
+            block_number: 100,
+            block_timestamp: 0,
+            tx_index: 0,
+            log_index,
+            is_revert: false,
+            update: PoolUpdate::V2Sync {
+                reserve0: 1,
+                reserve1: 2,
+            },
+        };
+        let checksum = |updates: &[PoolUpdateMessage]| {
+            let mut checksum = UpdateChecksum::default();
+            updates.iter().for_each(|u| checksum.push(u));
+            checksum.value()
+        };
+
+        assert_eq!(checksum(&[]), B256::ZERO);
+        assert_eq!(
+            checksum(&[update(0), update(1)]),
+            checksum(&[update(0), update(1)])
+        );
+        assert_ne!(
+            checksum(&[update(0), update(1)]),
+            checksum(&[update(1), update(0)])
+        );
+        assert_ne!(checksum(&[update(0)]), checksum(&[update(0), update(1)]));
+    }
+
     #[test]
     fn test_control_message_stream_seq() {
         let msg = ControlMessage::BeginBlock {
             stream_seq: 42,
+            chain_id: 1,
             block_number: 1000,
+            block_hash: B256::ZERO,
+            parent_hash: B256::ZERO,
             block_timestamp: 123,
             base_fee_per_gas: 1_000_000_000,
+            fee_recipient: Address::ZERO,
+            builder: None,
             is_revert: false,
+            is_historical: false,
         };
 
         assert_eq!(msg.stream_seq(), Some(42));
+        assert_eq!(
+            ControlMessage::Finalized { block_number: 1000 }.stream_seq(),
+            None
+        );
     }
 
     #[test]
@@ -531,4 +911,24 @@ mod tests {
             other => panic!("unexpected decoded variant: {other:?}"),
         }
     }
+
+    #[test]
+    fn test_v3_snapshot_roundtrip() {
+        let snapshot = TickSnapshot {
+            sqrt_price_x96: U256::from(1u128 << 96),
+            tick: -887_220,
+            liquidity: 42,
+            tick_bitmaps: vec![(-58, [0xFF; 32])],
+            ticks: vec![(-887_220, 42, -42)],
+        };
+        let update = PoolUpdate::V3Snapshot {
+            snapshot: snapshot.clone(),
+        };
+
+        let encoded = bincode::serialize(&update).expect("serialize");
+        match bincode::deserialize::<PoolUpdate>(&encoded).expect("deserialize") {
+            PoolUpdate::V3Snapshot { snapshot: decoded } => assert_eq!(decoded, snapshot),
+            other => panic!("unexpected decoded variant: {other:?}"),
+        }
+    }
 }
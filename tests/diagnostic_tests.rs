@@ -102,6 +102,7 @@ fn test_diagnostic_v2_event_processing() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -177,6 +178,7 @@ fn test_diagnostic_v2_event_wrong_pool() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -246,6 +248,7 @@ fn test_diagnostic_v3_event_processing() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -311,6 +314,7 @@ fn test_diagnostic_v4_event_processing() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -406,6 +410,7 @@ fn test_diagnostic_v4_wrong_pool_id() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -546,6 +551,7 @@ fn test_diagnostic_whitelist_not_applied() {
         balancer_weights: None,
         balancer_swap_fee: None,
         balancer_version: None,
+        event_mask: None,
     };
 
     // Begin block BEFORE queuing update
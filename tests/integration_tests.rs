@@ -32,6 +32,7 @@ mod event_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         }
     }
 
@@ -53,6 +54,7 @@ mod event_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         }
     }
 
@@ -74,6 +76,7 @@ mod event_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         }
     }
 
@@ -361,6 +364,7 @@ mod event_decoding_and_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -413,6 +417,7 @@ mod event_decoding_and_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -455,6 +460,7 @@ mod event_decoding_and_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -509,6 +515,7 @@ mod event_decoding_and_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -561,6 +568,7 @@ mod event_decoding_and_filtering {
             balancer_weights: None,
             balancer_swap_fee: None,
             balancer_version: None,
+            event_mask: None,
         };
 
         tracker.queue_update(WhitelistUpdate::Add(vec![pool_metadata]));
@@ -596,6 +604,7 @@ mod message_creation {
 
         // In main.rs, this would be created by create_pool_update().
         let message = reth_exex_liquidity::types::PoolUpdateMessage {
+            chain_id: 1,
             pool_id: PoolIdentifier::Address(pool_addr),
             protocol: Protocol::UniswapV2,
             update_type: UpdateType::Swap,
@@ -630,6 +639,7 @@ mod message_creation {
         let pool_addr = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
 
         let message = reth_exex_liquidity::types::PoolUpdateMessage {
+            chain_id: 1,
             pool_id: PoolIdentifier::Address(pool_addr),
             protocol: Protocol::UniswapV3,
             update_type: UpdateType::Swap,
@@ -665,6 +675,7 @@ mod message_creation {
         let pool_id = [1u8; 32];
 
         let message = reth_exex_liquidity::types::PoolUpdateMessage {
+            chain_id: 1,
             pool_id: PoolIdentifier::PoolId(pool_id),
             protocol: Protocol::UniswapV4,
             update_type: UpdateType::Swap,
@@ -698,10 +709,16 @@ mod block_boundaries {
         // Test BeginBlock message creation
         let begin_block = ControlMessage::BeginBlock {
             stream_seq: 1,
+            chain_id: 1,
             block_number: 12345,
+            block_hash: B256::ZERO,
+            parent_hash: B256::ZERO,
             block_timestamp: 1234567890,
             base_fee_per_gas: 1_000_000_000,
+            fee_recipient: Address::ZERO,
+            builder: None,
             is_revert: false,
+            is_historical: false,
         };
 
         match begin_block {
@@ -723,6 +740,7 @@ mod block_boundaries {
             stream_seq: 1,
             block_number: 12345,
             num_updates: 5,
+            updates_checksum: B256::ZERO,
         };
 
         match end_block {
@@ -743,10 +761,16 @@ mod block_boundaries {
         // Test BeginBlock with revert flag
         let begin_block_revert = ControlMessage::BeginBlock {
             stream_seq: 2,
+            chain_id: 1,
             block_number: 12345,
+            block_hash: B256::ZERO,
+            parent_hash: B256::ZERO,
             block_timestamp: 1234567890,
             base_fee_per_gas: 1_000_000_000,
+            fee_recipient: Address::ZERO,
+            builder: None,
             is_revert: true,
+            is_historical: false,
         };
 
         match begin_block_revert {
@@ -764,6 +788,7 @@ mod serialization {
     #[test]
     fn test_pool_update_message_serialization() {
         let message = reth_exex_liquidity::types::PoolUpdateMessage {
+            chain_id: 1,
             pool_id: PoolIdentifier::Address(Address::ZERO),
             protocol: Protocol::UniswapV2,
             update_type: UpdateType::Swap,
@@ -795,18 +820,31 @@ mod serialization {
     fn test_control_message_serialization() {
         let msg = ControlMessage::BeginBlock {
             stream_seq: 3,
+            chain_id: 1,
             block_number: 12345,
+            block_hash: B256::ZERO,
+            parent_hash: B256::ZERO,
             block_timestamp: 1234567890,
             base_fee_per_gas: 1_000_000_000,
+            fee_recipient: Address::ZERO,
+            builder: None,
             is_revert: false,
+            is_historical: false,
         };
 
         let encoded = bincode::serialize(&msg).expect("Should serialize");
         let decoded: ControlMessage = bincode::deserialize(&encoded).expect("Should deserialize");
 
         match decoded {
-            ControlMessage::BeginBlock { block_number, .. } => {
+            ControlMessage::BeginBlock {
+                block_number,
+                fee_recipient,
+                builder,
+                ..
+            } => {
                 assert_eq!(block_number, 12345);
+                assert_eq!(fee_recipient, Address::ZERO);
+                assert_eq!(builder, None);
             }
             _ => panic!("Expected BeginBlock"),
         }